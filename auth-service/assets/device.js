@@ -0,0 +1,83 @@
+const codeSection = document.getElementById("code-section");
+const consentSection = document.getElementById("consent-section");
+const doneSection = document.getElementById("done-section");
+
+const codeForm = document.getElementById("code-form");
+const codeErrAlert = document.getElementById("code-err-alert");
+const consentErrAlert = document.getElementById("consent-err-alert");
+
+function showSection(section) {
+    codeSection.style.display = section === codeSection ? "block" : "none";
+    consentSection.style.display = section === consentSection ? "block" : "none";
+    doneSection.style.display = section === doneSection ? "block" : "none";
+}
+
+function showError(alert, response) {
+    // the verification endpoints need a session
+    if (response.status === 400 || response.status === 401) {
+        alert.innerHTML = 'Please <a href="/">log in</a> and come back to this page.';
+        alert.style.display = "block";
+        return;
+    }
+    response.json().then(data => {
+        alert.textContent = data.error || "Something went wrong, please try again.";
+        alert.style.display = "block";
+    });
+}
+
+function lookup(userCode) {
+    fetch('/device/verify?user_code=' + encodeURIComponent(userCode))
+        .then(response => {
+            if (response.status !== 200) {
+                showSection(codeSection);
+                showError(codeErrAlert, response);
+                return;
+            }
+            response.json().then(data => {
+                document.getElementById("consent-client").textContent = data.client_name;
+                document.getElementById("consent-code").textContent = data.user_code;
+                const scopes = document.getElementById("consent-scopes");
+                scopes.replaceChildren(...data.scopes.map(scope => {
+                    const item = document.createElement("li");
+                    item.textContent = scope;
+                    return item;
+                }));
+                codeErrAlert.style.display = "none";
+                showSection(consentSection);
+            });
+        });
+}
+
+function decide(decision) {
+    const userCode = document.getElementById("consent-code").textContent;
+    fetch('/device/verify', {
+        method: 'POST',
+        headers: {
+            'Content-Type': 'application/json',
+        },
+        body: JSON.stringify({ user_code: userCode, decision }),
+    }).then(response => {
+        if (response.status !== 204) {
+            showError(consentErrAlert, response);
+            return;
+        }
+        document.getElementById("done-title").textContent =
+            decision === "approve" ? "Device connected" : "Request denied";
+        showSection(doneSection);
+    });
+}
+
+codeForm.addEventListener("submit", (e) => {
+    e.preventDefault();
+    lookup(codeForm.user_code.value);
+});
+
+document.getElementById("approve-button").addEventListener("click", () => decide("approve"));
+document.getElementById("deny-button").addEventListener("click", () => decide("deny"));
+
+// verification_uri_complete carries the code
+const userCode = new URLSearchParams(window.location.search).get("user_code");
+if (userCode) {
+    codeForm.user_code.value = userCode;
+    lookup(userCode);
+}
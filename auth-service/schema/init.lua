@@ -17,6 +17,186 @@ schema:table(
 		:column(Col.text("email"):primary_key())
 		:column(Col.text("password_hash"):not_null())
 		:column(Col.text("two_factor"):default_value("none"):not_null())
+		:column(Col.boolean("verified"):default_value("false"):not_null())
+		:column(Col.timestamptz("deletion_requested_at"))
+		:column(Col.timestamptz("locked_at"))
+)
+
+schema:table(
+	Table.new("passkey")
+		:description("Registered WebAuthn credentials")
+		:column(Col.text("credential_id"):primary_key())
+		:column(Col.text("email"):not_null())
+		:column(Col.text("passkey"):not_null())
+		:column(Col.bigint("sign_count"):default_value("0"):not_null())
+		:column(Col.timestamptz("created_at"):default_value("now()"):not_null())
+)
+
+schema:table(
+	Table.new("passkey_ceremony")
+		:description("In-flight WebAuthn registration/authentication ceremonies")
+		:column(Col.text("id"):primary_key())
+		:column(Col.text("ceremony"):not_null())
+		:column(Col.timestamptz("expires_at"):not_null())
+)
+
+schema:table(
+	Table.new("totp")
+		:description("Authenticator app (TOTP) secrets")
+		:column(Col.text("email"):primary_key())
+		:column(Col.text("secret"))
+		:column(Col.text("pending_secret"))
+		:column(Col.bigint("last_step"))
+)
+
+schema:table(
+	Table.new("two_factor")
+		:description("Pending second factor checks of logins in flight")
+		:column(Col.text("email"):primary_key())
+		:column(Col.text("id"):not_null())
+		:column(Col.text("code"):not_null())
+		:column(Col.bigint("failures"):default_value("0"):not_null())
+		:column(Col.timestamptz("created_at"):default_value("now()"):not_null())
+)
+
+schema:table(
+	Table.new("recovery_code")
+		:description("Hashed single-use 2FA recovery codes")
+		:column(Col.text("id"):primary_key())
+		:column(Col.text("email"):not_null())
+		:column(Col.text("code_hash"):not_null())
+		:column(Col.timestamptz("created_at"):default_value("now()"):not_null())
+)
+
+schema:table(
+	Table.new("refresh_token")
+		:description("Hashed refresh tokens grouped into rotation families")
+		:column(Col.text("token_hash"):primary_key())
+		:column(Col.text("family_id"):not_null())
+		:column(Col.text("email"):not_null())
+		:column(Col.timestamptz("expires_at"):not_null())
+		:column(Col.boolean("used"):default_value("false"):not_null())
+		:column(Col.timestamptz("auth_time"))
+		:column(Col.text("amr"):default_value(""):not_null())
+		:column(Col.timestamptz("created_at"):default_value("now()"):not_null())
+		:column(Col.text("client_id"))
+)
+
+schema:table(
+	Table.new("oauth_client")
+		:description("Registered OAuth clients")
+		:column(Col.text("client_id"):primary_key())
+		:column(Col.text("name"):not_null())
+		:column(Col.text("redirect_uris"):not_null())
+		:column(Col.text("allowed_scopes"):not_null())
+		:column(Col.text("secret_hash"))
+		:column(Col.timestamptz("created_at"):default_value("now()"):not_null())
+)
+
+schema:table(
+	Table.new("federated_identity")
+		:description("Upstream identity provider accounts linked to local users")
+		:column(Col.text("provider"):not_null())
+		:column(Col.text("subject"):not_null())
+		:column(Col.text("email"):not_null())
+		:column(Col.timestamptz("created_at"):default_value("now()"):not_null())
+		:primary_key("provider", "subject")
+)
+
+schema:table(
+	Table.new("api_key")
+		:description("Hashed personal API keys")
+		:column(Col.text("id"):primary_key())
+		:column(Col.text("key_hash"):not_null())
+		:column(Col.text("email"):not_null())
+		:column(Col.text("name"):not_null())
+		:column(Col.text("scopes"):default_value(""):not_null())
+		:column(Col.timestamptz("expires_at"))
+		:column(Col.timestamptz("created_at"):default_value("now()"):not_null())
+)
+
+schema:table(
+	Table.new("session")
+		:description("Logins of each user, the id is the sid claim of their access tokens")
+		:column(Col.text("id"):primary_key())
+		:column(Col.text("email"):not_null())
+		:column(Col.text("ip"))
+		:column(Col.text("user_agent"))
+		:column(Col.timestamptz("created_at"):default_value("now()"):not_null())
+		:column(Col.timestamptz("last_seen_at"):default_value("now()"):not_null())
+		:column(Col.timestamptz("expires_at"):not_null())
+		:column(Col.text("org_id"))
+)
+
+schema:table(
+	Table.new("role")
+		:description("Named sets of permissions")
+		:column(Col.text("name"):primary_key())
+		:column(Col.text("description"):default_value(""):not_null())
+		:column(Col.text("permissions"):default_value(""):not_null())
+		:column(Col.timestamptz("created_at"):default_value("now()"):not_null())
+)
+
+schema:table(
+	Table.new("user_role")
+		:description("Roles assigned to users")
+		:column(Col.text("email"):not_null())
+		:column(Col.text("role_name"):not_null())
+		:column(Col.timestamptz("created_at"):default_value("now()"):not_null())
+		:primary_key("email", "role_name")
+)
+
+schema:table(
+	Table.new("organization")
+		:description("Customer workspaces users are members of")
+		:column(Col.text("id"):primary_key())
+		:column(Col.text("name"):not_null())
+		:column(Col.timestamptz("created_at"):default_value("now()"):not_null())
+)
+
+schema:table(
+	Table.new("org_member")
+		:description("Members of each organization and their role in it")
+		:column(Col.text("org_id"):not_null())
+		:column(Col.text("email"):not_null())
+		:column(Col.text("role"):not_null())
+		:column(Col.timestamptz("created_at"):default_value("now()"):not_null())
+		:primary_key("org_id", "email")
+)
+
+schema:table(
+	Table.new("org_invitation")
+		:description("Pending invitations to join an organization")
+		:column(Col.text("id"):primary_key())
+		:column(Col.text("org_id"):not_null())
+		:column(Col.text("email"):not_null())
+		:column(Col.text("role"):not_null())
+		:column(Col.text("invited_by"):not_null())
+		:column(Col.timestamptz("expires_at"):not_null())
+		:column(Col.timestamptz("created_at"):default_value("now()"):not_null())
+)
+
+schema:table(
+	Table.new("banned_token")
+		:description("Auth tokens revoked before they expire")
+		:column(Col.text("token"):primary_key())
+		:column(Col.timestamptz("expires_at"):not_null())
+)
+
+schema:table(
+	Table.new("token_version")
+		:description("Per user token version, auth tokens carrying an older one are revoked")
+		:column(Col.text("email"):primary_key())
+		:column(Col.bigint("version"):default_value("0"):not_null())
+)
+
+schema:table(
+	Table.new("one_time_token")
+		:description("Outstanding single-use tokens sent out in links, by jti")
+		:column(Col.text("id"):primary_key())
+		:column(Col.text("purpose"):not_null())
+		:column(Col.text("email"):not_null())
+		:column(Col.timestamptz("expires_at"):not_null())
 )
 
 -- schema:table(
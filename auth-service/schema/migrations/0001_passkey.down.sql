@@ -0,0 +1,7 @@
+-- Migration: 0001_passkey (down)
+-- Created at: 2026-10-18T07:38:04.318351+00:00
+-- This migration reverses the changes made by the up migration.
+
+DROP TABLE "passkey_ceremony";
+--> +statement
+DROP TABLE "passkey";
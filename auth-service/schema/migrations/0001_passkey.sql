@@ -0,0 +1,21 @@
+-- Migration: 0001_passkey (up)
+-- Created at: 2026-10-18T07:38:04.318207+00:00
+-- To snapshot: 447b9caf-eb5f-4742-9d09-22a3f3ac189a
+
+CREATE TABLE "passkey" (
+  "credential_id" TEXT PRIMARY KEY NOT NULL,
+  "email" TEXT NOT NULL,
+  "passkey" TEXT NOT NULL,
+  "sign_count" BIGINT NOT NULL DEFAULT 0,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+--> +statement
+COMMENT ON TABLE "passkey" IS 'Registered WebAuthn credentials';
+--> +statement
+CREATE TABLE "passkey_ceremony" (
+  "id" TEXT PRIMARY KEY NOT NULL,
+  "ceremony" TEXT NOT NULL,
+  "expires_at" TIMESTAMPTZ NOT NULL
+);
+--> +statement
+COMMENT ON TABLE "passkey_ceremony" IS 'In-flight WebAuthn registration/authentication ceremonies';
@@ -0,0 +1,5 @@
+-- Migration: 0002_totp (down)
+-- Created at: 2026-10-18T08:16:27.902259+00:00
+-- This migration reverses the changes made by the up migration.
+
+DROP TABLE "totp";
@@ -0,0 +1,12 @@
+-- Migration: 0002_totp (up)
+-- Created at: 2026-10-18T08:16:27.902114+00:00
+-- To snapshot: 47404841-395b-4204-af5d-646e390de019
+
+CREATE TABLE "totp" (
+  "email" TEXT PRIMARY KEY NOT NULL,
+  "secret" TEXT,
+  "pending_secret" TEXT,
+  "last_step" BIGINT
+);
+--> +statement
+COMMENT ON TABLE "totp" IS 'Authenticator app (TOTP) secrets';
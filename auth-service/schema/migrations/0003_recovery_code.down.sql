@@ -0,0 +1,5 @@
+-- Migration: 0003_recovery_code (down)
+-- Created at: 2026-10-18T08:54:51.117566+00:00
+-- This migration reverses the changes made by the up migration.
+
+DROP TABLE "recovery_code";
@@ -0,0 +1,12 @@
+-- Migration: 0003_recovery_code (up)
+-- Created at: 2026-10-18T08:54:51.117430+00:00
+-- To snapshot: c3e6b47c-649f-4f5f-a39d-bb57640b44c7
+
+CREATE TABLE "recovery_code" (
+  "id" TEXT PRIMARY KEY NOT NULL,
+  "email" TEXT NOT NULL,
+  "code_hash" TEXT NOT NULL,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+--> +statement
+COMMENT ON TABLE "recovery_code" IS 'Hashed single-use 2FA recovery codes';
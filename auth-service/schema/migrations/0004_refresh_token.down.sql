@@ -0,0 +1,5 @@
+-- Migration: 0004_refresh_token (down)
+-- Created at: 2026-10-18T09:32:07.319021+00:00
+-- This migration reverses the changes made by the up migration.
+
+DROP TABLE "refresh_token";
@@ -0,0 +1,14 @@
+-- Migration: 0004_refresh_token (up)
+-- Created at: 2026-10-18T09:32:07.318904+00:00
+-- To snapshot: 06c0756b-af1f-4ca3-b601-9a6aabcad39e
+
+CREATE TABLE "refresh_token" (
+  "token_hash" TEXT PRIMARY KEY NOT NULL,
+  "family_id" TEXT NOT NULL,
+  "email" TEXT NOT NULL,
+  "expires_at" TIMESTAMPTZ NOT NULL,
+  "used" BOOLEAN NOT NULL DEFAULT false,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+--> +statement
+COMMENT ON TABLE "refresh_token" IS 'Hashed refresh tokens grouped into rotation families';
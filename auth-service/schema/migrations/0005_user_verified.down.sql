@@ -0,0 +1,5 @@
+-- Migration: 0005_user_verified (down)
+-- Created at: 2026-10-18T10:10:41.562419+00:00
+-- This migration reverses the changes made by the up migration.
+
+ALTER TABLE "user" DROP COLUMN "verified";
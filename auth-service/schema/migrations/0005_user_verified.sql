@@ -0,0 +1,5 @@
+-- Migration: 0005_user_verified (up)
+-- Created at: 2026-10-18T10:10:41.562310+00:00
+-- To snapshot: caf4c4c6-0f5a-4a25-b4bb-8e881fdd931c
+
+ALTER TABLE "user" ADD COLUMN "verified" BOOLEAN NOT NULL DEFAULT false;
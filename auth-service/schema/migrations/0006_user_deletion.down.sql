@@ -0,0 +1,5 @@
+-- Migration: 0006_user_deletion (down)
+-- Created at: 2026-10-18T10:48:19.632534+00:00
+-- This migration reverses the changes made by the up migration.
+
+ALTER TABLE "user" DROP COLUMN "deletion_requested_at";
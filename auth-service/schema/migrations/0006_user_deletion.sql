@@ -0,0 +1,5 @@
+-- Migration: 0006_user_deletion (up)
+-- Created at: 2026-10-18T10:48:19.632534+00:00
+-- To snapshot: 582aab7e-6cc3-4d11-8a97-8210a529fa2d
+
+ALTER TABLE "user" ADD COLUMN "deletion_requested_at" TIMESTAMPTZ;
@@ -0,0 +1,5 @@
+-- Migration: 0007_oauth_client (down)
+-- Created at: 2026-10-18T11:26:13.060152+00:00
+-- This migration reverses the changes made by the up migration.
+
+DROP TABLE "oauth_client";
@@ -0,0 +1,13 @@
+-- Migration: 0007_oauth_client (up)
+-- Created at: 2026-10-18T11:26:13.060152+00:00
+-- To snapshot: 38a12450-0a16-48d8-8e83-5cfdf75ec17f
+
+CREATE TABLE "oauth_client" (
+  "client_id" TEXT PRIMARY KEY NOT NULL,
+  "name" TEXT NOT NULL,
+  "redirect_uris" TEXT NOT NULL,
+  "allowed_scopes" TEXT NOT NULL,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+--> +statement
+COMMENT ON TABLE "oauth_client" IS 'Registered OAuth clients';
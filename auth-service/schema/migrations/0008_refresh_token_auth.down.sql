@@ -0,0 +1,7 @@
+-- Migration: 0008_refresh_token_auth (down)
+-- Created at: 2026-10-18T12:04:41.218604+00:00
+-- This migration reverses the changes made by the up migration.
+
+ALTER TABLE "refresh_token" DROP COLUMN "amr";
+--> +statement
+ALTER TABLE "refresh_token" DROP COLUMN "auth_time";
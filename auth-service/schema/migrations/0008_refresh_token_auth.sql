@@ -0,0 +1,7 @@
+-- Migration: 0008_refresh_token_auth (up)
+-- Created at: 2026-10-18T12:04:41.218604+00:00
+-- To snapshot: 39b937f5-bb45-44aa-b9b4-b029814206b3
+
+ALTER TABLE "refresh_token" ADD COLUMN "auth_time" TIMESTAMPTZ;
+--> +statement
+ALTER TABLE "refresh_token" ADD COLUMN "amr" TEXT NOT NULL DEFAULT '';
@@ -0,0 +1,5 @@
+-- Migration: 0009_oauth_client_secret (down)
+-- Created at: 2026-10-18T12:42:17.481305+00:00
+-- This migration reverses the changes made by the up migration.
+
+ALTER TABLE "oauth_client" DROP COLUMN "secret_hash";
@@ -0,0 +1,5 @@
+-- Migration: 0009_oauth_client_secret (up)
+-- Created at: 2026-10-18T12:42:17.481305+00:00
+-- To snapshot: 5f70b089-b8a7-4e84-8e35-81bd771d70aa
+
+ALTER TABLE "oauth_client" ADD COLUMN "secret_hash" TEXT;
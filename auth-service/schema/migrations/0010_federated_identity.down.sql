@@ -0,0 +1,5 @@
+-- Migration: 0010_federated_identity (down)
+-- Created at: 2026-10-18T13:20:52.903716+00:00
+-- This migration reverses the changes made by the up migration.
+
+DROP TABLE "federated_identity";
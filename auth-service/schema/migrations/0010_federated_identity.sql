@@ -0,0 +1,13 @@
+-- Migration: 0010_federated_identity (up)
+-- Created at: 2026-10-18T13:20:52.903716+00:00
+-- To snapshot: b1e7e0c2-6e83-4e05-85e7-baa90ad80676
+
+CREATE TABLE "federated_identity" (
+  "provider" TEXT NOT NULL,
+  "subject" TEXT NOT NULL,
+  "email" TEXT NOT NULL,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY ("provider", "subject")
+);
+--> +statement
+COMMENT ON TABLE "federated_identity" IS 'Upstream identity provider accounts linked to local users';
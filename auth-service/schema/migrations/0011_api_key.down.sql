@@ -0,0 +1,5 @@
+-- Migration: 0011_api_key (down)
+-- Created at: 2026-10-18T13:58:07.418230+00:00
+-- This migration reverses the changes made by the up migration.
+
+DROP TABLE "api_key";
@@ -0,0 +1,15 @@
+-- Migration: 0011_api_key (up)
+-- Created at: 2026-10-18T13:58:07.418230+00:00
+-- To snapshot: b4ce2815-5437-4a45-b598-af11095d2b3d
+
+CREATE TABLE "api_key" (
+  "id" TEXT PRIMARY KEY NOT NULL,
+  "key_hash" TEXT NOT NULL,
+  "email" TEXT NOT NULL,
+  "name" TEXT NOT NULL,
+  "scopes" TEXT NOT NULL DEFAULT '',
+  "expires_at" TIMESTAMPTZ,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+--> +statement
+COMMENT ON TABLE "api_key" IS 'Hashed personal API keys';
@@ -0,0 +1,5 @@
+-- Migration: 0012_session (down)
+-- Created at: 2026-10-18T14:36:36.502117+00:00
+-- This migration reverses the changes made by the up migration.
+
+DROP TABLE "session";
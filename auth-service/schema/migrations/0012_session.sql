@@ -0,0 +1,15 @@
+-- Migration: 0012_session (up)
+-- Created at: 2026-10-18T14:36:36.502117+00:00
+-- To snapshot: ee695d9d-7d17-411c-bdfa-5427e6de1c7a
+
+CREATE TABLE "session" (
+  "id" TEXT PRIMARY KEY NOT NULL,
+  "email" TEXT NOT NULL,
+  "ip" TEXT,
+  "user_agent" TEXT,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+  "last_seen_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+  "expires_at" TIMESTAMPTZ NOT NULL
+);
+--> +statement
+COMMENT ON TABLE "session" IS 'Logins of each user, the id is the sid claim of their access tokens';
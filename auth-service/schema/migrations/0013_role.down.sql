@@ -0,0 +1,7 @@
+-- Migration: 0013_role (down)
+-- Created at: 2026-10-18T15:14:12.840516+00:00
+-- This migration reverses the changes made by the up migration.
+
+DROP TABLE "user_role";
+--> +statement
+DROP TABLE "role";
@@ -0,0 +1,21 @@
+-- Migration: 0013_role (up)
+-- Created at: 2026-10-18T15:14:12.840516+00:00
+-- To snapshot: 668bbd72-d87d-4214-9662-9c6caa6e2dcb
+
+CREATE TABLE "role" (
+  "name" TEXT PRIMARY KEY NOT NULL,
+  "description" TEXT NOT NULL DEFAULT '',
+  "permissions" TEXT NOT NULL DEFAULT '',
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+--> +statement
+COMMENT ON TABLE "role" IS 'Named sets of permissions';
+--> +statement
+CREATE TABLE "user_role" (
+  "email" TEXT NOT NULL,
+  "role_name" TEXT NOT NULL,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY ("email", "role_name")
+);
+--> +statement
+COMMENT ON TABLE "user_role" IS 'Roles assigned to users';
@@ -0,0 +1,11 @@
+-- Migration: 0014_organization (down)
+-- Created at: 2026-10-18T15:52:55.217340+00:00
+-- This migration reverses the changes made by the up migration.
+
+DROP TABLE "org_invitation";
+--> +statement
+DROP TABLE "org_member";
+--> +statement
+DROP TABLE "organization";
+--> +statement
+ALTER TABLE "session" DROP COLUMN "org_id";
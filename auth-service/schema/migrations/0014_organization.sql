@@ -0,0 +1,35 @@
+-- Migration: 0014_organization (up)
+-- Created at: 2026-10-18T15:52:55.217340+00:00
+-- To snapshot: ce8b7ed6-d586-4fd0-97eb-050852d724aa
+
+ALTER TABLE "session" ADD COLUMN "org_id" TEXT;
+--> +statement
+CREATE TABLE "organization" (
+  "id" TEXT PRIMARY KEY NOT NULL,
+  "name" TEXT NOT NULL,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+--> +statement
+COMMENT ON TABLE "organization" IS 'Customer workspaces users are members of';
+--> +statement
+CREATE TABLE "org_member" (
+  "org_id" TEXT NOT NULL,
+  "email" TEXT NOT NULL,
+  "role" TEXT NOT NULL,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY ("org_id", "email")
+);
+--> +statement
+COMMENT ON TABLE "org_member" IS 'Members of each organization and their role in it';
+--> +statement
+CREATE TABLE "org_invitation" (
+  "id" TEXT PRIMARY KEY NOT NULL,
+  "org_id" TEXT NOT NULL,
+  "email" TEXT NOT NULL,
+  "role" TEXT NOT NULL,
+  "invited_by" TEXT NOT NULL,
+  "expires_at" TIMESTAMPTZ NOT NULL,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+--> +statement
+COMMENT ON TABLE "org_invitation" IS 'Pending invitations to join an organization';
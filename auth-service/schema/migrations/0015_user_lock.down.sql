@@ -0,0 +1,5 @@
+-- Migration: 0015_user_lock (down)
+-- Created at: 2026-10-18T16:30:06.381925+00:00
+-- This migration reverses the changes made by the up migration.
+
+ALTER TABLE "user" DROP COLUMN "locked_at";
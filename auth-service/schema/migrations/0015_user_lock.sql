@@ -0,0 +1,5 @@
+-- Migration: 0015_user_lock (up)
+-- Created at: 2026-10-18T16:30:06.381925+00:00
+-- To snapshot: a6094d66-0c0c-42e8-b450-8f9be83e135b
+
+ALTER TABLE "user" ADD COLUMN "locked_at" TIMESTAMPTZ;
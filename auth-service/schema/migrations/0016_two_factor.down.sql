@@ -0,0 +1,5 @@
+-- Migration: 0016_two_factor (down)
+-- Created at: 2026-10-18T17:08:14.527301+00:00
+-- This migration reverses the changes made by the up migration.
+
+DROP TABLE "two_factor";
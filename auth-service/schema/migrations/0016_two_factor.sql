@@ -0,0 +1,13 @@
+-- Migration: 0016_two_factor (up)
+-- Created at: 2026-10-18T17:08:14.527301+00:00
+-- To snapshot: 30eb903f-34b0-4a0b-8462-f45354fcec50
+
+CREATE TABLE "two_factor" (
+  "email" TEXT PRIMARY KEY NOT NULL,
+  "id" TEXT NOT NULL,
+  "code" TEXT NOT NULL,
+  "failures" BIGINT NOT NULL DEFAULT 0,
+  "created_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+--> +statement
+COMMENT ON TABLE "two_factor" IS 'Pending second factor checks of logins in flight';
@@ -0,0 +1,5 @@
+-- Migration: 0017_refresh_token_client (down)
+-- Created at: 2026-10-18T17:46:14.527301+00:00
+-- This migration reverses the changes made by the up migration.
+
+ALTER TABLE "refresh_token" DROP COLUMN "client_id";
@@ -0,0 +1,5 @@
+-- Migration: 0017_refresh_token_client (up)
+-- Created at: 2026-10-18T17:46:14.527301+00:00
+-- To snapshot: 321c2611-f644-45f4-af70-f8c7c1f8c410
+
+ALTER TABLE "refresh_token" ADD COLUMN "client_id" TEXT;
@@ -0,0 +1,7 @@
+-- Migration: 0018_token_revocation (down)
+-- Created at: 2026-10-18T18:21:37.118204+00:00
+-- This migration reverses the changes made by the up migration.
+
+DROP TABLE "token_version";
+--> +statement
+DROP TABLE "banned_token";
@@ -0,0 +1,17 @@
+-- Migration: 0018_token_revocation (up)
+-- Created at: 2026-10-18T18:21:37.118204+00:00
+-- To snapshot: fec27a9a-f66f-4b8a-b347-fd807ed05cdd
+
+CREATE TABLE "banned_token" (
+  "token" TEXT PRIMARY KEY NOT NULL,
+  "expires_at" TIMESTAMPTZ NOT NULL
+);
+--> +statement
+COMMENT ON TABLE "banned_token" IS 'Auth tokens revoked before they expire';
+--> +statement
+CREATE TABLE "token_version" (
+  "email" TEXT PRIMARY KEY NOT NULL,
+  "version" BIGINT NOT NULL DEFAULT 0
+);
+--> +statement
+COMMENT ON TABLE "token_version" IS 'Per user token version, auth tokens carrying an older one are revoked';
@@ -0,0 +1,5 @@
+-- Migration: 0019_one_time_token (down)
+-- Created at: 2026-10-18T18:34:52.640117+00:00
+-- This migration reverses the changes made by the up migration.
+
+DROP TABLE "one_time_token";
@@ -0,0 +1,12 @@
+-- Migration: 0019_one_time_token (up)
+-- Created at: 2026-10-18T18:34:52.640117+00:00
+-- To snapshot: 2e1747e2-4fbd-4088-94b7-cd27a72af04c
+
+CREATE TABLE "one_time_token" (
+  "id" TEXT PRIMARY KEY NOT NULL,
+  "purpose" TEXT NOT NULL,
+  "email" TEXT NOT NULL,
+  "expires_at" TIMESTAMPTZ NOT NULL
+);
+--> +statement
+COMMENT ON TABLE "one_time_token" IS 'Outstanding single-use tokens sent out in links, by jti';
@@ -1,3 +1,6 @@
+use std::net::IpAddr;
+
+use crate::domain::{OAuthClient, Role, TokenBucket};
 use crate::services::email::EmailConfig;
 
 #[derive(serde::Deserialize, Debug, Clone)]
@@ -22,6 +25,17 @@ pub enum ServerEnv {
     Production,
 }
 
+/// What happens to accounts whose email address hasn't been confirmed yet
+#[derive(serde::Deserialize, PartialEq, Eq, Clone, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailVerificationPolicy {
+    /// Login is refused until the address is verified
+    Block,
+    /// Login works, auth tokens carry `email_verified: false`
+    #[default]
+    Flag,
+}
+
 #[derive(serde::Deserialize, Debug, Clone)]
 pub struct ServerConfig {
     #[serde(default = "default_server_host")]
@@ -33,6 +47,11 @@ pub struct ServerConfig {
     // Extra allowed origins for CORS
     #[serde(default = "default_allowed_origins")]
     pub allowed_origins: Option<Vec<String>>,
+
+    /// Reverse proxies whose `X-Forwarded-For` is believed. Requests from any other
+    /// address are taken to come from that address, whatever headers they carry.
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
 }
 
 impl ServerConfig {
@@ -47,6 +66,7 @@ impl Default for ServerConfig {
             host: default_server_host(),
             port: default_server_port(),
             allowed_origins: default_allowed_origins(),
+            trusted_proxies: Vec::new(),
         }
     }
 }
@@ -58,6 +78,37 @@ pub struct AppConfig {
 
     #[serde(default = "default_auth_redirect_url")]
     pub two_factor_redirect_url: String,
+
+    /// Login page, OAuth authorization requests without a session are sent here as `{url}?return_to=...`
+    #[serde(default = "default_login_url")]
+    pub login_url: String,
+
+    #[serde(default = "default_magic_link_redirect_url")]
+    pub magic_link_redirect_url: String,
+
+    /// Page that collects the new password, linked from reset emails as `{url}?token=...`
+    #[serde(default = "default_password_reset_redirect_url")]
+    pub password_reset_redirect_url: String,
+
+    /// Page that confirms an address, linked from verification emails as `{url}?token=...`
+    #[serde(default = "default_email_verification_redirect_url")]
+    pub email_verification_redirect_url: String,
+
+    /// Page that confirms a new address, linked from email change emails as `{url}?token=...`
+    #[serde(default = "default_email_change_redirect_url")]
+    pub email_change_redirect_url: String,
+
+    /// Page that reverts an address change, linked from the notice sent to the old address
+    #[serde(default = "default_email_change_undo_redirect_url")]
+    pub email_change_undo_redirect_url: String,
+
+    /// Page that shows an organization invitation, linked from invitation emails as `{url}?id=...`
+    #[serde(default = "default_org_invitation_redirect_url")]
+    pub org_invitation_redirect_url: String,
+
+    /// Page that lifts a lockout, linked from unlock emails as `{url}?token=...`
+    #[serde(default = "default_account_unlock_redirect_url")]
+    pub account_unlock_redirect_url: String,
 }
 
 impl Default for AppConfig {
@@ -65,6 +116,380 @@ impl Default for AppConfig {
         Self {
             url: default_app_url(),
             two_factor_redirect_url: default_auth_redirect_url(),
+            login_url: default_login_url(),
+            magic_link_redirect_url: default_magic_link_redirect_url(),
+            password_reset_redirect_url: default_password_reset_redirect_url(),
+            email_verification_redirect_url: default_email_verification_redirect_url(),
+            email_change_redirect_url: default_email_change_redirect_url(),
+            email_change_undo_redirect_url: default_email_change_undo_redirect_url(),
+            org_invitation_redirect_url: default_org_invitation_redirect_url(),
+            account_unlock_redirect_url: default_account_unlock_redirect_url(),
+        }
+    }
+}
+
+/// Settings for the authentication flows themselves
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct AuthConfig {
+    /// Lifetime of a magic link in seconds
+    #[serde(default = "default_magic_link_ttl")]
+    pub magic_link_ttl: u64,
+
+    /// Lifetime of a pending passkey registration/authentication ceremony in seconds
+    #[serde(default = "default_passkey_ceremony_ttl")]
+    pub passkey_ceremony_ttl: u64,
+
+    /// Wrong second factor codes that end a login attempt, logging in again starts a new one
+    #[serde(default = "default_two_factor_max_attempts")]
+    pub two_factor_max_attempts: u32,
+
+    /// Where logins waiting for a second factor are kept. Unset, they go to the
+    /// database when one is connected and to memory otherwise.
+    #[serde(default)]
+    pub two_factor_backend: Option<StoreBackend>,
+
+    /// Where banned tokens and token versions are kept. They have to survive restarts
+    /// and reach every replica for revocation to stick, unset they go to the database
+    /// when one is connected and to memory otherwise.
+    #[serde(default)]
+    pub revocation_backend: Option<StoreBackend>,
+
+    /// Where the tokens behind magic, password reset, verification and email change
+    /// links are kept. Unset, they go to the database when one is connected and to
+    /// memory otherwise.
+    #[serde(default)]
+    pub one_time_token_backend: Option<StoreBackend>,
+
+    /// Number of 30s time steps either side of now in which a TOTP code is still accepted
+    #[serde(default = "default_totp_skew")]
+    pub totp_skew: u8,
+
+    /// Issuer label shown in authenticator apps
+    #[serde(default = "default_totp_issuer")]
+    pub totp_issuer: String,
+
+    /// How many recovery codes are issued when 2FA is enabled
+    #[serde(default = "default_recovery_code_count")]
+    pub recovery_code_count: usize,
+
+    /// Lifetime of a refresh token in seconds. Each rotation starts a new lifetime.
+    #[serde(default = "default_refresh_token_ttl")]
+    pub refresh_token_ttl: u64,
+
+    /// Lifetime of a password reset link in seconds
+    #[serde(default = "default_password_reset_ttl")]
+    pub password_reset_ttl: u64,
+
+    #[serde(default)]
+    pub email_verification: EmailVerificationPolicy,
+
+    /// Lifetime of an email verification link in seconds
+    #[serde(default = "default_email_verification_ttl")]
+    pub email_verification_ttl: u64,
+
+    /// How many verification emails can be requested per address within `verification_resend_window`
+    #[serde(default = "default_verification_resend_limit")]
+    pub verification_resend_limit: u32,
+
+    /// In seconds
+    #[serde(default = "default_verification_resend_window")]
+    pub verification_resend_window: u64,
+
+    /// Lifetime of the confirmation link sent to a new address in seconds
+    #[serde(default = "default_email_change_ttl")]
+    pub email_change_ttl: u64,
+
+    /// Lifetime of the undo link sent to the old address in seconds.
+    /// Outlives the confirmation so a hijacked account can still be taken back.
+    #[serde(default = "default_email_change_undo_ttl")]
+    pub email_change_undo_ttl: u64,
+
+    /// Seconds between a deletion request and the account being purged
+    #[serde(default = "default_account_deletion_grace_period")]
+    pub account_deletion_grace_period: u64,
+
+    /// How often the purge task looks for accounts past their grace period, in seconds
+    #[serde(default = "default_account_purge_interval")]
+    pub account_purge_interval: u64,
+
+    /// Lifetime of an invitation to join an organization in seconds
+    #[serde(default = "default_org_invitation_ttl")]
+    pub org_invitation_ttl: u64,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            magic_link_ttl: default_magic_link_ttl(),
+            passkey_ceremony_ttl: default_passkey_ceremony_ttl(),
+            two_factor_max_attempts: default_two_factor_max_attempts(),
+            two_factor_backend: None,
+            revocation_backend: None,
+            one_time_token_backend: None,
+            totp_skew: default_totp_skew(),
+            totp_issuer: default_totp_issuer(),
+            recovery_code_count: default_recovery_code_count(),
+            refresh_token_ttl: default_refresh_token_ttl(),
+            password_reset_ttl: default_password_reset_ttl(),
+            email_verification: EmailVerificationPolicy::default(),
+            email_verification_ttl: default_email_verification_ttl(),
+            verification_resend_limit: default_verification_resend_limit(),
+            verification_resend_window: default_verification_resend_window(),
+            email_change_ttl: default_email_change_ttl(),
+            email_change_undo_ttl: default_email_change_undo_ttl(),
+            account_deletion_grace_period: default_account_deletion_grace_period(),
+            account_purge_interval: default_account_purge_interval(),
+            org_invitation_ttl: default_org_invitation_ttl(),
+        }
+    }
+}
+
+/// Locking accounts after repeated failed password logins
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct LockoutConfig {
+    /// Failed logins within `window` that lock the account, 0 turns lockouts off
+    #[serde(default = "default_lockout_threshold")]
+    pub threshold: u32,
+
+    /// In seconds
+    #[serde(default = "default_lockout_window")]
+    pub window: u64,
+
+    /// Length of the first lockout in seconds, doubled for each lockout in a row
+    #[serde(default = "default_lockout_duration")]
+    pub duration: u64,
+
+    /// Longest a lockout can get in seconds
+    #[serde(default = "default_lockout_max_duration")]
+    pub max_duration: u64,
+
+    /// Email the owner a link that lifts the lock
+    #[serde(default = "default_false")]
+    pub unlock_by_email: bool,
+
+    /// Where failures are counted, `memory` or `redis` to share the count across replicas
+    #[serde(default)]
+    pub backend: StoreBackend,
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        Self {
+            threshold: default_lockout_threshold(),
+            window: default_lockout_window(),
+            duration: default_lockout_duration(),
+            max_duration: default_lockout_max_duration(),
+            unlock_by_email: false,
+            backend: StoreBackend::default(),
+        }
+    }
+}
+
+/// What rate limited requests are counted by
+#[derive(serde::Deserialize, PartialEq, Eq, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKey {
+    /// Client address, requests without a known address aren't limited
+    Ip,
+    /// The `email` in the request body, falling back to the client address
+    Email,
+    /// Both, a request needs a token from each bucket
+    #[default]
+    IpAndEmail,
+}
+
+/// Where token buckets are kept
+#[derive(serde::Deserialize, PartialEq, Eq, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitBackend {
+    /// Per process, each replica counts on its own
+    #[default]
+    Memory,
+    /// Shared through the configured Redis, so limits hold across replicas
+    Redis,
+}
+
+/// Where a store of short lived state is kept. Not every store can use every backend,
+/// an unsupported choice fails at startup.
+#[derive(serde::Deserialize, PartialEq, Eq, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreBackend {
+    /// Per process, lost on restart and not seen by other replicas
+    #[default]
+    Memory,
+    /// In the database, which has to be connected
+    Postgres,
+    /// Shared through the configured Redis
+    Redis,
+}
+
+/// Token bucket limits on the endpoints open to guessing
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct RateLimitConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+
+    #[serde(default)]
+    pub key: RateLimitKey,
+
+    #[serde(default)]
+    pub backend: RateLimitBackend,
+
+    #[serde(default = "default_login_rate_limit")]
+    pub login: TokenBucket,
+
+    #[serde(default = "default_signup_rate_limit")]
+    pub signup: TokenBucket,
+
+    #[serde(default = "default_verify_2fa_rate_limit")]
+    pub verify_2fa: TokenBucket,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            key: RateLimitKey::default(),
+            backend: RateLimitBackend::default(),
+            login: default_login_rate_limit(),
+            signup: default_signup_rate_limit(),
+            verify_2fa: default_verify_2fa_rate_limit(),
+        }
+    }
+}
+
+/// OAuth 2.0 authorization server settings
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct OAuthConfig {
+    /// Public base url of this service
+    #[serde(default = "default_oauth_issuer")]
+    pub issuer: String,
+
+    /// Lifetime of an authorization code in seconds
+    #[serde(default = "default_authorization_code_ttl")]
+    pub authorization_code_ttl: u64,
+
+    /// Lifetime of access tokens issued to clients in seconds
+    #[serde(default = "default_oauth_access_token_ttl")]
+    pub access_token_ttl: u64,
+
+    /// Lifetime of a device authorization request in seconds, RFC 8628
+    #[serde(default = "default_device_code_ttl")]
+    pub device_code_ttl: u64,
+
+    /// Seconds a device has to wait between polls of the token endpoint
+    #[serde(default = "default_device_poll_interval")]
+    pub device_poll_interval: u64,
+
+    /// Where pending device authorizations are kept, `memory` or `redis` so a device
+    /// can be approved and polled through any replica
+    #[serde(default)]
+    pub device_code_backend: StoreBackend,
+
+    /// Clients registered at startup
+    #[serde(default)]
+    pub clients: Vec<OAuthClient>,
+}
+
+impl Default for OAuthConfig {
+    fn default() -> Self {
+        Self {
+            issuer: default_oauth_issuer(),
+            authorization_code_ttl: default_authorization_code_ttl(),
+            access_token_ttl: default_oauth_access_token_ttl(),
+            device_code_ttl: default_device_code_ttl(),
+            device_poll_interval: default_device_poll_interval(),
+            device_code_backend: StoreBackend::default(),
+            clients: Vec::new(),
+        }
+    }
+}
+
+/// Role based access control
+#[derive(serde::Deserialize, Default, Debug, Clone)]
+pub struct RbacConfig {
+    /// Roles created at startup, existing roles are left as they are
+    #[serde(default)]
+    pub roles: Vec<Role>,
+
+    /// Emails given the `admin` role at startup, they need not have signed up yet
+    #[serde(default)]
+    pub admins: Vec<String>,
+}
+
+/// Upstream OpenID Connect provider users can sign in with
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct IdentityProviderConfig {
+    /// Used in the login and callback paths, `/federated/{id}/login`
+    pub id: String,
+    /// Shown on the sign in button
+    pub name: String,
+    /// Endpoints are discovered from `{issuer}/.well-known/openid-configuration`
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    #[serde(default = "default_identity_provider_scopes")]
+    pub scopes: Vec<String>,
+}
+
+/// Sign in through upstream identity providers
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct FederationConfig {
+    /// How long a login started at a provider can take to come back, in seconds
+    #[serde(default = "default_federated_login_ttl")]
+    pub login_ttl: u64,
+
+    /// Holds the state of the login in flight between redirect and callback
+    #[serde(default = "default_federated_login_cookie_name")]
+    pub cookie_name: String,
+
+    /// How long a provider's discovery document and signing keys are reused before
+    /// being fetched again, in seconds
+    #[serde(default = "default_federated_discovery_ttl")]
+    pub discovery_ttl: u64,
+
+    #[serde(default)]
+    pub providers: Vec<IdentityProviderConfig>,
+}
+
+impl FederationConfig {
+    pub fn provider(&self, id: &str) -> Option<&IdentityProviderConfig> {
+        self.providers.iter().find(|p| p.id == id)
+    }
+}
+
+impl Default for FederationConfig {
+    fn default() -> Self {
+        Self {
+            login_ttl: default_federated_login_ttl(),
+            cookie_name: default_federated_login_cookie_name(),
+            discovery_ttl: default_federated_discovery_ttl(),
+            providers: Vec::new(),
+        }
+    }
+}
+
+/// WebAuthn relying party settings
+#[derive(serde::Deserialize, Debug, Clone)]
+pub struct WebAuthnConfig {
+    /// Effective domain the passkeys are scoped to
+    #[serde(default = "default_rp_id")]
+    pub rp_id: String,
+
+    /// Origin the browser performs the ceremony from
+    #[serde(default = "default_rp_origin")]
+    pub rp_origin: String,
+
+    #[serde(default = "default_rp_name")]
+    pub rp_name: String,
+}
+
+impl Default for WebAuthnConfig {
+    fn default() -> Self {
+        Self {
+            rp_id: default_rp_id(),
+            rp_origin: default_rp_origin(),
+            rp_name: default_rp_name(),
         }
     }
 }
@@ -99,12 +524,21 @@ impl JwtKeySecret {
 pub struct JwtConfig {
     pub cookie_name: String,
     pub secret: JwtKeySecret,
+
+    /// Lifetime of access tokens in seconds
+    #[serde(default = "default_access_token_ttl")]
+    pub access_token_ttl: u64,
+
+    #[serde(default = "default_refresh_cookie_name")]
+    pub refresh_cookie_name: String,
 }
 
 impl Default for JwtConfig {
     fn default() -> Self {
         JwtConfig {
             cookie_name: "jwt_auth_token".to_string(),
+            access_token_ttl: default_access_token_ttl(),
+            refresh_cookie_name: default_refresh_cookie_name(),
             secret: JwtKeySecret::RSA {
                 pub_key: "tests/jwt-test-rsa.pub".to_string(),
                 priv_key: "tests/jwt-test-rsa.pem".to_string(),
@@ -138,9 +572,11 @@ pub struct RedisConfig {
     #[serde(default = "default_redis_port")]
     pub port: Option<String>,
 
+    /// How long a banned token stays banned in seconds, whichever store keeps it
     #[serde(default = "default_redis_ttl")]
     pub ttl_ban: u64,
 
+    /// Lifetime of a pending second factor check in seconds, whichever store keeps it
     #[serde(default = "default_redis_ttl")]
     pub ttl_2fa: u64,
 }
@@ -196,6 +632,27 @@ pub struct Config {
 
     #[serde(default = "AppConfig::default")]
     pub app: AppConfig,
+
+    #[serde(default = "AuthConfig::default")]
+    pub auth: AuthConfig,
+
+    #[serde(default = "WebAuthnConfig::default")]
+    pub webauthn: WebAuthnConfig,
+
+    #[serde(default = "OAuthConfig::default")]
+    pub oauth: OAuthConfig,
+
+    #[serde(default = "FederationConfig::default")]
+    pub federation: FederationConfig,
+
+    #[serde(default = "RbacConfig::default")]
+    pub rbac: RbacConfig,
+
+    #[serde(default = "LockoutConfig::default")]
+    pub lockout: LockoutConfig,
+
+    #[serde(default = "RateLimitConfig::default")]
+    pub rate_limit: RateLimitConfig,
 }
 
 fn default_database_url() -> Option<String> {
@@ -218,9 +675,9 @@ fn default_false() -> bool {
     false
 }
 
-// fn default_true() -> bool {
-//     true
-// }
+fn default_true() -> bool {
+    true
+}
 
 fn default_auth_redirect_url() -> String {
     "http://localhost:5173/login/2fa".to_string()
@@ -229,3 +686,196 @@ fn default_auth_redirect_url() -> String {
 fn default_app_url() -> String {
     "http://localhost:5173".to_string()
 }
+
+fn default_login_url() -> String {
+    "http://localhost:5173/login".to_string()
+}
+
+fn default_magic_link_redirect_url() -> String {
+    "http://localhost:5173/login/magic-link".to_string()
+}
+
+fn default_magic_link_ttl() -> u64 {
+    900
+}
+
+fn default_password_reset_redirect_url() -> String {
+    "http://localhost:5173/password/reset".to_string()
+}
+
+fn default_password_reset_ttl() -> u64 {
+    1800
+}
+
+fn default_email_verification_redirect_url() -> String {
+    "http://localhost:5173/verify-email".to_string()
+}
+
+fn default_email_verification_ttl() -> u64 {
+    60 * 60 * 24
+}
+
+fn default_verification_resend_limit() -> u32 {
+    3
+}
+
+fn default_verification_resend_window() -> u64 {
+    3600
+}
+
+fn default_email_change_redirect_url() -> String {
+    "http://localhost:5173/email/change".to_string()
+}
+
+fn default_email_change_undo_redirect_url() -> String {
+    "http://localhost:5173/email/change/undo".to_string()
+}
+
+fn default_org_invitation_redirect_url() -> String {
+    "http://localhost:5173/orgs/invitation".to_string()
+}
+
+fn default_account_unlock_redirect_url() -> String {
+    "http://localhost:5173/login/unlock".to_string()
+}
+
+fn default_email_change_ttl() -> u64 {
+    3600
+}
+
+fn default_email_change_undo_ttl() -> u64 {
+    60 * 60 * 24 * 7
+}
+
+fn default_org_invitation_ttl() -> u64 {
+    60 * 60 * 24 * 7
+}
+
+fn default_lockout_threshold() -> u32 {
+    5
+}
+
+fn default_lockout_window() -> u64 {
+    900
+}
+
+fn default_lockout_duration() -> u64 {
+    300
+}
+
+fn default_lockout_max_duration() -> u64 {
+    60 * 60 * 24
+}
+
+fn default_login_rate_limit() -> TokenBucket {
+    TokenBucket {
+        capacity: 10,
+        period: 60,
+    }
+}
+
+fn default_signup_rate_limit() -> TokenBucket {
+    TokenBucket {
+        capacity: 5,
+        period: 3600,
+    }
+}
+
+fn default_verify_2fa_rate_limit() -> TokenBucket {
+    TokenBucket {
+        capacity: 5,
+        period: 60,
+    }
+}
+
+fn default_account_deletion_grace_period() -> u64 {
+    60 * 60 * 24 * 30
+}
+
+fn default_account_purge_interval() -> u64 {
+    3600
+}
+
+fn default_passkey_ceremony_ttl() -> u64 {
+    300
+}
+
+fn default_two_factor_max_attempts() -> u32 {
+    5
+}
+
+fn default_totp_skew() -> u8 {
+    1
+}
+
+fn default_totp_issuer() -> String {
+    "LGR Auth".to_string()
+}
+
+fn default_recovery_code_count() -> usize {
+    10
+}
+
+fn default_refresh_token_ttl() -> u64 {
+    60 * 60 * 24 * 30
+}
+
+fn default_access_token_ttl() -> u64 {
+    900
+}
+
+fn default_refresh_cookie_name() -> String {
+    "refresh_token".to_string()
+}
+
+fn default_oauth_issuer() -> String {
+    "http://localhost:3000".to_string()
+}
+
+fn default_authorization_code_ttl() -> u64 {
+    60
+}
+
+fn default_oauth_access_token_ttl() -> u64 {
+    3600
+}
+
+fn default_device_code_ttl() -> u64 {
+    600
+}
+
+fn default_device_poll_interval() -> u64 {
+    5
+}
+
+fn default_identity_provider_scopes() -> Vec<String> {
+    vec![
+        "openid".to_string(),
+        "email".to_string(),
+        "profile".to_string(),
+    ]
+}
+
+fn default_federated_login_ttl() -> u64 {
+    600
+}
+
+fn default_federated_login_cookie_name() -> String {
+    "federated_login".to_string()
+}
+
+fn default_federated_discovery_ttl() -> u64 {
+    3600
+}
+
+fn default_rp_id() -> String {
+    "localhost".to_string()
+}
+
+fn default_rp_origin() -> String {
+    "http://localhost:5173".to_string()
+}
+
+fn default_rp_name() -> String {
+    "LGR Auth".to_string()
+}
@@ -0,0 +1,132 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::Email;
+use crate::error::AuthApiError;
+
+/// Marks a bearer token as an API key rather than a JWT
+pub const API_KEY_PREFIX: &str = "lgr_";
+
+/// Personal access token handed to the user once.
+///
+/// Only its SHA-256 hash is stored, so a leaked store can't be replayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKey(String);
+
+impl ApiKey {
+    /// 256 bits of randomness, base64url encoded behind `API_KEY_PREFIX`
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::rng().fill_bytes(&mut bytes);
+        ApiKey(format!("{API_KEY_PREFIX}{}", URL_SAFE_NO_PAD.encode(bytes)))
+    }
+
+    pub fn parse(value: &str) -> Result<Self, AuthApiError> {
+        let encoded = value
+            .strip_prefix(API_KEY_PREFIX)
+            .ok_or(AuthApiError::InvalidToken)?;
+        match URL_SAFE_NO_PAD.decode(encoded) {
+            Ok(bytes) if bytes.len() == 32 => Ok(ApiKey(value.to_string())),
+            _ => Err(AuthApiError::InvalidToken),
+        }
+    }
+
+    /// Whether `value` looks like an API key, without checking it is well formed
+    pub fn is_api_key(value: &str) -> bool {
+        value.starts_with(API_KEY_PREFIX)
+    }
+
+    /// Hex encoded SHA-256 of the key, used as the store key
+    pub fn hash(&self) -> String {
+        Sha256::digest(self.0.as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+}
+
+impl AsRef<str> for ApiKey {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A stored API key, everything but the key itself
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ApiKeyRecord {
+    pub id: Uuid,
+    #[serde(skip)]
+    pub key_hash: String,
+    #[schema(value_type = String)]
+    pub email: Email,
+    /// Chosen by the user, unique per user
+    pub name: String,
+    /// Empty for keys acting with everything the user can do
+    pub scopes: Vec<String>,
+    /// Unix timestamp in seconds
+    pub created_at: i64,
+    /// Unix timestamp in seconds, `None` for keys that don't expire
+    pub expires_at: Option<i64>,
+}
+
+impl ApiKeyRecord {
+    /// `ttl` in seconds, `None` for a key that doesn't expire
+    pub fn new(
+        key: &ApiKey,
+        email: &Email,
+        name: &str,
+        scopes: &[String],
+        ttl: Option<u64>,
+    ) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        ApiKeyRecord {
+            id: Uuid::new_v4(),
+            key_hash: key.hash(),
+            email: email.clone(),
+            name: name.to_string(),
+            scopes: scopes.to_vec(),
+            created_at: now,
+            expires_at: ttl.map(|ttl| now + ttl as i64),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|t| t <= chrono::Utc::now().timestamp())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_key_parse() {
+        let key = ApiKey::generate();
+        assert!(ApiKey::is_api_key(key.as_ref()));
+        assert_eq!(ApiKey::parse(key.as_ref()).unwrap(), key);
+        assert!(ApiKey::parse("lgr_short").is_err());
+        // a refresh token has the same shape without the prefix
+        let unprefixed = key.as_ref().trim_start_matches(API_KEY_PREFIX);
+        assert!(ApiKey::parse(unprefixed).is_err());
+    }
+
+    #[test]
+    fn test_api_key_record_expiry() {
+        let key = ApiKey::generate();
+        let email = Email::parse("api-key@test.com").unwrap();
+        let record = ApiKeyRecord::new(&key, &email, "ci", &[], None);
+        assert!(!record.is_expired());
+        assert_eq!(record.key_hash, key.hash());
+        let expired = ApiKeyRecord {
+            expires_at: Some(record.created_at - 1),
+            ..record
+        };
+        assert!(expired.is_expired());
+    }
+}
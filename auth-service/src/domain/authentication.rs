@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Authentication method reference values (`amr`), RFC 8176
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMethod {
+    /// Password
+    Pwd,
+    /// One-time code, emailed, TOTP or recovery code
+    Otp,
+    /// Proof of possession of a hardware bound key, i.e. a passkey
+    Hwk,
+    /// More than one factor was used
+    Mfa,
+    /// Link sent to the account address. Not registered in RFC 8176, nothing there fits.
+    Email,
+    /// Delegated to an upstream identity provider. Not registered in RFC 8176 either.
+    Fed,
+}
+
+/// When and how the user proved who they are.
+///
+/// Set once at login and carried unchanged through refreshes, so tokens issued
+/// later in the session still describe the original authentication.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Authentication {
+    /// Unix timestamp of the login
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_time: Option<i64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub amr: Vec<AuthMethod>,
+}
+
+impl Authentication {
+    /// A login that just completed with `methods`
+    pub fn now(methods: &[AuthMethod]) -> Self {
+        Self {
+            auth_time: Some(chrono::Utc::now().timestamp()),
+            amr: methods.to_vec(),
+        }
+    }
+
+    /// Space delimited `amr` values, the way they are stored
+    pub fn amr_string(&self) -> String {
+        self.amr
+            .iter()
+            .map(|m| serde_json::to_value(m).ok())
+            .filter_map(|v| v.and_then(|v| v.as_str().map(str::to_string)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Inverse of `amr_string`, unknown values are dropped
+    pub fn parse_amr(amr: &str) -> Vec<AuthMethod> {
+        amr.split(' ')
+            .filter_map(|m| serde_json::from_value(serde_json::Value::from(m)).ok())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_amr_round_trip() {
+        let auth = Authentication::now(&[AuthMethod::Pwd, AuthMethod::Otp, AuthMethod::Mfa]);
+        assert_eq!(auth.amr_string(), "pwd otp mfa");
+        assert_eq!(Authentication::parse_amr(&auth.amr_string()), auth.amr);
+        assert!(Authentication::parse_amr("").is_empty());
+        assert_eq!(
+            Authentication::parse_amr("hwk unknown"),
+            vec![AuthMethod::Hwk]
+        );
+    }
+}
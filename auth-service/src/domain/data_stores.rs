@@ -1,10 +1,49 @@
-use crate::domain::{Email, LoginAttemptId, Password, TwoFactorCode, TwoFactorMethod, User};
+use crate::config::LockoutConfig;
+use crate::domain::{
+    ApiKeyRecord, DeviceAuthorization, DeviceAuthorizationStatus, DevicePoll, Email, FailedLogins,
+    FederatedIdentity, Invitation, LoginAttemptId, Membership, OAuthClient, OneTimeTokenId,
+    OrgRole, Organization, PasskeyCeremony, PasskeyCeremonyId, PasskeyCredential, Password,
+    RecoveryCode, RefreshToken, RefreshTokenRecord, Role, Session, StoredRecoveryCode, TokenBucket,
+    TokenPurpose, TotpSecret, TwoFactorCode, TwoFactorMethod, User, UserCode,
+};
 use crate::error::AuthApiError;
 
 #[async_trait::async_trait]
 pub trait UserStore: Send + Sync + std::fmt::Debug {
     async fn add_user(&mut self, user: User) -> Result<(), AuthApiError>;
     async fn get_user(&self, email: &Email) -> Result<User, AuthApiError>;
+    async fn set_two_factor(
+        &mut self,
+        email: &Email,
+        two_factor: &TwoFactorMethod,
+    ) -> Result<(), AuthApiError>;
+    /// Overwrite the stored record for `user.email`
+    async fn update_user(&mut self, user: &User) -> Result<(), AuthApiError>;
+    /// Re-key the user from `old` to `new` in one step, returns the moved record.
+    /// Fails with `UserAlreadyExists` if `new` is taken.
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<User, AuthApiError>;
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError>;
+    /// Accounts whose deletion was requested at or before `requested_before` (unix timestamp)
+    async fn users_pending_deletion(
+        &self,
+        requested_before: i64,
+    ) -> Result<Vec<Email>, AuthApiError>;
+    /// Users whose email contains `search`, ignoring case, ordered by email.
+    /// An empty `search` matches everyone.
+    async fn search_users(
+        &self,
+        search: &str,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<User>, AuthApiError>;
+    /// Number of users `search_users` pages through for `search`
+    async fn count_users(&self, search: &str) -> Result<u64, AuthApiError>;
+    /// Lock the account as of `locked_at` (unix timestamp), `None` unlocks it
+    async fn set_locked(
+        &mut self,
+        email: &Email,
+        locked_at: Option<i64>,
+    ) -> Result<(), AuthApiError>;
     async fn validate_credentials(
         &self,
         email: &Email,
@@ -22,15 +61,44 @@ pub trait UserStore: Send + Sync + std::fmt::Debug {
     }
 }
 
+/// Changes spanning every store keyed by an account's address, for when those stores
+/// share a database and can be changed in one transaction
+#[async_trait::async_trait]
+pub trait AccountStore: Send + Sync + std::fmt::Debug {
+    /// Re-key the user and everything stored against it from `old` to `new` in one
+    /// step, returns the moved record. Fails with `UserAlreadyExists` if `new` is taken.
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<User, AuthApiError>;
+}
+
 #[async_trait::async_trait]
 pub trait BannedTokenStore: Send + Sync + std::fmt::Debug {
     async fn ban_token(&mut self, token: &str) -> Result<(), AuthApiError>;
+    /// Ban for `ttl` seconds instead of the configured default, e.g. the token's remaining lifetime
+    async fn ban_token_for(&mut self, token: &str, ttl: u64) -> Result<(), AuthApiError>;
     async fn unban_token(&mut self, token: &str) -> Result<(), AuthApiError>;
-    async fn is_token_banned(&self, token: &str) -> bool;
+    /// Fails rather than answer `false` when the store can't be reached
+    async fn is_token_banned(&self, token: &str) -> Result<bool, AuthApiError>;
+
+    /// Current token version for the user, auth tokens carrying an older one are rejected.
+    /// Fails rather than answer 0 when the store can't be reached.
+    async fn token_version(&self, email: &Email) -> Result<u64, AuthApiError>;
+
+    /// Revoke every auth token issued to the user so far, returns the new version
+    async fn bump_token_version(&mut self, email: &Email) -> Result<u64, AuthApiError>;
+
+    /// Forget the token version of a purged account. Only safe once every token
+    /// issued to it has expired, otherwise they would pass the version check again.
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError>;
 }
 
+/// The second factor check pending for each user's login in flight.
+///
+/// Wrong guesses count against the attempt, which is dropped after `max_attempts` of them.
+/// Answers for another attempt id are refused without counting, so knowing an email
+/// isn't enough to cancel someone's login.
 #[async_trait::async_trait]
 pub trait TwoFactorCodeStore: Send + Sync + std::fmt::Debug {
+    /// Replaces any attempt pending for `email`
     async fn new_login_attempt(
         &mut self,
         email: &Email,
@@ -44,13 +112,445 @@ pub trait TwoFactorCodeStore: Send + Sync + std::fmt::Debug {
 
     async fn remove_code(&mut self, email: &Email) -> Result<(), AuthApiError>;
 
+    /// Check `code` against the attempt in one step: a match removes the attempt so the
+    /// code only works once, a miss counts against it.
+    /// Fails with `TwoFactorCodeNotFound` if no attempt is pending.
     async fn verify_code(
-        &self,
+        &mut self,
         email: &Email,
         attempt_id: &LoginAttemptId,
         code: &TwoFactorCode,
-    ) -> Result<bool, AuthApiError> {
-        let (id, stored_code) = self.get_code(email).await?;
-        Ok(stored_code == *code && id == *attempt_id)
+        max_attempts: u32,
+    ) -> Result<bool, AuthApiError>;
+
+    /// Count a wrong guess at a factor checked outside the store, e.g. a TOTP code
+    async fn record_failure(
+        &mut self,
+        email: &Email,
+        attempt_id: &LoginAttemptId,
+        max_attempts: u32,
+    ) -> Result<(), AuthApiError>;
+
+    /// Remove the attempt once a factor checked outside the store matched.
+    /// Fails with `TwoFactorCodeNotFound` unless it was still pending, so only one
+    /// request gets to complete it.
+    async fn consume_attempt(
+        &mut self,
+        email: &Email,
+        attempt_id: &LoginAttemptId,
+    ) -> Result<(), AuthApiError>;
+}
+
+/// Hashed single-use recovery codes for users with 2FA
+#[async_trait::async_trait]
+pub trait RecoveryCodeStore: Send + Sync + std::fmt::Debug {
+    /// Replace every code for `email` with `codes`
+    async fn replace_codes(
+        &mut self,
+        email: &Email,
+        codes: Vec<StoredRecoveryCode>,
+    ) -> Result<(), AuthApiError>;
+
+    async fn get_codes(&self, email: &Email) -> Result<Vec<StoredRecoveryCode>, AuthApiError>;
+
+    /// Fails with `TwoFactorCodeNotFound` if the code was already removed
+    async fn remove_code(&mut self, email: &Email, id: &uuid::Uuid) -> Result<(), AuthApiError>;
+
+    /// Move the codes of `old` over to `new`
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError>;
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError>;
+
+    /// Find the code matching `candidate` and remove it so it can't be used again
+    async fn consume_code(
+        &mut self,
+        email: &Email,
+        candidate: &RecoveryCode,
+    ) -> Result<(), AuthApiError> {
+        for stored in self.get_codes(email).await? {
+            if stored.hash.verify(candidate).await.is_ok() {
+                return self.remove_code(email, &stored.id).await;
+            }
+        }
+        Err(AuthApiError::TwoFactorCodeMismatch)
     }
 }
+
+/// Tracks outstanding single-use tokens (e.g. magic links).
+///
+/// The tokens themselves are signed JWTs; the store only remembers which ids
+/// are still redeemable so each one can be consumed exactly once.
+#[async_trait::async_trait]
+pub trait OneTimeTokenStore: Send + Sync + std::fmt::Debug {
+    async fn add_token(
+        &mut self,
+        id: &OneTimeTokenId,
+        purpose: &TokenPurpose,
+        email: &Email,
+        ttl: u64,
+    ) -> Result<(), AuthApiError>;
+
+    /// Removes the token and returns the email it was issued for.
+    /// Fails with `InvalidToken` if it was never issued, already used, or expired.
+    async fn consume_token(
+        &mut self,
+        id: &OneTimeTokenId,
+        purpose: &TokenPurpose,
+    ) -> Result<Email, AuthApiError>;
+
+    /// Drops every outstanding token issued to `email`, whatever its purpose
+    async fn revoke_tokens(&mut self, email: &Email) -> Result<(), AuthApiError>;
+}
+
+/// Registered WebAuthn credentials plus the state of ceremonies in progress
+#[async_trait::async_trait]
+pub trait PasskeyStore: Send + Sync + std::fmt::Debug {
+    async fn add_passkey(&mut self, credential: PasskeyCredential) -> Result<(), AuthApiError>;
+
+    async fn get_passkeys(&self, email: &Email) -> Result<Vec<PasskeyCredential>, AuthApiError>;
+
+    /// Persist the updated counter/flags of an existing credential
+    async fn update_passkey(&mut self, credential: &PasskeyCredential) -> Result<(), AuthApiError>;
+
+    /// Move every credential of `old` over to `new`
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError>;
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError>;
+
+    async fn add_ceremony(
+        &mut self,
+        id: &PasskeyCeremonyId,
+        ceremony: PasskeyCeremony,
+        ttl: u64,
+    ) -> Result<(), AuthApiError>;
+
+    /// Removes and returns the ceremony so a challenge can only be answered once
+    async fn take_ceremony(
+        &mut self,
+        id: &PasskeyCeremonyId,
+    ) -> Result<PasskeyCeremony, AuthApiError>;
+}
+
+/// Authenticator app secrets.
+///
+/// A freshly enrolled secret stays pending until a code generated from it is
+/// confirmed, so a half finished enrollment never locks the user out.
+#[async_trait::async_trait]
+pub trait TotpStore: Send + Sync + std::fmt::Debug {
+    /// Replaces any pending secret. An already active secret keeps working.
+    async fn set_pending_secret(
+        &mut self,
+        email: &Email,
+        secret: &TotpSecret,
+    ) -> Result<(), AuthApiError>;
+
+    async fn get_pending_secret(&self, email: &Email) -> Result<TotpSecret, AuthApiError>;
+
+    /// Promote the pending secret to the active one
+    async fn activate_secret(&mut self, email: &Email) -> Result<(), AuthApiError>;
+
+    async fn get_secret(&self, email: &Email) -> Result<TotpSecret, AuthApiError>;
+
+    /// Record the time step of an accepted code.
+    /// Fails if a code from this or a later step was already accepted (replay).
+    async fn use_step(&mut self, email: &Email, step: u64) -> Result<(), AuthApiError>;
+
+    /// Move the secrets of `old` over to `new`
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError>;
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError>;
+}
+
+/// Hashed refresh tokens, grouped into rotation families
+#[async_trait::async_trait]
+pub trait RefreshTokenStore: Send + Sync + std::fmt::Debug {
+    async fn add_token(&mut self, record: RefreshTokenRecord) -> Result<(), AuthApiError>;
+
+    /// Fails with `InvalidToken` if the token is unknown or expired
+    async fn get_token(&self, token_hash: &str) -> Result<RefreshTokenRecord, AuthApiError>;
+
+    /// Marks the presented token used and stores `next` in the same family.
+    ///
+    /// Presenting a token that was already rotated revokes the whole family
+    /// and fails with `RefreshTokenReuse`.
+    async fn rotate_token(
+        &mut self,
+        token_hash: &str,
+        next: &RefreshToken,
+        ttl: u64,
+    ) -> Result<RefreshTokenRecord, AuthApiError>;
+
+    /// Removes every token in the family
+    async fn revoke_family(&mut self, family_id: &uuid::Uuid) -> Result<(), AuthApiError>;
+
+    /// Removes every token issued to `email`
+    async fn revoke_user(&mut self, email: &Email) -> Result<(), AuthApiError>;
+}
+
+/// Request counters keyed by e.g. action and email
+#[async_trait::async_trait]
+pub trait RateLimitStore: Send + Sync + std::fmt::Debug {
+    /// Counts a hit against `key`. Fails with `TooManyRequests` once more than `limit`
+    /// hits land within `window` seconds.
+    async fn hit(&mut self, key: &str, limit: u32, window: u64) -> Result<(), AuthApiError>;
+    /// Takes a token from each of the buckets at `keys`, or from none of them. Fails with
+    /// `TooManyRequests` while any is empty, concurrent requests never share a token.
+    async fn take_tokens(
+        &mut self,
+        keys: &[String],
+        bucket: &TokenBucket,
+    ) -> Result<(), AuthApiError>;
+}
+
+/// Failed password logins per account, see `FailedLogins`
+#[async_trait::async_trait]
+pub trait FailedLoginStore: Send + Sync + std::fmt::Debug {
+    /// A default record if `email` has no recent failures
+    async fn get(&self, email: &Email) -> Result<FailedLogins, AuthApiError>;
+    /// Count a failure against `email` and return the updated record. The count is
+    /// updated atomically, so each of a burst of concurrent guesses sees its own.
+    async fn record_failure(
+        &mut self,
+        email: &Email,
+        config: &LockoutConfig,
+    ) -> Result<FailedLogins, AuthApiError>;
+    /// Forget the failures and lockouts of `email`
+    async fn clear(&mut self, email: &Email) -> Result<(), AuthApiError>;
+    /// Move the failures and lockouts of `old` over to `new`, replacing any of its own
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError>;
+}
+
+/// Registered OAuth clients
+#[async_trait::async_trait]
+pub trait ClientStore: Send + Sync + std::fmt::Debug {
+    /// Fails with `InvalidData` if the client id is taken
+    async fn add_client(&mut self, client: OAuthClient) -> Result<(), AuthApiError>;
+
+    /// Fails with `OAuth(InvalidClient)` for an unknown client id
+    async fn get_client(&self, client_id: &str) -> Result<OAuthClient, AuthApiError>;
+}
+
+/// Upstream identity provider accounts linked to local users
+#[async_trait::async_trait]
+pub trait IdentityStore: Send + Sync + std::fmt::Debug {
+    /// Fails with `InvalidData` if the provider account is already linked
+    async fn add_identity(&mut self, identity: FederatedIdentity) -> Result<(), AuthApiError>;
+
+    /// Fails with `UserNotFound` if the provider account isn't linked
+    async fn get_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<FederatedIdentity, AuthApiError>;
+
+    /// Move every identity linked to `old` over to `new`
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError>;
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError>;
+}
+
+/// Hashed personal API keys
+#[async_trait::async_trait]
+pub trait ApiKeyStore: Send + Sync + std::fmt::Debug {
+    /// Fails with `InvalidData` if the user already has a key with the same name
+    async fn add_key(&mut self, record: ApiKeyRecord) -> Result<(), AuthApiError>;
+
+    /// Fails with `InvalidToken` if the key is unknown or expired
+    async fn get_key(&self, key_hash: &str) -> Result<ApiKeyRecord, AuthApiError>;
+
+    /// Every key of `email`, expired ones included, oldest first
+    async fn list_keys(&self, email: &Email) -> Result<Vec<ApiKeyRecord>, AuthApiError>;
+
+    /// Fails with `ApiKeyNotFound` unless `email` has a key with that id
+    async fn revoke_key(&mut self, email: &Email, id: &uuid::Uuid) -> Result<(), AuthApiError>;
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError>;
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError>;
+}
+
+/// Device authorization requests waiting for a user, RFC 8628
+#[async_trait::async_trait]
+pub trait DeviceCodeStore: Send + Sync + std::fmt::Debug {
+    /// Kept for `retention` seconds, longer than the request lives so late polls can be
+    /// told it expired. Fails with `InvalidData` if the user code is already taken.
+    async fn add(
+        &mut self,
+        authorization: DeviceAuthorization,
+        retention: u64,
+    ) -> Result<(), AuthApiError>;
+
+    /// Fails with `InvalidToken` unless a pending, unexpired request has that user code
+    async fn get_by_user_code(&self, code: &UserCode) -> Result<DeviceAuthorization, AuthApiError>;
+
+    /// Settle a pending request, fails with `InvalidToken` unless it is pending and unexpired
+    async fn set_status(
+        &mut self,
+        code: &UserCode,
+        status: DeviceAuthorizationStatus,
+    ) -> Result<(), AuthApiError>;
+
+    /// Record a poll with `DeviceAuthorization::poll`. A final answer removes the
+    /// request, so it is given once. Fails with `InvalidToken`, leaving the request
+    /// untouched, for unknown codes and codes issued to another client.
+    async fn poll(
+        &mut self,
+        device_code_hash: &str,
+        client_id: &str,
+    ) -> Result<DevicePoll, AuthApiError>;
+
+    /// Drop the requests `email` approved that no device has collected yet
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError>;
+}
+
+/// Logins of each user, see `Session`
+#[async_trait::async_trait]
+pub trait SessionStore: Send + Sync + std::fmt::Debug {
+    async fn add_session(&mut self, session: Session) -> Result<(), AuthApiError>;
+
+    /// Fails with `InvalidToken` if the session is unknown, revoked or expired
+    async fn get_session(&self, id: &uuid::Uuid) -> Result<Session, AuthApiError>;
+
+    /// Live sessions of `email`, oldest first
+    async fn list_sessions(&self, email: &Email) -> Result<Vec<Session>, AuthApiError>;
+
+    /// Record activity at `last_seen_at`, `expires_at` is only moved when given.
+    /// Fails with `InvalidToken` if the session is gone.
+    async fn touch_session(
+        &mut self,
+        id: &uuid::Uuid,
+        last_seen_at: i64,
+        expires_at: Option<i64>,
+    ) -> Result<(), AuthApiError>;
+
+    /// Fails with `SessionNotFound` unless `email` has a session with that id
+    async fn revoke_session(&mut self, email: &Email, id: &uuid::Uuid) -> Result<(), AuthApiError>;
+
+    /// Switch the organization tokens of the session are issued for, `None` for none.
+    /// Fails with `InvalidToken` if the session is gone.
+    async fn set_active_org(
+        &mut self,
+        id: &uuid::Uuid,
+        org_id: Option<uuid::Uuid>,
+    ) -> Result<(), AuthApiError>;
+
+    /// Removes every session of `email`
+    async fn revoke_user(&mut self, email: &Email) -> Result<(), AuthApiError>;
+}
+
+/// Roles and who they are assigned to, see `Role`
+#[async_trait::async_trait]
+pub trait RoleStore: Send + Sync + std::fmt::Debug {
+    /// Fails with `InvalidData` if a role with that name exists
+    async fn add_role(&mut self, role: Role) -> Result<(), AuthApiError>;
+
+    /// Fails with `RoleNotFound` if there is no role with that name
+    async fn get_role(&self, name: &str) -> Result<Role, AuthApiError>;
+
+    /// Every role, by name
+    async fn list_roles(&self) -> Result<Vec<Role>, AuthApiError>;
+
+    /// Overwrite the description and permissions, fails with `RoleNotFound` if it doesn't exist
+    async fn update_role(&mut self, role: &Role) -> Result<(), AuthApiError>;
+
+    /// Removes the role from everyone it was assigned to.
+    /// Fails with `RoleNotFound` if it doesn't exist.
+    async fn delete_role(&mut self, name: &str) -> Result<(), AuthApiError>;
+
+    /// Assigning a role twice is a no-op. Fails with `RoleNotFound` if it doesn't exist.
+    async fn assign_role(&mut self, email: &Email, name: &str) -> Result<(), AuthApiError>;
+
+    /// Fails with `RoleNotFound` unless `email` has the role
+    async fn unassign_role(&mut self, email: &Email, name: &str) -> Result<(), AuthApiError>;
+
+    /// Roles assigned to `email`, by name
+    async fn user_roles(&self, email: &Email) -> Result<Vec<Role>, AuthApiError>;
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError>;
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError>;
+}
+
+/// Organizations and their members, see `Organization`
+#[async_trait::async_trait]
+pub trait OrganizationStore: Send + Sync + std::fmt::Debug {
+    /// Adds the organization with `owner` as its first member
+    async fn add_org(&mut self, org: Organization, owner: &Email) -> Result<(), AuthApiError>;
+
+    /// Fails with `OrganizationNotFound` if it doesn't exist
+    async fn get_org(&self, id: &uuid::Uuid) -> Result<Organization, AuthApiError>;
+
+    /// Fails with `OrganizationNotFound` if it doesn't exist
+    async fn rename_org(&mut self, id: &uuid::Uuid, name: &str) -> Result<(), AuthApiError>;
+
+    /// Removes the members along with it. Fails with `OrganizationNotFound` if it doesn't exist.
+    async fn delete_org(&mut self, id: &uuid::Uuid) -> Result<(), AuthApiError>;
+
+    /// Fails with `InvalidData` if `email` is a member already
+    async fn add_member(&mut self, membership: Membership) -> Result<(), AuthApiError>;
+
+    /// Fails with `OrganizationNotFound` unless `email` is a member, so non-members
+    /// can't tell which organizations exist
+    async fn get_membership(
+        &self,
+        org_id: &uuid::Uuid,
+        email: &Email,
+    ) -> Result<Membership, AuthApiError>;
+
+    /// Members of the organization, oldest first
+    async fn list_members(&self, org_id: &uuid::Uuid) -> Result<Vec<Membership>, AuthApiError>;
+
+    /// Organizations `email` is a member of, oldest membership first
+    async fn list_memberships(&self, email: &Email) -> Result<Vec<Membership>, AuthApiError>;
+
+    /// Fails with `OrganizationNotFound` unless `email` is a member
+    async fn set_member_role(
+        &mut self,
+        org_id: &uuid::Uuid,
+        email: &Email,
+        role: OrgRole,
+    ) -> Result<(), AuthApiError>;
+
+    /// Fails with `OrganizationNotFound` unless `email` is a member
+    async fn remove_member(
+        &mut self,
+        org_id: &uuid::Uuid,
+        email: &Email,
+    ) -> Result<(), AuthApiError>;
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError>;
+
+    /// Removes every membership of `email`
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError>;
+}
+
+/// Pending invitations to join an organization, see `Invitation`
+#[async_trait::async_trait]
+pub trait InvitationStore: Send + Sync + std::fmt::Debug {
+    /// Replaces an earlier invitation of the same address to the same organization
+    async fn add_invitation(&mut self, invitation: Invitation) -> Result<(), AuthApiError>;
+
+    /// Fails with `InvitationNotFound` if it is unknown or expired
+    async fn get_invitation(&self, id: &uuid::Uuid) -> Result<Invitation, AuthApiError>;
+
+    /// Unexpired invitations to the organization, oldest first
+    async fn list_org_invitations(
+        &self,
+        org_id: &uuid::Uuid,
+    ) -> Result<Vec<Invitation>, AuthApiError>;
+
+    /// Unexpired invitations addressed to `email`, oldest first
+    async fn list_user_invitations(&self, email: &Email) -> Result<Vec<Invitation>, AuthApiError>;
+
+    /// Fails with `InvitationNotFound` if it doesn't exist
+    async fn delete_invitation(&mut self, id: &uuid::Uuid) -> Result<(), AuthApiError>;
+
+    /// Removes every invitation to the organization
+    async fn delete_org(&mut self, org_id: &uuid::Uuid) -> Result<(), AuthApiError>;
+
+    /// Readdress every invitation to `old` to `new`
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError>;
+
+    /// Removes every invitation addressed to `email`
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError>;
+}
@@ -0,0 +1,13 @@
+//! Generated by shki - DO NOT EDIT
+
+///Hashed personal API keys
+#[derive(Debug, Clone, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
+pub struct ApiKeyRow {
+    pub id: String,
+    pub key_hash: String,
+    pub email: String,
+    pub name: String,
+    pub scopes: String,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
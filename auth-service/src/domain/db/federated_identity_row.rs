@@ -0,0 +1,10 @@
+//! Generated by shki - DO NOT EDIT
+
+///Upstream identity provider accounts linked to local users
+#[derive(Debug, Clone, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
+pub struct FederatedIdentityRow {
+    pub provider: String,
+    pub subject: String,
+    pub email: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
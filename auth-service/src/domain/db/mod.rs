@@ -1,5 +1,35 @@
 //! Generated by shki - DO NOT EDIT
 
+mod api_key_row;
+mod federated_identity_row;
+mod oauth_client_row;
+mod org_invitation_row;
+mod org_member_row;
+mod organization_row;
+mod passkey_ceremony_row;
+mod passkey_row;
+mod recovery_code_row;
+mod refresh_token_row;
+mod role_row;
+mod session_row;
+mod totp_row;
+mod two_factor_row;
+mod user_role_row;
 mod user_row;
 
+pub use api_key_row::ApiKeyRow;
+pub use federated_identity_row::FederatedIdentityRow;
+pub use oauth_client_row::OauthClientRow;
+pub use org_invitation_row::OrgInvitationRow;
+pub use org_member_row::OrgMemberRow;
+pub use organization_row::OrganizationRow;
+pub use passkey_ceremony_row::PasskeyCeremonyRow;
+pub use passkey_row::PasskeyRow;
+pub use recovery_code_row::RecoveryCodeRow;
+pub use refresh_token_row::RefreshTokenRow;
+pub use role_row::RoleRow;
+pub use session_row::SessionRow;
+pub use totp_row::TotpRow;
+pub use two_factor_row::TwoFactorRow;
+pub use user_role_row::UserRoleRow;
 pub use user_row::UserRow;
@@ -0,0 +1,12 @@
+//! Generated by shki - DO NOT EDIT
+
+///Registered OAuth clients
+#[derive(Debug, Clone, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
+pub struct OauthClientRow {
+    pub client_id: String,
+    pub name: String,
+    pub redirect_uris: String,
+    pub allowed_scopes: String,
+    pub secret_hash: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,10 @@
+//! Generated by shki - DO NOT EDIT
+
+///Members of each organization and their role in it
+#[derive(Debug, Clone, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
+pub struct OrgMemberRow {
+    pub org_id: String,
+    pub email: String,
+    pub role: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
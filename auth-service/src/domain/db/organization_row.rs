@@ -0,0 +1,9 @@
+//! Generated by shki - DO NOT EDIT
+
+///Customer workspaces users are members of
+#[derive(Debug, Clone, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
+pub struct OrganizationRow {
+    pub id: String,
+    pub name: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,9 @@
+//! Generated by shki - DO NOT EDIT
+
+///In-flight WebAuthn registration/authentication ceremonies
+#[derive(Debug, Clone, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
+pub struct PasskeyCeremonyRow {
+    pub id: String,
+    pub ceremony: String,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,11 @@
+//! Generated by shki - DO NOT EDIT
+
+///Registered WebAuthn credentials
+#[derive(Debug, Clone, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
+pub struct PasskeyRow {
+    pub credential_id: String,
+    pub email: String,
+    pub passkey: String,
+    pub sign_count: i64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,10 @@
+//! Generated by shki - DO NOT EDIT
+
+///Hashed single-use 2FA recovery codes
+#[derive(Debug, Clone, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
+pub struct RecoveryCodeRow {
+    pub id: String,
+    pub email: String,
+    pub code_hash: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,15 @@
+//! Generated by shki - DO NOT EDIT
+
+///Hashed refresh tokens grouped into rotation families
+#[derive(Debug, Clone, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
+pub struct RefreshTokenRow {
+    pub token_hash: String,
+    pub family_id: String,
+    pub email: String,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub used: bool,
+    pub auth_time: Option<chrono::DateTime<chrono::Utc>>,
+    pub amr: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub client_id: Option<String>,
+}
@@ -0,0 +1,10 @@
+//! Generated by shki - DO NOT EDIT
+
+///Named sets of permissions
+#[derive(Debug, Clone, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
+pub struct RoleRow {
+    pub name: String,
+    pub description: String,
+    pub permissions: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
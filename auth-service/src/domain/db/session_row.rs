@@ -0,0 +1,14 @@
+//! Generated by shki - DO NOT EDIT
+
+///Logins of each user, the id is the sid claim of their access tokens
+#[derive(Debug, Clone, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
+pub struct SessionRow {
+    pub id: String,
+    pub email: String,
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_seen_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub org_id: Option<String>,
+}
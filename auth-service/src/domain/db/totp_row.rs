@@ -0,0 +1,10 @@
+//! Generated by shki - DO NOT EDIT
+
+///Authenticator app (TOTP) secrets
+#[derive(Debug, Clone, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
+pub struct TotpRow {
+    pub email: String,
+    pub secret: Option<String>,
+    pub pending_secret: Option<String>,
+    pub last_step: Option<i64>,
+}
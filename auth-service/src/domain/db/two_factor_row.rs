@@ -0,0 +1,11 @@
+//! Generated by shki - DO NOT EDIT
+
+///Pending second factor checks of logins in flight
+#[derive(Debug, Clone, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
+pub struct TwoFactorRow {
+    pub email: String,
+    pub id: String,
+    pub code: String,
+    pub failures: i64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,9 @@
+//! Generated by shki - DO NOT EDIT
+
+///Roles assigned to users
+#[derive(Debug, Clone, sqlx::FromRow, serde::Deserialize, serde::Serialize)]
+pub struct UserRoleRow {
+    pub email: String,
+    pub role_name: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
@@ -6,5 +6,7 @@ pub struct UserRow {
     pub email: String,
     pub password_hash: String,
     pub two_factor: String,
+    pub verified: bool,
+    pub deletion_requested_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub locked_at: Option<chrono::DateTime<chrono::Utc>>,
 }
-
@@ -0,0 +1,248 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rand::distr::slice::Choose;
+use rand::{Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::domain::Email;
+use crate::error::AuthApiError;
+
+/// `grant_type` of a token request polling for a device authorization, RFC 8628 section 3.4
+pub const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Seconds added to the polling interval each time a device polls too fast, RFC 8628 section 3.5
+pub const SLOW_DOWN_INCREMENT: u64 = 5;
+
+/// Consonants only, so codes can't spell words and can't be misread as digits
+const USER_CODE_ALPHABET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+const USER_CODE_LENGTH: usize = 8;
+
+/// Opaque code the device polls the token endpoint with.
+///
+/// Only its SHA-256 hash is stored, so a leaked store can't be replayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceCode(String);
+
+impl DeviceCode {
+    /// 256 bits of randomness, base64url encoded
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::rng().fill_bytes(&mut bytes);
+        DeviceCode(URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    pub fn parse(value: &str) -> Result<Self, AuthApiError> {
+        match URL_SAFE_NO_PAD.decode(value) {
+            Ok(bytes) if bytes.len() == 32 => Ok(DeviceCode(value.to_string())),
+            _ => Err(AuthApiError::InvalidToken),
+        }
+    }
+
+    /// Hex encoded SHA-256 of the code, used as the store key
+    pub fn hash(&self) -> String {
+        Sha256::digest(self.0.as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+}
+
+impl AsRef<str> for DeviceCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Short code the user types on a second device, RFC 8628 section 6.1
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct UserCode(String);
+
+impl UserCode {
+    pub fn generate() -> Self {
+        let alphabet = Choose::new(USER_CODE_ALPHABET).expect("alphabet is not empty");
+        let code = rand::rng()
+            .sample_iter(alphabet)
+            .take(USER_CODE_LENGTH)
+            .map(|&c| c as char)
+            .collect();
+        UserCode(code)
+    }
+
+    /// Case and separators are ignored, so `bcdf-ghjk` matches `BCDFGHJK`
+    pub fn parse(value: &str) -> Result<Self, AuthApiError> {
+        let code: String = value
+            .chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        if code.len() != USER_CODE_LENGTH || !code.bytes().all(|c| USER_CODE_ALPHABET.contains(&c))
+        {
+            return Err(AuthApiError::InvalidData("Invalid user code".to_string()));
+        }
+        Ok(UserCode(code))
+    }
+
+    /// The way it is shown to the user, `XXXX-XXXX`
+    pub fn formatted(&self) -> String {
+        let (first, second) = self.0.split_at(USER_CODE_LENGTH / 2);
+        format!("{first}-{second}")
+    }
+}
+
+impl AsRef<str> for UserCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Where the user is with a device authorization request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DeviceAuthorizationStatus {
+    Pending,
+    Approved { email: Email },
+    Denied,
+}
+
+/// What a device learns when it polls the token endpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DevicePoll {
+    Pending,
+    /// Polled before the interval was up, the interval has grown
+    SlowDown,
+    Expired,
+    Denied,
+    /// `scope` is what the request was granted
+    Approved {
+        email: Email,
+        scope: String,
+    },
+}
+
+impl DevicePoll {
+    /// Whether the request is settled and can be forgotten
+    pub fn is_final(&self) -> bool {
+        !matches!(self, DevicePoll::Pending | DevicePoll::SlowDown)
+    }
+}
+
+/// A pending device authorization request, RFC 8628 section 3.2
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceAuthorization {
+    pub device_code_hash: String,
+    pub user_code: UserCode,
+    pub client_id: String,
+    /// Space delimited scopes granted on approval
+    pub scope: String,
+    /// Unix timestamp in seconds
+    pub expires_at: i64,
+    /// Minimum seconds between polls
+    pub interval: u64,
+    /// Unix timestamp in seconds
+    pub last_polled_at: Option<i64>,
+    #[serde(flatten)]
+    pub status: DeviceAuthorizationStatus,
+}
+
+impl DeviceAuthorization {
+    /// `ttl` and `interval` in seconds
+    pub fn new(
+        device_code: &DeviceCode,
+        user_code: UserCode,
+        client_id: &str,
+        scope: &str,
+        ttl: u64,
+        interval: u64,
+    ) -> Self {
+        DeviceAuthorization {
+            device_code_hash: device_code.hash(),
+            user_code,
+            client_id: client_id.to_string(),
+            scope: scope.to_string(),
+            expires_at: chrono::Utc::now().timestamp() + ttl as i64,
+            interval,
+            last_polled_at: None,
+            status: DeviceAuthorizationStatus::Pending,
+        }
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at <= now
+    }
+
+    /// Record a poll at `now` and answer it.
+    ///
+    /// Expiry wins over everything else, and a device polling too fast is slowed down
+    /// before it learns the outcome.
+    pub fn poll(&mut self, now: i64) -> DevicePoll {
+        if self.is_expired(now) {
+            return DevicePoll::Expired;
+        }
+        let too_fast = self
+            .last_polled_at
+            .is_some_and(|last| now - last < self.interval as i64);
+        self.last_polled_at = Some(now);
+        if too_fast {
+            self.interval += SLOW_DOWN_INCREMENT;
+            return DevicePoll::SlowDown;
+        }
+        match &self.status {
+            DeviceAuthorizationStatus::Pending => DevicePoll::Pending,
+            DeviceAuthorizationStatus::Denied => DevicePoll::Denied,
+            DeviceAuthorizationStatus::Approved { email } => DevicePoll::Approved {
+                email: email.clone(),
+                scope: self.scope.clone(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_code_parse() {
+        let code = UserCode::generate();
+        assert_eq!(code.as_ref().len(), USER_CODE_LENGTH);
+        assert_eq!(UserCode::parse(&code.formatted()).unwrap(), code);
+        assert_eq!(
+            UserCode::parse(" bcdf-ghjk ").unwrap().formatted(),
+            "BCDF-GHJK"
+        );
+        // vowels and digits are never generated
+        assert!(UserCode::parse("BCDF-GHJA").is_err());
+        assert!(UserCode::parse("BCDF-GHJ1").is_err());
+        assert!(UserCode::parse("BCDF-GHJ").is_err());
+    }
+
+    #[test]
+    fn test_device_poll() {
+        let device_code = DeviceCode::generate();
+        let mut auth =
+            DeviceAuthorization::new(&device_code, UserCode::generate(), "cli", "read", 600, 5);
+        let now = chrono::Utc::now().timestamp();
+        assert_eq!(auth.poll(now), DevicePoll::Pending);
+        assert_eq!(auth.poll(now + 1), DevicePoll::SlowDown);
+        assert_eq!(auth.interval, 10);
+        // the slowed down interval counts from the last poll
+        assert_eq!(auth.poll(now + 10), DevicePoll::SlowDown);
+        assert_eq!(auth.poll(now + 25), DevicePoll::Pending);
+
+        let email = Email::parse("device@test.com").unwrap();
+        auth.status = DeviceAuthorizationStatus::Approved {
+            email: email.clone(),
+        };
+        assert_eq!(
+            auth.poll(now + 40),
+            DevicePoll::Approved {
+                email,
+                scope: "read".to_string()
+            }
+        );
+        assert_eq!(auth.poll(now + 600), DevicePoll::Expired);
+        assert!(DevicePoll::Expired.is_final());
+        assert!(!DevicePoll::SlowDown.is_final());
+    }
+}
@@ -12,15 +12,105 @@ pub struct TwoFactorEmailData {
     pub redirect_url: String,
 }
 
+#[derive(Template, Clone, Debug)]
+#[template(path = "magic_link.html")]
+pub struct MagicLinkEmailData {
+    pub email: String,
+    pub site_url: String,
+    pub link_url: String,
+}
+
+#[derive(Template, Clone, Debug)]
+#[template(path = "password_reset.html")]
+pub struct PasswordResetEmailData {
+    pub email: String,
+    pub site_url: String,
+    pub link_url: String,
+}
+
+#[derive(Template, Clone, Debug)]
+#[template(path = "email_verification.html")]
+pub struct EmailVerificationEmailData {
+    pub email: String,
+    pub site_url: String,
+    pub link_url: String,
+}
+
+/// Sent to the new address, `email` is the new address
+#[derive(Template, Clone, Debug)]
+#[template(path = "email_change.html")]
+pub struct EmailChangeEmailData {
+    pub email: String,
+    pub old_email: String,
+    pub site_url: String,
+    pub link_url: String,
+}
+
+/// Sent to the old address, `link_url` undoes the change
+#[derive(Template, Clone, Debug)]
+#[template(path = "email_change_notice.html")]
+pub struct EmailChangeNoticeEmailData {
+    pub email: String,
+    pub new_email: String,
+    pub site_url: String,
+    pub link_url: String,
+}
+
+#[derive(Template, Clone, Debug)]
+#[template(path = "account_deletion.html")]
+pub struct AccountDeletionEmailData {
+    pub email: String,
+    pub site_url: String,
+    /// Human readable date the account is purged on
+    pub purge_date: String,
+}
+
+/// Sent to the invitee, `link_url` shows the invitation
+#[derive(Template, Clone, Debug)]
+#[template(path = "org_invitation.html")]
+pub struct OrgInvitationEmailData {
+    pub email: String,
+    pub invited_by: String,
+    pub org_name: String,
+    pub role: String,
+    pub site_url: String,
+    pub link_url: String,
+}
+
+/// Sent when failed logins lock the account, `link_url` lifts the lock
+#[derive(Template, Clone, Debug)]
+#[template(path = "account_unlock.html")]
+pub struct AccountUnlockEmailData {
+    pub email: String,
+    pub site_url: String,
+    pub link_url: String,
+}
+
 #[derive(Clone, Debug)]
 pub enum EmailTemplate {
     TwoFactor(TwoFactorEmailData),
+    MagicLink(MagicLinkEmailData),
+    PasswordReset(PasswordResetEmailData),
+    EmailVerification(EmailVerificationEmailData),
+    EmailChange(EmailChangeEmailData),
+    EmailChangeNotice(EmailChangeNoticeEmailData),
+    AccountDeletion(AccountDeletionEmailData),
+    OrgInvitation(OrgInvitationEmailData),
+    AccountUnlock(AccountUnlockEmailData),
 }
 
 impl EmailTemplate {
     pub fn render(&self) -> String {
         match self {
             EmailTemplate::TwoFactor(data) => data.render().expect("valid html"),
+            EmailTemplate::MagicLink(data) => data.render().expect("valid html"),
+            EmailTemplate::PasswordReset(data) => data.render().expect("valid html"),
+            EmailTemplate::EmailVerification(data) => data.render().expect("valid html"),
+            EmailTemplate::EmailChange(data) => data.render().expect("valid html"),
+            EmailTemplate::EmailChangeNotice(data) => data.render().expect("valid html"),
+            EmailTemplate::AccountDeletion(data) => data.render().expect("valid html"),
+            EmailTemplate::OrgInvitation(data) => data.render().expect("valid html"),
+            EmailTemplate::AccountUnlock(data) => data.render().expect("valid html"),
         }
     }
 }
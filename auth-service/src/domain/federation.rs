@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::Email;
+
+/// An account at an upstream identity provider, linked to a local user.
+///
+/// Keyed by the provider's `sub`, which is stable, unlike the email address it reports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FederatedIdentity {
+    /// Id of the provider in `FederationConfig::providers`
+    pub provider: String,
+    /// `sub` claim issued by the provider
+    pub subject: String,
+    pub email: Email,
+}
+
+/// Identity claims from an upstream id_token, OIDC Core section 2.
+///
+/// `iss`, `aud` and `exp` are checked while decoding and not kept.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpstreamIdentity {
+    pub sub: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+}
+
+impl UpstreamIdentity {
+    /// The address to link by, only when the provider vouches for it
+    pub fn verified_email(&self) -> Option<Email> {
+        match (&self.email, self.email_verified) {
+            (Some(email), Some(true)) => Email::parse(email).ok(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verified_email_requires_email_verified() {
+        let identity = UpstreamIdentity {
+            sub: "123".to_string(),
+            nonce: None,
+            email: Some("fed@test.com".to_string()),
+            email_verified: Some(true),
+        };
+        assert_eq!(
+            identity.verified_email(),
+            Some(Email::parse("fed@test.com").unwrap())
+        );
+        let unverified = UpstreamIdentity {
+            email_verified: None,
+            ..identity.clone()
+        };
+        assert_eq!(unverified.verified_email(), None);
+        let invalid = UpstreamIdentity {
+            email: Some("not-an-email".to_string()),
+            ..identity
+        };
+        assert_eq!(invalid.verified_email(), None);
+    }
+}
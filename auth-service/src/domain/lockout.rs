@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::LockoutConfig;
+
+/// Failed password logins of one account.
+///
+/// Failures are counted within a window, reaching the threshold locks the account.
+/// Each lockout in a row doubles the next one, up to the configured maximum.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailedLogins {
+    /// Failures in the current window
+    pub failures: u32,
+    /// Unix timestamp in seconds
+    pub window_started_at: i64,
+    /// Lockouts since the last successful login, drives the backoff
+    pub lockouts: u32,
+    /// Unix timestamp in seconds
+    pub locked_until: Option<i64>,
+}
+
+impl FailedLogins {
+    /// Seconds left on the lock, if the account is locked at `now`
+    pub fn locked_for(&self, now: i64) -> Option<u64> {
+        self.locked_until
+            .filter(|until| *until > now)
+            .map(|until| (until - now) as u64)
+    }
+
+    /// Count a failure at `now`, locking the account once `threshold` is reached.
+    ///
+    /// Failures made while locked are still counted, without extending the lock, so a
+    /// count past `threshold` shows the account was already locked when it was made.
+    pub fn record_failure(&mut self, now: i64, config: &LockoutConfig) {
+        let locked = self.locked_for(now).is_some();
+        if !locked
+            && (self.failures == 0
+                || self.locked_until.is_some()
+                || now - self.window_started_at >= config.window as i64)
+        {
+            self.failures = 0;
+            self.window_started_at = now;
+            self.locked_until = None;
+        }
+        self.failures = self.failures.saturating_add(1);
+        if self.failures == config.threshold {
+            let backoff = 2u64.saturating_pow(self.lockouts);
+            let duration = config
+                .duration
+                .saturating_mul(backoff)
+                .min(config.max_duration);
+            self.locked_until = Some(now + duration as i64);
+            self.lockouts = self.lockouts.saturating_add(1);
+        }
+    }
+
+    /// How long the record is worth keeping after `now`, in seconds.
+    ///
+    /// Past this the backoff has no lock left to build on and starts over.
+    pub fn retention(&self, now: i64, config: &LockoutConfig) -> u64 {
+        let window_left = (self.window_started_at + config.window as i64 - now).max(0) as u64;
+        let lock_left = self.locked_for(now).unwrap_or(0);
+        window_left.max(lock_left + config.max_duration).max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> LockoutConfig {
+        LockoutConfig {
+            threshold: 3,
+            window: 60,
+            duration: 100,
+            max_duration: 300,
+            unlock_by_email: false,
+            ..LockoutConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_locks_at_threshold_with_backoff() {
+        let config = config();
+        let mut record = FailedLogins::default();
+        for _ in 0..2 {
+            record.record_failure(1000, &config);
+        }
+        assert_eq!(record.locked_for(1000), None);
+        record.record_failure(1000, &config);
+        assert_eq!(record.locked_for(1000), Some(100));
+        assert_eq!(record.locked_for(1100), None);
+
+        // the next lockout doubles, then hits the ceiling
+        for _ in 0..3 {
+            record.record_failure(1100, &config);
+        }
+        assert_eq!(record.locked_for(1100), Some(200));
+        for _ in 0..3 {
+            record.record_failure(1300, &config);
+        }
+        assert_eq!(record.locked_for(1300), Some(300));
+    }
+
+    #[test]
+    fn test_failures_while_locked_count_past_the_threshold() {
+        let config = config();
+        let mut record = FailedLogins::default();
+        for _ in 0..3 {
+            record.record_failure(1000, &config);
+        }
+        // the window runs out before the lock does
+        record.record_failure(1070, &config);
+        assert_eq!(record.failures, 4);
+        assert_eq!(record.locked_for(1070), Some(30));
+        assert_eq!(record.lockouts, 1);
+    }
+
+    #[test]
+    fn test_failures_outside_the_window_start_over() {
+        let config = config();
+        let mut record = FailedLogins::default();
+        record.record_failure(1000, &config);
+        record.record_failure(1000, &config);
+        record.record_failure(1060, &config);
+        assert_eq!(record.failures, 1);
+        assert_eq!(record.locked_for(1060), None);
+    }
+}
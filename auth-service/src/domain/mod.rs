@@ -12,3 +12,33 @@ pub mod db;
 pub use db::*;
 pub mod redis;
 pub use redis::*;
+pub mod one_time_token;
+pub use one_time_token::*;
+pub mod passkey;
+pub use passkey::*;
+pub mod totp;
+pub use totp::*;
+pub mod recovery_code;
+pub use recovery_code::*;
+pub mod refresh_token;
+pub use refresh_token::*;
+pub mod oauth;
+pub use oauth::*;
+pub mod authentication;
+pub use authentication::*;
+pub mod federation;
+pub use federation::*;
+pub mod api_key;
+pub use api_key::*;
+pub mod device;
+pub use device::*;
+pub mod session;
+pub use session::*;
+pub mod role;
+pub use role::*;
+pub mod organization;
+pub use organization::*;
+pub mod lockout;
+pub use lockout::*;
+pub mod rate_limit;
+pub use rate_limit::*;
@@ -0,0 +1,292 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+use crate::domain::{Authentication, Email, HashedPassword};
+use crate::error::AuthApiError;
+
+/// Error codes from RFC 6749 section 4.1.2.1 and 5.2
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OAuthError {
+    InvalidRequest,
+    InvalidClient,
+    InvalidGrant,
+    UnauthorizedClient,
+    UnsupportedGrantType,
+    UnsupportedResponseType,
+    InvalidScope,
+    AccessDenied,
+    /// Bearer token errors, RFC 6750 section 3.1
+    InvalidToken,
+    InsufficientScope,
+    /// Device authorization grant errors, RFC 8628 section 3.5
+    AuthorizationPending,
+    SlowDown,
+    ExpiredToken,
+}
+
+impl std::fmt::Display for OAuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = serde_json::to_value(self).unwrap_or_default();
+        write!(f, "{}", value.as_str().unwrap_or_default())
+    }
+}
+
+/// An application allowed to request tokens on behalf of users
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct OAuthClient {
+    pub client_id: String,
+    /// Shown on the consent page
+    pub name: String,
+    /// Exact match only, no prefix or wildcard matching
+    pub redirect_uris: Vec<String>,
+    pub allowed_scopes: Vec<String>,
+    /// Confidential clients authenticate with a secret, public ones only send their id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub secret_hash: Option<HashedClientSecret>,
+}
+
+impl OAuthClient {
+    pub fn is_confidential(&self) -> bool {
+        self.secret_hash.is_some()
+    }
+
+    /// Check the secret presented by the client, RFC 6749 section 2.3.1.
+    /// Public clients must not send one.
+    pub async fn authenticate(&self, secret: Option<&str>) -> Result<(), AuthApiError> {
+        let invalid =
+            |description: &str| AuthApiError::OAuth(OAuthError::InvalidClient, description.into());
+        match (&self.secret_hash, secret) {
+            (None, None) => Ok(()),
+            (None, Some(_)) => Err(invalid("Public clients have no secret")),
+            (Some(_), None) => Err(invalid("Client authentication required")),
+            (Some(hash), Some(secret)) => hash
+                .verify(&ClientSecret(secret.to_string()))
+                .await
+                .map_err(|_| invalid("Client authentication failed")),
+        }
+    }
+
+    /// The registered redirect uri matching `requested`.
+    /// May be omitted when the client only has one.
+    pub fn redirect_uri(&self, requested: Option<&str>) -> Option<&str> {
+        match requested {
+            Some(uri) => self
+                .redirect_uris
+                .iter()
+                .find(|registered| *registered == uri)
+                .map(String::as_str),
+            None if self.redirect_uris.len() == 1 => Some(&self.redirect_uris[0]),
+            None => None,
+        }
+    }
+
+    /// Scopes to grant for a request, all allowed ones when none are asked for
+    pub fn grant_scopes(&self, requested: Option<&str>) -> Result<Vec<String>, OAuthError> {
+        let requested = match requested.map(parse_scopes) {
+            Some(scopes) if !scopes.is_empty() => scopes,
+            _ => return Ok(self.allowed_scopes.clone()),
+        };
+        if requested.iter().all(|s| self.allowed_scopes.contains(s)) {
+            Ok(requested)
+        } else {
+            Err(OAuthError::InvalidScope)
+        }
+    }
+}
+
+/// Secret of a confidential client, shown once when the client is registered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientSecret(String);
+
+impl ClientSecret {
+    /// 256 bits of randomness, base64url encoded
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::rng().fill_bytes(&mut bytes);
+        ClientSecret(URL_SAFE_NO_PAD.encode(bytes))
+    }
+}
+
+impl AsRef<str> for ClientSecret {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Argon2 hash of a client secret, using the same parameters as `HashedPassword`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct HashedClientSecret(String);
+
+impl HashedClientSecret {
+    pub async fn parse(secret: &ClientSecret) -> Result<Self, AuthApiError> {
+        let hashed = HashedPassword::compute_password_hash(secret.as_ref()).await?;
+        Ok(HashedClientSecret(hashed))
+    }
+
+    /// Wrap a hash read back from storage or config
+    pub fn from_hash(hash: String) -> Self {
+        HashedClientSecret(hash)
+    }
+
+    pub async fn verify(&self, candidate: &ClientSecret) -> Result<(), AuthApiError> {
+        HashedPassword::parse_password_hash(self.0.clone())?
+            .verify_raw_password(candidate.as_ref())
+            .await
+    }
+}
+
+impl AsRef<str> for HashedClientSecret {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// What the user approved on the consent page, carried by the authorization code
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthorizationGrant {
+    pub sub: Email,
+    pub client_id: String,
+    pub redirect_uri: String,
+    /// Whether the authorization request named `redirect_uri`, the token request has to
+    /// repeat it then
+    #[serde(default)]
+    pub redirect_uri_given: bool,
+    pub scope: String,
+    /// PKCE `S256` challenge from the authorization request
+    pub code_challenge: String,
+    /// Echoed in the id_token
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    /// The login the user approved the request from
+    #[serde(flatten)]
+    pub authentication: Authentication,
+}
+
+impl AuthorizationGrant {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scope.split(' ').any(|s| s == scope)
+    }
+}
+
+/// Split a space delimited scope parameter, dropping duplicates
+pub fn parse_scopes(scope: &str) -> Vec<String> {
+    let mut scopes: Vec<String> = Vec::new();
+    for s in scope.split(' ').filter(|s| !s.is_empty()) {
+        if !scopes.iter().any(|existing| existing == s) {
+            scopes.push(s.to_string());
+        }
+    }
+    scopes
+}
+
+/// Scope asking for an id_token, OIDC Core section 3.1.2.1
+pub const OPENID_SCOPE: &str = "openid";
+
+/// `gty` claim of tokens issued to a client acting on its own behalf, RFC 6749 section 4.4
+pub const CLIENT_CREDENTIALS_GRANT: &str = "client_credentials";
+
+/// PKCE transformation, only `S256` is supported
+pub const CODE_CHALLENGE_METHOD: &str = "S256";
+
+/// `S256` challenge for a PKCE `code_verifier`, RFC 7636 section 4.2
+pub fn code_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+/// Check a PKCE `code_verifier` against the `S256` challenge sent with the authorization request
+pub fn verify_code_challenge(verifier: &str, challenge: &str) -> bool {
+    // RFC 7636 section 4.1
+    let well_formed = (43..=128).contains(&verifier.len())
+        && verifier
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~'));
+    well_formed && code_challenge(verifier) == challenge
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client() -> OAuthClient {
+        OAuthClient {
+            client_id: "app".to_string(),
+            name: "App".to_string(),
+            redirect_uris: vec!["https://app.test/callback".to_string()],
+            allowed_scopes: vec!["openid".to_string(), "profile".to_string()],
+            secret_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_verify_code_challenge() {
+        // example from RFC 7636 appendix B
+        let verifier = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        let challenge = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+        assert!(verify_code_challenge(verifier, challenge));
+        assert!(!verify_code_challenge(
+            "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXX",
+            challenge
+        ));
+        assert!(!verify_code_challenge("short", challenge));
+    }
+
+    #[test]
+    fn test_redirect_uri_exact_match() {
+        let client = client();
+        assert_eq!(
+            client.redirect_uri(Some("https://app.test/callback")),
+            Some("https://app.test/callback")
+        );
+        assert_eq!(
+            client.redirect_uri(Some("https://app.test/callback/x")),
+            None
+        );
+        assert_eq!(client.redirect_uri(None), Some("https://app.test/callback"));
+    }
+
+    #[test]
+    fn test_grant_scopes() {
+        let client = client();
+        assert_eq!(client.grant_scopes(None).unwrap(), client.allowed_scopes);
+        assert_eq!(
+            client.grant_scopes(Some("openid openid")).unwrap(),
+            vec!["openid".to_string()]
+        );
+        assert_eq!(
+            client.grant_scopes(Some("openid admin")),
+            Err(OAuthError::InvalidScope)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_authenticate_confidential_client() {
+        let secret = ClientSecret::generate();
+        let confidential = OAuthClient {
+            secret_hash: Some(HashedClientSecret::parse(&secret).await.unwrap()),
+            ..client()
+        };
+        assert!(confidential.is_confidential());
+        assert!(
+            confidential
+                .authenticate(Some(secret.as_ref()))
+                .await
+                .is_ok()
+        );
+        assert!(matches!(
+            confidential.authenticate(Some("wrong")).await,
+            Err(AuthApiError::OAuth(OAuthError::InvalidClient, _))
+        ));
+        assert!(confidential.authenticate(None).await.is_err());
+
+        let public = client();
+        assert!(public.authenticate(None).await.is_ok());
+        assert!(public.authenticate(Some(secret.as_ref())).await.is_err());
+    }
+}
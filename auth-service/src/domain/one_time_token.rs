@@ -0,0 +1,95 @@
+use crate::error::AuthApiError;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// What a one-time token may be redeemed for.
+///
+/// The purpose is embedded in the signed token and checked again against the
+/// store on consumption, so a token issued for one flow can't be replayed in another.
+#[derive(serde::Deserialize, serde::Serialize, Debug, ToSchema, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenPurpose {
+    MagicLink,
+    PasswordReset,
+    EmailVerification,
+    /// Confirms a pending address change, sent to the new address
+    EmailChange,
+    /// Reverts an address change, sent to the old address
+    EmailChangeUndo,
+    /// OAuth authorization code, exchanged once at the token endpoint
+    AuthorizationCode,
+    /// Lifts a lockout after repeated failed logins
+    AccountUnlock,
+}
+
+impl std::fmt::Display for TokenPurpose {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let value = serde_json::to_value(self).unwrap_or_default();
+        write!(f, "{}", value.as_str().unwrap_or_default())
+    }
+}
+
+/// Identifier (`jti`) of a single-use token
+#[derive(Debug, Clone, PartialEq, Eq, Hash, ToSchema, serde::Serialize, serde::Deserialize)]
+pub struct OneTimeTokenId(Uuid);
+
+impl OneTimeTokenId {
+    pub fn new() -> Self {
+        OneTimeTokenId(Uuid::new_v4())
+    }
+}
+
+impl Default for OneTimeTokenId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TryFrom<&str> for OneTimeTokenId {
+    type Error = AuthApiError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let uuid = Uuid::parse_str(value).map_err(|_| AuthApiError::InvalidToken)?;
+        Ok(OneTimeTokenId(uuid))
+    }
+}
+
+impl AsRef<Uuid> for OneTimeTokenId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_purpose_display() {
+        assert_eq!(TokenPurpose::MagicLink.to_string(), "magic_link");
+        assert_eq!(TokenPurpose::PasswordReset.to_string(), "password_reset");
+        assert_eq!(
+            TokenPurpose::EmailVerification.to_string(),
+            "email_verification"
+        );
+        assert_eq!(TokenPurpose::EmailChange.to_string(), "email_change");
+        assert_eq!(
+            TokenPurpose::EmailChangeUndo.to_string(),
+            "email_change_undo"
+        );
+        assert_eq!(
+            TokenPurpose::AuthorizationCode.to_string(),
+            "authorization_code"
+        );
+    }
+
+    #[test]
+    fn test_one_time_token_id_try_from() {
+        let id = OneTimeTokenId::new();
+        let parsed = OneTimeTokenId::try_from(id.as_ref().to_string().as_str()).unwrap();
+        assert_eq!(id, parsed);
+
+        let err = OneTimeTokenId::try_from("not-a-uuid").unwrap_err();
+        assert!(matches!(err, AuthApiError::InvalidToken));
+    }
+}
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::Email;
+use crate::error::AuthApiError;
+
+const MAX_ORG_NAME_LENGTH: usize = 100;
+
+/// A customer workspace users are members of
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Organization {
+    pub id: Uuid,
+    pub name: String,
+    /// Unix timestamp in seconds
+    pub created_at: i64,
+}
+
+impl Organization {
+    pub fn new(name: &str) -> Result<Self, AuthApiError> {
+        Ok(Organization {
+            id: Uuid::new_v4(),
+            name: parse_org_name(name)?,
+            created_at: chrono::Utc::now().timestamp(),
+        })
+    }
+}
+
+/// Trimmed, 1 to `MAX_ORG_NAME_LENGTH` characters
+pub fn parse_org_name(name: &str) -> Result<String, AuthApiError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_ORG_NAME_LENGTH {
+        return Err(AuthApiError::InvalidData(format!(
+            "Organization names are 1 to {MAX_ORG_NAME_LENGTH} characters"
+        )));
+    }
+    Ok(name.to_string())
+}
+
+/// What a member may do within their organization
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum OrgRole {
+    Member,
+    /// Manages members and invitations
+    Admin,
+    /// Everything an admin can, plus deleting the organization and managing owners
+    Owner,
+}
+
+impl OrgRole {
+    pub fn can_manage(&self) -> bool {
+        *self >= OrgRole::Admin
+    }
+}
+
+impl AsRef<str> for OrgRole {
+    fn as_ref(&self) -> &str {
+        match self {
+            OrgRole::Member => "member",
+            OrgRole::Admin => "admin",
+            OrgRole::Owner => "owner",
+        }
+    }
+}
+
+impl std::str::FromStr for OrgRole {
+    type Err = AuthApiError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "member" => Ok(OrgRole::Member),
+            "admin" => Ok(OrgRole::Admin),
+            "owner" => Ok(OrgRole::Owner),
+            _ => Err(AuthApiError::InvalidData(format!("Unknown org role: {s}"))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Membership {
+    pub org_id: Uuid,
+    #[schema(value_type = String)]
+    pub email: Email,
+    pub role: OrgRole,
+    /// Unix timestamp in seconds
+    pub joined_at: i64,
+}
+
+impl Membership {
+    pub fn new(org_id: Uuid, email: &Email, role: OrgRole) -> Self {
+        Membership {
+            org_id,
+            email: email.clone(),
+            role,
+            joined_at: chrono::Utc::now().timestamp(),
+        }
+    }
+}
+
+/// An offer to join an organization, accepted by logging in as `email`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Invitation {
+    pub id: Uuid,
+    pub org_id: Uuid,
+    /// Who is invited, they need not have signed up yet
+    #[schema(value_type = String)]
+    pub email: Email,
+    /// Role the invitee joins with
+    pub role: OrgRole,
+    #[schema(value_type = String)]
+    pub invited_by: Email,
+    /// Unix timestamp in seconds
+    pub created_at: i64,
+    /// Unix timestamp in seconds
+    pub expires_at: i64,
+}
+
+impl Invitation {
+    /// `ttl` in seconds
+    pub fn new(org_id: Uuid, email: &Email, role: OrgRole, invited_by: &Email, ttl: u64) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        Invitation {
+            id: Uuid::new_v4(),
+            org_id,
+            email: email.clone(),
+            role,
+            invited_by: invited_by.clone(),
+            created_at: now,
+            expires_at: now + ttl as i64,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= chrono::Utc::now().timestamp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_org_role_order() {
+        assert!(OrgRole::Owner.can_manage());
+        assert!(OrgRole::Admin.can_manage());
+        assert!(!OrgRole::Member.can_manage());
+        for role in [OrgRole::Member, OrgRole::Admin, OrgRole::Owner] {
+            assert_eq!(role.as_ref().parse::<OrgRole>().unwrap(), role);
+        }
+        assert!("root".parse::<OrgRole>().is_err());
+    }
+
+    #[test]
+    fn test_org_name() {
+        assert_eq!(Organization::new("  Acme ").unwrap().name, "Acme");
+        assert!(Organization::new(" ").is_err());
+        assert!(Organization::new(&"x".repeat(101)).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use uuid::Uuid;
+use webauthn_rs::prelude::{CredentialID, Passkey, PasskeyAuthentication, PasskeyRegistration};
+
+use crate::error::AuthApiError;
+
+use super::Email;
+
+/// Encode a WebAuthn credential id the same way browsers report it (base64url, no padding)
+pub fn encode_credential_id(id: &CredentialID) -> String {
+    URL_SAFE_NO_PAD.encode(id.as_ref())
+}
+
+/// Reject a signature counter that didn't move forward.
+///
+/// Per WebAuthn §6.1.1, if either the stored or the presented counter is non-zero the
+/// presented value must be strictly greater; anything else suggests a cloned authenticator.
+/// Authenticators that don't implement counters always report 0 and are let through.
+pub fn verify_sign_count(stored: u32, presented: u32) -> Result<(), AuthApiError> {
+    if (presented > 0 || stored > 0) && presented <= stored {
+        return Err(AuthApiError::PasskeyCounterRegression);
+    }
+    Ok(())
+}
+
+/// A registered passkey and the account it belongs to
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+pub struct PasskeyCredential {
+    pub email: Email,
+    pub passkey: Passkey,
+    /// Last signature counter reported by the authenticator
+    pub sign_count: u32,
+}
+
+impl PasskeyCredential {
+    pub fn new(email: Email, passkey: Passkey) -> Self {
+        Self {
+            email,
+            passkey,
+            sign_count: 0,
+        }
+    }
+
+    pub fn credential_id(&self) -> String {
+        encode_credential_id(self.passkey.cred_id())
+    }
+}
+
+/// Identifies an in-flight registration or authentication ceremony
+#[derive(
+    Debug, Clone, PartialEq, Eq, Hash, utoipa::ToSchema, serde::Serialize, serde::Deserialize,
+)]
+pub struct PasskeyCeremonyId(Uuid);
+
+impl PasskeyCeremonyId {
+    pub fn new() -> Self {
+        PasskeyCeremonyId(Uuid::new_v4())
+    }
+}
+
+impl Default for PasskeyCeremonyId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TryFrom<&str> for PasskeyCeremonyId {
+    type Error = AuthApiError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let uuid = Uuid::parse_str(value).map_err(|_| AuthApiError::Unauthorized)?;
+        Ok(PasskeyCeremonyId(uuid))
+    }
+}
+
+impl AsRef<Uuid> for PasskeyCeremonyId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+/// Server side state kept between the challenge and finish steps of a ceremony
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PasskeyCeremony {
+    Registration {
+        email: Email,
+        state: PasskeyRegistration,
+    },
+    Authentication {
+        email: Email,
+        state: PasskeyAuthentication,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_count_must_increase() {
+        assert!(verify_sign_count(0, 0).is_ok());
+        assert!(verify_sign_count(0, 1).is_ok());
+        assert!(verify_sign_count(4, 5).is_ok());
+        assert!(matches!(
+            verify_sign_count(5, 5),
+            Err(AuthApiError::PasskeyCounterRegression)
+        ));
+        assert!(matches!(
+            verify_sign_count(5, 2),
+            Err(AuthApiError::PasskeyCounterRegression)
+        ));
+        assert!(matches!(
+            verify_sign_count(5, 0),
+            Err(AuthApiError::PasskeyCounterRegression)
+        ));
+    }
+
+    #[test]
+    fn test_passkey_ceremony_id_try_from() {
+        let id = PasskeyCeremonyId::new();
+        let parsed = PasskeyCeremonyId::try_from(id.as_ref().to_string().as_str()).unwrap();
+        assert_eq!(id, parsed);
+        assert!(PasskeyCeremonyId::try_from("nope").is_err());
+    }
+}
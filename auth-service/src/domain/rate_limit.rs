@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+
+/// Token bucket limit: up to `capacity` requests in a burst, with tokens refilled
+/// evenly so an empty bucket is full again after `period` seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenBucket {
+    pub capacity: u32,
+    /// In seconds
+    pub period: u64,
+}
+
+/// Tokens left in a bucket as of `updated_at`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BucketState {
+    pub tokens: f64,
+    /// Unix timestamp in milliseconds
+    pub updated_at: i64,
+}
+
+impl TokenBucket {
+    /// A bucket always holds at least one token
+    fn capacity(&self) -> f64 {
+        self.capacity.max(1) as f64
+    }
+
+    fn tokens_per_ms(&self) -> f64 {
+        self.capacity() / (self.period.max(1) * 1000) as f64
+    }
+
+    /// Take a token at `now` (in milliseconds) from a bucket last seen in `state`,
+    /// a new bucket starts full.
+    ///
+    /// Returns the state to store, along with the seconds until a token is available
+    /// when the bucket is empty. Refused requests don't use up anything.
+    pub fn take(&self, state: Option<BucketState>, now: i64) -> (BucketState, Result<(), u64>) {
+        let capacity = self.capacity();
+        let tokens = match state {
+            Some(state) => {
+                let elapsed = (now - state.updated_at).max(0) as f64;
+                (state.tokens + elapsed * self.tokens_per_ms()).min(capacity)
+            }
+            None => capacity,
+        };
+        if tokens >= 1.0 {
+            let state = BucketState {
+                tokens: tokens - 1.0,
+                updated_at: now,
+            };
+            return (state, Ok(()));
+        }
+        let wait_ms = (1.0 - tokens) / self.tokens_per_ms();
+        let retry_after = (wait_ms / 1000.0).ceil().max(1.0) as u64;
+        let state = BucketState {
+            tokens,
+            updated_at: now,
+        };
+        (state, Err(retry_after))
+    }
+
+    /// When a bucket last seen in `state` is full again, in milliseconds. From then
+    /// on it is no different from a bucket never used, so it needn't be kept.
+    pub fn full_at(&self, state: &BucketState) -> i64 {
+        let missing = (self.capacity() - state.tokens).max(0.0);
+        state.updated_at + (missing / self.tokens_per_ms()).ceil() as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_drains_and_refills() {
+        let bucket = TokenBucket {
+            capacity: 2,
+            period: 10,
+        };
+        let (state, result) = bucket.take(None, 0);
+        assert!(result.is_ok());
+        let (state, result) = bucket.take(Some(state), 0);
+        assert!(result.is_ok());
+        let (state, result) = bucket.take(Some(state), 0);
+        assert_eq!(result, Err(5));
+
+        // one token every 5s
+        let (state, result) = bucket.take(Some(state), 4_000);
+        assert_eq!(result, Err(1));
+        let (state, result) = bucket.take(Some(state), 5_000);
+        assert!(result.is_ok());
+        assert!(state.tokens < 1.0);
+
+        // never more than the capacity
+        let (state, _) = bucket.take(Some(state), 60_000);
+        assert_eq!(state.tokens, 1.0);
+    }
+}
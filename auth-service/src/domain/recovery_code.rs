@@ -0,0 +1,147 @@
+use rand::Rng;
+use rand::distr::Uniform;
+use uuid::Uuid;
+
+use crate::error::AuthApiError;
+
+use super::HashedPassword;
+
+/// Lowercase letters and digits without the easily confused `0 o 1 l i`
+const RECOVERY_CODE_ALPHABET: &[u8] = b"23456789abcdefghjkmnpqrstuvwxyz";
+const RECOVERY_CODE_LEN: usize = 10;
+
+/// A single-use fallback code for when the second factor is unavailable.
+///
+/// Shown to the user once, formatted as `xxxxx-xxxxx`; only the hash is stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecoveryCode(String);
+
+impl RecoveryCode {
+    pub fn new() -> Self {
+        let mut rng = rand::rng();
+        let distribution =
+            Uniform::new(0, RECOVERY_CODE_ALPHABET.len()).expect("non-empty alphabet");
+        let code = (0..RECOVERY_CODE_LEN)
+            .map(|_| RECOVERY_CODE_ALPHABET[rng.sample(distribution)] as char)
+            .collect();
+        RecoveryCode(code)
+    }
+
+    /// Generate a fresh set of `count` codes
+    pub fn generate_set(count: usize) -> Vec<Self> {
+        (0..count).map(|_| Self::new()).collect()
+    }
+
+    /// Accepts user input with or without the dash, in any case
+    pub fn parse(code: &str) -> Result<Self, AuthApiError> {
+        let normalized: String = code
+            .chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        if normalized.len() != RECOVERY_CODE_LEN
+            || !normalized
+                .bytes()
+                .all(|b| RECOVERY_CODE_ALPHABET.contains(&b))
+        {
+            return Err(AuthApiError::InvalidData(
+                "Invalid recovery code".to_string(),
+            ));
+        }
+        Ok(RecoveryCode(normalized))
+    }
+
+    /// Display form handed to the user
+    pub fn formatted(&self) -> String {
+        let (head, tail) = self.0.split_at(RECOVERY_CODE_LEN / 2);
+        format!("{head}-{tail}")
+    }
+}
+
+impl Default for RecoveryCode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsRef<str> for RecoveryCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Argon2 hash of a recovery code, using the same parameters as `HashedPassword`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct HashedRecoveryCode(String);
+
+impl HashedRecoveryCode {
+    pub async fn parse(code: &RecoveryCode) -> Result<Self, AuthApiError> {
+        let hashed = HashedPassword::compute_password_hash(code.as_ref()).await?;
+        Ok(HashedRecoveryCode(hashed))
+    }
+
+    /// Wrap a hash read back from storage
+    pub fn from_hash(hash: String) -> Self {
+        HashedRecoveryCode(hash)
+    }
+
+    pub async fn verify(&self, candidate: &RecoveryCode) -> Result<(), AuthApiError> {
+        HashedPassword::parse_password_hash(self.0.clone())?
+            .verify_raw_password(candidate.as_ref())
+            .await
+    }
+}
+
+impl AsRef<str> for HashedRecoveryCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A stored recovery code hash and the id used to remove it once redeemed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredRecoveryCode {
+    pub id: Uuid,
+    pub hash: HashedRecoveryCode,
+}
+
+impl StoredRecoveryCode {
+    pub fn new(hash: HashedRecoveryCode) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            hash,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recovery_code_parse_normalizes() {
+        let code = RecoveryCode::new();
+        let formatted = code.formatted();
+        assert_eq!(formatted.len(), RECOVERY_CODE_LEN + 1);
+        assert_eq!(RecoveryCode::parse(&formatted).unwrap(), code);
+        assert_eq!(
+            RecoveryCode::parse(&formatted.to_uppercase()).unwrap(),
+            code
+        );
+    }
+
+    #[test]
+    fn test_recovery_code_parse_rejects_2fa_codes() {
+        assert!(RecoveryCode::parse("123456").is_err());
+        assert!(RecoveryCode::parse("abcde-fghi0").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hashed_recovery_code_verify() {
+        let code = RecoveryCode::new();
+        let hashed = HashedRecoveryCode::parse(&code).await.unwrap();
+        assert_ne!(hashed.as_ref(), code.as_ref());
+        assert!(hashed.verify(&code).await.is_ok());
+        assert!(hashed.verify(&RecoveryCode::new()).await.is_err());
+    }
+}
@@ -1,5 +1,3 @@
-use std::ops::DerefMut;
-
 use redis::Connection;
 
 pub struct RedisConnection(pub Connection);
@@ -11,16 +9,35 @@ impl std::fmt::Debug for RedisConnection {
 }
 impl AsRef<Connection> for RedisConnection {
     fn as_ref(&self) -> &Connection {
-        return &self.0;
+        &self.0
     }
 }
 
 impl AsMut<Connection> for RedisConnection {
     fn as_mut(&mut self) -> &mut Connection {
-        return &mut self.0;
+        &mut self.0
     }
 }
 
 pub fn make_redis_key(prefix: &str, suffix: &str) -> String {
     format!("{}_{}", prefix, suffix)
 }
+
+/// Queue commands that set the expiry of `key` to `ttl` seconds from now, unless it
+/// already lives longer. Used for index sets shared by entries with different lifetimes.
+pub fn extend_expiry<'a>(
+    pipe: &'a mut redis::Pipeline,
+    key: &str,
+    ttl: u64,
+) -> &'a mut redis::Pipeline {
+    pipe.cmd("EXPIRE")
+        .arg(key)
+        .arg(ttl)
+        .arg("NX")
+        .ignore()
+        .cmd("EXPIRE")
+        .arg(key)
+        .arg(ttl)
+        .arg("GT")
+        .ignore()
+}
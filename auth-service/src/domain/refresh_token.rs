@@ -0,0 +1,118 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::domain::{Authentication, Email};
+use crate::error::AuthApiError;
+
+/// Opaque refresh token handed to the client.
+///
+/// Only its SHA-256 hash is stored, so a leaked store can't be replayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefreshToken(String);
+
+impl RefreshToken {
+    /// 256 bits of randomness, base64url encoded
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::rng().fill_bytes(&mut bytes);
+        RefreshToken(URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    pub fn parse(value: &str) -> Result<Self, AuthApiError> {
+        match URL_SAFE_NO_PAD.decode(value) {
+            Ok(bytes) if bytes.len() == 32 => Ok(RefreshToken(value.to_string())),
+            _ => Err(AuthApiError::InvalidToken),
+        }
+    }
+
+    /// Hex encoded SHA-256 of the token, used as the store key
+    pub fn hash(&self) -> String {
+        Sha256::digest(self.0.as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+}
+
+impl AsRef<str> for RefreshToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// A stored refresh token.
+///
+/// Every token issued by rotating another shares its `family_id`, going back to the login
+/// that started the chain. Presenting a `used` token means the chain leaked,
+/// so the whole family is revoked.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RefreshTokenRecord {
+    pub token_hash: String,
+    pub family_id: Uuid,
+    pub email: Email,
+    /// Unix timestamp in seconds
+    pub expires_at: i64,
+    pub used: bool,
+    /// The login that started the family
+    #[serde(default)]
+    pub authentication: Authentication,
+    /// OAuth client the family was issued to, the only one that may revoke it
+    #[serde(default)]
+    pub client_id: Option<String>,
+}
+
+impl RefreshTokenRecord {
+    /// Start a new family for a fresh login
+    pub fn new(token: &RefreshToken, email: &Email, ttl: u64) -> Self {
+        Self::in_family(token, Uuid::new_v4(), email, ttl)
+    }
+
+    pub fn in_family(token: &RefreshToken, family_id: Uuid, email: &Email, ttl: u64) -> Self {
+        RefreshTokenRecord {
+            token_hash: token.hash(),
+            family_id,
+            email: email.clone(),
+            expires_at: chrono::Utc::now().timestamp() + ttl as i64,
+            used: false,
+            authentication: Authentication::default(),
+            client_id: None,
+        }
+    }
+
+    /// The token replacing this one when it is rotated
+    pub fn next_in_family(&self, token: &RefreshToken, ttl: u64) -> Self {
+        RefreshTokenRecord {
+            authentication: self.authentication.clone(),
+            client_id: self.client_id.clone(),
+            ..Self::in_family(token, self.family_id, &self.email, ttl)
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= chrono::Utc::now().timestamp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refresh_token_parse() {
+        let token = RefreshToken::generate();
+        assert_eq!(RefreshToken::parse(token.as_ref()).unwrap(), token);
+        assert!(RefreshToken::parse("short").is_err());
+        assert!(RefreshToken::parse("not base64 at all!").is_err());
+    }
+
+    #[test]
+    fn test_refresh_token_hash() {
+        let token = RefreshToken::generate();
+        assert_eq!(token.hash(), token.hash());
+        assert_eq!(token.hash().len(), 64);
+        assert_ne!(token.hash(), RefreshToken::generate().hash());
+    }
+}
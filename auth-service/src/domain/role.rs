@@ -0,0 +1,145 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::error::AuthApiError;
+
+/// Role allowed to manage roles and their assignments, created at startup
+pub const ADMIN_ROLE: &str = "admin";
+
+const MAX_ROLE_NAME_LENGTH: usize = 64;
+
+/// A named set of permissions assigned to users.
+///
+/// Permissions are opaque to this service, e.g. `orders:read`. What they allow is up
+/// to the services checking them through `/verify-token`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Role {
+    /// Lowercase letters, digits, `-` and `_`
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+impl Role {
+    /// Check the name and permissions, permissions are deduplicated and sorted
+    pub fn parse(
+        name: &str,
+        description: &str,
+        permissions: &[String],
+    ) -> Result<Self, AuthApiError> {
+        validate_role_name(name)?;
+        if let Some(p) = permissions.iter().find(|p| !is_valid_permission(p)) {
+            return Err(AuthApiError::InvalidData(format!(
+                "Invalid permission: {p}"
+            )));
+        }
+        let mut permissions = permissions.to_vec();
+        permissions.sort();
+        permissions.dedup();
+        Ok(Role {
+            name: name.to_string(),
+            description: description.trim().to_string(),
+            permissions,
+        })
+    }
+}
+
+pub fn validate_role_name(name: &str) -> Result<(), AuthApiError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_ROLE_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid {
+        return Err(AuthApiError::InvalidData(format!(
+            "Role names are 1 to {MAX_ROLE_NAME_LENGTH} lowercase letters, digits, - or _"
+        )));
+    }
+    Ok(())
+}
+
+/// RFC 6749 section 3.3 scope tokens, so permissions fit in a space delimited list
+fn is_valid_permission(permission: &str) -> bool {
+    !permission.is_empty()
+        && permission
+            .chars()
+            .all(|c| c.is_ascii_graphic() && c != '"' && c != '\\')
+}
+
+/// Roles of a user and the permissions they add up to, as carried in auth tokens
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Grants {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permissions: Vec<String>,
+}
+
+impl Grants {
+    pub fn from_roles(roles: &[Role]) -> Self {
+        let mut names: Vec<String> = roles.iter().map(|r| r.name.clone()).collect();
+        names.sort();
+        let mut permissions: Vec<String> = roles
+            .iter()
+            .flat_map(|r| r.permissions.iter().cloned())
+            .collect();
+        permissions.sort();
+        permissions.dedup();
+        Grants {
+            roles: names,
+            permissions,
+        }
+    }
+
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
+    }
+
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|p| p == permission)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_parse() {
+        let role = Role::parse(
+            "support",
+            " Helpdesk ",
+            &[
+                "orders:read".to_string(),
+                "users:read".to_string(),
+                "orders:read".to_string(),
+            ],
+        )
+        .unwrap();
+        assert_eq!(role.description, "Helpdesk");
+        assert_eq!(role.permissions, vec!["orders:read", "users:read"]);
+
+        assert!(Role::parse("Support", "", &[]).is_err());
+        assert!(Role::parse("", "", &[]).is_err());
+        assert!(Role::parse("support", "", &["orders read".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_grants_from_roles() {
+        let support = Role::parse("support", "", &["orders:read".to_string()]).unwrap();
+        let billing = Role::parse(
+            "billing",
+            "",
+            &["orders:read".to_string(), "invoices:write".to_string()],
+        )
+        .unwrap();
+        let grants = Grants::from_roles(&[support, billing]);
+        assert_eq!(grants.roles, vec!["billing", "support"]);
+        assert_eq!(grants.permissions, vec!["invoices:write", "orders:read"]);
+        assert!(grants.has_role("support"));
+        assert!(grants.has_permission("invoices:write"));
+        assert!(!grants.has_permission("invoices:read"));
+    }
+}
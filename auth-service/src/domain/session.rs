@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::Email;
+
+/// How long `last_seen_at` may lag behind, so not every request writes to the store
+pub const SESSION_TOUCH_INTERVAL: i64 = 60;
+
+/// Longest user agent kept on a session, the header is client controlled
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+/// Where a request came from.
+///
+/// The address is the peer's, or what the configured trusted proxies forwarded.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientInfo {
+    pub ip: Option<String>,
+    pub user_agent: Option<String>,
+    /// OAuth client the app logging in claims to be, from the `X-Client-Id` header.
+    /// Checked against the registered clients before a login is tied to it.
+    pub client_id: Option<String>,
+}
+
+impl ClientInfo {
+    pub fn new(ip: Option<String>, user_agent: Option<&str>) -> Self {
+        ClientInfo {
+            ip,
+            user_agent: user_agent.map(|ua| ua.chars().take(MAX_USER_AGENT_LENGTH).collect()),
+            client_id: None,
+        }
+    }
+}
+
+/// A login, from the first token to logout or revocation.
+///
+/// Its id is the `sid` claim of the access tokens issued for it and the family id of
+/// its refresh tokens, so revoking the session ends both.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct Session {
+    pub id: Uuid,
+    #[schema(value_type = String)]
+    pub email: Email,
+    /// Unix timestamp in seconds
+    pub created_at: i64,
+    /// Unix timestamp in seconds, updated at most every `SESSION_TOUCH_INTERVAL`
+    pub last_seen_at: i64,
+    /// Unix timestamp in seconds, moved along with every refresh
+    pub expires_at: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// Organization the session's tokens are issued for, see `/switch-org`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<Uuid>,
+}
+
+impl Session {
+    /// `ttl` in seconds, the lifetime of the refresh token issued with it
+    pub fn new(id: Uuid, email: &Email, client: &ClientInfo, ttl: u64) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        Session {
+            id,
+            email: email.clone(),
+            created_at: now,
+            last_seen_at: now,
+            expires_at: now + ttl as i64,
+            ip: client.ip.clone(),
+            user_agent: client.user_agent.clone(),
+            org_id: None,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= chrono::Utc::now().timestamp()
+    }
+
+    /// Whether a request at `now` should be recorded as `last_seen_at`
+    pub fn needs_touch(&self, now: i64) -> bool {
+        now - self.last_seen_at >= SESSION_TOUCH_INTERVAL
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_touch_and_expiry() {
+        let email = Email::parse("session@test.com").unwrap();
+        let client = ClientInfo::new(Some("127.0.0.1".to_string()), Some(&"x".repeat(1000)));
+        assert_eq!(client.user_agent.as_ref().map(String::len), Some(512));
+
+        let session = Session::new(Uuid::new_v4(), &email, &client, 60);
+        assert!(!session.is_expired());
+        assert!(!session.needs_touch(session.last_seen_at + 1));
+        assert!(session.needs_touch(session.last_seen_at + SESSION_TOUCH_INTERVAL));
+
+        let expired = Session {
+            expires_at: session.created_at - 1,
+            ..session
+        };
+        assert!(expired.is_expired());
+    }
+}
@@ -5,11 +5,14 @@ use uuid::Uuid;
 use rand::Rng;
 use rand::distr::Uniform;
 
-#[derive(serde::Deserialize, serde::Serialize, Debug, ToSchema, Default, Clone)]
+#[derive(serde::Deserialize, serde::Serialize, Debug, ToSchema, Default, Clone, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum TwoFactorMethod {
     Email,
 
+    /// Time-based one-time password from an authenticator app (RFC 6238)
+    Totp,
+
     #[default]
     None,
 }
@@ -50,6 +50,15 @@ pub struct User {
     pub email: Email,
     pub password: HashedPassword,
     pub two_factor: TwoFactorMethod,
+    /// Whether the owner of the address has confirmed it
+    #[serde(default)]
+    pub verified: bool,
+    /// Unix timestamp of a pending deletion request, the account is purged once the grace period is over
+    #[serde(default)]
+    pub deletion_requested_at: Option<i64>,
+    /// Unix timestamp the account was locked by an admin, logins are refused until it is unlocked
+    #[serde(default)]
+    pub locked_at: Option<i64>,
 }
 
 impl From<UserRow> for User {
@@ -62,6 +71,9 @@ impl From<UserRow> for User {
             password: HashedPassword::parse_password_hash(value.password_hash)
                 .expect("valid hash from db"),
             two_factor: value.two_factor.try_into().unwrap_or_default(),
+            verified: value.verified,
+            deletion_requested_at: value.deletion_requested_at.map(|t| t.timestamp()),
+            locked_at: value.locked_at.map(|t| t.timestamp()),
         }
     }
 }
@@ -72,6 +84,13 @@ impl From<User> for UserRow {
             email: value.email.as_ref().to_owned(),
             password_hash: value.password.as_ref().to_owned(),
             two_factor: value.two_factor.to_string(),
+            verified: value.verified,
+            deletion_requested_at: value
+                .deletion_requested_at
+                .and_then(|t| chrono::DateTime::from_timestamp(t, 0)),
+            locked_at: value
+                .locked_at
+                .and_then(|t| chrono::DateTime::from_timestamp(t, 0)),
         }
     }
 }
@@ -82,8 +101,26 @@ impl User {
             email,
             password,
             two_factor,
+            verified: false,
+            deletion_requested_at: None,
+            locked_at: None,
         }
     }
+
+    /// Move the account to a new address. Only done once the owner has confirmed
+    /// the new address, so it counts as verified.
+    pub fn change_email(&mut self, email: Email) {
+        self.email = email;
+        self.verified = true;
+    }
+
+    pub fn is_pending_deletion(&self) -> bool {
+        self.deletion_requested_at.is_some()
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.locked_at.is_some()
+    }
 }
 
 #[cfg(test)]
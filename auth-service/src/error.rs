@@ -1,14 +1,23 @@
-use axum::http::StatusCode;
+use axum::http::{StatusCode, header};
 use axum::response::IntoResponse;
 use redis::RedisError;
 use serde::{Deserialize, Serialize};
 use thiserror::Error as ThisError;
 
+use crate::domain::OAuthError;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ErrorResponse {
     pub error: String,
 }
 
+/// Error body defined by RFC 6749 section 5.2
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OAuthErrorResponse {
+    pub error: OAuthError,
+    pub error_description: String,
+}
+
 pub trait StatusCoded {
     fn status_code(&self) -> axum::http::StatusCode;
 }
@@ -74,6 +83,10 @@ pub enum AuthApiError {
     #[error("Invalid login attempt id")]
     InvalidLoginAttemptId,
 
+    /// Passkey signature counter didn't increase - possible cloned authenticator
+    #[error("Passkey signature counter regressed")]
+    PasskeyCounterRegression,
+
     /// Invalid credentials provided
     #[error("Invalid credentials provided")]
     InvalidCredentials,
@@ -82,6 +95,58 @@ pub enum AuthApiError {
     #[error("Invalid jwt token")]
     InvalidToken,
 
+    /// An already rotated refresh token was presented again
+    #[error("Refresh token reused")]
+    RefreshTokenReuse,
+
+    /// Login refused until the email address is confirmed
+    #[error("Email address not verified")]
+    EmailNotVerified,
+
+    /// Login refused while the account waits to be purged
+    #[error("Account scheduled for deletion")]
+    AccountPendingDeletion,
+
+    /// Login refused until an admin unlocks the account
+    #[error("Account locked")]
+    AccountLocked,
+
+    /// Retry after the given number of seconds
+    #[error("Too many requests, retry in {0}s")]
+    TooManyRequests(u64),
+
+    /// OAuth protocol error, answered in the RFC 6749 format
+    #[error("{1}")]
+    OAuth(OAuthError, String),
+
+    /// No API key with that id for the user
+    #[error("API key not found")]
+    ApiKeyNotFound,
+
+    /// No session with that id for the user
+    #[error("Session not found")]
+    SessionNotFound,
+
+    /// No role with that name, or not assigned to the user
+    #[error("Role not found")]
+    RoleNotFound,
+
+    /// No organization with that id the user is a member of
+    #[error("Organization not found")]
+    OrganizationNotFound,
+
+    /// No pending invitation with that id for the user
+    #[error("Invitation not found")]
+    InvitationNotFound,
+
+    /// Authenticated, but lacking the role or permission required
+    #[error("Forbidden")]
+    Forbidden,
+
+    /// An upstream identity provider couldn't be reached or answered with garbage
+    #[error("Identity provider error: {0}")]
+    IdentityProvider(String),
+
     /// Missing token in request
     #[error("Missing token in request")]
     MissingToken,
@@ -98,6 +163,13 @@ impl Serialize for AuthApiError {
     where
         S: serde::Serializer,
     {
+        if let AuthApiError::OAuth(error, description) = self {
+            return OAuthErrorResponse {
+                error: error.clone(),
+                error_description: description.clone(),
+            }
+            .serialize(serializer);
+        }
         let error_response = ErrorResponse {
             error: self.to_string(),
         };
@@ -130,6 +202,24 @@ impl StatusCoded for AuthApiError {
             AuthApiError::Redis(_) => StatusCode::INTERNAL_SERVER_ERROR,
             AuthApiError::TwoFactorCodeGenFailedToSave => StatusCode::INTERNAL_SERVER_ERROR,
             AuthApiError::SerializationError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AuthApiError::PasskeyCounterRegression => StatusCode::UNAUTHORIZED,
+            AuthApiError::RefreshTokenReuse => StatusCode::UNAUTHORIZED,
+            AuthApiError::EmailNotVerified => StatusCode::FORBIDDEN,
+            AuthApiError::AccountPendingDeletion => StatusCode::FORBIDDEN,
+            AuthApiError::AccountLocked => StatusCode::FORBIDDEN,
+            AuthApiError::OAuth(OAuthError::InvalidClient | OAuthError::InvalidToken, _) => {
+                StatusCode::UNAUTHORIZED
+            }
+            AuthApiError::OAuth(OAuthError::InsufficientScope, _) => StatusCode::FORBIDDEN,
+            AuthApiError::OAuth(_, _) => StatusCode::BAD_REQUEST,
+            AuthApiError::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            AuthApiError::IdentityProvider(_) => StatusCode::BAD_GATEWAY,
+            AuthApiError::ApiKeyNotFound
+            | AuthApiError::SessionNotFound
+            | AuthApiError::RoleNotFound
+            | AuthApiError::OrganizationNotFound
+            | AuthApiError::InvitationNotFound => StatusCode::NOT_FOUND,
+            AuthApiError::Forbidden => StatusCode::FORBIDDEN,
         }
     }
 }
@@ -143,6 +233,10 @@ impl IntoResponse for AuthApiError {
             })
             .unwrap()
         });
+        if let AuthApiError::TooManyRequests(retry_after) = self {
+            let retry_after = [(header::RETRY_AFTER, retry_after.to_string())];
+            return (status_code, retry_after, body).into_response();
+        }
         (status_code, body).into_response()
     }
 }
@@ -9,12 +9,15 @@ pub mod services;
 pub mod state;
 pub mod utils;
 
+use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
 
 use tokio::net::TcpListener;
 
-use axum::{Router, serve::Serve};
+use axum::extract::connect_info::IntoMakeServiceWithConnectInfo;
+use axum::middleware::AddExtension;
+use axum::{Router, extract::ConnectInfo, serve::Serve};
 
 use tokio::sync::RwLock;
 use tower_http::cors::CorsLayer;
@@ -24,19 +27,89 @@ use tracing::Level;
 
 use utoipa_scalar::{Scalar, Servable};
 
+use crate::config::{RateLimitBackend, StoreBackend};
 use crate::routes::build_app_router;
 
 use self::database::Database;
-use self::domain::UserStore;
+use self::domain::{
+    ADMIN_ROLE, AccountStore, ApiKeyStore, BannedTokenStore, ClientStore, DeviceCodeStore, Email,
+    FailedLoginStore, IdentityStore, InvitationStore, OneTimeTokenStore, OrganizationStore,
+    PasskeyStore, RateLimitStore, RecoveryCodeStore, RefreshTokenStore, Role, RoleStore,
+    SessionStore, TotpStore, TwoFactorCodeStore, UserStore,
+};
+use self::error::AuthApiError;
+use self::services::account::pg::PostgresAccountStore;
+use self::services::api_key::mem::InMemoryApiKeyStore;
+use self::services::api_key::pg::PostgresApiKeyStore;
 use self::services::banned_token::mem::InMemoryBannedTokenStore;
+use self::services::banned_token::pg::PostgresBannedTokenStore;
+use self::services::banned_token::redis::RedisBannedTokenStore;
+use self::services::device_code::mem::InMemoryDeviceCodeStore;
+use self::services::device_code::redis::RedisDeviceCodeStore;
 use self::services::email::Emailer;
+use self::services::failed_login::mem::InMemoryFailedLoginStore;
+use self::services::failed_login::redis::RedisFailedLoginStore;
+use self::services::identity::mem::InMemoryIdentityStore;
+use self::services::identity::pg::PostgresIdentityStore;
+use self::services::invitation::mem::InMemoryInvitationStore;
+use self::services::invitation::pg::PostgresInvitationStore;
+use self::services::oauth_client::mem::InMemoryClientStore;
+use self::services::oauth_client::pg::PostgresClientStore;
+use self::services::one_time_token::mem::InMemoryOneTimeTokenStore;
+use self::services::one_time_token::pg::PostgresOneTimeTokenStore;
+use self::services::one_time_token::redis::RedisOneTimeTokenStore;
+use self::services::organization::mem::InMemoryOrganizationStore;
+use self::services::organization::pg::PostgresOrganizationStore;
+use self::services::passkey::mem::InMemoryPasskeyStore;
+use self::services::passkey::pg::PostgresPasskeyStore;
+use self::services::rate_limit::mem::InMemoryRateLimitStore;
+use self::services::rate_limit::redis::RedisRateLimitStore;
+use self::services::recovery_code::mem::InMemoryRecoveryCodeStore;
+use self::services::recovery_code::pg::PostgresRecoveryCodeStore;
+use self::services::refresh_token::mem::InMemoryRefreshTokenStore;
+use self::services::refresh_token::pg::PostgresRefreshTokenStore;
+use self::services::role::mem::InMemoryRoleStore;
+use self::services::role::pg::PostgresRoleStore;
+use self::services::session::mem::InMemorySessionStore;
+use self::services::session::pg::PostgresSessionStore;
+use self::services::totp::mem::InMemoryTotpStore;
+use self::services::totp::pg::PostgresTotpStore;
 use self::services::two_factor_code::mem::InMemoryTwoFactorCodeStore;
+use self::services::two_factor_code::pg::PostgresTwoFactorStore;
+use self::services::two_factor_code::redis::RedisTwoFactorStore;
 use self::services::user_store::PostgresUserStore;
 use self::services::user_store::mem::InMemoryUserStore;
 
+/// The configured backend of a store, or when unset the database if one is connected
+/// and memory otherwise
+fn store_backend(configured: Option<StoreBackend>, db: &anyhow::Result<Database>) -> StoreBackend {
+    match (configured, db) {
+        (Some(backend), _) => backend,
+        (None, Ok(_)) => StoreBackend::Postgres,
+        (None, Err(_)) => StoreBackend::Memory,
+    }
+}
+
+/// The database a store was configured to use, there is no quietly falling back to memory
+fn connected<'a>(
+    db: &'a anyhow::Result<Database>,
+    store: &str,
+) -> Result<&'a Database, AuthApiError> {
+    db.as_ref()
+        .map_err(|e| AuthApiError::Config(format!("{store} are kept in Postgres, but {e}")))
+}
+
+fn unsupported(store: &str, backend: &str) -> anyhow::Error {
+    AuthApiError::Config(format!("{store} can't be kept in {backend}")).into()
+}
+
 #[derive(Debug)]
 pub struct Application {
-    server: Serve<TcpListener, Router, Router>,
+    server: Serve<
+        TcpListener,
+        IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+        AddExtension<Router, ConnectInfo<SocketAddr>>,
+    >,
     pub address: String,
 }
 
@@ -53,14 +126,194 @@ impl Application {
         } else {
             Arc::new(RwLock::new(InMemoryUserStore::new()))
         };
-        let banned_tokens = Arc::new(RwLock::new(InMemoryBannedTokenStore::new()));
-        let two_factor_codes = Arc::new(RwLock::new(InMemoryTwoFactorCodeStore::default()));
+        let banned_tokens: Arc<RwLock<dyn BannedTokenStore>> =
+            match store_backend(config.auth.revocation_backend, &db) {
+                StoreBackend::Memory => Arc::new(RwLock::new(InMemoryBannedTokenStore::new())),
+                StoreBackend::Postgres => {
+                    let db = connected(&db, "Token revocations")?;
+                    Arc::new(RwLock::new(PostgresBannedTokenStore::new(
+                        db.pool().clone(),
+                        config.redis.ttl_ban,
+                    )))
+                }
+                StoreBackend::Redis => {
+                    Arc::new(RwLock::new(RedisBannedTokenStore::new(&config.redis)?))
+                }
+            };
+        let two_factor_codes: Arc<RwLock<dyn TwoFactorCodeStore>> =
+            match store_backend(config.auth.two_factor_backend, &db) {
+                StoreBackend::Memory => Arc::new(RwLock::new(InMemoryTwoFactorCodeStore::new(
+                    config.redis.ttl_2fa,
+                ))),
+                StoreBackend::Postgres => {
+                    let db = connected(&db, "Pending second factor checks")?;
+                    Arc::new(RwLock::new(PostgresTwoFactorStore::new(
+                        db.pool().clone(),
+                        config.redis.ttl_2fa,
+                    )))
+                }
+                StoreBackend::Redis => {
+                    Arc::new(RwLock::new(RedisTwoFactorStore::new(&config.redis)?))
+                }
+            };
+        let one_time_tokens: Arc<RwLock<dyn OneTimeTokenStore>> =
+            match store_backend(config.auth.one_time_token_backend, &db) {
+                StoreBackend::Memory => Arc::new(RwLock::new(InMemoryOneTimeTokenStore::default())),
+                StoreBackend::Postgres => {
+                    let db = connected(&db, "One-time tokens")?;
+                    Arc::new(RwLock::new(PostgresOneTimeTokenStore::new(
+                        db.pool().clone(),
+                    )))
+                }
+                StoreBackend::Redis => {
+                    Arc::new(RwLock::new(RedisOneTimeTokenStore::new(&config.redis)?))
+                }
+            };
+        let passkeys: Arc<RwLock<dyn PasskeyStore>> = if let Ok(db) = &db {
+            Arc::new(RwLock::new(PostgresPasskeyStore::new(db.pool().clone())))
+        } else {
+            Arc::new(RwLock::new(InMemoryPasskeyStore::default()))
+        };
+        let totp: Arc<RwLock<dyn TotpStore>> = if let Ok(db) = &db {
+            Arc::new(RwLock::new(PostgresTotpStore::new(db.pool().clone())))
+        } else {
+            Arc::new(RwLock::new(InMemoryTotpStore::default()))
+        };
+        let recovery_codes: Arc<RwLock<dyn RecoveryCodeStore>> = if let Ok(db) = &db {
+            Arc::new(RwLock::new(PostgresRecoveryCodeStore::new(
+                db.pool().clone(),
+            )))
+        } else {
+            Arc::new(RwLock::new(InMemoryRecoveryCodeStore::default()))
+        };
+        let refresh_tokens: Arc<RwLock<dyn RefreshTokenStore>> = if let Ok(db) = &db {
+            Arc::new(RwLock::new(PostgresRefreshTokenStore::new(
+                db.pool().clone(),
+            )))
+        } else {
+            Arc::new(RwLock::new(InMemoryRefreshTokenStore::default()))
+        };
+        let rate_limits: Arc<RwLock<dyn RateLimitStore>> = match config.rate_limit.backend {
+            RateLimitBackend::Redis => {
+                Arc::new(RwLock::new(RedisRateLimitStore::new(&config.redis)?))
+            }
+            RateLimitBackend::Memory => Arc::new(RwLock::new(InMemoryRateLimitStore::default())),
+        };
+        let failed_logins: Arc<RwLock<dyn FailedLoginStore>> = match config.lockout.backend {
+            StoreBackend::Memory => Arc::new(RwLock::new(InMemoryFailedLoginStore::default())),
+            StoreBackend::Postgres => return Err(unsupported("Failed logins", "postgres")),
+            StoreBackend::Redis => {
+                Arc::new(RwLock::new(RedisFailedLoginStore::new(&config.redis)?))
+            }
+        };
+        let clients: Arc<RwLock<dyn ClientStore>> = if let Ok(db) = &db {
+            Arc::new(RwLock::new(PostgresClientStore::new(db.pool().clone())))
+        } else {
+            Arc::new(RwLock::new(InMemoryClientStore::default()))
+        };
+        for client in &config.oauth.clients {
+            match clients.write().await.add_client(client.clone()).await {
+                // persisted by an earlier start
+                Err(AuthApiError::InvalidData(_)) => {
+                    tracing::debug!("OAuth client {} already registered", client.client_id)
+                }
+                result => result?,
+            }
+        }
+        let identities: Arc<RwLock<dyn IdentityStore>> = if let Ok(db) = &db {
+            Arc::new(RwLock::new(PostgresIdentityStore::new(db.pool().clone())))
+        } else {
+            Arc::new(RwLock::new(InMemoryIdentityStore::default()))
+        };
+        let api_keys: Arc<RwLock<dyn ApiKeyStore>> = if let Ok(db) = &db {
+            Arc::new(RwLock::new(PostgresApiKeyStore::new(db.pool().clone())))
+        } else {
+            Arc::new(RwLock::new(InMemoryApiKeyStore::default()))
+        };
+        let device_codes: Arc<RwLock<dyn DeviceCodeStore>> = match config.oauth.device_code_backend
+        {
+            StoreBackend::Memory => Arc::new(RwLock::new(InMemoryDeviceCodeStore::default())),
+            StoreBackend::Postgres => return Err(unsupported("Device authorizations", "postgres")),
+            StoreBackend::Redis => Arc::new(RwLock::new(RedisDeviceCodeStore::new(&config.redis)?)),
+        };
+        let sessions: Arc<RwLock<dyn SessionStore>> = if let Ok(db) = &db {
+            Arc::new(RwLock::new(PostgresSessionStore::new(db.pool().clone())))
+        } else {
+            Arc::new(RwLock::new(InMemorySessionStore::default()))
+        };
+        let roles: Arc<RwLock<dyn RoleStore>> = if let Ok(db) = &db {
+            Arc::new(RwLock::new(PostgresRoleStore::new(db.pool().clone())))
+        } else {
+            Arc::new(RwLock::new(InMemoryRoleStore::default()))
+        };
+        Application::seed_roles(config, &roles).await?;
+        let organizations: Arc<RwLock<dyn OrganizationStore>> = if let Ok(db) = &db {
+            Arc::new(RwLock::new(PostgresOrganizationStore::new(
+                db.pool().clone(),
+            )))
+        } else {
+            Arc::new(RwLock::new(InMemoryOrganizationStore::default()))
+        };
+        let invitations: Arc<RwLock<dyn InvitationStore>> = if let Ok(db) = &db {
+            Arc::new(RwLock::new(PostgresInvitationStore::new(db.pool().clone())))
+        } else {
+            Arc::new(RwLock::new(InMemoryInvitationStore::default()))
+        };
+        // every store keyed by the address is in the database exactly when one is connected
+        let accounts: Option<Arc<RwLock<dyn AccountStore>>> = match &db {
+            Ok(db) => Some(Arc::new(RwLock::new(PostgresAccountStore::new(
+                db.pool().clone(),
+            )))),
+            Err(_) => None,
+        };
         let emailer = Arc::new(RwLock::new(Emailer::new(&config.email)));
-        let state =
-            state::AppState::new(config, user_store, banned_tokens, two_factor_codes, emailer);
+        let state = state::AppState::new(
+            config,
+            user_store,
+            banned_tokens,
+            two_factor_codes,
+            one_time_tokens,
+            passkeys,
+            totp,
+            recovery_codes,
+            refresh_tokens,
+            rate_limits,
+            failed_logins,
+            clients,
+            identities,
+            api_keys,
+            device_codes,
+            sessions,
+            roles,
+            organizations,
+            invitations,
+            accounts,
+            emailer,
+        );
         Ok(state)
     }
 
+    /// Create the `admin` role and the configured roles, and hand out the admin role
+    async fn seed_roles(
+        config: &config::Config,
+        roles: &Arc<RwLock<dyn RoleStore>>,
+    ) -> anyhow::Result<()> {
+        let admin = Role::parse(ADMIN_ROLE, "Manages roles and users", &[])?;
+        let mut roles = roles.write().await;
+        for role in std::iter::once(admin).chain(config.rbac.roles.iter().cloned()) {
+            let role = Role::parse(&role.name, &role.description, &role.permissions)?;
+            match roles.add_role(role).await {
+                // persisted by an earlier start
+                Err(AuthApiError::InvalidData(_)) => {}
+                result => result?,
+            }
+        }
+        for email in &config.rbac.admins {
+            roles.assign_role(&Email::parse(email)?, ADMIN_ROLE).await?;
+        }
+        Ok(())
+    }
+
     /// Build the main application router with middleware and documentation.
     ///
     /// This may be useful when using axum_test to map per test routers.
@@ -86,7 +339,12 @@ impl Application {
         }
 
         let cors = CorsLayer::new()
-            .allow_methods(vec![axum::http::Method::GET, axum::http::Method::POST])
+            .allow_methods(vec![
+                axum::http::Method::GET,
+                axum::http::Method::POST,
+                axum::http::Method::PUT,
+                axum::http::Method::DELETE,
+            ])
             .allow_headers(vec![
                 axum::http::header::ORIGIN,
                 axum::http::header::AUTHORIZATION,
@@ -131,6 +389,23 @@ impl Application {
         Ok(router)
     }
 
+    /// Periodically purge accounts whose deletion grace period is over
+    fn spawn_account_purge(state: state::AppState) {
+        let period = Duration::from_secs(state.config.auth.account_purge_interval.max(1));
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                match routes::purge_deleted_accounts(&state).await {
+                    Ok(0) => {}
+                    Ok(purged) => tracing::info!("Purged {} deleted accounts", purged),
+                    Err(e) => tracing::warn!("Account purge failed: {}", &e),
+                }
+            }
+        });
+    }
+
     /// Main application builder.
     ///
     /// Arguments:
@@ -140,6 +415,7 @@ impl Application {
     /// - `Application`: The constructed application instance.
     pub async fn build(config: &config::Config) -> anyhow::Result<Self> {
         let state = Application::build_app_state(config).await?;
+        Application::spawn_account_purge(state.clone());
         let router = Application::build_router(config, state).await?;
         // Here we should use ip 0.0.0.0 so the service is listening on all the configured network interfaces.
         // This is needed for Docker to work, which we will add later on.
@@ -147,7 +423,10 @@ impl Application {
         let address = format!("{}:{}", config.server.host, config.server.port);
         let listener = TcpListener::bind(address.clone()).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
         Ok(Self { server, address })
     }
 
@@ -0,0 +1,257 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum_extra::extract::CookieJar;
+use axum_extra::extract::cookie::Cookie;
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::domain::{AccountDeletionEmailData, Email, EmailTemplate, Password, User};
+use crate::error::AuthApiError;
+use crate::routes::{
+    clear_failed_logins, count_login_attempt, record_failed_login, revoke_all_tokens,
+};
+use crate::state::AppState;
+use crate::utils::{AuthenticatedUser, FormOrJson};
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct DeleteAccountRequest {
+    /// Current password, deleting an account needs a fresh proof of identity
+    pub password: String,
+}
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct DeleteAccountResponse {
+    pub message: String,
+    /// Unix timestamp after which the account is purged
+    pub purge_at: i64,
+}
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct RestoreAccountRequest {
+    pub email: String,
+    /// The account is signed out everywhere, so the password is the only proof left
+    pub password: String,
+}
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct RestoreAccountResponse {
+    pub message: String,
+}
+
+/// Refuse a login for an account that is locked or waiting to be purged
+pub fn check_account_active(user: &User) -> Result<(), AuthApiError> {
+    if user.is_pending_deletion() {
+        return Err(AuthApiError::AccountPendingDeletion);
+    }
+    if user.is_locked() {
+        return Err(AuthApiError::AccountLocked);
+    }
+    Ok(())
+}
+
+async fn send_deletion_email(state: &AppState, email: &Email, purge_at: i64) {
+    let purge_date = chrono::DateTime::from_timestamp(purge_at, 0)
+        .map(|t| t.format("%B %-d, %Y").to_string())
+        .unwrap_or_default();
+    let template = EmailTemplate::AccountDeletion(AccountDeletionEmailData {
+        email: email.as_ref().to_string(),
+        site_url: state.config.app.url.clone(),
+        purge_date,
+    });
+    let emailer = &state.email_client.read().await;
+    if let Err(e) = emailer
+        .send_email(email, "Your Account Will Be Deleted", &template)
+        .await
+    {
+        tracing::warn!("Unable to send mail: {}", &e);
+    }
+}
+
+async fn delete_account(
+    state: &AppState,
+    email: &Email,
+    body: DeleteAccountRequest,
+) -> Result<i64, AuthApiError> {
+    let password = Password::parse(&body.password).map_err(|_| AuthApiError::Unauthorized)?;
+    let mut user = state.user_store.read().await.get_user(email).await?;
+    user.password
+        .verify_raw_password(password.as_ref())
+        .await
+        .map_err(|_| AuthApiError::Unauthorized)?;
+
+    // asking twice keeps the original date
+    let requested_at = *user
+        .deletion_requested_at
+        .get_or_insert_with(|| chrono::Utc::now().timestamp());
+    state.user_store.write().await.update_user(&user).await?;
+    revoke_all_tokens(state, email).await?;
+
+    let purge_at = requested_at + state.config.auth.account_deletion_grace_period as i64;
+    send_deletion_email(state, email, purge_at).await;
+    Ok(purge_at)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/account",
+    tag = "Authentication",
+    responses(
+        (status = 202, description = "Account scheduled for deletion, all sessions revoked", body = DeleteAccountResponse),
+        (status = 400, description = "Missing auth token"),
+        (status = 401, description = "Unauthorized or wrong password")
+    )
+)]
+#[instrument(skip(jar, state, user, body))]
+pub async fn delete_account_handler(
+    jar: CookieJar,
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    FormOrJson(body): FormOrJson<DeleteAccountRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthApiError>) {
+    let purge_at = match delete_account(&state, &user.email, body).await {
+        Ok(purge_at) => purge_at,
+        Err(e) => return (jar, Err(e)),
+    };
+    let jar = jar
+        .remove(Cookie::from(state.config.jwt.cookie_name.clone()))
+        .remove(Cookie::from(state.config.jwt.refresh_cookie_name.clone()));
+    (
+        jar,
+        Ok((
+            StatusCode::ACCEPTED,
+            Json(DeleteAccountResponse {
+                message: "Account scheduled for deletion".to_string(),
+                purge_at,
+            }),
+        )),
+    )
+}
+
+async fn restore_account(
+    state: &AppState,
+    body: RestoreAccountRequest,
+) -> Result<(), AuthApiError> {
+    let email = Email::parse(&body.email)?;
+    let password = Password::parse(&body.password).map_err(|_| AuthApiError::Unauthorized)?;
+    // as open to guessing as the login form, so it counts toward the same lockout
+    let attempt = count_login_attempt(state, &email).await?;
+    let user = {
+        let user_store = state.user_store.read().await;
+        match user_store.get_user(&email).await {
+            Ok(user) => user_store
+                .validate_credentials(&email, &password)
+                .await
+                .map(|_| user),
+            Err(e) => Err(e),
+        }
+    };
+    let mut user = match user {
+        Ok(user) => user,
+        Err(_) => {
+            record_failed_login(state, &email, &attempt).await?;
+            return Err(AuthApiError::Unauthorized);
+        }
+    };
+    clear_failed_logins(state, &email).await?;
+
+    if user.deletion_requested_at.take().is_some() {
+        state.user_store.write().await.update_user(&user).await?;
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/account/restore",
+    tag = "Authentication",
+    responses(
+        (status = 200, description = "Deletion cancelled, the account can log in again", body = RestoreAccountResponse),
+        (status = 401, description = "Wrong email or password"),
+        (status = 429, description = "Locked out after wrong passwords, see the Retry-After header")
+    )
+)]
+#[instrument(skip(state, body))]
+pub async fn restore_account_handler(
+    State(state): State<AppState>,
+    FormOrJson(body): FormOrJson<RestoreAccountRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    restore_account(&state, body).await?;
+    Ok((
+        StatusCode::OK,
+        Json(RestoreAccountResponse {
+            message: "Account deletion cancelled".to_string(),
+        }),
+    ))
+}
+
+/// Remove the account and every per-user row kept by the other stores.
+/// The user record goes last, so a failure part way is picked up again on the next run.
+async fn purge_user(state: &AppState, email: &Email) -> Result<(), AuthApiError> {
+    state
+        .one_time_tokens
+        .write()
+        .await
+        .revoke_tokens(email)
+        .await?;
+    state
+        .refresh_tokens
+        .write()
+        .await
+        .revoke_user(email)
+        .await?;
+    state.sessions.write().await.revoke_user(email).await?;
+    // nothing to remove is fine, there may be no login in flight
+    _ = state.two_factor.write().await.remove_code(email).await;
+    state.passkeys.write().await.delete_user(email).await?;
+    state.totp.write().await.delete_user(email).await?;
+    state
+        .recovery_codes
+        .write()
+        .await
+        .delete_user(email)
+        .await?;
+    state.identities.write().await.delete_user(email).await?;
+    state.api_keys.write().await.delete_user(email).await?;
+    state.roles.write().await.delete_user(email).await?;
+    state.organizations.write().await.delete_user(email).await?;
+    state.invitations.write().await.delete_user(email).await?;
+    state.device_codes.write().await.delete_user(email).await?;
+    clear_failed_logins(state, email).await?;
+    state.banned_tokens.write().await.delete_user(email).await?;
+    state.user_store.write().await.delete_user(email).await
+}
+
+/// Purge every account whose grace period is over, returns how many were removed
+pub async fn purge_deleted_accounts(state: &AppState) -> Result<usize, AuthApiError> {
+    // the address can be signed up again once purged, so every access token of the old
+    // account, session or OAuth, has to be expired (plus the 60s validation leeway)
+    // before its version is dropped
+    let config = &state.config;
+    let token_ttl = config
+        .jwt
+        .access_token_ttl
+        .max(config.oauth.access_token_ttl);
+    let grace = config
+        .auth
+        .account_deletion_grace_period
+        .max(token_ttl + 60);
+    let cutoff = chrono::Utc::now().timestamp() - grace as i64;
+    let due = state
+        .user_store
+        .read()
+        .await
+        .users_pending_deletion(cutoff)
+        .await?;
+
+    let mut purged = 0;
+    for email in due {
+        match purge_user(state, &email).await {
+            Ok(()) => purged += 1,
+            Err(e) => tracing::warn!("Unable to purge account: {}", &e),
+        }
+    }
+    Ok(purged)
+}
@@ -0,0 +1,306 @@
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+use utoipa_axum::{router::OpenApiRouter, routes};
+
+use crate::domain::{Email, TwoFactorMethod, User};
+use crate::error::AuthApiError;
+use crate::routes::{clear_failed_logins, revoke_sessions};
+use crate::state::AppState;
+use crate::utils::AdminUser;
+
+const DEFAULT_PER_PAGE: u64 = 20;
+const MAX_PER_PAGE: u64 = 100;
+
+/// Support tooling, every route requires the `admin` role
+pub fn admin_router() -> OpenApiRouter<AppState> {
+    OpenApiRouter::new()
+        .routes(routes!(list_users_handler))
+        .routes(routes!(get_user_handler))
+        .routes(routes!(lock_user_handler))
+        .routes(routes!(unlock_user_handler))
+        .routes(routes!(reset_two_factor_handler))
+        .routes(routes!(logout_user_handler))
+}
+
+#[derive(Serialize, Deserialize, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct UserSearchParams {
+    /// Part of the email, ignoring case
+    pub search: Option<String>,
+    /// Starts at 1
+    pub page: Option<u64>,
+    /// Defaults to 20, at most 100
+    pub per_page: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct UserSummary {
+    #[schema(value_type = String)]
+    pub email: Email,
+    pub verified: bool,
+    pub two_factor: TwoFactorMethod,
+    /// Unix timestamp the account was locked
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked_at: Option<i64>,
+    /// Unix timestamp the owner asked for the account to be deleted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deletion_requested_at: Option<i64>,
+}
+
+impl From<User> for UserSummary {
+    fn from(user: User) -> Self {
+        UserSummary {
+            email: user.email,
+            verified: user.verified,
+            two_factor: user.two_factor,
+            locked_at: user.locked_at,
+            deletion_requested_at: user.deletion_requested_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct UserPage {
+    pub users: Vec<UserSummary>,
+    /// Users matching the search across every page
+    pub total: u64,
+    pub page: u64,
+    pub per_page: u64,
+}
+
+/// A user along with the second factors they have set up
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct UserDetails {
+    #[serde(flatten)]
+    pub user: UserSummary,
+    pub passkeys: usize,
+    /// Unused recovery codes
+    pub recovery_codes: usize,
+}
+
+async fn existing_user(state: &AppState, email: &str) -> Result<User, AuthApiError> {
+    let email = Email::parse(email).map_err(|_| AuthApiError::UserNotFound)?;
+    state.user_store.read().await.get_user(&email).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/users",
+    tag = "Admin",
+    params(UserSearchParams),
+    responses(
+        (status = 200, description = "A page of users ordered by email", body = UserPage),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin")
+    )
+)]
+#[instrument(skip(state, _admin))]
+pub async fn list_users_handler(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    Query(params): Query<UserSearchParams>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let search = params.search.unwrap_or_default();
+    let search = search.trim();
+    let page = params.page.unwrap_or(1).max(1);
+    let per_page = params
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+    let user_store = state.user_store.read().await;
+    let total = user_store.count_users(search).await?;
+    let users = user_store
+        .search_users(search, (page - 1).saturating_mul(per_page), per_page)
+        .await?;
+    Ok((
+        StatusCode::OK,
+        Json(UserPage {
+            users: users.into_iter().map(UserSummary::from).collect(),
+            total,
+            page,
+            per_page,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{email}",
+    tag = "Admin",
+    params(("email" = String, Path, description = "Email of the user")),
+    responses(
+        (status = 200, description = "The user and their second factors", body = UserDetails),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "User not found")
+    )
+)]
+#[instrument(skip(state, _admin))]
+pub async fn get_user_handler(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let user = existing_user(&state, &email).await?;
+    let passkeys = state
+        .passkeys
+        .read()
+        .await
+        .get_passkeys(&user.email)
+        .await?
+        .len();
+    let recovery_codes = state
+        .recovery_codes
+        .read()
+        .await
+        .get_codes(&user.email)
+        .await?
+        .len();
+    Ok((
+        StatusCode::OK,
+        Json(UserDetails {
+            user: user.into(),
+            passkeys,
+            recovery_codes,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{email}/lock",
+    tag = "Admin",
+    params(("email" = String, Path, description = "Email of the user")),
+    responses(
+        (status = 204, description = "Account locked and signed out everywhere, logins are refused until it is unlocked"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "User not found"),
+        (status = 422, description = "Admins can't lock themselves")
+    )
+)]
+#[instrument(skip(state, admin))]
+pub async fn lock_user_handler(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let user = existing_user(&state, &email).await?;
+    if user.email == admin.email {
+        return Err(AuthApiError::InvalidData(
+            "Admins can't lock themselves".to_string(),
+        ));
+    }
+    if !user.is_locked() {
+        let now = chrono::Utc::now().timestamp();
+        state
+            .user_store
+            .write()
+            .await
+            .set_locked(&user.email, Some(now))
+            .await?;
+    }
+    revoke_sessions(&state, &user.email).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{email}/unlock",
+    tag = "Admin",
+    params(("email" = String, Path, description = "Email of the user")),
+    responses(
+        (status = 204, description = "Account unlocked, including lockouts from failed logins"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "User not found")
+    )
+)]
+#[instrument(skip(state, _admin))]
+pub async fn unlock_user_handler(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let user = existing_user(&state, &email).await?;
+    state
+        .user_store
+        .write()
+        .await
+        .set_locked(&user.email, None)
+        .await?;
+    clear_failed_logins(&state, &user.email).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/{email}/2fa",
+    tag = "Admin",
+    params(("email" = String, Path, description = "Email of the user")),
+    responses(
+        (status = 204, description = "Second factor turned off, the authenticator secret, recovery codes, logins waiting for a code and sessions are gone. Passkeys are kept, they are a first factor."),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "User not found")
+    )
+)]
+#[instrument(skip(state, _admin))]
+pub async fn reset_two_factor_handler(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let user = existing_user(&state, &email).await?;
+    state
+        .user_store
+        .write()
+        .await
+        .set_two_factor(&user.email, &TwoFactorMethod::None)
+        .await?;
+    state.totp.write().await.delete_user(&user.email).await?;
+    state
+        .recovery_codes
+        .write()
+        .await
+        .delete_user(&user.email)
+        .await?;
+    // nothing to remove is fine, there may be no login in flight
+    _ = state
+        .two_factor
+        .write()
+        .await
+        .remove_code(&user.email)
+        .await;
+    // whoever has the lost authenticator may have used it to get in
+    revoke_sessions(&state, &user.email).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{email}/logout",
+    tag = "Admin",
+    params(("email" = String, Path, description = "Email of the user")),
+    responses(
+        (status = 204, description = "Every session, access token and refresh token of the user revoked"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "User not found")
+    )
+)]
+#[instrument(skip(state, _admin))]
+pub async fn logout_user_handler(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let user = existing_user(&state, &email).await?;
+    revoke_sessions(&state, &user.email).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,137 @@
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::{ApiKey, ApiKeyRecord};
+use crate::error::AuthApiError;
+use crate::state::AppState;
+use crate::utils::{AuthenticatedUser, FormOrJson};
+
+const MAX_NAME_LENGTH: usize = 64;
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct CreateApiKeyRequest {
+    /// Unique among the user's keys
+    pub name: String,
+    /// Limits what the key can be used for, none to act with everything the user can do
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Lifetime in seconds, the key doesn't expire without one
+    pub expires_in: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct CreateApiKeyResponse {
+    /// Shown once - only a hash is kept
+    pub key: String,
+    #[serde(flatten)]
+    pub record: ApiKeyRecord,
+}
+
+fn validate_request(body: &CreateApiKeyRequest) -> Result<(), AuthApiError> {
+    let name = body.name.trim();
+    if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+        return Err(AuthApiError::InvalidData(format!(
+            "API key names are 1 to {MAX_NAME_LENGTH} characters"
+        )));
+    }
+    // RFC 6749 section 3.3 scope tokens, so they can be checked like OAuth scopes
+    if body.scopes.iter().any(|s| {
+        s.is_empty()
+            || s.chars()
+                .any(|c| c.is_whitespace() || c == '"' || c == '\\')
+    }) {
+        return Err(AuthApiError::InvalidData("Invalid scope".to_string()));
+    }
+    if body.expires_in == Some(0) {
+        return Err(AuthApiError::InvalidData(
+            "expires_in must be positive".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api-keys",
+    tag = "API Keys",
+    responses(
+        (status = 201, description = "API key created, the key is only ever shown here", body = CreateApiKeyResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 422, description = "Invalid name or scopes, or the name is taken")
+    )
+)]
+#[instrument(skip(state, user, body))]
+pub async fn create_api_key_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    FormOrJson(body): FormOrJson<CreateApiKeyRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    validate_request(&body)?;
+    let key = ApiKey::generate();
+    let record = ApiKeyRecord::new(
+        &key,
+        &user.email,
+        body.name.trim(),
+        &body.scopes,
+        body.expires_in,
+    );
+    state.api_keys.write().await.add_key(record.clone()).await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateApiKeyResponse {
+            key: key.as_ref().to_string(),
+            record,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api-keys",
+    tag = "API Keys",
+    responses(
+        (status = 200, description = "The user's API keys, without the keys themselves", body = Vec<ApiKeyRecord>),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+#[instrument(skip(state, user))]
+pub async fn list_api_keys_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let keys = state.api_keys.read().await.list_keys(&user.email).await?;
+    Ok((StatusCode::OK, Json(keys)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api-keys/{id}",
+    tag = "API Keys",
+    params(("id" = Uuid, Path, description = "Id of the API key")),
+    responses(
+        (status = 204, description = "API key revoked"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "The user has no API key with that id")
+    )
+)]
+#[instrument(skip(state, user))]
+pub async fn revoke_api_key_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let id = Uuid::parse_str(&id).map_err(|_| AuthApiError::ApiKeyNotFound)?;
+    state
+        .api_keys
+        .write()
+        .await
+        .revoke_key(&user.email, &id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,370 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::domain::{
+    Email, EmailChangeEmailData, EmailChangeNoticeEmailData, EmailTemplate, TokenPurpose, User,
+};
+use crate::error::AuthApiError;
+use crate::routes::{revoke_all_tokens, revoke_sessions};
+use crate::state::AppState;
+use crate::utils::auth::{
+    OneTimeTokenClaims, generate_email_change_token, validate_one_time_token,
+};
+use crate::utils::{AuthenticatedUser, FormOrJson};
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct ChangeEmailRequest {
+    pub new_email: String,
+}
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct EmailChangeTokenRequest {
+    /// Token from the confirmation or undo email
+    pub token: String,
+}
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct ChangeEmailResponse {
+    pub message: String,
+}
+
+/// What is stored against the account's address, in the order an email change moves
+/// it when the stores can't share a transaction
+#[derive(Debug, Clone, Copy)]
+enum AccountPart {
+    User,
+    Passkeys,
+    Totp,
+    RecoveryCodes,
+    Identities,
+    ApiKeys,
+    Roles,
+    Memberships,
+    Invitations,
+    FailedLogins,
+}
+
+/// The user record goes first, it is what refuses an address that is already taken
+const ACCOUNT_PARTS: [AccountPart; 10] = [
+    AccountPart::User,
+    AccountPart::Passkeys,
+    AccountPart::Totp,
+    AccountPart::RecoveryCodes,
+    AccountPart::Identities,
+    AccountPart::ApiKeys,
+    AccountPart::Roles,
+    AccountPart::Memberships,
+    AccountPart::Invitations,
+    AccountPart::FailedLogins,
+];
+
+async fn move_part(
+    state: &AppState,
+    part: AccountPart,
+    from: &Email,
+    to: &Email,
+) -> Result<(), AuthApiError> {
+    match part {
+        AccountPart::User => {
+            let mut users = state.user_store.write().await;
+            users.change_email(from, to).await.map(|_| ())
+        }
+        AccountPart::Passkeys => state.passkeys.write().await.change_email(from, to).await,
+        AccountPart::Totp => state.totp.write().await.change_email(from, to).await,
+        AccountPart::RecoveryCodes => {
+            let mut codes = state.recovery_codes.write().await;
+            codes.change_email(from, to).await
+        }
+        AccountPart::Identities => state.identities.write().await.change_email(from, to).await,
+        AccountPart::ApiKeys => state.api_keys.write().await.change_email(from, to).await,
+        AccountPart::Roles => state.roles.write().await.change_email(from, to).await,
+        AccountPart::Memberships => {
+            let mut organizations = state.organizations.write().await;
+            organizations.change_email(from, to).await
+        }
+        AccountPart::Invitations => {
+            let mut invitations = state.invitations.write().await;
+            invitations.change_email(from, to).await
+        }
+        AccountPart::FailedLogins => {
+            let mut failed_logins = state.failed_logins.write().await;
+            failed_logins.change_email(from, to).await
+        }
+    }
+}
+
+/// A rollback that didn't go through leaves the account split across both addresses,
+/// that has to reach the caller rather than only the log
+fn split_account(parts: &[String]) -> AuthApiError {
+    AuthApiError::UnexpectedError(format!(
+        "Email change only partly undone, {} left under the new address",
+        parts.join(", ")
+    ))
+}
+
+/// Move `parts` one store at a time. If one fails, the ones already moved are moved back
+/// in reverse, so the user record is last and nobody can take `old` in the meantime.
+async fn move_parts(
+    state: &AppState,
+    parts: &[AccountPart],
+    old: &Email,
+    new: &Email,
+) -> Result<(), AuthApiError> {
+    for (moved, part) in parts.iter().enumerate() {
+        let Err(e) = move_part(state, *part, old, new).await else {
+            continue;
+        };
+        tracing::error!("Unable to move {:?} to the new address: {}", part, e);
+        // each store moves in a single statement, the one that failed has nothing to undo
+        let mut stuck = Vec::new();
+        for part in parts[..moved].iter().rev() {
+            if let Err(e) = move_part(state, *part, new, old).await {
+                tracing::error!("Unable to move {:?} back to the old address: {}", part, e);
+                stuck.push(format!("{part:?}"));
+            }
+        }
+        if !stuck.is_empty() {
+            return Err(split_account(&stuck));
+        }
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Re-key the account and everything stored against it from `old` to `new`. With the
+/// stores in the database that is one transaction, only the failed logins, which never
+/// are, follow it separately and undo it if they can't.
+async fn move_account(state: &AppState, old: &Email, new: &Email) -> Result<User, AuthApiError> {
+    let Some(accounts) = &state.accounts else {
+        move_parts(state, &ACCOUNT_PARTS, old, new).await?;
+        return state.user_store.read().await.get_user(new).await;
+    };
+    let user = accounts.write().await.change_email(old, new).await?;
+    if let Err(e) = move_part(state, AccountPart::FailedLogins, old, new).await {
+        tracing::error!("Unable to move FailedLogins to the new address: {}", e);
+        if let Err(e) = accounts.write().await.change_email(new, old).await {
+            tracing::error!("Unable to move the account back to the old address: {}", e);
+            return Err(split_account(&["the account".to_string()]));
+        }
+        return Err(e);
+    }
+    Ok(user)
+}
+
+/// Sign, register and mail a link for `purpose` to `recipient`
+async fn send_change_link(
+    state: &AppState,
+    email: &Email,
+    new_email: &Email,
+    purpose: TokenPurpose,
+) -> Result<(), AuthApiError> {
+    let (ttl, redirect_url, recipient) = match purpose {
+        TokenPurpose::EmailChangeUndo => (
+            state.config.auth.email_change_undo_ttl,
+            &state.config.app.email_change_undo_redirect_url,
+            email,
+        ),
+        _ => (
+            state.config.auth.email_change_ttl,
+            &state.config.app.email_change_redirect_url,
+            new_email,
+        ),
+    };
+    let (id, token) =
+        generate_email_change_token(email, new_email, &purpose, ttl, &state.config.jwt.secret)
+            .map_err(|e| AuthApiError::UnexpectedError(e.to_string()))?;
+    // both are tracked against the current address, so revoking its tokens cancels the change
+    state
+        .one_time_tokens
+        .write()
+        .await
+        .add_token(&id, &purpose, email, ttl)
+        .await?;
+
+    let link_url = format!("{}?token={}", redirect_url, token);
+    let site_url = state.config.app.url.clone();
+    let (subject, template) = match purpose {
+        TokenPurpose::EmailChangeUndo => (
+            "Your Email Is Being Changed",
+            EmailTemplate::EmailChangeNotice(EmailChangeNoticeEmailData {
+                email: email.as_ref().to_string(),
+                new_email: new_email.as_ref().to_string(),
+                site_url,
+                link_url,
+            }),
+        ),
+        _ => (
+            "Confirm Your New Email",
+            EmailTemplate::EmailChange(EmailChangeEmailData {
+                email: new_email.as_ref().to_string(),
+                old_email: email.as_ref().to_string(),
+                site_url,
+                link_url,
+            }),
+        ),
+    };
+    let emailer = &state.email_client.read().await;
+    if let Err(e) = emailer.send_email(recipient, subject, &template).await {
+        tracing::warn!("Unable to send mail: {}", &e);
+    }
+    Ok(())
+}
+
+/// Validate and burn an email change token, returns its claims with the new address
+async fn consume_change_token(
+    state: &AppState,
+    token: &str,
+    purpose: &TokenPurpose,
+) -> Result<(OneTimeTokenClaims, Email), AuthApiError> {
+    let claims = validate_one_time_token(token, purpose, &state.config.jwt)
+        .await
+        .map_err(|_| AuthApiError::InvalidToken)?;
+    let email = state
+        .one_time_tokens
+        .write()
+        .await
+        .consume_token(&claims.jti, purpose)
+        .await?;
+    if email != claims.sub {
+        return Err(AuthApiError::InvalidToken);
+    }
+    let new_email = claims.new_email.clone().ok_or(AuthApiError::InvalidToken)?;
+    Ok((claims, new_email))
+}
+
+#[utoipa::path(
+    post,
+    path = "/email/change",
+    tag = "Authentication",
+    responses(
+        (status = 202, description = "Confirmation sent to the new address, notice sent to the old one", body = ChangeEmailResponse),
+        (status = 400, description = "Missing auth token or invalid email"),
+        (status = 401, description = "Unauthorized"),
+        (status = 409, description = "Address already in use"),
+        (status = 422, description = "New address matches the current one")
+    )
+)]
+#[instrument(skip(state, user, body))]
+pub async fn change_email_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    FormOrJson(body): FormOrJson<ChangeEmailRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let new_email = Email::parse(&body.new_email)?;
+    if new_email == user.email {
+        return Err(AuthApiError::InvalidData(
+            "New email matches the current one".to_string(),
+        ));
+    }
+    if state
+        .user_store
+        .read()
+        .await
+        .get_user(&new_email)
+        .await
+        .is_ok()
+    {
+        return Err(AuthApiError::UserAlreadyExists);
+    }
+
+    send_change_link(&state, &user.email, &new_email, TokenPurpose::EmailChange).await?;
+    send_change_link(
+        &state,
+        &user.email,
+        &new_email,
+        TokenPurpose::EmailChangeUndo,
+    )
+    .await?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(ChangeEmailResponse {
+            message: "Check the new address to confirm the change".to_string(),
+        }),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/email/change/confirm",
+    tag = "Authentication",
+    responses(
+        (status = 200, description = "Account moved to the new address, all sessions revoked", body = ChangeEmailResponse),
+        (status = 401, description = "Invalid, expired or already used token"),
+        (status = 409, description = "Address taken in the meantime")
+    )
+)]
+#[instrument(skip(state, body))]
+pub async fn confirm_email_change_handler(
+    State(state): State<AppState>,
+    FormOrJson(body): FormOrJson<EmailChangeTokenRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let (claims, new_email) =
+        consume_change_token(&state, &body.token, &TokenPurpose::EmailChange).await?;
+    move_account(&state, &claims.sub, &new_email)
+        .await
+        .map_err(|e| match e {
+            AuthApiError::UserNotFound => AuthApiError::InvalidToken,
+            e => e,
+        })?;
+    // the undo link is tracked against the old address, so only sessions are revoked there
+    revoke_sessions(&state, &claims.sub).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ChangeEmailResponse {
+            message: "Email updated".to_string(),
+        }),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/email/change/undo",
+    tag = "Authentication",
+    responses(
+        (status = 200, description = "Change cancelled or reverted, all sessions revoked", body = ChangeEmailResponse),
+        (status = 401, description = "Invalid, expired or already used token"),
+        (status = 409, description = "Old address taken in the meantime")
+    )
+)]
+#[instrument(skip(state, body))]
+pub async fn undo_email_change_handler(
+    State(state): State<AppState>,
+    FormOrJson(body): FormOrJson<EmailChangeTokenRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let (claims, new_email) =
+        consume_change_token(&state, &body.token, &TokenPurpose::EmailChangeUndo).await?;
+    let old_email = claims.sub;
+
+    let confirmed = state
+        .user_store
+        .read()
+        .await
+        .get_user(&old_email)
+        .await
+        .is_err();
+    if confirmed {
+        move_account(&state, &new_email, &old_email)
+            .await
+            .map_err(|e| match e {
+                AuthApiError::UserNotFound => AuthApiError::InvalidToken,
+                e => e,
+            })?;
+        revoke_all_tokens(&state, &new_email).await?;
+    }
+    // whoever asked for the change may still hold a session, and a pending confirmation dies here too
+    revoke_all_tokens(&state, &old_email).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ChangeEmailResponse {
+            message: "Email change undone".to_string(),
+        }),
+    ))
+}
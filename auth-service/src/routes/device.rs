@@ -0,0 +1,305 @@
+use axum::Json;
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::domain::{
+    DeviceAuthorization, DeviceAuthorizationStatus, DeviceCode, DevicePoll, OAuthClient,
+    OAuthError, SLOW_DOWN_INCREMENT, UserCode, parse_scopes,
+};
+use crate::error::AuthApiError;
+use crate::routes::{TokenRequest, TokenResponse, authenticate_client, check_account_active};
+use crate::state::AppState;
+use crate::utils::auth::generate_oauth_access_token;
+use crate::utils::{AuthenticatedUser, FormOrJson};
+
+/// Attempts at drawing a user code that isn't already taken
+const USER_CODE_ATTEMPTS: usize = 3;
+
+/// Device authorization request, RFC 8628 section 3.1
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct DeviceCodeRequest {
+    /// Can be sent in an `Authorization: Basic` header instead
+    pub client_id: Option<String>,
+    /// `client_secret_post` authentication, confidential clients only
+    pub client_secret: Option<String>,
+    /// Space delimited, defaults to every scope the client is allowed
+    pub scope: Option<String>,
+}
+
+/// RFC 8628 section 3.2
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct DeviceCodeResponse {
+    /// Polled with at the token endpoint
+    pub device_code: String,
+    /// Shown to the user, who enters it at `verification_uri`
+    pub user_code: String,
+    pub verification_uri: String,
+    /// `verification_uri` with the user code filled in, e.g. for a QR code
+    pub verification_uri_complete: String,
+    pub expires_in: u64,
+    /// Minimum seconds between polls
+    pub interval: u64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct DeviceVerifyParams {
+    /// Case and dashes are ignored
+    pub user_code: String,
+}
+
+/// What the user is asked to approve
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct DeviceVerifyResponse {
+    pub user_code: String,
+    pub client_name: String,
+    pub scopes: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct DeviceDecision {
+    pub user_code: String,
+    /// `approve` or anything else to deny
+    pub decision: String,
+}
+
+fn unknown_user_code() -> AuthApiError {
+    AuthApiError::InvalidData("Unknown or expired user code".to_string())
+}
+
+/// The pending request behind a user code typed in by the user
+async fn pending_authorization(
+    state: &AppState,
+    user_code: &str,
+) -> Result<DeviceAuthorization, AuthApiError> {
+    let code = UserCode::parse(user_code)?;
+    state
+        .device_codes
+        .read()
+        .await
+        .get_by_user_code(&code)
+        .await
+        .map_err(|_| unknown_user_code())
+}
+
+#[utoipa::path(
+    post,
+    path = "/device/code",
+    tag = "OAuth",
+    request_body(content = DeviceCodeRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Device and user codes issued", body = DeviceCodeResponse),
+        (status = 400, description = "Scope not allowed for this client"),
+        (status = 401, description = "Unknown client or failed client authentication")
+    )
+)]
+#[instrument(skip(state, headers, body))]
+pub async fn device_code_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    FormOrJson(body): FormOrJson<DeviceCodeRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let client = authenticate_client(
+        &state,
+        &headers,
+        body.client_id.as_deref(),
+        body.client_secret.as_deref(),
+    )
+    .await?;
+    let scope = client
+        .grant_scopes(body.scope.as_deref())
+        .map_err(|e| AuthApiError::OAuth(e, "Scope not allowed for this client".to_string()))?
+        .join(" ");
+
+    let config = &state.config.oauth;
+    let device_code = DeviceCode::generate();
+    let mut attempts = 0;
+    let user_code = loop {
+        let user_code = UserCode::generate();
+        let authorization = DeviceAuthorization::new(
+            &device_code,
+            user_code.clone(),
+            &client.client_id,
+            &scope,
+            config.device_code_ttl,
+            config.device_poll_interval,
+        );
+        // kept past expiry so a late poll is told expired_token rather than invalid_grant
+        let retention = config.device_code_ttl * 2;
+        match state
+            .device_codes
+            .write()
+            .await
+            .add(authorization, retention)
+            .await
+        {
+            Ok(()) => break user_code,
+            Err(AuthApiError::InvalidData(_)) if attempts + 1 < USER_CODE_ATTEMPTS => attempts += 1,
+            Err(e) => return Err(e),
+        }
+    };
+
+    let verification_uri = format!("{}/device.html", config.issuer.trim_end_matches('/'));
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CACHE_CONTROL, "no-store"),
+            (header::PRAGMA, "no-cache"),
+        ],
+        Json(DeviceCodeResponse {
+            device_code: device_code.as_ref().to_string(),
+            user_code: user_code.formatted(),
+            verification_uri_complete: format!(
+                "{verification_uri}?user_code={}",
+                user_code.formatted()
+            ),
+            verification_uri,
+            expires_in: config.device_code_ttl,
+            interval: config.device_poll_interval,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/device/verify",
+    tag = "OAuth",
+    params(DeviceVerifyParams),
+    responses(
+        (status = 200, description = "The request waiting for the user's decision", body = DeviceVerifyResponse),
+        (status = 400, description = "Missing auth token"),
+        (status = 401, description = "Unauthorized"),
+        (status = 422, description = "Unknown or expired user code")
+    )
+)]
+#[instrument(skip(state, _user))]
+pub async fn device_verify_handler(
+    State(state): State<AppState>,
+    _user: AuthenticatedUser,
+    Query(params): Query<DeviceVerifyParams>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let authorization = pending_authorization(&state, &params.user_code).await?;
+    let client = state
+        .clients
+        .read()
+        .await
+        .get_client(&authorization.client_id)
+        .await
+        .map_err(|_| unknown_user_code())?;
+    Ok((
+        StatusCode::OK,
+        Json(DeviceVerifyResponse {
+            user_code: authorization.user_code.formatted(),
+            client_name: client.name,
+            scopes: parse_scopes(&authorization.scope),
+        }),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/device/verify",
+    tag = "OAuth",
+    request_body = DeviceDecision,
+    responses(
+        (status = 204, description = "Decision recorded, the device learns it on its next poll"),
+        (status = 400, description = "Missing auth token"),
+        (status = 401, description = "Unauthorized"),
+        (status = 422, description = "Unknown, expired or already decided user code")
+    )
+)]
+#[instrument(skip(state, user, body))]
+pub async fn device_decision_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    FormOrJson(body): FormOrJson<DeviceDecision>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let authorization = pending_authorization(&state, &body.user_code).await?;
+    let status = match body.decision.as_str() {
+        "approve" => DeviceAuthorizationStatus::Approved { email: user.email },
+        _ => DeviceAuthorizationStatus::Denied,
+    };
+    state
+        .device_codes
+        .write()
+        .await
+        .set_status(&authorization.user_code, status)
+        .await
+        .map_err(|_| unknown_user_code())?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Token for a device the user approved, RFC 8628 section 3.4
+pub async fn device_code_grant(
+    state: &AppState,
+    client: OAuthClient,
+    body: TokenRequest,
+) -> Result<TokenResponse, AuthApiError> {
+    let oauth_error = |error, description: &str| AuthApiError::OAuth(error, description.into());
+    let Some(device_code) = body.device_code.as_deref() else {
+        return Err(oauth_error(
+            OAuthError::InvalidRequest,
+            "device_code is required",
+        ));
+    };
+    let invalid = || oauth_error(OAuthError::InvalidGrant, "Invalid device_code");
+    let device_code = DeviceCode::parse(device_code).map_err(|_| invalid())?;
+    let poll = state
+        .device_codes
+        .write()
+        .await
+        .poll(&device_code.hash(), &client.client_id)
+        .await
+        .map_err(|_| invalid())?;
+
+    let (email, scope) = match poll {
+        DevicePoll::Pending => {
+            return Err(oauth_error(
+                OAuthError::AuthorizationPending,
+                "The user has not decided yet",
+            ));
+        }
+        DevicePoll::SlowDown => {
+            return Err(oauth_error(
+                OAuthError::SlowDown,
+                &format!("Polling too fast, wait {SLOW_DOWN_INCREMENT} more seconds between polls"),
+            ));
+        }
+        DevicePoll::Expired => {
+            return Err(oauth_error(
+                OAuthError::ExpiredToken,
+                "The device_code expired",
+            ));
+        }
+        DevicePoll::Denied => {
+            return Err(oauth_error(
+                OAuthError::AccessDenied,
+                "The user denied the request",
+            ));
+        }
+        DevicePoll::Approved { email, scope } => (email, scope),
+    };
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .map_err(|_| oauth_error(OAuthError::InvalidGrant, "User no longer exists"))?;
+    check_account_active(&user)
+        .map_err(|e| oauth_error(OAuthError::InvalidGrant, &e.to_string()))?;
+
+    let access_token =
+        generate_oauth_access_token(state, &email, &client.client_id, &scope).await?;
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: state.config.oauth.access_token_ttl,
+        scope,
+        id_token: None,
+    })
+}
@@ -0,0 +1,349 @@
+use axum::Json;
+use axum::extract::{Path, Query, State};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum_extra::extract::CookieJar;
+use axum_extra::extract::cookie::{Cookie, SameSite};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use webauthn_rs::prelude::Url;
+
+use crate::config::IdentityProviderConfig;
+use crate::domain::{
+    AuthMethod, ClientInfo, Email, FederatedIdentity, HashedPassword, TwoFactorMethod,
+    UpstreamIdentity, User, code_challenge,
+};
+use crate::error::AuthApiError;
+use crate::routes::{LoginResponse, complete_login, revoke_all_tokens, start_second_factor};
+use crate::state::AppState;
+use crate::utils::auth::{FederatedLoginClaims, generate_federated_login_token, validate_token};
+
+/// Sign in option shown on the login page
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct FederatedProvider {
+    pub id: String,
+    pub name: String,
+    pub login_url: String,
+}
+
+#[derive(Serialize, Deserialize, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct FederatedLoginParams {
+    /// Where to go once logged in, must be on the app's site. Defaults to the app.
+    pub return_to: Option<String>,
+}
+
+/// Authorization response from the provider, OIDC Core sections 3.1.2.5 and 3.1.2.6
+#[derive(Serialize, Deserialize, IntoParams, Debug)]
+#[into_params(parameter_in = Query)]
+pub struct FederatedCallbackParams {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    /// Set instead of `code` when the user or provider refused
+    pub error: Option<String>,
+}
+
+fn random_value() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn provider_config<'a>(
+    state: &'a AppState,
+    id: &str,
+) -> Result<&'a IdentityProviderConfig, AuthApiError> {
+    state
+        .config
+        .federation
+        .provider(id)
+        .ok_or_else(|| AuthApiError::InvalidData("Unknown identity provider".to_string()))
+}
+
+fn callback_url(state: &AppState, id: &str) -> String {
+    format!(
+        "{}/federated/{}/callback",
+        state.config.oauth.issuer.trim_end_matches('/'),
+        id
+    )
+}
+
+/// Only return to the app itself, anything else would make this an open redirect.
+/// Relative paths are taken relative to the app, absolute urls need its exact scheme,
+/// host and port, a prefix match would let `http://app.example.com.evil.com` through.
+fn safe_return_to(state: &AppState, return_to: Option<&str>) -> String {
+    let app_url = &state.config.app.url;
+    let (Ok(app), Some(return_to)) = (Url::parse(app_url), return_to) else {
+        return app_url.clone();
+    };
+    match app.join(return_to) {
+        Ok(url) if url.origin() == app.origin() => url.to_string(),
+        _ => app_url.clone(),
+    }
+}
+
+async fn start_login(
+    state: &AppState,
+    id: &str,
+    params: &FederatedLoginParams,
+) -> Result<(Cookie<'static>, String), AuthApiError> {
+    let config = provider_config(state, id)?;
+    let provider = state.federation.provider(config).await?;
+
+    let login_state = random_value();
+    let nonce = random_value();
+    let code_verifier = random_value();
+    let url = provider.authorization_url(
+        &callback_url(state, id),
+        &login_state,
+        &nonce,
+        &code_challenge(&code_verifier),
+    )?;
+
+    let federation = &state.config.federation;
+    let token = generate_federated_login_token(
+        id,
+        &login_state,
+        &nonce,
+        &code_verifier,
+        &safe_return_to(state, params.return_to.as_deref()),
+        federation.login_ttl,
+        &state.config.jwt.secret,
+    )
+    .map_err(|e| AuthApiError::UnexpectedError(e.to_string()))?;
+    // Lax so it comes along on the provider's top level redirect back
+    let cookie = Cookie::build((federation.cookie_name.clone(), token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .build();
+    Ok((cookie, url))
+}
+
+/// Sign out whoever signed up with an address they didn't prove was theirs and drop
+/// the second factors they enrolled, so they can't get back in once it is taken over
+async fn evict_squatter(state: &AppState, email: &Email) -> Result<(), AuthApiError> {
+    revoke_all_tokens(state, email).await?;
+    // nothing to remove is fine, there may be no login in flight
+    _ = state.two_factor.write().await.remove_code(email).await;
+    state.totp.write().await.delete_user(email).await?;
+    state
+        .recovery_codes
+        .write()
+        .await
+        .delete_user(email)
+        .await?;
+    state.passkeys.write().await.delete_user(email).await?;
+    state.api_keys.write().await.delete_user(email).await
+}
+
+/// Local account for the provider account, linked by verified email on first sign in
+async fn link_identity(
+    state: &AppState,
+    id: &str,
+    upstream: &UpstreamIdentity,
+) -> Result<Email, AuthApiError> {
+    if let Ok(identity) = state
+        .identities
+        .read()
+        .await
+        .get_identity(id, &upstream.sub)
+        .await
+    {
+        return Ok(identity.email);
+    }
+    let Some(email) = upstream.verified_email() else {
+        return Err(AuthApiError::Unauthorized);
+    };
+
+    let mut users = state.user_store.write().await;
+    // federated accounts get a random password nobody knows, like passkey-only ones
+    let password = format!("{}{}", Uuid::new_v4(), Uuid::new_v4());
+    let squatted = match users.get_user(&email).await {
+        Ok(mut user) if !user.verified => {
+            // anyone could have signed up with the address, their password has to go
+            user.password = HashedPassword::parse(&password).await?;
+            user.verified = true;
+            user.two_factor = TwoFactorMethod::None;
+            users.update_user(&user).await?;
+            true
+        }
+        Ok(_) => false,
+        Err(AuthApiError::UserNotFound) => {
+            let user = User {
+                verified: true,
+                ..User::new(
+                    email.clone(),
+                    HashedPassword::parse(&password).await?,
+                    TwoFactorMethod::None,
+                )
+            };
+            users.add_user(user).await?;
+            false
+        }
+        Err(e) => return Err(e),
+    };
+    drop(users);
+    if squatted {
+        evict_squatter(state, &email).await?;
+    }
+
+    state
+        .identities
+        .write()
+        .await
+        .add_identity(FederatedIdentity {
+            provider: id.to_string(),
+            subject: upstream.sub.clone(),
+            email: email.clone(),
+        })
+        .await?;
+    Ok(email)
+}
+
+/// Check the callback belongs to the login this browser started and redeem the code.
+/// Returns the local account and where to send the user afterwards.
+async fn finish_login(
+    state: &AppState,
+    jar: &CookieJar,
+    id: &str,
+    params: &FederatedCallbackParams,
+) -> Result<(Email, String), AuthApiError> {
+    let cookie = jar
+        .get(&state.config.federation.cookie_name)
+        .ok_or(AuthApiError::Unauthorized)?;
+    let claims = validate_token::<FederatedLoginClaims>(cookie.value(), &state.config.jwt)
+        .await
+        .map_err(|_| AuthApiError::InvalidToken)?;
+    if claims.provider != id || params.state.as_deref() != Some(claims.state.as_str()) {
+        return Err(AuthApiError::InvalidToken);
+    }
+    if let Some(error) = &params.error {
+        tracing::info!("Identity provider {} refused the login: {}", id, error);
+        return Err(AuthApiError::Unauthorized);
+    }
+    let code = params
+        .code
+        .as_deref()
+        .ok_or_else(|| AuthApiError::MissingField("code".to_string()))?;
+
+    let config = provider_config(state, id)?;
+    let provider = state.federation.provider(config).await?;
+    let upstream = provider
+        .exchange_code(
+            code,
+            &callback_url(state, id),
+            &claims.code_verifier,
+            &claims.nonce,
+        )
+        .await?;
+    let email = link_identity(state, id, &upstream).await?;
+    Ok((email, claims.return_to))
+}
+
+#[utoipa::path(
+    get,
+    path = "/federated/providers",
+    tag = "Authentication",
+    responses(
+        (status = 200, description = "Identity providers users can sign in with", body = Vec<FederatedProvider>)
+    )
+)]
+#[instrument(skip(state))]
+pub async fn federated_providers_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let issuer = state.config.oauth.issuer.trim_end_matches('/');
+    let providers = state
+        .config
+        .federation
+        .providers
+        .iter()
+        .map(|p| FederatedProvider {
+            id: p.id.clone(),
+            name: p.name.clone(),
+            login_url: format!("{}/federated/{}/login", issuer, p.id),
+        })
+        .collect::<Vec<_>>();
+    Json(providers)
+}
+
+#[utoipa::path(
+    get,
+    path = "/federated/{provider}/login",
+    tag = "Authentication",
+    params(
+        ("provider" = String, Path, description = "Id of the identity provider"),
+        FederatedLoginParams
+    ),
+    responses(
+        (status = 303, description = "To the identity provider's sign in page"),
+        (status = 422, description = "Unknown identity provider"),
+        (status = 502, description = "Identity provider unreachable")
+    )
+)]
+#[instrument(skip(jar, state))]
+pub async fn federated_login_handler(
+    jar: CookieJar,
+    State(state): State<AppState>,
+    Path(provider): Path<String>,
+    Query(params): Query<FederatedLoginParams>,
+) -> (CookieJar, Result<Redirect, AuthApiError>) {
+    match start_login(&state, &provider, &params).await {
+        Ok((cookie, url)) => (jar.add(cookie), Ok(Redirect::to(&url))),
+        Err(e) => (jar, Err(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/federated/{provider}/callback",
+    tag = "Authentication",
+    params(
+        ("provider" = String, Path, description = "Id of the identity provider"),
+        FederatedCallbackParams
+    ),
+    responses(
+        (status = 303, description = "Logged in, or on to the two factor page when the account has it enabled"),
+        (status = 401, description = "Login not started here, refused by the provider, or an invalid id_token"),
+        (status = 502, description = "Identity provider unreachable")
+    )
+)]
+#[instrument(skip(jar, state, params))]
+pub async fn federated_callback_handler(
+    jar: CookieJar,
+    State(state): State<AppState>,
+    client: ClientInfo,
+    Path(provider): Path<String>,
+    Query(params): Query<FederatedCallbackParams>,
+) -> (CookieJar, Response) {
+    let result = finish_login(&state, &jar, &provider, &params).await;
+    let jar = jar.remove(Cookie::build(state.config.federation.cookie_name.clone()).path("/"));
+    let (email, return_to) = match result {
+        Ok(login) => login,
+        Err(e) => return (jar, e.into_response()),
+    };
+
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(e) => return (jar, e.into_response()),
+    };
+    if let TwoFactorMethod::Email | TwoFactorMethod::Totp = user.two_factor {
+        let (jar, result) = start_second_factor(jar, &state, &user).await;
+        return match result {
+            Ok((_, Json(LoginResponse::TwoFactor { url, .. }))) => {
+                (jar, Redirect::to(&url).into_response())
+            }
+            Ok((status, body)) => (jar, (status, body).into_response()),
+            Err(e) => (jar, e.into_response()),
+        };
+    }
+
+    let (jar, result) = complete_login(jar, &email, &state, &[AuthMethod::Fed], &client).await;
+    match result {
+        Ok(_) => (jar, Redirect::to(&return_to).into_response()),
+        Err(e) => (jar, e.into_response()),
+    }
+}
@@ -1,7 +1,7 @@
 use axum::extract::Path;
 use axum::http::StatusCode;
 use axum::response::Html;
-use axum::{response::IntoResponse, Json};
+use axum::{Json, response::IntoResponse};
 use tracing::instrument;
 
 /// Health check endpoint
@@ -0,0 +1,139 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::domain::{OAuthError, RefreshToken};
+use crate::error::AuthApiError;
+use crate::routes::authenticate_client;
+use crate::state::AppState;
+use crate::utils::FormOrJson;
+use crate::utils::auth::validate_auth_token;
+
+/// RFC 7662 section 2.1
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct IntrospectionRequest {
+    pub token: String,
+    /// `access_token` or `refresh_token`, only decides which kind is looked up first
+    pub token_type_hint: Option<String>,
+    /// Can be sent in an `Authorization: Basic` header instead
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+/// RFC 7662 section 2.2, only `active` is set for a token that isn't
+#[derive(Serialize, Deserialize, ToSchema, Debug, Default, PartialEq, Eq)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    /// User email, or the client id for client credentials tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
+    /// Space delimited, only on tokens issued to an OAuth client
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// `Bearer` for access tokens, `refresh_token` for refresh tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+}
+
+async fn introspect_access_token(state: &AppState, token: &str) -> Option<IntrospectionResponse> {
+    let claims = validate_auth_token(token, state).await.ok()?;
+    Some(IntrospectionResponse {
+        active: true,
+        sub: Some(claims.sub),
+        exp: Some(claims.exp),
+        iat: claims.iat,
+        scope: claims.scope,
+        client_id: claims.client_id,
+        token_type: Some("Bearer".to_string()),
+    })
+}
+
+async fn introspect_refresh_token(state: &AppState, token: &str) -> Option<IntrospectionResponse> {
+    let token = RefreshToken::parse(token).ok()?;
+    let record = state
+        .refresh_tokens
+        .read()
+        .await
+        .get_token(&token.hash())
+        .await
+        .ok()?;
+    if record.used || record.is_expired() {
+        return None;
+    }
+    Some(IntrospectionResponse {
+        active: true,
+        sub: Some(record.email.as_ref().to_string()),
+        exp: Some(record.expires_at as usize),
+        token_type: Some("refresh_token".to_string()),
+        ..IntrospectionResponse::default()
+    })
+}
+
+async fn introspect(
+    state: &AppState,
+    headers: &HeaderMap,
+    body: IntrospectionRequest,
+) -> Result<IntrospectionResponse, AuthApiError> {
+    let client = authenticate_client(
+        state,
+        headers,
+        body.client_id.as_deref(),
+        body.client_secret.as_deref(),
+    )
+    .await?;
+    // anyone could pass a client id, only a secret tells us who is asking
+    if !client.is_confidential() {
+        return Err(AuthApiError::OAuth(
+            OAuthError::InvalidClient,
+            "Only confidential clients can introspect tokens".to_string(),
+        ));
+    }
+
+    // RFC 7662 section 2.1, a wrong hint only costs an extra lookup
+    let response = if body.token_type_hint.as_deref() == Some("refresh_token") {
+        match introspect_refresh_token(state, &body.token).await {
+            Some(response) => Some(response),
+            None => introspect_access_token(state, &body.token).await,
+        }
+    } else {
+        match introspect_access_token(state, &body.token).await {
+            Some(response) => Some(response),
+            None => introspect_refresh_token(state, &body.token).await,
+        }
+    };
+    Ok(response.unwrap_or_default())
+}
+
+#[utoipa::path(
+    post,
+    path = "/introspect",
+    tag = "OAuth",
+    request_body(content = IntrospectionRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "State of the token, inactive when invalid, expired or revoked", body = IntrospectionResponse),
+        (status = 401, description = "Unknown, public or unauthenticated client")
+    )
+)]
+#[instrument(skip(state, headers, body))]
+pub async fn introspect_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    FormOrJson(body): FormOrJson<IntrospectionRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let response = introspect(&state, &headers, body).await?;
+    Ok((
+        StatusCode::OK,
+        [(header::CACHE_CONTROL, "no-store")],
+        Json(response),
+    ))
+}
@@ -0,0 +1,162 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::domain::{AccountUnlockEmailData, Email, EmailTemplate, FailedLogins, TokenPurpose};
+use crate::error::AuthApiError;
+use crate::state::AppState;
+use crate::utils::FormOrJson;
+use crate::utils::auth::{generate_one_time_token, validate_one_time_token};
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct UnlockAccountRequest {
+    /// Token from the unlock email
+    pub token: String,
+}
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct UnlockAccountResponse {
+    pub message: String,
+}
+
+/// Refuse a password login while `email` is locked out.
+///
+/// Runs before the password is checked, so a locked account costs no hashing.
+pub async fn check_lockout(state: &AppState, email: &Email) -> Result<(), AuthApiError> {
+    if state.config.lockout.threshold == 0 {
+        return Ok(());
+    }
+    let record = state.failed_logins.read().await.get(email).await?;
+    match record.locked_for(chrono::Utc::now().timestamp()) {
+        Some(seconds) => Err(AuthApiError::TooManyRequests(seconds)),
+        None => Ok(()),
+    }
+}
+
+/// Count a password check against `email` as a failure before it runs, a right
+/// password clears it again with `clear_failed_logins`.
+///
+/// Counting and comparing happen in one step, so concurrent guesses can't all slip
+/// past a lockout check made before any of them was counted. Unknown addresses are
+/// counted too, so lockouts don't tell which accounts exist.
+/// Fails with `TooManyRequests`, without a password check, while the account is locked.
+pub async fn count_login_attempt(
+    state: &AppState,
+    email: &Email,
+) -> Result<FailedLogins, AuthApiError> {
+    let config = &state.config.lockout;
+    if config.threshold == 0 {
+        return Ok(FailedLogins::default());
+    }
+    let record = state
+        .failed_logins
+        .write()
+        .await
+        .record_failure(email, config)
+        .await?;
+    if record.failures > config.threshold {
+        let seconds = record.locked_for(chrono::Utc::now().timestamp());
+        return Err(AuthApiError::TooManyRequests(seconds.unwrap_or(1)));
+    }
+    Ok(record)
+}
+
+/// The password checked after `count_login_attempt` was wrong.
+///
+/// Fails with `TooManyRequests` when that attempt locked the account.
+pub async fn record_failed_login(
+    state: &AppState,
+    email: &Email,
+    attempt: &FailedLogins,
+) -> Result<(), AuthApiError> {
+    let config = &state.config.lockout;
+    let Some(seconds) = attempt.locked_for(chrono::Utc::now().timestamp()) else {
+        return Ok(());
+    };
+    tracing::warn!("Locked out {} for {}s", email.as_ref(), seconds);
+    if config.unlock_by_email {
+        send_unlock_link(state, email, seconds).await?;
+    }
+    Err(AuthApiError::TooManyRequests(seconds))
+}
+
+/// Forget the failed logins of `email`, after a successful login or an unlock
+pub async fn clear_failed_logins(state: &AppState, email: &Email) -> Result<(), AuthApiError> {
+    state.failed_logins.write().await.clear(email).await
+}
+
+/// The link is only good while the lock lasts
+async fn send_unlock_link(state: &AppState, email: &Email, ttl: u64) -> Result<(), AuthApiError> {
+    if state.user_store.read().await.get_user(email).await.is_err() {
+        return Ok(());
+    }
+
+    let purpose = TokenPurpose::AccountUnlock;
+    let (id, token) = generate_one_time_token(email, &purpose, ttl, &state.config.jwt.secret)
+        .map_err(|e| AuthApiError::UnexpectedError(e.to_string()))?;
+    state
+        .one_time_tokens
+        .write()
+        .await
+        .add_token(&id, &purpose, email, ttl)
+        .await?;
+
+    let link_url = format!(
+        "{}?token={}",
+        &state.config.app.account_unlock_redirect_url, token
+    );
+    let template = EmailTemplate::AccountUnlock(AccountUnlockEmailData {
+        email: email.as_ref().to_string(),
+        site_url: state.config.app.url.clone(),
+        link_url,
+    });
+    let emailer = &state.email_client.read().await;
+    if let Err(e) = emailer
+        .send_email(email, "Your Account Was Locked", &template)
+        .await
+    {
+        tracing::warn!("Unable to send mail: {}", &e);
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/unlock-account",
+    tag = "Authentication",
+    responses(
+        (status = 200, description = "Lockout lifted, failed logins forgotten", body = UnlockAccountResponse),
+        (status = 401, description = "Invalid, expired or already used token")
+    )
+)]
+#[instrument(skip(state, body))]
+pub async fn unlock_account_handler(
+    State(state): State<AppState>,
+    FormOrJson(body): FormOrJson<UnlockAccountRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let purpose = TokenPurpose::AccountUnlock;
+    let claims = validate_one_time_token(&body.token, &purpose, &state.config.jwt)
+        .await
+        .map_err(|_| AuthApiError::InvalidToken)?;
+    let email = state
+        .one_time_tokens
+        .write()
+        .await
+        .consume_token(&claims.jti, &purpose)
+        .await?;
+    if email != claims.sub {
+        return Err(AuthApiError::InvalidToken);
+    }
+    clear_failed_logins(&state, &email).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(UnlockAccountResponse {
+            message: "Account unlocked".to_string(),
+        }),
+    ))
+}
@@ -7,16 +7,24 @@ use axum_extra::extract::CookieJar;
 use serde::Serialize;
 use tracing::instrument;
 use utoipa::ToSchema;
+use webauthn_rs::prelude::RequestChallengeResponse;
 
 use crate::domain::{
-    Email, EmailClient, EmailTemplate, LoginAttemptId, Password, TwoFactorCode, TwoFactorCodeStore,
-    TwoFactorEmailData, TwoFactorMethod,
+    AuthMethod, Authentication, ClientInfo, Email, EmailTemplate, LoginAttemptId,
+    MagicLinkEmailData, PasskeyCeremonyId, Password, RefreshToken, RefreshTokenRecord, Session,
+    TokenPurpose, TwoFactorCode, TwoFactorEmailData, TwoFactorMethod, User,
 };
 use crate::error::AuthApiError;
+use crate::routes::{
+    check_account_active, check_email_verified, clear_failed_logins, count_login_attempt,
+    record_failed_login, start_passkey_authentication,
+};
 use crate::state::AppState;
 
 use crate::utils::FormOrJson;
-use crate::utils::auth::{generate_2fa_token, generate_auth_cookie};
+use crate::utils::auth::{
+    generate_2fa_token, generate_one_time_token, generate_refresh_cookie, issue_auth_cookie,
+};
 
 #[derive(serde::Deserialize, Serialize, Debug, ToSchema)]
 #[serde(tag = "method", rename_all = "snake_case")]
@@ -25,15 +33,12 @@ pub enum LoginRequest {
     #[schema(title = "Email/Password")]
     EmailPassword { email: String, password: String },
 
-    /// Signup using magic link sent to email
-    ///
-    /// Coming soon...
+    /// Login using a single-use link sent to email
     #[schema(title = "Magic Link")]
     MagicLink { email: String },
 
-    /// Signup using passkey (WebAuthn)
-    ///
-    /// Coming soon...
+    /// Login using a registered passkey (WebAuthn).
+    /// Responds with a challenge to complete at `/passkey/login/finish`
     #[schema(title = "Passkey/WebAuthn")]
     Passkey { email: String },
 }
@@ -41,8 +46,14 @@ pub enum LoginRequest {
 #[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
 #[serde(tag = "status", rename_all = "snake_case")]
 pub enum LoginResponse {
+    /// `token` is a short-lived access token, renewed at `/token/refresh`
+    /// with the single-use `refresh_token`
     #[schema(title = "Auth Token")]
-    Success { email: Email, token: String },
+    Success {
+        email: Email,
+        token: String,
+        refresh_token: String,
+    },
 
     /// Two Factor assumes user has validated primary credentials
     /// i.e. email/password
@@ -52,6 +63,20 @@ pub enum LoginResponse {
         method: TwoFactorMethod,
         url: String,
     },
+
+    /// A login link was emailed, if the account exists.
+    /// The response is the same either way so accounts can't be enumerated.
+    #[schema(title = "Magic Link Sent")]
+    MagicLink { email: Email, message: String },
+
+    /// Options for `navigator.credentials.get()`, answered at `/passkey/login/finish`
+    #[schema(title = "Passkey Challenge")]
+    Passkey {
+        email: Email,
+        id: PasskeyCeremonyId,
+        #[schema(value_type = Object)]
+        options: RequestChallengeResponse,
+    },
 }
 
 impl LoginResponse {
@@ -59,6 +84,8 @@ impl LoginResponse {
         match self {
             LoginResponse::Success { .. } => StatusCode::OK,
             LoginResponse::TwoFactor { .. } => StatusCode::PARTIAL_CONTENT, // 206
+            LoginResponse::MagicLink { .. } => StatusCode::ACCEPTED,        // 202
+            LoginResponse::Passkey { .. } => StatusCode::OK,
         }
     }
 }
@@ -79,12 +106,25 @@ pub enum LoginResult {
         id: LoginAttemptId,
         code: TwoFactorCode,
     },
+
+    /// `token` is only set when the account exists
+    #[schema(title = "Magic Link")]
+    MagicLink { email: Email, token: Option<String> },
+
+    #[schema(title = "Passkey Challenge")]
+    Passkey {
+        email: Email,
+        id: PasskeyCeremonyId,
+        #[schema(value_type = Object)]
+        options: RequestChallengeResponse,
+    },
 }
 
-async fn handle_2fa(
+pub async fn handle_2fa(
     jar: CookieJar,
     email: &Email,
     state: &AppState,
+    method: &TwoFactorMethod,
     attempt_id: &LoginAttemptId,
     code: &TwoFactorCode,
 ) -> (
@@ -100,18 +140,21 @@ async fn handle_2fa(
                 "{}?payload={}",
                 &state.config.app.two_factor_redirect_url, mfa_payload,
             );
-            let emailer = &state.email_client.read().await;
-            let template = EmailTemplate::TwoFactor(TwoFactorEmailData {
-                email: email.as_ref().to_string(),
-                code: code.as_ref().to_string(),
-                site_url: state.config.app.url.clone(),
-                redirect_url: redirect_url.clone(),
-            });
-            if let Err(e) = emailer.send_email(email, "Confirm Login", &template).await {
-                tracing::warn!("Unable to send mail: {}", &e);
-                // FIXME: what should happen if email failes to send in two_factor case
-                // - need retry, and/or ability to trigger resend emails
-                // return (jar, Err(e));
+            // TOTP codes come from the user's authenticator app, nothing to send
+            if let TwoFactorMethod::Email = method {
+                let emailer = &state.email_client.read().await;
+                let template = EmailTemplate::TwoFactor(TwoFactorEmailData {
+                    email: email.as_ref().to_string(),
+                    code: code.as_ref().to_string(),
+                    site_url: state.config.app.url.clone(),
+                    redirect_url: redirect_url.clone(),
+                });
+                if let Err(e) = emailer.send_email(email, "Confirm Login", &template).await {
+                    tracing::warn!("Unable to send mail: {}", &e);
+                    // FIXME: what should happen if email failes to send in two_factor case
+                    // - need retry, and/or ability to trigger resend emails
+                    // return (jar, Err(e));
+                }
             }
             redirect_url
         } else {
@@ -124,27 +167,139 @@ async fn handle_2fa(
             StatusCode::PARTIAL_CONTENT, // 206
             Json(LoginResponse::TwoFactor {
                 email: email.clone(),
-                method: TwoFactorMethod::Email,
+                method: method.clone(),
                 url: redirect,
             }),
         )),
     )
 }
 
-fn handle_successful_login(
+/// Ask for the second factor of `user` instead of completing the login.
+///
+/// For flows that prove the first factor on their own, like a magic link or an upstream
+/// provider, so they can't be used to skip 2FA.
+pub async fn start_second_factor(
+    jar: CookieJar,
+    state: &AppState,
+    user: &User,
+) -> (
+    CookieJar,
+    Result<(StatusCode, Json<LoginResponse>), AuthApiError>,
+) {
+    if let Err(e) = check_account_active(user) {
+        return (jar, Err(e));
+    }
+    let attempt = state
+        .two_factor
+        .write()
+        .await
+        .new_login_attempt(&user.email, &user.two_factor)
+        .await;
+    match attempt {
+        Ok((attempt_id, code)) => {
+            handle_2fa(
+                jar,
+                &user.email,
+                state,
+                &user.two_factor,
+                &attempt_id,
+                &code,
+            )
+            .await
+        }
+        Err(e) => (jar, Err(e)),
+    }
+}
+
+async fn handle_magic_link(
+    jar: CookieJar,
+    email: &Email,
+    state: &AppState,
+    token: Option<String>,
+) -> (
+    CookieJar,
+    Result<(StatusCode, Json<LoginResponse>), AuthApiError>,
+) {
+    if let Some(token) = token {
+        let link_url = format!(
+            "{}?token={}",
+            &state.config.app.magic_link_redirect_url, token
+        );
+        let emailer = &state.email_client.read().await;
+        let template = EmailTemplate::MagicLink(MagicLinkEmailData {
+            email: email.as_ref().to_string(),
+            site_url: state.config.app.url.clone(),
+            link_url,
+        });
+        if let Err(e) = emailer
+            .send_email(email, "Your Login Link", &template)
+            .await
+        {
+            tracing::warn!("Unable to send mail: {}", &e);
+        }
+    }
+
+    (
+        jar,
+        Ok((
+            StatusCode::ACCEPTED, // 202
+            Json(LoginResponse::MagicLink {
+                email: email.clone(),
+                message: "Check your email".to_string(),
+            }),
+        )),
+    )
+}
+
+/// Start a session for `email`: issue an access token and a new refresh token family.
+///
+/// Every login flow ends here once the user is fully authenticated with `methods`.
+pub async fn complete_login(
     jar: CookieJar,
     email: &Email,
     state: &AppState,
+    methods: &[AuthMethod],
+    client: &ClientInfo,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthApiError>,
 ) {
-    let token = generate_auth_cookie(email, &state.config.jwt);
-    if token.is_err() {
-        return (jar, Err(AuthApiError::InvalidCredentials));
+    let user = match state.user_store.read().await.get_user(email).await {
+        Ok(user) => user,
+        Err(e) => return (jar, Err(e)),
+    };
+    if let Err(e) = check_account_active(&user).and_then(|_| check_email_verified(state, &user)) {
+        return (jar, Err(e));
     }
-    let token = token.unwrap();
-    let jar = jar.add(token.clone());
+    // an unknown client id is refused rather than ignored, the app would be unable to revoke
+    if let Some(client_id) = &client.client_id
+        && let Err(e) = state.clients.read().await.get_client(client_id).await
+    {
+        return (jar, Err(e));
+    }
+    let authentication = Authentication::now(methods);
+    let ttl = state.config.auth.refresh_token_ttl;
+    let refresh_token = RefreshToken::generate();
+    let record = RefreshTokenRecord {
+        authentication: authentication.clone(),
+        client_id: client.client_id.clone(),
+        ..RefreshTokenRecord::new(&refresh_token, email, ttl)
+    };
+    // the session shares its id with the refresh token family, revoking one ends both
+    let session = Session::new(record.family_id, email, client, ttl);
+    if let Err(e) = state.sessions.write().await.add_session(session).await {
+        return (jar, Err(e));
+    }
+    let token = match issue_auth_cookie(state, &user, &authentication, &record.family_id).await {
+        Ok(token) => token,
+        Err(e) => return (jar, Err(e)),
+    };
+    if let Err(e) = state.refresh_tokens.write().await.add_token(record).await {
+        return (jar, Err(e));
+    }
+    let jar = jar
+        .add(token.clone())
+        .add(generate_refresh_cookie(&refresh_token, &state.config.jwt));
 
     (
         jar,
@@ -153,6 +308,7 @@ fn handle_successful_login(
             Json(LoginResponse::Success {
                 email: email.clone(),
                 token: token.value().to_string(),
+                refresh_token: refresh_token.as_ref().to_string(),
             }),
         )),
     )
@@ -163,18 +319,29 @@ async fn login(state: &AppState, body: &LoginRequest) -> Result<LoginResult, Aut
         LoginRequest::EmailPassword { email, password } => {
             let email = Email::parse(email)?;
             let password = Password::parse(password)?;
-            let user_store = &state.user_store.read().await;
-            let user = user_store
-                .get_user(&email)
-                .await
-                .map_err(|_| AuthApiError::Unauthorized)?;
+            let attempt = count_login_attempt(state, &email).await?;
+            let user = {
+                let user_store = &state.user_store.read().await;
+                match user_store.get_user(&email).await {
+                    Ok(user) => user_store
+                        .validate_credentials(&email, &password)
+                        .await
+                        .map(|_| user),
+                    Err(e) => Err(e),
+                }
+            };
+            let user = match user {
+                Ok(user) => user,
+                Err(_) => {
+                    record_failed_login(state, &email, &attempt).await?;
+                    return Err(AuthApiError::Unauthorized);
+                }
+            };
+            clear_failed_logins(state, &email).await?;
+            check_account_active(&user)?;
+            check_email_verified(state, &user)?;
 
-            user_store
-                .validate_credentials(&email, &password)
-                .await
-                .map_err(|_| AuthApiError::Unauthorized)?;
-
-            if let TwoFactorMethod::Email = user.two_factor {
+            if let TwoFactorMethod::Email | TwoFactorMethod::Totp = user.two_factor {
                 let mut codes = state.two_factor.write().await;
                 let (login_attempt_id, code) =
                     codes.new_login_attempt(&email, &user.two_factor).await?;
@@ -192,12 +359,31 @@ async fn login(state: &AppState, body: &LoginRequest) -> Result<LoginResult, Aut
                 }) // token will be generated in handler
             }
         }
-        // magic link / OTP requires a different flow so will need to think about what thes
-        // login endpoint return types should should like
-        LoginRequest::MagicLink { .. } => Err(AuthApiError::MalformedRequest),
+        LoginRequest::MagicLink { email } => {
+            let email = Email::parse(email)?;
+            let user_store = &state.user_store.read().await;
+            if user_store.get_user(&email).await.is_err() {
+                return Ok(LoginResult::MagicLink { email, token: None });
+            }
+
+            let ttl = state.config.auth.magic_link_ttl;
+            let purpose = TokenPurpose::MagicLink;
+            let (id, token) =
+                generate_one_time_token(&email, &purpose, ttl, &state.config.jwt.secret)
+                    .map_err(|e| AuthApiError::UnexpectedError(e.to_string()))?;
+            let mut tokens = state.one_time_tokens.write().await;
+            tokens.add_token(&id, &purpose, &email, ttl).await?;
+            Ok(LoginResult::MagicLink {
+                email,
+                token: Some(token),
+            })
+        }
 
-        // passkeys.rs likely - use WebAuthn flows
-        LoginRequest::Passkey { .. } => Err(AuthApiError::MalformedRequest),
+        LoginRequest::Passkey { email } => {
+            let email = Email::parse(email)?;
+            let (id, options) = start_passkey_authentication(state, &email).await?;
+            Ok(LoginResult::Passkey { email, id, options })
+        }
     }
 }
 
@@ -205,24 +391,46 @@ async fn login(state: &AppState, body: &LoginRequest) -> Result<LoginResult, Aut
     post,
     path = "/login",
     tag = "Authentication",
+    params(
+        ("X-Client-Id" = Option<String>, Header, description = "Registered OAuth client the app logs in as, lets it revoke the refresh token at /revoke")
+    ),
     responses(
-        (status = 200, description = "Login successful"),
+        (status = 200, description = "Login successful, or passkey challenge issued"),
+        (status = 202, description = "Magic link sent"),
         (status = 401, description = "Unauthorized"),
-        (status = 422, description = "Unprocessable Entity")
+        (status = 422, description = "Unprocessable Entity"),
+        (status = 429, description = "Too many attempts or locked out after failed logins, see the Retry-After header")
     )
 )]
 #[instrument(skip(state, body, jar))]
 pub async fn login_handler(
     jar: CookieJar, // must come before the body extractor
     State(state): State<AppState>,
+    client: ClientInfo,
     FormOrJson(body): FormOrJson<LoginRequest>, // must be last
 ) -> (CookieJar, Result<impl IntoResponse, AuthApiError>) {
     let result = login(&state, &body).await;
     match result {
-        Ok(LoginResult::Success { email, .. }) => handle_successful_login(jar, &email, &state),
+        Ok(LoginResult::Success { email, .. }) => {
+            complete_login(jar, &email, &state, &[AuthMethod::Pwd], &client).await
+        }
         Ok(LoginResult::TwoFactor {
-            id, email, code, ..
-        }) => handle_2fa(jar, &email, &state, &id, &code).await,
+            id,
+            email,
+            method,
+            code,
+            ..
+        }) => handle_2fa(jar, &email, &state, &method, &id, &code).await,
+        Ok(LoginResult::MagicLink { email, token }) => {
+            handle_magic_link(jar, &email, &state, token).await
+        }
+        Ok(LoginResult::Passkey { email, id, options }) => (
+            jar,
+            Ok((
+                StatusCode::OK,
+                Json(LoginResponse::Passkey { email, id, options }),
+            )),
+        ),
         Err(error) => (jar, (Err(error))),
     }
 }
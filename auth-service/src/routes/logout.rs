@@ -2,7 +2,7 @@ use axum::{extract::State, http::StatusCode, response::IntoResponse};
 use axum_extra::extract::{CookieJar, cookie::Cookie};
 use tracing::instrument;
 
-use crate::domain::BannedTokenStore;
+use crate::domain::{Email, RefreshToken};
 use crate::error::{AuthApiError, StatusCoded};
 use crate::state::AppState;
 use crate::utils::auth::{Claims, validate_token};
@@ -12,12 +12,40 @@ async fn logout(state: &AppState, jar: CookieJar) -> Result<CookieJar, AuthApiEr
         .get(&state.config.jwt.cookie_name)
         .ok_or(AuthApiError::MissingToken)?;
     let token = cookie.value().to_string();
-    _ = validate_token::<Claims>(&token, &state.config.jwt)
+    let claims = validate_token::<Claims>(&token, &state.config.jwt)
         .await
         .map_err(|_| AuthApiError::InvalidToken)?;
-    let mut banned = state.banned_tokens.write().await;
-    banned.ban_token(&token).await?;
-    Ok(jar.remove(Cookie::from(state.config.jwt.cookie_name.clone())))
+    state.banned_tokens.write().await.ban_token(&token).await?;
+    if let (Some(sid), Ok(email)) = (&claims.sid, Email::parse(&claims.sub)) {
+        // may already be gone, e.g. revoked from another device
+        _ = state
+            .sessions
+            .write()
+            .await
+            .revoke_session(&email, sid)
+            .await;
+        state
+            .refresh_tokens
+            .write()
+            .await
+            .revoke_family(sid)
+            .await?;
+    }
+
+    // end the refresh token family too, otherwise the session could be revived
+    let refresh_cookie = state.config.jwt.refresh_cookie_name.clone();
+    if let Some(refresh) = jar
+        .get(&refresh_cookie)
+        .and_then(|c| RefreshToken::parse(c.value()).ok())
+    {
+        let mut refresh_tokens = state.refresh_tokens.write().await;
+        if let Ok(record) = refresh_tokens.get_token(&refresh.hash()).await {
+            refresh_tokens.revoke_family(&record.family_id).await?;
+        }
+    }
+    Ok(jar
+        .remove(Cookie::from(state.config.jwt.cookie_name.clone()))
+        .remove(Cookie::from(refresh_cookie)))
 }
 
 #[utoipa::path(
@@ -1,36 +1,167 @@
 use utoipa::OpenApi;
 use utoipa_axum::{router::OpenApiRouter, routes};
 
+mod account;
+mod admin;
+mod api_keys;
+mod change_email;
+mod device;
+mod federated;
 mod health;
+mod introspect;
 mod jwks;
+mod lockout;
 mod login;
 mod logout;
+mod oauth;
+mod oidc;
+mod orgs;
+mod passkey;
+mod password;
+mod recovery_codes;
+mod refresh_token;
+mod revoke;
+mod roles;
+mod sessions;
 mod signup;
+mod totp;
 mod verify_2fa;
+mod verify_email;
+mod verify_magic_link;
 mod verify_token;
 
+pub use account::*;
+pub use admin::*;
+pub use api_keys::*;
+pub use change_email::*;
+pub use device::*;
+pub use federated::*;
 pub use health::*;
+pub use introspect::*;
 pub use jwks::*;
+pub use lockout::*;
 pub use login::*;
 pub use logout::*;
+pub use oauth::*;
+pub use oidc::*;
+pub use orgs::*;
+pub use passkey::*;
+pub use password::*;
+pub use recovery_codes::*;
+pub use refresh_token::*;
+pub use revoke::*;
+pub use roles::*;
+pub use sessions::*;
 pub use signup::*;
+pub use totp::*;
 pub use verify_2fa::*;
+pub use verify_email::*;
+pub use verify_magic_link::*;
 pub use verify_token::*;
 
+use crate::domain::TokenBucket;
 use crate::openapi::ApiDoc;
 use crate::state::AppState;
+use crate::utils::{RateLimit, rate_limit};
+
+/// `router` behind the `rate_limit` middleware, taking tokens from `name` buckets
+fn rate_limited(
+    router: OpenApiRouter<AppState>,
+    state: &AppState,
+    name: &'static str,
+    bucket: TokenBucket,
+) -> OpenApiRouter<AppState> {
+    let limit = RateLimit {
+        state: state.clone(),
+        name,
+        bucket,
+    };
+    router.route_layer(axum::middleware::from_fn_with_state(limit, rate_limit))
+}
 
 pub fn build_app_router(state: AppState) -> OpenApiRouter {
+    let limits = &state.config.rate_limit;
     OpenApiRouter::with_openapi(ApiDoc::openapi())
         .routes(routes!(root))
         .routes(routes!(hello_handler))
         .routes(routes!(healthz))
         .routes(routes!(livez))
-        .routes(routes!(login_handler))
-        .routes(routes!(signup_handler))
+        .merge(rate_limited(
+            OpenApiRouter::new()
+                .routes(routes!(login_handler))
+                .routes(routes!(restore_account_handler)),
+            &state,
+            "login",
+            limits.login,
+        ))
+        .merge(rate_limited(
+            OpenApiRouter::new().routes(routes!(signup_handler)),
+            &state,
+            "signup",
+            limits.signup,
+        ))
+        .routes(routes!(totp_enroll_handler))
+        .routes(routes!(totp_confirm_handler))
+        .routes(routes!(regenerate_recovery_codes_handler))
+        .routes(routes!(forgot_password_handler))
+        .routes(routes!(reset_password_handler))
+        .routes(routes!(change_password_handler))
+        .routes(routes!(unlock_account_handler))
+        .routes(routes!(change_email_handler))
+        .routes(routes!(confirm_email_change_handler))
+        .routes(routes!(undo_email_change_handler))
+        .routes(routes!(delete_account_handler))
+        .routes(routes!(create_api_key_handler, list_api_keys_handler))
+        .routes(routes!(revoke_api_key_handler))
+        .routes(routes!(list_sessions_handler))
+        .routes(routes!(revoke_session_handler))
+        .routes(routes!(create_role_handler, list_roles_handler))
+        .routes(routes!(update_role_handler, delete_role_handler))
+        .routes(routes!(list_user_roles_handler, assign_role_handler))
+        .routes(routes!(unassign_role_handler))
+        .nest("/admin", admin_router())
+        .routes(routes!(create_org_handler, list_orgs_handler))
+        .routes(routes!(
+            get_org_handler,
+            rename_org_handler,
+            delete_org_handler
+        ))
+        .routes(routes!(list_members_handler))
+        .routes(routes!(update_member_handler, remove_member_handler))
+        .routes(routes!(
+            create_invitation_handler,
+            list_org_invitations_handler
+        ))
+        .routes(routes!(revoke_invitation_handler))
+        .routes(routes!(list_invitations_handler))
+        .routes(routes!(accept_invitation_handler))
+        .routes(routes!(decline_invitation_handler))
+        .routes(routes!(switch_org_handler))
+        .routes(routes!(refresh_token_handler))
         .routes(routes!(logout_handler))
+        .routes(routes!(passkey_register_finish_handler))
+        .routes(routes!(passkey_login_finish_handler))
+        .routes(routes!(federated_providers_handler))
+        .routes(routes!(federated_login_handler))
+        .routes(routes!(federated_callback_handler))
+        .routes(routes!(authorize_handler, authorize_decision_handler))
+        .routes(routes!(token_handler))
+        .routes(routes!(device_code_handler))
+        .routes(routes!(device_verify_handler, device_decision_handler))
+        .routes(routes!(introspect_handler))
+        .routes(routes!(revoke_handler))
+        .routes(routes!(userinfo_handler))
+        .routes(routes!(openid_configuration_handler))
         .routes(routes!(jwks_handler))
-        .routes(routes!(verify_2fa_handler))
+        .merge(rate_limited(
+            OpenApiRouter::new().routes(routes!(verify_2fa_handler)),
+            &state,
+            "verify_2fa",
+            limits.verify_2fa,
+        ))
+        .routes(routes!(verify_magic_link_handler))
+        .routes(routes!(verify_email_handler))
+        .routes(routes!(resend_verification_handler))
         .routes(routes!(verify_token_handler))
         .routes(routes!(readyz))
         .with_state(state)
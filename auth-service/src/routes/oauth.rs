@@ -0,0 +1,495 @@
+use askama::Template;
+use axum::Json;
+use axum::extract::{Form, OriginalUri, Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+use webauthn_rs::prelude::Url;
+
+use crate::domain::{
+    AuthorizationGrant, CLIENT_CREDENTIALS_GRANT, CODE_CHALLENGE_METHOD, DEVICE_CODE_GRANT,
+    OAuthClient, OAuthError, OPENID_SCOPE, TokenPurpose, verify_code_challenge,
+};
+use crate::error::AuthApiError;
+use crate::routes::{check_account_active, device_code_grant};
+use crate::state::AppState;
+use crate::utils::auth::{
+    AuthorizationCodeClaims, generate_authorization_code, generate_client_access_token,
+    generate_id_token, generate_oauth_access_token, validate_token,
+};
+use crate::utils::{AuthenticatedUser, FormOrJson, basic_credentials};
+
+/// Parameters of an authorization request, RFC 6749 section 4.1.1 and RFC 7636 section 4.3
+#[derive(Serialize, Deserialize, ToSchema, IntoParams, Debug, Clone)]
+#[into_params(parameter_in = Query)]
+pub struct AuthorizeParams {
+    /// Only `code` is supported
+    pub response_type: Option<String>,
+    pub client_id: String,
+    pub redirect_uri: Option<String>,
+    /// Space delimited, defaults to every scope the client is allowed
+    pub scope: Option<String>,
+    /// Opaque value echoed back to the client
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    /// Only `S256` is supported
+    pub code_challenge_method: Option<String>,
+    /// OpenID Connect, echoed in the id_token
+    pub nonce: Option<String>,
+}
+
+/// Submitted by the consent page
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct AuthorizeDecision {
+    #[serde(flatten)]
+    pub params: AuthorizeParams,
+    /// `approve` or anything else to deny
+    pub decision: String,
+}
+
+/// Token request for every supported grant, RFC 6749 sections 4.1.3 and 4.4.2
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct TokenRequest {
+    /// `authorization_code`, `client_credentials` or
+    /// `urn:ietf:params:oauth:grant-type:device_code`
+    pub grant_type: String,
+    /// Only for `authorization_code`
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    /// Can be sent in an `Authorization: Basic` header instead
+    pub client_id: Option<String>,
+    /// `client_secret_post` authentication, confidential clients only
+    pub client_secret: Option<String>,
+    /// Only for `client_credentials`, defaults to every scope the client is allowed
+    pub scope: Option<String>,
+    /// Only for the device code grant
+    pub device_code: Option<String>,
+}
+
+/// RFC 6749 section 5.1
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: u64,
+    pub scope: String,
+    /// Only when the `openid` scope was granted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id_token: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "oauth_consent.html")]
+struct ConsentPage<'a> {
+    client_name: &'a str,
+    email: &'a str,
+    scopes: &'a [String],
+    params: &'a AuthorizeParams,
+    redirect_uri: &'a str,
+    scope: String,
+}
+
+/// An authorization request that passed every check
+struct ValidRequest {
+    client: OAuthClient,
+    redirect_uri: String,
+    scopes: Vec<String>,
+    code_challenge: String,
+}
+
+/// Failures that must not be sent to the redirect uri, it could not be trusted
+fn invalid_client(description: &str) -> AuthApiError {
+    AuthApiError::OAuth(OAuthError::InvalidRequest, description.to_string())
+}
+
+/// Send the user agent back to the client with `params` and the request `state`
+fn redirect_to_client(
+    redirect_uri: &str,
+    params: &[(&str, &str)],
+    state: Option<&str>,
+) -> Response {
+    let Ok(mut url) = Url::parse(redirect_uri) else {
+        return invalid_client("Invalid redirect_uri").into_response();
+    };
+    {
+        let mut query = url.query_pairs_mut();
+        for (key, value) in params {
+            query.append_pair(key, value);
+        }
+        if let Some(state) = state {
+            query.append_pair("state", state);
+        }
+    }
+    Redirect::to(url.as_str()).into_response()
+}
+
+fn redirect_error(
+    redirect_uri: &str,
+    error: OAuthError,
+    description: &str,
+    state: Option<&str>,
+) -> Response {
+    redirect_to_client(
+        redirect_uri,
+        &[
+            ("error", &error.to_string()),
+            ("error_description", description),
+        ],
+        state,
+    )
+}
+
+/// Check an authorization request. Problems with the client or redirect uri are
+/// answered directly, everything after that goes back to the client as a redirect.
+async fn validate_request(
+    state: &AppState,
+    params: &AuthorizeParams,
+) -> Result<ValidRequest, Response> {
+    let client = state
+        .clients
+        .read()
+        .await
+        .get_client(&params.client_id)
+        .await
+        .map_err(|_| invalid_client("Unknown client_id").into_response())?;
+    let redirect_uri = client
+        .redirect_uri(params.redirect_uri.as_deref())
+        .ok_or_else(|| {
+            invalid_client("redirect_uri is not registered for this client").into_response()
+        })?
+        .to_string();
+
+    let fail = |error, description| {
+        redirect_error(&redirect_uri, error, description, params.state.as_deref())
+    };
+    if params.response_type.as_deref() != Some("code") {
+        return Err(fail(
+            OAuthError::UnsupportedResponseType,
+            "Only the code response type is supported",
+        ));
+    }
+    let Some(code_challenge) = params.code_challenge.clone() else {
+        return Err(fail(
+            OAuthError::InvalidRequest,
+            "code_challenge is required",
+        ));
+    };
+    if params.code_challenge_method.as_deref() != Some(CODE_CHALLENGE_METHOD) {
+        return Err(fail(
+            OAuthError::InvalidRequest,
+            "Only the S256 code_challenge_method is supported",
+        ));
+    }
+    let scopes = client
+        .grant_scopes(params.scope.as_deref())
+        .map_err(|e| fail(e, "Scope not allowed for this client"))?;
+
+    Ok(ValidRequest {
+        client,
+        redirect_uri,
+        scopes,
+        code_challenge,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/oauth/authorize",
+    tag = "OAuth",
+    params(AuthorizeParams),
+    responses(
+        (status = 200, description = "Consent page", content_type = "text/html"),
+        (status = 303, description = "To the login page without a session, or back to the client with an error"),
+        (status = 400, description = "Unknown client or unregistered redirect_uri")
+    )
+)]
+#[instrument(skip(state, user))]
+pub async fn authorize_handler(
+    State(state): State<AppState>,
+    OriginalUri(uri): OriginalUri,
+    user: Result<AuthenticatedUser, AuthApiError>,
+    Query(params): Query<AuthorizeParams>,
+) -> Response {
+    let request = match validate_request(&state, &params).await {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+    let Ok(user) = user else {
+        let return_to = format!(
+            "{}{}",
+            state.config.oauth.issuer,
+            uri.path_and_query().map(|p| p.as_str()).unwrap_or_default()
+        );
+        let mut login_url = match Url::parse(&state.config.app.login_url) {
+            Ok(url) => url,
+            Err(e) => return AuthApiError::UnexpectedError(e.to_string()).into_response(),
+        };
+        login_url
+            .query_pairs_mut()
+            .append_pair("return_to", &return_to);
+        return Redirect::to(login_url.as_str()).into_response();
+    };
+
+    let page = ConsentPage {
+        client_name: &request.client.name,
+        email: user.email.as_ref(),
+        scopes: &request.scopes,
+        params: &params,
+        redirect_uri: &request.redirect_uri,
+        scope: request.scopes.join(" "),
+    };
+    match page.render() {
+        Ok(html) => Html(html).into_response(),
+        Err(e) => AuthApiError::UnexpectedError(e.to_string()).into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/oauth/authorize",
+    tag = "OAuth",
+    request_body(content = AuthorizeDecision, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 303, description = "Back to the client with a code, or with access_denied"),
+        (status = 400, description = "Missing auth token, unknown client or unregistered redirect_uri"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+#[instrument(skip(state, user, form))]
+pub async fn authorize_decision_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Form(form): Form<AuthorizeDecision>,
+) -> Response {
+    let params = &form.params;
+    let request = match validate_request(&state, params).await {
+        Ok(request) => request,
+        Err(response) => return response,
+    };
+    if form.decision != "approve" {
+        return redirect_error(
+            &request.redirect_uri,
+            OAuthError::AccessDenied,
+            "The user denied the request",
+            params.state.as_deref(),
+        );
+    }
+
+    let ttl = state.config.oauth.authorization_code_ttl;
+    let grant = AuthorizationGrant {
+        sub: user.email.clone(),
+        client_id: request.client.client_id,
+        redirect_uri: request.redirect_uri.clone(),
+        redirect_uri_given: params.redirect_uri.is_some(),
+        scope: request.scopes.join(" "),
+        code_challenge: request.code_challenge,
+        nonce: params.nonce.clone(),
+        authentication: user.claims.authentication,
+    };
+    let (id, code) = match generate_authorization_code(grant, ttl, &state.config.jwt.secret) {
+        Ok(generated) => generated,
+        Err(e) => return AuthApiError::UnexpectedError(e.to_string()).into_response(),
+    };
+    if let Err(e) = state
+        .one_time_tokens
+        .write()
+        .await
+        .add_token(&id, &TokenPurpose::AuthorizationCode, &user.email, ttl)
+        .await
+    {
+        return e.into_response();
+    }
+
+    redirect_to_client(
+        &request.redirect_uri,
+        &[("code", &code)],
+        params.state.as_deref(),
+    )
+}
+
+/// Validate and burn an authorization code, it is spent even when a later check fails
+async fn consume_code(state: &AppState, code: &str) -> Result<AuthorizationGrant, AuthApiError> {
+    let invalid = || AuthApiError::OAuth(OAuthError::InvalidGrant, "Invalid code".to_string());
+    let claims = validate_token::<AuthorizationCodeClaims>(code, &state.config.jwt)
+        .await
+        .map_err(|_| invalid())?;
+    let email = state
+        .one_time_tokens
+        .write()
+        .await
+        .consume_token(&claims.jti, &TokenPurpose::AuthorizationCode)
+        .await
+        .map_err(|_| invalid())?;
+    if email != claims.grant.sub {
+        return Err(invalid());
+    }
+    Ok(claims.grant)
+}
+
+/// Identify the client calling a token endpoint and check its secret, sent either
+/// as `client_secret_basic` or `client_secret_post`. Public clients only send their id.
+pub async fn authenticate_client(
+    state: &AppState,
+    headers: &HeaderMap,
+    client_id: Option<&str>,
+    client_secret: Option<&str>,
+) -> Result<OAuthClient, AuthApiError> {
+    let request_error =
+        |description: &str| AuthApiError::OAuth(OAuthError::InvalidRequest, description.into());
+    let (client_id, client_secret) = match basic_credentials(headers) {
+        // RFC 6749 section 2.3, a single authentication method per request
+        Some(_) if client_secret.is_some() => {
+            return Err(request_error("Use only one client authentication method"));
+        }
+        Some((id, _)) if client_id.is_some_and(|body_id| body_id != id) => {
+            return Err(request_error("client_id does not match the credentials"));
+        }
+        Some((id, secret)) => (id, Some(secret)),
+        None => {
+            let id = client_id.ok_or_else(|| {
+                AuthApiError::OAuth(
+                    OAuthError::InvalidClient,
+                    "Client authentication required".to_string(),
+                )
+            })?;
+            (id.to_string(), client_secret.map(str::to_string))
+        }
+    };
+    let client = state.clients.read().await.get_client(&client_id).await?;
+    client.authenticate(client_secret.as_deref()).await?;
+    Ok(client)
+}
+
+async fn exchange_code(
+    state: &AppState,
+    client: OAuthClient,
+    body: TokenRequest,
+) -> Result<TokenResponse, AuthApiError> {
+    let grant_error =
+        |description: &str| AuthApiError::OAuth(OAuthError::InvalidGrant, description.to_string());
+    let (Some(code), Some(code_verifier)) = (&body.code, &body.code_verifier) else {
+        return Err(AuthApiError::OAuth(
+            OAuthError::InvalidRequest,
+            "code and code_verifier are required".to_string(),
+        ));
+    };
+    let grant = consume_code(state, code).await?;
+
+    if grant.client_id != client.client_id {
+        return Err(grant_error("Code was issued to another client"));
+    }
+    // only optional when the authorization request left it out too, RFC 6749 section 4.1.3
+    let redirect_uri_matches = match body.redirect_uri.as_deref() {
+        Some(uri) => uri == grant.redirect_uri,
+        None => !grant.redirect_uri_given,
+    };
+    if !redirect_uri_matches {
+        return Err(grant_error("redirect_uri does not match"));
+    }
+    if !verify_code_challenge(code_verifier, &grant.code_challenge) {
+        return Err(grant_error("PKCE verification failed"));
+    }
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&grant.sub)
+        .await
+        .map_err(|_| grant_error("User no longer exists"))?;
+    check_account_active(&user).map_err(|e| grant_error(&e.to_string()))?;
+
+    let access_token =
+        generate_oauth_access_token(state, &grant.sub, &client.client_id, &grant.scope).await?;
+    let id_token = match grant.has_scope(OPENID_SCOPE) {
+        true => Some(generate_id_token(state, &grant, &user)?),
+        false => None,
+    };
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: state.config.oauth.access_token_ttl,
+        scope: grant.scope,
+        id_token,
+    })
+}
+
+/// Token for the client itself, RFC 6749 section 4.4
+fn client_credentials(
+    state: &AppState,
+    client: OAuthClient,
+    scope: Option<&str>,
+) -> Result<TokenResponse, AuthApiError> {
+    if !client.is_confidential() {
+        return Err(AuthApiError::OAuth(
+            OAuthError::UnauthorizedClient,
+            "Public clients can't use the client_credentials grant".to_string(),
+        ));
+    }
+    let scope = client
+        .grant_scopes(scope)
+        .map_err(|e| AuthApiError::OAuth(e, "Scope not allowed for this client".to_string()))?
+        .join(" ");
+    let access_token = generate_client_access_token(state, &client.client_id, &scope)?;
+    Ok(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: state.config.oauth.access_token_ttl,
+        scope,
+        id_token: None,
+    })
+}
+
+async fn issue_token(
+    state: &AppState,
+    headers: &HeaderMap,
+    body: TokenRequest,
+) -> Result<TokenResponse, AuthApiError> {
+    let client = authenticate_client(
+        state,
+        headers,
+        body.client_id.as_deref(),
+        body.client_secret.as_deref(),
+    )
+    .await?;
+    match body.grant_type.as_str() {
+        "authorization_code" => exchange_code(state, client, body).await,
+        CLIENT_CREDENTIALS_GRANT => client_credentials(state, client, body.scope.as_deref()),
+        DEVICE_CODE_GRANT => device_code_grant(state, client, body).await,
+        _ => Err(AuthApiError::OAuth(
+            OAuthError::UnsupportedGrantType,
+            "Only the authorization_code, client_credentials and device_code grants are supported"
+                .to_string(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/oauth/token",
+    tag = "OAuth",
+    request_body(content = TokenRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Access token issued", body = TokenResponse),
+        (status = 400, description = "Invalid, expired or reused code, PKCE failure, scope not allowed, or a device authorization that is pending, polled too fast, expired or denied"),
+        (status = 401, description = "Unknown client or failed client authentication")
+    )
+)]
+#[instrument(skip(state, headers, body))]
+pub async fn token_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    FormOrJson(body): FormOrJson<TokenRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let response = issue_token(&state, &headers, body).await?;
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CACHE_CONTROL, "no-store"),
+            (header::PRAGMA, "no-cache"),
+        ],
+        Json(response),
+    ))
+}
@@ -0,0 +1,176 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::domain::{
+    CLIENT_CREDENTIALS_GRANT, CODE_CHALLENGE_METHOD, DEVICE_CODE_GRANT, Email, OAuthError,
+    OPENID_SCOPE,
+};
+use crate::error::AuthApiError;
+use crate::state::AppState;
+use crate::utils::auth::validate_auth_token;
+use crate::utils::bearer_token;
+
+/// OpenID Provider metadata, OIDC Discovery section 3
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: String,
+    /// RFC 8414 section 2
+    pub introspection_endpoint: String,
+    /// RFC 8414 section 2
+    pub revocation_endpoint: String,
+    /// RFC 8628 section 4
+    pub device_authorization_endpoint: String,
+    pub jwks_uri: String,
+    pub scopes_supported: Vec<String>,
+    pub response_types_supported: Vec<String>,
+    pub grant_types_supported: Vec<String>,
+    pub subject_types_supported: Vec<String>,
+    pub id_token_signing_alg_values_supported: Vec<String>,
+    pub token_endpoint_auth_methods_supported: Vec<String>,
+    pub code_challenge_methods_supported: Vec<String>,
+    pub claims_supported: Vec<String>,
+}
+
+/// OIDC Core section 5.3.2, claims depend on the scopes granted to the token
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct UserInfoResponse {
+    pub sub: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+#[utoipa::path(
+    get,
+    path = "/.well-known/openid-configuration",
+    tag = "OAuth",
+    responses(
+        (status = 200, description = "OpenID Provider metadata", body = OpenIdConfiguration)
+    )
+)]
+#[instrument(skip(state))]
+pub async fn openid_configuration_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let issuer = state.config.oauth.issuer.trim_end_matches('/');
+    let alg = format!("{:?}", state.config.jwt.secret.alg());
+    (
+        StatusCode::OK,
+        Json(OpenIdConfiguration {
+            issuer: issuer.to_string(),
+            authorization_endpoint: format!("{issuer}/oauth/authorize"),
+            token_endpoint: format!("{issuer}/oauth/token"),
+            userinfo_endpoint: format!("{issuer}/userinfo"),
+            introspection_endpoint: format!("{issuer}/introspect"),
+            revocation_endpoint: format!("{issuer}/revoke"),
+            device_authorization_endpoint: format!("{issuer}/device/code"),
+            jwks_uri: format!("{issuer}/.well-known/jwks.json"),
+            scopes_supported: strings(&[OPENID_SCOPE, "email"]),
+            response_types_supported: strings(&["code"]),
+            grant_types_supported: strings(&[
+                "authorization_code",
+                CLIENT_CREDENTIALS_GRANT,
+                DEVICE_CODE_GRANT,
+            ]),
+            subject_types_supported: strings(&["public"]),
+            id_token_signing_alg_values_supported: vec![alg],
+            token_endpoint_auth_methods_supported: strings(&[
+                "none",
+                "client_secret_basic",
+                "client_secret_post",
+            ]),
+            code_challenge_methods_supported: strings(&[CODE_CHALLENGE_METHOD]),
+            claims_supported: strings(&[
+                "iss",
+                "sub",
+                "aud",
+                "exp",
+                "iat",
+                "nonce",
+                "auth_time",
+                "amr",
+                "email",
+                "email_verified",
+            ]),
+        }),
+    )
+}
+
+/// Bearer token errors carry a `WWW-Authenticate` challenge, RFC 6750 section 3
+fn bearer_error(error: OAuthError, description: &str) -> Response {
+    let challenge = format!(r#"Bearer error="{error}", error_description="{description}""#);
+    let mut response = AuthApiError::OAuth(error, description.to_string()).into_response();
+    if let Ok(value) = HeaderValue::from_str(&challenge) {
+        response
+            .headers_mut()
+            .insert(header::WWW_AUTHENTICATE, value);
+    }
+    response
+}
+
+async fn user_info(state: &AppState, token: &str) -> Result<UserInfoResponse, Response> {
+    let claims = validate_auth_token(token, state)
+        .await
+        .map_err(|_| bearer_error(OAuthError::InvalidToken, "Invalid or revoked token"))?;
+    if claims.is_client_credentials() {
+        return Err(bearer_error(
+            OAuthError::InvalidToken,
+            "Token was not issued for a user",
+        ));
+    }
+    let scopes = claims.scope.as_deref().unwrap_or_default();
+    if !scopes.split(' ').any(|s| s == OPENID_SCOPE) {
+        return Err(bearer_error(
+            OAuthError::InsufficientScope,
+            "The openid scope is required",
+        ));
+    }
+    let email = Email::parse(&claims.sub)
+        .map_err(|_| bearer_error(OAuthError::InvalidToken, "Invalid subject"))?;
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .map_err(|_| bearer_error(OAuthError::InvalidToken, "User no longer exists"))?;
+
+    let with_email = scopes.split(' ').any(|s| s == "email");
+    Ok(UserInfoResponse {
+        sub: claims.sub,
+        email: with_email.then(|| user.email.as_ref().to_string()),
+        email_verified: with_email.then_some(user.verified),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/userinfo",
+    tag = "OAuth",
+    responses(
+        (status = 200, description = "Claims about the user the token was issued for", body = UserInfoResponse),
+        (status = 401, description = "Missing, invalid or revoked bearer token"),
+        (status = 403, description = "Token was not granted the openid scope")
+    )
+)]
+#[instrument(skip(state, headers))]
+pub async fn userinfo_handler(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let Some(token) = bearer_token(&headers) else {
+        return bearer_error(OAuthError::InvalidToken, "Missing bearer token");
+    };
+    match user_info(&state, token).await {
+        Ok(info) => (StatusCode::OK, Json(info)).into_response(),
+        Err(response) => response,
+    }
+}
@@ -0,0 +1,709 @@
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum_extra::extract::CookieJar;
+use axum_extra::extract::cookie::Cookie;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::{
+    Email, EmailTemplate, Invitation, Membership, OrgInvitationEmailData, OrgRole, Organization,
+    parse_org_name,
+};
+use crate::error::AuthApiError;
+use crate::state::AppState;
+use crate::utils::auth::issue_auth_cookie;
+use crate::utils::{AuthenticatedUser, FormOrJson};
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct OrgRequest {
+    pub name: String,
+}
+
+/// An organization along with the caller's role in it
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct OrgResponse {
+    #[serde(flatten)]
+    pub organization: Organization,
+    pub role: OrgRole,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct MemberRoleRequest {
+    pub role: OrgRole,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct InviteRequest {
+    pub email: String,
+    /// Role the invitee joins with, `member` if left out
+    #[serde(default = "default_invite_role")]
+    pub role: OrgRole,
+}
+
+fn default_invite_role() -> OrgRole {
+    OrgRole::Member
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct SwitchOrgRequest {
+    /// Organization to act for, `null` to act for the user alone
+    #[serde(default)]
+    pub org_id: Option<Uuid>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct SwitchOrgResponse {
+    /// Access token carrying the new organization, also set as the auth cookie
+    pub token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_role: Option<OrgRole>,
+}
+
+/// Membership of `email` in the organization `id`, unknown organizations and
+/// organizations the user isn't a member of look the same
+async fn membership(state: &AppState, id: &str, email: &Email) -> Result<Membership, AuthApiError> {
+    let id = Uuid::parse_str(id).map_err(|_| AuthApiError::OrganizationNotFound)?;
+    state
+        .organizations
+        .read()
+        .await
+        .get_membership(&id, email)
+        .await
+}
+
+/// Membership of `email` in `id` if it may manage members and invitations
+async fn manager(state: &AppState, id: &str, email: &Email) -> Result<Membership, AuthApiError> {
+    let membership = membership(state, id, email).await?;
+    if !membership.role.can_manage() {
+        return Err(AuthApiError::Forbidden);
+    }
+    Ok(membership)
+}
+
+/// Refuse to take away the only owner, someone has to be able to delete the organization
+async fn check_other_owner(
+    state: &AppState,
+    org_id: &Uuid,
+    email: &Email,
+) -> Result<(), AuthApiError> {
+    let members = state
+        .organizations
+        .read()
+        .await
+        .list_members(org_id)
+        .await?;
+    if !members
+        .iter()
+        .any(|m| m.role == OrgRole::Owner && m.email != *email)
+    {
+        return Err(AuthApiError::InvalidData(
+            "An organization needs at least one owner".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+async fn send_invitation_email(
+    state: &AppState,
+    invitation: &Invitation,
+    org: &Organization,
+) -> Result<(), AuthApiError> {
+    let template = EmailTemplate::OrgInvitation(OrgInvitationEmailData {
+        email: invitation.email.as_ref().to_string(),
+        invited_by: invitation.invited_by.as_ref().to_string(),
+        org_name: org.name.clone(),
+        role: invitation.role.as_ref().to_string(),
+        site_url: state.config.app.url.clone(),
+        link_url: format!(
+            "{}?id={}",
+            state.config.app.org_invitation_redirect_url, invitation.id
+        ),
+    });
+    let emailer = &state.email_client.read().await;
+    let subject = format!("You're Invited to Join {}", org.name);
+    if let Err(e) = emailer
+        .send_email(&invitation.email, &subject, &template)
+        .await
+    {
+        tracing::warn!("Unable to send mail: {}", &e);
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/orgs",
+    tag = "Organizations",
+    request_body = OrgRequest,
+    responses(
+        (status = 201, description = "Organization created, the caller is its owner", body = OrgResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 422, description = "Invalid name")
+    )
+)]
+#[instrument(skip(state, user, body))]
+pub async fn create_org_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    FormOrJson(body): FormOrJson<OrgRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let org = Organization::new(&body.name)?;
+    state
+        .organizations
+        .write()
+        .await
+        .add_org(org.clone(), &user.email)
+        .await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(OrgResponse {
+            organization: org,
+            role: OrgRole::Owner,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/orgs",
+    tag = "Organizations",
+    responses(
+        (status = 200, description = "Organizations the user is a member of", body = Vec<OrgResponse>),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+#[instrument(skip(state, user))]
+pub async fn list_orgs_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let organizations = state.organizations.read().await;
+    let memberships = organizations.list_memberships(&user.email).await?;
+    let mut orgs = Vec::with_capacity(memberships.len());
+    for membership in memberships {
+        orgs.push(OrgResponse {
+            organization: organizations.get_org(&membership.org_id).await?,
+            role: membership.role,
+        });
+    }
+    Ok((StatusCode::OK, Json(orgs)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/orgs/{id}",
+    tag = "Organizations",
+    params(("id" = Uuid, Path, description = "Id of the organization")),
+    responses(
+        (status = 200, description = "The organization", body = OrgResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Not a member of an organization with that id")
+    )
+)]
+#[instrument(skip(state, user))]
+pub async fn get_org_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let membership = membership(&state, &id, &user.email).await?;
+    let org = state
+        .organizations
+        .read()
+        .await
+        .get_org(&membership.org_id)
+        .await?;
+    Ok((
+        StatusCode::OK,
+        Json(OrgResponse {
+            organization: org,
+            role: membership.role,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    put,
+    path = "/orgs/{id}",
+    tag = "Organizations",
+    params(("id" = Uuid, Path, description = "Id of the organization")),
+    request_body = OrgRequest,
+    responses(
+        (status = 200, description = "Organization renamed", body = OrgResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin of the organization"),
+        (status = 404, description = "Not a member of an organization with that id"),
+        (status = 422, description = "Invalid name")
+    )
+)]
+#[instrument(skip(state, user, body))]
+pub async fn rename_org_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(id): Path<String>,
+    FormOrJson(body): FormOrJson<OrgRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let membership = manager(&state, &id, &user.email).await?;
+    let name = parse_org_name(&body.name)?;
+    let mut organizations = state.organizations.write().await;
+    organizations.rename_org(&membership.org_id, &name).await?;
+    let org = organizations.get_org(&membership.org_id).await?;
+    Ok((
+        StatusCode::OK,
+        Json(OrgResponse {
+            organization: org,
+            role: membership.role,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/orgs/{id}",
+    tag = "Organizations",
+    params(("id" = Uuid, Path, description = "Id of the organization")),
+    responses(
+        (status = 204, description = "Organization deleted along with its memberships and invitations"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an owner of the organization"),
+        (status = 404, description = "Not a member of an organization with that id")
+    )
+)]
+#[instrument(skip(state, user))]
+pub async fn delete_org_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let membership = membership(&state, &id, &user.email).await?;
+    if membership.role != OrgRole::Owner {
+        return Err(AuthApiError::Forbidden);
+    }
+    // tokens acting for it are refused once the memberships are gone
+    state
+        .organizations
+        .write()
+        .await
+        .delete_org(&membership.org_id)
+        .await?;
+    state
+        .invitations
+        .write()
+        .await
+        .delete_org(&membership.org_id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/orgs/{id}/members",
+    tag = "Organizations",
+    params(("id" = Uuid, Path, description = "Id of the organization")),
+    responses(
+        (status = 200, description = "Members of the organization", body = Vec<Membership>),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Not a member of an organization with that id")
+    )
+)]
+#[instrument(skip(state, user))]
+pub async fn list_members_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let membership = membership(&state, &id, &user.email).await?;
+    let members = state
+        .organizations
+        .read()
+        .await
+        .list_members(&membership.org_id)
+        .await?;
+    Ok((StatusCode::OK, Json(members)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/orgs/{id}/members/{email}",
+    tag = "Organizations",
+    params(
+        ("id" = Uuid, Path, description = "Id of the organization"),
+        ("email" = String, Path, description = "Email of the member")
+    ),
+    request_body = MemberRoleRequest,
+    responses(
+        (status = 204, description = "Role changed, it shows in the member's next token"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin, or an admin changing an owner"),
+        (status = 404, description = "Not a member of an organization with that id, or no such member"),
+        (status = 422, description = "The last owner can't be demoted")
+    )
+)]
+#[instrument(skip(state, user, body))]
+pub async fn update_member_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path((id, email)): Path<(String, String)>,
+    FormOrJson(body): FormOrJson<MemberRoleRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let caller = manager(&state, &id, &user.email).await?;
+    let email = Email::parse(&email).map_err(|_| AuthApiError::OrganizationNotFound)?;
+    let member = state
+        .organizations
+        .read()
+        .await
+        .get_membership(&caller.org_id, &email)
+        .await?;
+    if (member.role == OrgRole::Owner || body.role == OrgRole::Owner)
+        && caller.role != OrgRole::Owner
+    {
+        return Err(AuthApiError::Forbidden);
+    }
+    if member.role == OrgRole::Owner && body.role != OrgRole::Owner {
+        check_other_owner(&state, &caller.org_id, &email).await?;
+    }
+    state
+        .organizations
+        .write()
+        .await
+        .set_member_role(&caller.org_id, &email, body.role)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/orgs/{id}/members/{email}",
+    tag = "Organizations",
+    params(
+        ("id" = Uuid, Path, description = "Id of the organization"),
+        ("email" = String, Path, description = "Email of the member, or the caller's own to leave")
+    ),
+    responses(
+        (status = 204, description = "Member removed, their tokens for the organization are refused from now on"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin, or an admin removing an owner"),
+        (status = 404, description = "Not a member of an organization with that id, or no such member"),
+        (status = 422, description = "The last owner can't leave")
+    )
+)]
+#[instrument(skip(state, user))]
+pub async fn remove_member_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path((id, email)): Path<(String, String)>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let caller = membership(&state, &id, &user.email).await?;
+    let email = Email::parse(&email).map_err(|_| AuthApiError::OrganizationNotFound)?;
+    let member = if email == user.email {
+        caller.clone()
+    } else {
+        if !caller.role.can_manage() {
+            return Err(AuthApiError::Forbidden);
+        }
+        let member = state
+            .organizations
+            .read()
+            .await
+            .get_membership(&caller.org_id, &email)
+            .await?;
+        if member.role == OrgRole::Owner && caller.role != OrgRole::Owner {
+            return Err(AuthApiError::Forbidden);
+        }
+        member
+    };
+    if member.role == OrgRole::Owner {
+        check_other_owner(&state, &caller.org_id, &email).await?;
+    }
+    state
+        .organizations
+        .write()
+        .await
+        .remove_member(&caller.org_id, &email)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/orgs/{id}/invitations",
+    tag = "Organizations",
+    params(("id" = Uuid, Path, description = "Id of the organization")),
+    request_body = InviteRequest,
+    responses(
+        (status = 201, description = "Invitation emailed, it replaces any earlier one for the address", body = Invitation),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin, or an admin inviting an owner"),
+        (status = 404, description = "Not a member of an organization with that id"),
+        (status = 422, description = "Invalid email, or already a member")
+    )
+)]
+#[instrument(skip(state, user, body))]
+pub async fn create_invitation_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(id): Path<String>,
+    FormOrJson(body): FormOrJson<InviteRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let caller = manager(&state, &id, &user.email).await?;
+    if body.role == OrgRole::Owner && caller.role != OrgRole::Owner {
+        return Err(AuthApiError::Forbidden);
+    }
+    let email = Email::parse(&body.email)?;
+    let organizations = state.organizations.read().await;
+    match organizations.get_membership(&caller.org_id, &email).await {
+        Ok(_) => {
+            return Err(AuthApiError::InvalidData(
+                "Already a member of the organization".to_string(),
+            ));
+        }
+        Err(AuthApiError::OrganizationNotFound) => {}
+        Err(e) => return Err(e),
+    }
+    let org = organizations.get_org(&caller.org_id).await?;
+    drop(organizations);
+    let invitation = Invitation::new(
+        caller.org_id,
+        &email,
+        body.role,
+        &user.email,
+        state.config.auth.org_invitation_ttl,
+    );
+    state
+        .invitations
+        .write()
+        .await
+        .add_invitation(invitation.clone())
+        .await?;
+    send_invitation_email(&state, &invitation, &org).await?;
+    Ok((StatusCode::CREATED, Json(invitation)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/orgs/{id}/invitations",
+    tag = "Organizations",
+    params(("id" = Uuid, Path, description = "Id of the organization")),
+    responses(
+        (status = 200, description = "Pending invitations to the organization", body = Vec<Invitation>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin of the organization"),
+        (status = 404, description = "Not a member of an organization with that id")
+    )
+)]
+#[instrument(skip(state, user))]
+pub async fn list_org_invitations_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let caller = manager(&state, &id, &user.email).await?;
+    let invitations = state
+        .invitations
+        .read()
+        .await
+        .list_org_invitations(&caller.org_id)
+        .await?;
+    Ok((StatusCode::OK, Json(invitations)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/orgs/{id}/invitations/{invitation_id}",
+    tag = "Organizations",
+    params(
+        ("id" = Uuid, Path, description = "Id of the organization"),
+        ("invitation_id" = Uuid, Path, description = "Id of the invitation")
+    ),
+    responses(
+        (status = 204, description = "Invitation withdrawn"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin of the organization"),
+        (status = 404, description = "No pending invitation with that id in the organization")
+    )
+)]
+#[instrument(skip(state, user))]
+pub async fn revoke_invitation_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path((id, invitation_id)): Path<(String, String)>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let caller = manager(&state, &id, &user.email).await?;
+    let invitation_id =
+        Uuid::parse_str(&invitation_id).map_err(|_| AuthApiError::InvitationNotFound)?;
+    let mut invitations = state.invitations.write().await;
+    let invitation = invitations.get_invitation(&invitation_id).await?;
+    if invitation.org_id != caller.org_id {
+        return Err(AuthApiError::InvitationNotFound);
+    }
+    invitations.delete_invitation(&invitation_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/invitations",
+    tag = "Organizations",
+    responses(
+        (status = 200, description = "Pending invitations for the user", body = Vec<Invitation>),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+#[instrument(skip(state, user))]
+pub async fn list_invitations_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let invitations = state
+        .invitations
+        .read()
+        .await
+        .list_user_invitations(&user.email)
+        .await?;
+    Ok((StatusCode::OK, Json(invitations)))
+}
+
+/// Pending invitation `id` addressed to `email`, other users' invitations look missing
+async fn own_invitation(
+    state: &AppState,
+    id: &str,
+    email: &Email,
+) -> Result<Invitation, AuthApiError> {
+    let id = Uuid::parse_str(id).map_err(|_| AuthApiError::InvitationNotFound)?;
+    let invitation = state.invitations.read().await.get_invitation(&id).await?;
+    if invitation.email != *email {
+        return Err(AuthApiError::InvitationNotFound);
+    }
+    Ok(invitation)
+}
+
+#[utoipa::path(
+    post,
+    path = "/invitations/{id}/accept",
+    tag = "Organizations",
+    params(("id" = Uuid, Path, description = "Id of the invitation")),
+    responses(
+        (status = 200, description = "Joined the organization", body = Membership),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "No pending invitation with that id for the user, or the organization is gone"),
+        (status = 422, description = "Already a member")
+    )
+)]
+#[instrument(skip(state, user))]
+pub async fn accept_invitation_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let invitation = own_invitation(&state, &id, &user.email).await?;
+    let membership = Membership::new(invitation.org_id, &user.email, invitation.role);
+    state
+        .organizations
+        .write()
+        .await
+        .add_member(membership.clone())
+        .await?;
+    state
+        .invitations
+        .write()
+        .await
+        .delete_invitation(&invitation.id)
+        .await?;
+    Ok((StatusCode::OK, Json(membership)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/invitations/{id}",
+    tag = "Organizations",
+    params(("id" = Uuid, Path, description = "Id of the invitation")),
+    responses(
+        (status = 204, description = "Invitation declined"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "No pending invitation with that id for the user")
+    )
+)]
+#[instrument(skip(state, user))]
+pub async fn decline_invitation_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let invitation = own_invitation(&state, &id, &user.email).await?;
+    state
+        .invitations
+        .write()
+        .await
+        .delete_invitation(&invitation.id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn switch_org(
+    state: &AppState,
+    user: &AuthenticatedUser,
+    org_id: Option<Uuid>,
+) -> Result<(Cookie<'static>, SwitchOrgResponse), AuthApiError> {
+    // tokens that aren't backed by a session have nowhere to keep the choice
+    let sid = user.claims.sid.ok_or_else(|| {
+        AuthApiError::InvalidData("Only session tokens can switch organization".to_string())
+    })?;
+    let org_role = match &org_id {
+        Some(org_id) => Some(
+            state
+                .organizations
+                .read()
+                .await
+                .get_membership(org_id, &user.email)
+                .await?
+                .role,
+        ),
+        None => None,
+    };
+    state
+        .sessions
+        .write()
+        .await
+        .set_active_org(&sid, org_id)
+        .await?;
+    let account = state.user_store.read().await.get_user(&user.email).await?;
+    // tokens issued for the previous organization no longer match the session
+    let cookie = issue_auth_cookie(state, &account, &user.claims.authentication, &sid).await?;
+    let response = SwitchOrgResponse {
+        token: cookie.value().to_string(),
+        org_id,
+        org_role,
+    };
+    Ok((cookie, response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/switch-org",
+    tag = "Organizations",
+    request_body = SwitchOrgRequest,
+    responses(
+        (status = 200, description = "Token reissued for the organization, tokens issued for the previous one are refused", body = SwitchOrgResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Not a member of an organization with that id"),
+        (status = 422, description = "The token isn't tied to a session")
+    )
+)]
+#[instrument(skip(jar, state, user, body))]
+pub async fn switch_org_handler(
+    jar: CookieJar,
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    FormOrJson(body): FormOrJson<SwitchOrgRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthApiError>) {
+    match switch_org(&state, &user, body.org_id).await {
+        Ok((cookie, response)) => (jar.add(cookie), Ok((StatusCode::OK, Json(response)))),
+        Err(e) => (jar, Err(e)),
+    }
+}
@@ -0,0 +1,228 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum_extra::extract::CookieJar;
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use webauthn_rs::prelude::{
+    CreationChallengeResponse, PublicKeyCredential, RegisterPublicKeyCredential,
+    RequestChallengeResponse,
+};
+
+use crate::domain::{
+    AuthMethod, ClientInfo, Email, HashedPassword, PasskeyCeremony, PasskeyCeremonyId,
+    PasskeyCredential, TwoFactorMethod, User, encode_credential_id, verify_sign_count,
+};
+use crate::error::AuthApiError;
+use crate::routes::{SignupResponse, complete_login, send_verification_email};
+use crate::state::AppState;
+use crate::utils::FormOrJson;
+
+/// Options to hand to `navigator.credentials.create()`
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct PasskeyRegistrationChallenge {
+    pub id: PasskeyCeremonyId,
+    #[schema(value_type = Object)]
+    pub options: CreationChallengeResponse,
+}
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct PasskeyRegisterFinishRequest {
+    pub id: String,
+    /// Output of `navigator.credentials.create()`
+    #[schema(value_type = Object)]
+    pub credential: RegisterPublicKeyCredential,
+}
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct PasskeyLoginFinishRequest {
+    pub id: String,
+    /// Output of `navigator.credentials.get()`
+    #[schema(value_type = Object)]
+    pub credential: PublicKeyCredential,
+}
+
+/// Issue a registration challenge for a new account
+pub async fn start_passkey_registration(
+    state: &AppState,
+    email: &Email,
+) -> Result<PasskeyRegistrationChallenge, AuthApiError> {
+    if state.user_store.read().await.get_user(email).await.is_ok() {
+        return Err(AuthApiError::UserAlreadyExists);
+    }
+
+    let (options, registration) = state
+        .webauthn
+        .start_passkey_registration(Uuid::new_v4(), email.as_ref(), email.as_ref(), None)
+        .map_err(|e| AuthApiError::UnexpectedError(e.to_string()))?;
+
+    let id = PasskeyCeremonyId::new();
+    let ceremony = PasskeyCeremony::Registration {
+        email: email.clone(),
+        state: registration,
+    };
+    let ttl = state.config.auth.passkey_ceremony_ttl;
+    state
+        .passkeys
+        .write()
+        .await
+        .add_ceremony(&id, ceremony, ttl)
+        .await?;
+    Ok(PasskeyRegistrationChallenge { id, options })
+}
+
+/// Issue an assertion challenge for the passkeys registered to `email`
+pub async fn start_passkey_authentication(
+    state: &AppState,
+    email: &Email,
+) -> Result<(PasskeyCeremonyId, RequestChallengeResponse), AuthApiError> {
+    let mut passkeys = state.passkeys.write().await;
+    let credentials: Vec<_> = passkeys
+        .get_passkeys(email)
+        .await?
+        .into_iter()
+        .map(|c| c.passkey)
+        .collect();
+    if credentials.is_empty() {
+        return Err(AuthApiError::Unauthorized);
+    }
+
+    let (options, authentication) = state
+        .webauthn
+        .start_passkey_authentication(&credentials)
+        .map_err(|e| AuthApiError::UnexpectedError(e.to_string()))?;
+
+    let id = PasskeyCeremonyId::new();
+    let ceremony = PasskeyCeremony::Authentication {
+        email: email.clone(),
+        state: authentication,
+    };
+    let ttl = state.config.auth.passkey_ceremony_ttl;
+    passkeys.add_ceremony(&id, ceremony, ttl).await?;
+    Ok((id, options))
+}
+
+async fn finish_passkey_registration(
+    state: &AppState,
+    body: PasskeyRegisterFinishRequest,
+) -> Result<(), AuthApiError> {
+    let id = PasskeyCeremonyId::try_from(body.id.as_str())?;
+    let mut passkeys = state.passkeys.write().await;
+    let PasskeyCeremony::Registration {
+        email,
+        state: registration,
+    } = passkeys.take_ceremony(&id).await?
+    else {
+        return Err(AuthApiError::Unauthorized);
+    };
+
+    let passkey = state
+        .webauthn
+        .finish_passkey_registration(&body.credential, &registration)
+        .map_err(|e| {
+            tracing::warn!("Passkey registration failed: {}", e);
+            AuthApiError::Unauthorized
+        })?;
+
+    // passkey-only accounts get a random password nobody knows
+    let password = format!("{}{}", Uuid::new_v4(), Uuid::new_v4());
+    let user = User::new(
+        email.clone(),
+        HashedPassword::parse(&password).await?,
+        TwoFactorMethod::None,
+    );
+    state.user_store.write().await.add_user(user).await?;
+    passkeys
+        .add_passkey(PasskeyCredential::new(email.clone(), passkey))
+        .await?;
+    drop(passkeys);
+    send_verification_email(state, &email).await
+}
+
+async fn finish_passkey_authentication(
+    state: &AppState,
+    body: PasskeyLoginFinishRequest,
+) -> Result<Email, AuthApiError> {
+    let id = PasskeyCeremonyId::try_from(body.id.as_str())?;
+    let mut passkeys = state.passkeys.write().await;
+    let PasskeyCeremony::Authentication {
+        email,
+        state: authentication,
+    } = passkeys.take_ceremony(&id).await?
+    else {
+        return Err(AuthApiError::Unauthorized);
+    };
+
+    let result = state
+        .webauthn
+        .finish_passkey_authentication(&body.credential, &authentication)
+        .map_err(|e| {
+            tracing::warn!("Passkey authentication failed: {}", e);
+            AuthApiError::Unauthorized
+        })?;
+
+    let credential_id = encode_credential_id(result.cred_id());
+    let mut credential = passkeys
+        .get_passkeys(&email)
+        .await?
+        .into_iter()
+        .find(|c| c.credential_id() == credential_id)
+        .ok_or(AuthApiError::Unauthorized)?;
+
+    verify_sign_count(credential.sign_count, result.counter())?;
+    credential.passkey.update_credential(&result);
+    credential.sign_count = result.counter();
+    passkeys.update_passkey(&credential).await?;
+    Ok(email)
+}
+
+#[utoipa::path(
+    post,
+    path = "/passkey/register/finish",
+    tag = "Authentication",
+    responses(
+        (status = 201, description = "Passkey registered and account created"),
+        (status = 401, description = "Invalid, expired or already used challenge"),
+        (status = 409, description = "User already exists")
+    )
+)]
+#[instrument(skip(state, body))]
+pub async fn passkey_register_finish_handler(
+    State(state): State<AppState>,
+    FormOrJson(body): FormOrJson<PasskeyRegisterFinishRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    finish_passkey_registration(&state, body).await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(SignupResponse {
+            message: "Signup successful".to_string(),
+            recovery_codes: None,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/passkey/login/finish",
+    tag = "Authentication",
+    responses(
+        (status = 200, description = "Passkey login successful"),
+        (status = 401, description = "Invalid assertion, challenge or signature counter")
+    )
+)]
+#[instrument(skip(jar, state, body))]
+pub async fn passkey_login_finish_handler(
+    jar: CookieJar,
+    State(state): State<AppState>,
+    client: ClientInfo,
+    FormOrJson(body): FormOrJson<PasskeyLoginFinishRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthApiError>) {
+    let email = match finish_passkey_authentication(&state, body).await {
+        Ok(email) => email,
+        Err(e) => return (jar, Err(e)),
+    };
+    complete_login(jar, &email, &state, &[AuthMethod::Hwk], &client).await
+}
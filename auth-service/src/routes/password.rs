@@ -0,0 +1,241 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum_extra::extract::CookieJar;
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::domain::{
+    AuthMethod, ClientInfo, Email, EmailTemplate, HashedPassword, Password, PasswordResetEmailData,
+    TokenPurpose,
+};
+use crate::error::AuthApiError;
+use crate::routes::{
+    clear_failed_logins, complete_login, count_login_attempt, record_failed_login,
+};
+use crate::state::AppState;
+use crate::utils::auth::{generate_one_time_token, validate_one_time_token};
+use crate::utils::{AuthenticatedUser, FormOrJson};
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct ForgotPasswordRequest {
+    pub email: String,
+}
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct ResetPasswordRequest {
+    /// Token from the reset email
+    pub token: String,
+    pub password: String,
+}
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    pub new_password: String,
+}
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct PasswordResponse {
+    pub message: String,
+}
+
+/// Sign `email` out everywhere: access tokens (via the token version), refresh tokens
+/// and the session records
+pub async fn revoke_sessions(state: &AppState, email: &Email) -> Result<(), AuthApiError> {
+    state
+        .banned_tokens
+        .write()
+        .await
+        .bump_token_version(email)
+        .await?;
+    state
+        .refresh_tokens
+        .write()
+        .await
+        .revoke_user(email)
+        .await?;
+    state.sessions.write().await.revoke_user(email).await
+}
+
+/// Revoke everything that could still authenticate as `email`:
+/// sessions and outstanding one-time tokens.
+pub async fn revoke_all_tokens(state: &AppState, email: &Email) -> Result<(), AuthApiError> {
+    revoke_sessions(state, email).await?;
+    state
+        .one_time_tokens
+        .write()
+        .await
+        .revoke_tokens(email)
+        .await
+}
+
+async fn send_reset_link(state: &AppState, email: &Email) -> Result<(), AuthApiError> {
+    if state.user_store.read().await.get_user(email).await.is_err() {
+        return Ok(());
+    }
+
+    let ttl = state.config.auth.password_reset_ttl;
+    let purpose = TokenPurpose::PasswordReset;
+    let (id, token) = generate_one_time_token(email, &purpose, ttl, &state.config.jwt.secret)
+        .map_err(|e| AuthApiError::UnexpectedError(e.to_string()))?;
+    state
+        .one_time_tokens
+        .write()
+        .await
+        .add_token(&id, &purpose, email, ttl)
+        .await?;
+
+    let link_url = format!(
+        "{}?token={}",
+        &state.config.app.password_reset_redirect_url, token
+    );
+    let template = EmailTemplate::PasswordReset(PasswordResetEmailData {
+        email: email.as_ref().to_string(),
+        site_url: state.config.app.url.clone(),
+        link_url,
+    });
+    let emailer = &state.email_client.read().await;
+    if let Err(e) = emailer
+        .send_email(email, "Reset Your Password", &template)
+        .await
+    {
+        tracing::warn!("Unable to send mail: {}", &e);
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/password/forgot",
+    tag = "Authentication",
+    responses(
+        (status = 202, description = "Reset link sent, if the account exists", body = PasswordResponse),
+        (status = 400, description = "Invalid email")
+    )
+)]
+#[instrument(skip(state, body))]
+pub async fn forgot_password_handler(
+    State(state): State<AppState>,
+    FormOrJson(body): FormOrJson<ForgotPasswordRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let email = Email::parse(&body.email)?;
+    send_reset_link(&state, &email).await?;
+    // same answer either way so accounts can't be enumerated
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(PasswordResponse {
+            message: "If the account exists, a reset link has been sent".to_string(),
+        }),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/password/reset",
+    tag = "Authentication",
+    responses(
+        (status = 200, description = "Password changed, all sessions revoked", body = PasswordResponse),
+        (status = 400, description = "Password does not meet requirements"),
+        (status = 401, description = "Invalid, expired or already used token")
+    )
+)]
+#[instrument(skip(state, body))]
+pub async fn reset_password_handler(
+    State(state): State<AppState>,
+    FormOrJson(body): FormOrJson<ResetPasswordRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    // check the new password before burning the token
+    let password = Password::parse(&body.password)?;
+
+    let purpose = TokenPurpose::PasswordReset;
+    let claims = validate_one_time_token(&body.token, &purpose, &state.config.jwt)
+        .await
+        .map_err(|_| AuthApiError::InvalidToken)?;
+    let email = state
+        .one_time_tokens
+        .write()
+        .await
+        .consume_token(&claims.jti, &purpose)
+        .await?;
+    if email != claims.sub {
+        return Err(AuthApiError::InvalidToken);
+    }
+
+    let mut user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthApiError::InvalidToken)?;
+    user.password = HashedPassword::parse(password.as_ref()).await?;
+    // the reset link went to the inbox, so the address is confirmed too
+    user.verified = true;
+    state.user_store.write().await.update_user(&user).await?;
+    revoke_all_tokens(&state, &email).await?;
+    // proving access to the inbox lifts a lockout too
+    clear_failed_logins(&state, &email).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(PasswordResponse {
+            message: "Password updated".to_string(),
+        }),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/password/change",
+    tag = "Authentication",
+    responses(
+        (status = 200, description = "Password changed, other sessions revoked and fresh tokens issued"),
+        (status = 400, description = "Missing auth token or new password does not meet requirements"),
+        (status = 401, description = "Unauthorized or wrong current password"),
+        (status = 429, description = "Locked out after wrong current passwords, see the Retry-After header")
+    )
+)]
+#[instrument(skip(jar, state, user, body))]
+pub async fn change_password_handler(
+    jar: CookieJar,
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    client: ClientInfo,
+    FormOrJson(body): FormOrJson<ChangePasswordRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthApiError>) {
+    if let Err(e) = change_password(&state, &user.email, body).await {
+        return (jar, Err(e));
+    }
+    // everything issued so far is revoked, this session carries on with new tokens
+    complete_login(jar, &user.email, &state, &[AuthMethod::Pwd], &client).await
+}
+
+async fn change_password(
+    state: &AppState,
+    email: &Email,
+    body: ChangePasswordRequest,
+) -> Result<(), AuthApiError> {
+    let new_password = Password::parse(&body.new_password)?;
+    let current =
+        Password::parse(&body.current_password).map_err(|_| AuthApiError::Unauthorized)?;
+    // a stolen session is no reason to allow more guesses than the login form does
+    let attempt = count_login_attempt(state, email).await?;
+    let mut user = state.user_store.read().await.get_user(email).await?;
+    if user
+        .password
+        .verify_raw_password(current.as_ref())
+        .await
+        .is_err()
+    {
+        record_failed_login(state, email, &attempt).await?;
+        return Err(AuthApiError::Unauthorized);
+    }
+    clear_failed_logins(state, email).await?;
+
+    user.password = HashedPassword::parse(new_password.as_ref()).await?;
+    state.user_store.write().await.update_user(&user).await?;
+    revoke_all_tokens(state, email).await
+}
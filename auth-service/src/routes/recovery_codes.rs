@@ -0,0 +1,127 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::domain::{
+    Email, HashedRecoveryCode, Password, RecoveryCode, StoredRecoveryCode, TwoFactorMethod, User,
+};
+use crate::error::AuthApiError;
+use crate::routes::{
+    clear_failed_logins, count_login_attempt, record_failed_login, verify_totp_code,
+};
+use crate::state::AppState;
+use crate::utils::{AuthenticatedUser, FormOrJson};
+
+/// Proof that the account holder is asking, either one will do
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct RegenerateRecoveryCodesRequest {
+    /// The current password
+    pub password: Option<String>,
+    /// A code from the authenticator app, for accounts using TOTP
+    pub code: Option<String>,
+}
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct RecoveryCodesResponse {
+    /// Shown once - only hashes are kept
+    pub recovery_codes: Vec<String>,
+}
+
+/// Generate a new set of recovery codes for `email`, replacing any previous set.
+///
+/// Returns the plaintext codes for display.
+pub async fn issue_recovery_codes(
+    state: &AppState,
+    email: &Email,
+) -> Result<Vec<String>, AuthApiError> {
+    let codes = RecoveryCode::generate_set(state.config.auth.recovery_code_count);
+    // hash concurrently, each Argon2 run lands on the blocking pool anyway
+    let hashing: Vec<_> = codes
+        .iter()
+        .cloned()
+        .map(|code| tokio::spawn(async move { HashedRecoveryCode::parse(&code).await }))
+        .collect();
+    let mut stored = Vec::with_capacity(codes.len());
+    for handle in hashing {
+        let hash = handle
+            .await
+            .map_err(|e| AuthApiError::UnexpectedError(format!("{e}")))??;
+        stored.push(StoredRecoveryCode::new(hash));
+    }
+    state
+        .recovery_codes
+        .write()
+        .await
+        .replace_codes(email, stored)
+        .await?;
+    Ok(codes.iter().map(RecoveryCode::formatted).collect())
+}
+
+/// Whether `body` holds the password, or a TOTP code if the account uses TOTP
+async fn proves_account_holder(
+    state: &AppState,
+    account: &User,
+    body: &RegenerateRecoveryCodesRequest,
+) -> bool {
+    if let Some(password) = &body.password {
+        let Ok(password) = Password::parse(password) else {
+            return false;
+        };
+        return account
+            .password
+            .verify_raw_password(password.as_ref())
+            .await
+            .is_ok();
+    }
+    match &body.code {
+        Some(code) if account.two_factor == TwoFactorMethod::Totp => {
+            verify_totp_code(state, &account.email, code, false)
+                .await
+                .is_ok()
+        }
+        _ => false,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/2fa/recovery-codes",
+    tag = "Two Factor",
+    request_body = RegenerateRecoveryCodesRequest,
+    responses(
+        (status = 200, description = "New recovery codes issued, old ones revoked", body = RecoveryCodesResponse),
+        (status = 401, description = "Unauthorized, or neither the password nor a TOTP code given is right"),
+        (status = 422, description = "Two factor authentication is not enabled"),
+        (status = 429, description = "Locked out after wrong guesses, see the Retry-After header")
+    )
+)]
+#[instrument(skip(state, user, body))]
+pub async fn regenerate_recovery_codes_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    FormOrJson(body): FormOrJson<RegenerateRecoveryCodesRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let account = state.user_store.read().await.get_user(&user.email).await?;
+    if account.two_factor == TwoFactorMethod::None {
+        return Err(AuthApiError::InvalidData(
+            "Two factor authentication is not enabled".to_string(),
+        ));
+    }
+    // a stolen session mustn't be enough to replace the codes, nor to guess for them
+    let attempt = count_login_attempt(&state, &user.email).await?;
+    if !proves_account_holder(&state, &account, &body).await {
+        record_failed_login(&state, &user.email, &attempt).await?;
+        return Err(AuthApiError::Unauthorized);
+    }
+    clear_failed_logins(&state, &user.email).await?;
+
+    let recovery_codes = issue_recovery_codes(&state, &user.email).await?;
+    Ok((
+        StatusCode::OK,
+        Json(RecoveryCodesResponse { recovery_codes }),
+    ))
+}
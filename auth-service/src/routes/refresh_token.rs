@@ -0,0 +1,103 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum_extra::extract::CookieJar;
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::domain::{RefreshToken, RefreshTokenRecord};
+use crate::error::AuthApiError;
+use crate::routes::{LoginResponse, check_account_active};
+use crate::state::AppState;
+use crate::utils::auth::{generate_refresh_cookie, issue_auth_cookie};
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
+async fn refresh(
+    state: &AppState,
+    presented: &str,
+) -> Result<(RefreshToken, RefreshTokenRecord), AuthApiError> {
+    let presented = RefreshToken::parse(presented)?;
+    let next = RefreshToken::generate();
+    let record = state
+        .refresh_tokens
+        .write()
+        .await
+        .rotate_token(
+            &presented.hash(),
+            &next,
+            state.config.auth.refresh_token_ttl,
+        )
+        .await?;
+    // the session lives as long as its refresh tokens, a revoked one can't be refreshed
+    let now = chrono::Utc::now().timestamp();
+    let expires_at = now + state.config.auth.refresh_token_ttl as i64;
+    state
+        .sessions
+        .write()
+        .await
+        .touch_session(&record.family_id, now, Some(expires_at))
+        .await
+        .map_err(|_| AuthApiError::InvalidToken)?;
+    Ok((next, record))
+}
+
+#[utoipa::path(
+    post,
+    path = "/token/refresh",
+    tag = "Authentication",
+    request_body(content = Option<RefreshTokenRequest>, description = "Falls back to the refresh token cookie"),
+    responses(
+        (status = 200, description = "New access and refresh tokens issued"),
+        (status = 400, description = "Missing refresh token"),
+        (status = 401, description = "Invalid, expired or reused refresh token"),
+        (status = 403, description = "Account locked or pending deletion")
+    )
+)]
+#[instrument(skip(jar, state, body))]
+pub async fn refresh_token_handler(
+    jar: CookieJar,
+    State(state): State<AppState>,
+    body: Option<Json<RefreshTokenRequest>>,
+) -> (CookieJar, Result<impl IntoResponse, AuthApiError>) {
+    let presented = match (body, jar.get(&state.config.jwt.refresh_cookie_name)) {
+        (Some(Json(body)), _) => body.refresh_token,
+        (None, Some(cookie)) => cookie.value().to_string(),
+        (None, None) => return (jar, Err(AuthApiError::MissingToken)),
+    };
+    let (next, record) = match refresh(&state, &presented).await {
+        Ok(rotated) => rotated,
+        Err(e) => return (jar, Err(e)),
+    };
+    let user = match state.user_store.read().await.get_user(&record.email).await {
+        Ok(user) => user,
+        Err(_) => return (jar, Err(AuthApiError::InvalidToken)),
+    };
+    if let Err(e) = check_account_active(&user) {
+        return (jar, Err(e));
+    }
+    let token =
+        match issue_auth_cookie(&state, &user, &record.authentication, &record.family_id).await {
+            Ok(token) => token,
+            Err(e) => return (jar, Err(e)),
+        };
+    let jar = jar
+        .add(token.clone())
+        .add(generate_refresh_cookie(&next, &state.config.jwt));
+    (
+        jar,
+        Ok((
+            StatusCode::OK,
+            Json(LoginResponse::Success {
+                email: record.email,
+                token: token.value().to_string(),
+                refresh_token: next.as_ref().to_string(),
+            }),
+        )),
+    )
+}
@@ -0,0 +1,137 @@
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::domain::{OAuthClient, OAuthError, RefreshToken};
+use crate::error::AuthApiError;
+use crate::routes::authenticate_client;
+use crate::state::AppState;
+use crate::utils::FormOrJson;
+use crate::utils::auth::{Claims, validate_token};
+
+/// RFC 7009 section 2.1
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct RevocationRequest {
+    pub token: String,
+    /// `access_token` or `refresh_token`, only decides which kind is looked up first
+    pub token_type_hint: Option<String>,
+    /// Can be sent in an `Authorization: Basic` header instead
+    pub client_id: Option<String>,
+    /// Confidential clients only
+    pub client_secret: Option<String>,
+}
+
+/// Ban an access token until it expires. `false` if it isn't one of ours or already expired.
+async fn revoke_access_token(
+    state: &AppState,
+    client: &OAuthClient,
+    token: &str,
+) -> Result<bool, AuthApiError> {
+    let Ok(claims) = validate_token::<Claims>(token, &state.config.jwt).await else {
+        return Ok(false);
+    };
+    // session tokens name no client, only logging out ends them
+    if claims.client_id.as_deref() != Some(client.client_id.as_str()) {
+        return Err(AuthApiError::OAuth(
+            OAuthError::UnauthorizedClient,
+            "Token was issued to another client".to_string(),
+        ));
+    }
+    let remaining = claims.exp as i64 - chrono::Utc::now().timestamp();
+    state
+        .banned_tokens
+        .write()
+        .await
+        .ban_token_for(token, remaining.max(1) as u64)
+        .await?;
+    Ok(true)
+}
+
+/// End the rotation family of a refresh token and its session. `false` if it isn't a known one.
+async fn revoke_refresh_token(
+    state: &AppState,
+    client: &OAuthClient,
+    token: &str,
+) -> Result<bool, AuthApiError> {
+    let Ok(token) = RefreshToken::parse(token) else {
+        return Ok(false);
+    };
+    let Ok(record) = state
+        .refresh_tokens
+        .read()
+        .await
+        .get_token(&token.hash())
+        .await
+    else {
+        return Ok(false);
+    };
+    // RFC 7009 section 2.1, logins that named no client can only be ended by logging out
+    if record.client_id.as_deref() != Some(client.client_id.as_str()) {
+        return Err(AuthApiError::OAuth(
+            OAuthError::UnauthorizedClient,
+            "Token was issued to another client".to_string(),
+        ));
+    }
+    state
+        .refresh_tokens
+        .write()
+        .await
+        .revoke_family(&record.family_id)
+        .await?;
+    // may already be gone, e.g. revoked from another device
+    _ = state
+        .sessions
+        .write()
+        .await
+        .revoke_session(&record.email, &record.family_id)
+        .await;
+    Ok(true)
+}
+
+async fn revoke(
+    state: &AppState,
+    headers: &HeaderMap,
+    body: RevocationRequest,
+) -> Result<(), AuthApiError> {
+    let client = authenticate_client(
+        state,
+        headers,
+        body.client_id.as_deref(),
+        body.client_secret.as_deref(),
+    )
+    .await?;
+
+    // RFC 7009 section 2.1, a wrong hint only costs an extra lookup
+    if body.token_type_hint.as_deref() == Some("refresh_token") {
+        if !revoke_refresh_token(state, &client, &body.token).await? {
+            revoke_access_token(state, &client, &body.token).await?;
+        }
+    } else if !revoke_access_token(state, &client, &body.token).await? {
+        revoke_refresh_token(state, &client, &body.token).await?;
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/revoke",
+    tag = "OAuth",
+    request_body(content = RevocationRequest, content_type = "application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Token revoked, or it was invalid to begin with"),
+        (status = 400, description = "Token was issued to another client, or to a login that named none"),
+        (status = 401, description = "Unknown or unauthenticated client")
+    )
+)]
+#[instrument(skip(state, headers, body))]
+pub async fn revoke_handler(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    FormOrJson(body): FormOrJson<RevocationRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    revoke(&state, &headers, body).await?;
+    Ok(StatusCode::OK)
+}
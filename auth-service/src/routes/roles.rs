@@ -0,0 +1,221 @@
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::domain::{ADMIN_ROLE, Email, Role, validate_role_name};
+use crate::error::AuthApiError;
+use crate::state::AppState;
+use crate::utils::{AdminUser, FormOrJson};
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct UpdateRoleRequest {
+    #[serde(default)]
+    pub description: String,
+    /// Replaces the role's permissions
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct AssignRoleRequest {
+    pub role: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/roles",
+    tag = "Roles",
+    request_body = Role,
+    responses(
+        (status = 201, description = "Role created", body = Role),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 422, description = "Invalid name or permissions, or the name is taken")
+    )
+)]
+#[instrument(skip(state, _admin, body))]
+pub async fn create_role_handler(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    FormOrJson(body): FormOrJson<Role>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let role = Role::parse(&body.name, &body.description, &body.permissions)?;
+    state.roles.write().await.add_role(role.clone()).await?;
+    Ok((StatusCode::CREATED, Json(role)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/roles",
+    tag = "Roles",
+    responses(
+        (status = 200, description = "Every role", body = Vec<Role>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin")
+    )
+)]
+#[instrument(skip(state, _admin))]
+pub async fn list_roles_handler(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let roles = state.roles.read().await.list_roles().await?;
+    Ok((StatusCode::OK, Json(roles)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/roles/{name}",
+    tag = "Roles",
+    params(("name" = String, Path, description = "Name of the role")),
+    request_body = UpdateRoleRequest,
+    responses(
+        (status = 200, description = "Role updated, tokens issued from now on carry the new permissions", body = Role),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "No role with that name"),
+        (status = 422, description = "Invalid permissions")
+    )
+)]
+#[instrument(skip(state, _admin, body))]
+pub async fn update_role_handler(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    Path(name): Path<String>,
+    FormOrJson(body): FormOrJson<UpdateRoleRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    validate_role_name(&name).map_err(|_| AuthApiError::RoleNotFound)?;
+    let role = Role::parse(&name, &body.description, &body.permissions)?;
+    state.roles.write().await.update_role(&role).await?;
+    Ok((StatusCode::OK, Json(role)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/roles/{name}",
+    tag = "Roles",
+    params(("name" = String, Path, description = "Name of the role")),
+    responses(
+        (status = 204, description = "Role deleted and taken away from its users"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "No role with that name"),
+        (status = 422, description = "The admin role can't be deleted")
+    )
+)]
+#[instrument(skip(state, _admin))]
+pub async fn delete_role_handler(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    if name == ADMIN_ROLE {
+        return Err(AuthApiError::InvalidData(
+            "The admin role can't be deleted".to_string(),
+        ));
+    }
+    state.roles.write().await.delete_role(&name).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `email` of an existing user
+async fn existing_user(state: &AppState, email: &str) -> Result<Email, AuthApiError> {
+    let email = Email::parse(email)?;
+    state.user_store.read().await.get_user(&email).await?;
+    Ok(email)
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{email}/roles",
+    tag = "Roles",
+    params(("email" = String, Path, description = "Email of the user")),
+    responses(
+        (status = 200, description = "Roles assigned to the user", body = Vec<Role>),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "User not found")
+    )
+)]
+#[instrument(skip(state, _admin))]
+pub async fn list_user_roles_handler(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let email = existing_user(&state, &email).await?;
+    let roles = state.roles.read().await.user_roles(&email).await?;
+    Ok((StatusCode::OK, Json(roles)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{email}/roles",
+    tag = "Roles",
+    params(("email" = String, Path, description = "Email of the user")),
+    request_body = AssignRoleRequest,
+    responses(
+        (status = 204, description = "Role assigned, it shows in the user's next token"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "User or role not found")
+    )
+)]
+#[instrument(skip(state, _admin, body))]
+pub async fn assign_role_handler(
+    State(state): State<AppState>,
+    _admin: AdminUser,
+    Path(email): Path<String>,
+    FormOrJson(body): FormOrJson<AssignRoleRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let email = existing_user(&state, &email).await?;
+    state
+        .roles
+        .write()
+        .await
+        .assign_role(&email, &body.role)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/{email}/roles/{name}",
+    tag = "Roles",
+    params(
+        ("email" = String, Path, description = "Email of the user"),
+        ("name" = String, Path, description = "Name of the role")
+    ),
+    responses(
+        (status = 204, description = "Role taken away, it is gone from the user's next token"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "The user doesn't have that role"),
+        (status = 422, description = "Admins can't take the admin role from themselves")
+    )
+)]
+#[instrument(skip(state, admin))]
+pub async fn unassign_role_handler(
+    State(state): State<AppState>,
+    AdminUser(admin): AdminUser,
+    Path((email, name)): Path<(String, String)>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let email = Email::parse(&email)?;
+    // so the last admin can't lock everyone out
+    if email == admin.email && name == ADMIN_ROLE {
+        return Err(AuthApiError::InvalidData(
+            "Admins can't take the admin role from themselves".to_string(),
+        ));
+    }
+    state
+        .roles
+        .write()
+        .await
+        .unassign_role(&email, &name)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,85 @@
+use axum::Json;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::domain::Session;
+use crate::error::AuthApiError;
+use crate::state::AppState;
+use crate::utils::AuthenticatedUser;
+
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
+pub struct SessionResponse {
+    #[serde(flatten)]
+    pub session: Session,
+    /// Whether this is the session the request was made with
+    pub current: bool,
+}
+
+#[utoipa::path(
+    get,
+    path = "/sessions",
+    tag = "Sessions",
+    responses(
+        (status = 200, description = "The user's active sessions", body = Vec<SessionResponse>),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+#[instrument(skip(state, user))]
+pub async fn list_sessions_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let sessions = state
+        .sessions
+        .read()
+        .await
+        .list_sessions(&user.email)
+        .await?;
+    let sessions: Vec<SessionResponse> = sessions
+        .into_iter()
+        .map(|session| SessionResponse {
+            current: user.claims.sid == Some(session.id),
+            session,
+        })
+        .collect();
+    Ok((StatusCode::OK, Json(sessions)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/sessions/{id}",
+    tag = "Sessions",
+    params(("id" = Uuid, Path, description = "Id of the session")),
+    responses(
+        (status = 204, description = "Session revoked along with its refresh tokens"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "The user has no session with that id")
+    )
+)]
+#[instrument(skip(state, user))]
+pub async fn revoke_session_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let id = Uuid::parse_str(&id).map_err(|_| AuthApiError::SessionNotFound)?;
+    state
+        .sessions
+        .write()
+        .await
+        .revoke_session(&user.email, &id)
+        .await?;
+    // access tokens of the session are refused from now on, its refresh tokens go too
+    state
+        .refresh_tokens
+        .write()
+        .await
+        .revoke_family(&id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
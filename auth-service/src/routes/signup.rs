@@ -6,8 +6,9 @@ use serde::Serialize;
 use tracing::instrument;
 use utoipa::ToSchema;
 
-use crate::domain::{Email, HashedPassword, TwoFactorMethod, User, UserStore};
+use crate::domain::{Email, HashedPassword, TwoFactorMethod, User};
 use crate::error::AuthApiError;
+use crate::routes::{issue_recovery_codes, send_verification_email, start_passkey_registration};
 use crate::state::AppState;
 use crate::utils::FormOrJson;
 
@@ -33,11 +34,17 @@ pub enum SignupRequest {
     #[schema(title = "Magic Link")]
     MagicLink { email: String },
 
-    /// Signup using passkey (WebAuthn)
-    ///
-    /// Coming soon...
+    /// Signup using passkey (WebAuthn).
+    /// Responds with a challenge to complete at `/passkey/register/finish`
     #[schema(title = "Passkey/WebAuthn")]
-    Passkey { email: String },
+    Passkey(PasskeySignupRequest),
+}
+
+/// Only the email, a password or 2FA method makes it an invalid method/input pair
+#[derive(serde::Deserialize, Serialize, Debug, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PasskeySignupRequest {
+    pub email: String,
 }
 
 async fn user_from_signup_request(req: SignupRequest) -> Result<User, AuthApiError> {
@@ -48,6 +55,12 @@ async fn user_from_signup_request(req: SignupRequest) -> Result<User, AuthApiErr
             two_factor,
         } => {
             let email: Email = email.try_into()?;
+            if two_factor == TwoFactorMethod::Totp {
+                // needs a confirmed authenticator first, see /2fa/totp/enroll
+                return Err(AuthApiError::InvalidData(
+                    "TOTP can only be enabled after signup".to_string(),
+                ));
+            }
             let hashed_password = HashedPassword::parse(&password).await?;
             Ok(User::new(email, hashed_password, two_factor))
         }
@@ -55,9 +68,13 @@ async fn user_from_signup_request(req: SignupRequest) -> Result<User, AuthApiErr
     }
 }
 
-#[derive(Serialize, ToSchema)]
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
 pub struct SignupResponse {
     pub message: String,
+
+    /// Issued when signing up with two factor authentication enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery_codes: Option<Vec<String>>,
 }
 
 #[utoipa::path(
@@ -65,8 +82,11 @@ pub struct SignupResponse {
     path = "/signup",
     tag = "Authentication",
     responses(
+        (status = 200, description = "Passkey registration challenge issued"),
         (status = 201, description = "Signup successful"),
-        (status = 400, description = "Bad Request")
+        (status = 400, description = "Bad Request"),
+        (status = 409, description = "User already exists"),
+        (status = 429, description = "Too many signups, see the Retry-After header")
     )
 )]
 #[instrument(skip(state, request))]
@@ -74,16 +94,30 @@ pub async fn signup_handler(
     State(state): State<AppState>,
     FormOrJson(request): FormOrJson<SignupRequest>,
 ) -> Result<impl IntoResponse, AuthApiError> {
-    // Placeholder for signup logic
+    if let SignupRequest::Passkey(PasskeySignupRequest { email }) = &request {
+        let email = Email::parse(email)?;
+        let challenge = start_passkey_registration(&state, &email).await?;
+        return Ok((StatusCode::OK, Json(challenge)).into_response());
+    }
+
     let user: User = user_from_signup_request(request).await?;
-    let mut user_store = state.user_store.write().await;
-    user_store.add_user(user).await?;
+    let email = user.email.clone();
+    let two_factor = user.two_factor.clone();
+    state.user_store.write().await.add_user(user).await?;
+    send_verification_email(&state, &email).await?;
+
+    let recovery_codes = match two_factor {
+        TwoFactorMethod::None => None,
+        _ => Some(issue_recovery_codes(&state, &email).await?),
+    };
     Ok((
         StatusCode::CREATED,
         Json(SignupResponse {
             message: "Signup successful".to_string(),
+            recovery_codes,
         }),
-    ))
+    )
+        .into_response())
 }
 
 #[cfg(test)]
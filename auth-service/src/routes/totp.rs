@@ -0,0 +1,120 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::domain::{Email, TotpSecret, TwoFactorMethod, totp_now};
+use crate::error::AuthApiError;
+use crate::routes::issue_recovery_codes;
+use crate::state::AppState;
+use crate::utils::{AuthenticatedUser, FormOrJson};
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct TotpEnrollResponse {
+    /// `otpauth://totp/...` URI, usually rendered as a QR code
+    pub otpauth_uri: String,
+    /// Base32 secret for manual entry
+    pub secret: String,
+}
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct TotpConfirmRequest {
+    pub code: String,
+}
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct TotpConfirmResponse {
+    pub message: String,
+    /// Shown once - only hashes are kept
+    pub recovery_codes: Vec<String>,
+}
+
+/// Check a code against the user's active (or pending) secret and burn its time step
+pub async fn verify_totp_code(
+    state: &AppState,
+    email: &Email,
+    code: &str,
+    pending: bool,
+) -> Result<(), AuthApiError> {
+    let mut totp = state.totp.write().await;
+    let secret = if pending {
+        totp.get_pending_secret(email).await?
+    } else {
+        totp.get_secret(email).await?
+    };
+    let step = secret
+        .verify(code, state.config.auth.totp_skew, totp_now())
+        .ok_or(AuthApiError::TwoFactorCodeMismatch)?;
+    if pending {
+        totp.activate_secret(email).await?;
+    }
+    totp.use_step(email, step).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/2fa/totp/enroll",
+    tag = "Two Factor",
+    responses(
+        (status = 200, description = "TOTP secret generated, pending confirmation", body = TotpEnrollResponse),
+        (status = 400, description = "Missing auth token"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+#[instrument(skip(state, user))]
+pub async fn totp_enroll_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let secret = TotpSecret::generate();
+    let otpauth_uri = secret.otpauth_uri(&state.config.auth.totp_issuer, &user.email)?;
+    state
+        .totp
+        .write()
+        .await
+        .set_pending_secret(&user.email, &secret)
+        .await?;
+    Ok((
+        StatusCode::OK,
+        Json(TotpEnrollResponse {
+            otpauth_uri,
+            secret: secret.as_ref().to_string(),
+        }),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/2fa/totp/confirm",
+    tag = "Two Factor",
+    responses(
+        (status = 200, description = "TOTP enabled", body = TotpConfirmResponse),
+        (status = 401, description = "Unauthorized or wrong code"),
+        (status = 404, description = "No pending enrollment")
+    )
+)]
+#[instrument(skip(state, user, body))]
+pub async fn totp_confirm_handler(
+    State(state): State<AppState>,
+    user: AuthenticatedUser,
+    FormOrJson(body): FormOrJson<TotpConfirmRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    verify_totp_code(&state, &user.email, &body.code, true).await?;
+    state
+        .user_store
+        .write()
+        .await
+        .set_two_factor(&user.email, &TwoFactorMethod::Totp)
+        .await?;
+    let recovery_codes = issue_recovery_codes(&state, &user.email).await?;
+    Ok((
+        StatusCode::OK,
+        Json(TotpConfirmResponse {
+            message: "TOTP enabled".to_string(),
+            recovery_codes,
+        }),
+    ))
+}
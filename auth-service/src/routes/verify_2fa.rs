@@ -1,12 +1,11 @@
-use crate::domain::{Email, LoginAttemptId, TwoFactorCode, TwoFactorCodeStore, TwoFactorMethod};
+use crate::domain::{
+    AuthMethod, ClientInfo, Email, LoginAttemptId, RecoveryCode, TwoFactorCode, TwoFactorMethod,
+};
 use crate::error::AuthApiError;
-use crate::routes::LoginResponse;
+use crate::routes::{complete_login, verify_totp_code};
 use crate::state::AppState;
 use crate::utils::FormOrJson;
-use crate::utils::auth::generate_auth_cookie;
-use axum::Json;
 use axum::extract::State;
-use axum::http::StatusCode;
 use axum::response::IntoResponse;
 use axum_extra::extract::CookieJar;
 use serde::Serialize;
@@ -23,24 +22,125 @@ pub struct Verify2FARequest {
 
 // Consumes the request to verify the 2FA code. If successful, returns the email associated with the login attempt.
 pub async fn verify_2fa(state: &AppState, body: Verify2FARequest) -> Result<Email, AuthApiError> {
-    let two_factor = &state.two_factor.read().await;
     let email: Email = body
         .email
         .try_into()
         .map_err(|_| AuthApiError::Unauthorized)?;
     let attempt_id: LoginAttemptId = body.id.try_into().map_err(|_| AuthApiError::Unauthorized)?;
-    let code: TwoFactorCode = body
-        .code
-        .try_into()
-        .map_err(|_| AuthApiError::Unauthorized)?;
-    if !two_factor
-        .verify_code(&email, &attempt_id, &code)
+
+    // the method is the user's, the request only gets to confirm it
+    let user = state
+        .user_store
+        .read()
         .await
-        .unwrap_or(false)
-    {
+        .get_user(&email)
+        .await
+        .map_err(|_| AuthApiError::Unauthorized)?;
+    if user.two_factor != body.method {
         return Err(AuthApiError::Unauthorized);
     }
-    Ok(email)
+
+    // a recovery code stands in for either kind of second factor
+    if let Ok(recovery_code) = RecoveryCode::parse(&body.code) {
+        if body.method == TwoFactorMethod::None {
+            return Err(AuthApiError::Unauthorized);
+        }
+        let (id, _) = state
+            .two_factor
+            .read()
+            .await
+            .get_code(&email)
+            .await
+            .map_err(|_| AuthApiError::Unauthorized)?;
+        if id != attempt_id {
+            return Err(AuthApiError::Unauthorized);
+        }
+        if state
+            .recovery_codes
+            .write()
+            .await
+            .consume_code(&email, &recovery_code)
+            .await
+            .is_err()
+        {
+            return fail_attempt(state, &email, &attempt_id).await;
+        }
+        return complete_attempt(state, &email, &attempt_id).await;
+    }
+
+    match body.method {
+        TwoFactorMethod::Email => {
+            let max_attempts = state.config.auth.two_factor_max_attempts;
+            let code: TwoFactorCode = body
+                .code
+                .try_into()
+                .map_err(|_| AuthApiError::Unauthorized)?;
+            // a match is consumed in the same step, so the code can't be replayed
+            if !state
+                .two_factor
+                .write()
+                .await
+                .verify_code(&email, &attempt_id, &code, max_attempts)
+                .await
+                .unwrap_or(false)
+            {
+                return Err(AuthApiError::Unauthorized);
+            }
+            Ok(email)
+        }
+        TwoFactorMethod::Totp => {
+            let (id, _) = state
+                .two_factor
+                .read()
+                .await
+                .get_code(&email)
+                .await
+                .map_err(|_| AuthApiError::Unauthorized)?;
+            if id != attempt_id {
+                return Err(AuthApiError::Unauthorized);
+            }
+            if verify_totp_code(state, &email, &body.code, false)
+                .await
+                .is_err()
+            {
+                return fail_attempt(state, &email, &attempt_id).await;
+            }
+            complete_attempt(state, &email, &attempt_id).await
+        }
+        TwoFactorMethod::None => Err(AuthApiError::Unauthorized),
+    }
+}
+
+/// Count a wrong TOTP or recovery code against the login attempt
+async fn fail_attempt(
+    state: &AppState,
+    email: &Email,
+    attempt_id: &LoginAttemptId,
+) -> Result<Email, AuthApiError> {
+    let max_attempts = state.config.auth.two_factor_max_attempts;
+    _ = state
+        .two_factor
+        .write()
+        .await
+        .record_failure(email, attempt_id, max_attempts)
+        .await;
+    Err(AuthApiError::Unauthorized)
+}
+
+/// End the login attempt after a TOTP or recovery code matched, only one request may
+async fn complete_attempt(
+    state: &AppState,
+    email: &Email,
+    attempt_id: &LoginAttemptId,
+) -> Result<Email, AuthApiError> {
+    state
+        .two_factor
+        .write()
+        .await
+        .consume_attempt(email, attempt_id)
+        .await
+        .map_err(|_| AuthApiError::Unauthorized)?;
+    Ok(email.clone())
 }
 
 #[utoipa::path(
@@ -49,13 +149,15 @@ pub async fn verify_2fa(state: &AppState, body: Verify2FARequest) -> Result<Emai
     tag = "Authentication",
     responses(
         (status = 200, description = "2FA verification successful"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 429, description = "Too many attempts, see the Retry-After header")
     )
 )]
 #[instrument(skip(jar, state, body))]
 pub async fn verify_2fa_handler(
     jar: CookieJar,
     State(state): State<AppState>,
+    client: ClientInfo,
     FormOrJson(body): FormOrJson<Verify2FARequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthApiError>) {
     let result = verify_2fa(&state, body).await;
@@ -63,20 +165,12 @@ pub async fn verify_2fa_handler(
         return (jar, Err(result.err().unwrap()));
     }
     let email = result.unwrap();
-    let token = generate_auth_cookie(&email, &state.config.jwt);
-    if token.is_err() {
-        return (jar, Err(AuthApiError::InvalidCredentials));
-    }
-    let token = token.unwrap();
-    let jar = jar.add(token.clone());
-    (
+    complete_login(
         jar,
-        Ok((
-            StatusCode::OK,
-            Json(LoginResponse::Success {
-                email: email.clone(),
-                token: token.value().to_string(),
-            }),
-        )),
+        &email,
+        &state,
+        &[AuthMethod::Pwd, AuthMethod::Otp, AuthMethod::Mfa],
+        &client,
     )
+    .await
 }
@@ -0,0 +1,162 @@
+use axum::Json;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+
+use crate::config::EmailVerificationPolicy;
+use crate::domain::{Email, EmailTemplate, EmailVerificationEmailData, TokenPurpose, User};
+use crate::error::AuthApiError;
+use crate::state::AppState;
+use crate::utils::FormOrJson;
+use crate::utils::auth::{generate_one_time_token, validate_one_time_token};
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct VerifyEmailRequest {
+    /// Token from the verification email
+    pub token: String,
+}
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct ResendVerificationRequest {
+    pub email: String,
+}
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct VerifyEmailResponse {
+    pub message: String,
+}
+
+/// Refuse a login for an unverified account when the policy is `Block`
+pub fn check_email_verified(state: &AppState, user: &User) -> Result<(), AuthApiError> {
+    if !user.verified && state.config.auth.email_verification == EmailVerificationPolicy::Block {
+        return Err(AuthApiError::EmailNotVerified);
+    }
+    Ok(())
+}
+
+pub async fn mark_email_verified(state: &AppState, email: &Email) -> Result<(), AuthApiError> {
+    let mut users = state.user_store.write().await;
+    let mut user = users.get_user(email).await?;
+    if !user.verified {
+        user.verified = true;
+        users.update_user(&user).await?;
+    }
+    Ok(())
+}
+
+/// Email a single-use verification link to `email`
+pub async fn send_verification_email(state: &AppState, email: &Email) -> Result<(), AuthApiError> {
+    let ttl = state.config.auth.email_verification_ttl;
+    let purpose = TokenPurpose::EmailVerification;
+    let (id, token) = generate_one_time_token(email, &purpose, ttl, &state.config.jwt.secret)
+        .map_err(|e| AuthApiError::UnexpectedError(e.to_string()))?;
+    state
+        .one_time_tokens
+        .write()
+        .await
+        .add_token(&id, &purpose, email, ttl)
+        .await?;
+
+    let link_url = format!(
+        "{}?token={}",
+        &state.config.app.email_verification_redirect_url, token
+    );
+    let template = EmailTemplate::EmailVerification(EmailVerificationEmailData {
+        email: email.as_ref().to_string(),
+        site_url: state.config.app.url.clone(),
+        link_url,
+    });
+    let emailer = &state.email_client.read().await;
+    if let Err(e) = emailer
+        .send_email(email, "Verify Your Email", &template)
+        .await
+    {
+        tracing::warn!("Unable to send mail: {}", &e);
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/verify-email",
+    tag = "Authentication",
+    responses(
+        (status = 200, description = "Email address verified", body = VerifyEmailResponse),
+        (status = 401, description = "Invalid, expired or already used token")
+    )
+)]
+#[instrument(skip(state, body))]
+pub async fn verify_email_handler(
+    State(state): State<AppState>,
+    FormOrJson(body): FormOrJson<VerifyEmailRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let purpose = TokenPurpose::EmailVerification;
+    let claims = validate_one_time_token(&body.token, &purpose, &state.config.jwt)
+        .await
+        .map_err(|_| AuthApiError::InvalidToken)?;
+    let email = state
+        .one_time_tokens
+        .write()
+        .await
+        .consume_token(&claims.jti, &purpose)
+        .await?;
+    if email != claims.sub {
+        return Err(AuthApiError::InvalidToken);
+    }
+    mark_email_verified(&state, &email)
+        .await
+        .map_err(|_| AuthApiError::InvalidToken)?;
+
+    Ok((
+        StatusCode::OK,
+        Json(VerifyEmailResponse {
+            message: "Email verified".to_string(),
+        }),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/verify-email/resend",
+    tag = "Authentication",
+    responses(
+        (status = 202, description = "Verification email sent, if the account exists and is unverified", body = VerifyEmailResponse),
+        (status = 400, description = "Invalid email"),
+        (status = 429, description = "Too many requests for this address")
+    )
+)]
+#[instrument(skip(state, body))]
+pub async fn resend_verification_handler(
+    State(state): State<AppState>,
+    FormOrJson(body): FormOrJson<ResendVerificationRequest>,
+) -> Result<impl IntoResponse, AuthApiError> {
+    let email = Email::parse(&body.email)?;
+    // limited per address whether or not it exists, so the limit doesn't leak accounts
+    state
+        .rate_limits
+        .write()
+        .await
+        .hit(
+            &format!("verification_resend_{}", email.as_ref()),
+            state.config.auth.verification_resend_limit,
+            state.config.auth.verification_resend_window,
+        )
+        .await?;
+
+    let user = state.user_store.read().await.get_user(&email).await;
+    if let Ok(user) = user
+        && !user.verified
+    {
+        send_verification_email(&state, &email).await?;
+    }
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(VerifyEmailResponse {
+            message: "If the account needs verifying, an email has been sent".to_string(),
+        }),
+    ))
+}
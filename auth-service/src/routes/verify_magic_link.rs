@@ -0,0 +1,74 @@
+use crate::domain::{AuthMethod, ClientInfo, Email, TokenPurpose, TwoFactorMethod};
+use crate::error::AuthApiError;
+use crate::routes::{check_lockout, complete_login, mark_email_verified, start_second_factor};
+use crate::state::AppState;
+use crate::utils::FormOrJson;
+use crate::utils::auth::validate_one_time_token;
+use axum::extract::State;
+use axum::response::IntoResponse;
+use axum_extra::extract::CookieJar;
+use serde::Serialize;
+use tracing::instrument;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema, serde::Deserialize, Debug)]
+pub struct VerifyMagicLinkRequest {
+    token: String,
+}
+
+// Consumes the magic link token. If it is valid and unused, returns the email it was issued for.
+pub async fn verify_magic_link(
+    state: &AppState,
+    body: VerifyMagicLinkRequest,
+) -> Result<Email, AuthApiError> {
+    let purpose = TokenPurpose::MagicLink;
+    let claims = validate_one_time_token(&body.token, &purpose, &state.config.jwt)
+        .await
+        .map_err(|_| AuthApiError::InvalidToken)?;
+    // refused before the link is spent, it can be used once the lock is over
+    check_lockout(state, &claims.sub).await?;
+    let email = state
+        .one_time_tokens
+        .write()
+        .await
+        .consume_token(&claims.jti, &purpose)
+        .await?;
+    if email != claims.sub {
+        return Err(AuthApiError::InvalidToken);
+    }
+    // the link was delivered, which proves the address as well as a verification email would
+    mark_email_verified(state, &email).await?;
+    Ok(email)
+}
+
+#[utoipa::path(
+    post,
+    path = "/verify-magic-link",
+    tag = "Authentication",
+    responses(
+        (status = 200, description = "Magic link login successful"),
+        (status = 206, description = "Second factor required, see `/verify-2fa`"),
+        (status = 401, description = "Invalid, expired or already used link"),
+        (status = 429, description = "Locked out after failed logins, see the Retry-After header")
+    )
+)]
+#[instrument(skip(jar, state, body))]
+pub async fn verify_magic_link_handler(
+    jar: CookieJar,
+    State(state): State<AppState>,
+    client: ClientInfo,
+    FormOrJson(body): FormOrJson<VerifyMagicLinkRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthApiError>) {
+    let email = match verify_magic_link(&state, body).await {
+        Ok(email) => email,
+        Err(e) => return (jar, Err(e)),
+    };
+    let user = match state.user_store.read().await.get_user(&email).await {
+        Ok(user) => user,
+        Err(e) => return (jar, Err(e)),
+    };
+    if let TwoFactorMethod::Email | TwoFactorMethod::Totp = user.two_factor {
+        return start_second_factor(jar, &state, &user).await;
+    }
+    complete_login(jar, &email, &state, &[AuthMethod::Email], &client).await
+}
@@ -1,13 +1,44 @@
+use crate::domain::{ApiKey, OrgRole};
 use crate::error::AuthApiError;
 use crate::state::AppState;
-use crate::utils::auth::{Claims, validate_token};
+use crate::utils::auth::{user_grants, validate_api_key, validate_auth_token};
 use axum::{Json, extract::State, http::StatusCode, response::IntoResponse};
+use serde::Serialize;
 use tracing::instrument;
 use utoipa::ToSchema;
+use uuid::Uuid;
 
 #[derive(serde::Deserialize, ToSchema, Debug, Clone)]
 pub struct VerifyTokenRequest {
+    /// An access token, or a personal API key
     pub token: String,
+    /// Answer 403 unless the token grants this permission
+    #[serde(default)]
+    pub permission: Option<String>,
+}
+
+/// Who the token was issued for and what it may be used for
+#[derive(Serialize, serde::Deserialize, ToSchema, Debug, Clone)]
+pub struct VerifyTokenResponse {
+    /// User email, or the client id for client credentials tokens
+    pub sub: String,
+    /// Empty for session tokens and API keys created without scopes
+    pub scopes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// Session the token belongs to, only set for tokens issued through a login
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<Uuid>,
+    /// Organization the token acts for, only set after `/switch-org`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_role: Option<OrgRole>,
+    /// Roles of the user, as of when the token was issued
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permissions: Vec<String>,
 }
 
 #[utoipa::path(
@@ -15,8 +46,9 @@ pub struct VerifyTokenRequest {
     path = "/verify-token",
     tag = "Authentication",
     responses(
-        (status = 200, description = "Token verification successful"),
-        (status = 401, description = "Unauthorized")
+        (status = 200, description = "Token verification successful", body = VerifyTokenResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "The token doesn't grant the required permission")
     )
 )]
 #[instrument]
@@ -24,15 +56,38 @@ pub async fn verify_token_handler(
     State(state): State<AppState>,
     Json(body): Json<VerifyTokenRequest>,
 ) -> Result<impl IntoResponse, AuthApiError> {
-    let banned = state.banned_tokens.read().await;
-
-    if banned.is_token_banned(&body.token).await {
-        return Err(AuthApiError::Unauthorized);
+    let response = if ApiKey::is_api_key(&body.token) {
+        let key = validate_api_key(&body.token, &state).await?;
+        // keys aren't reissued, so they act with the roles the user has now
+        let grants = user_grants(&state, &key.email).await?;
+        VerifyTokenResponse {
+            sub: key.email.as_ref().to_string(),
+            scopes: key.scopes,
+            client_id: None,
+            session_id: None,
+            org_id: None,
+            org_role: None,
+            roles: grants.roles,
+            permissions: grants.permissions,
+        }
+    } else {
+        let claims = validate_auth_token(&body.token, &state).await?;
+        VerifyTokenResponse {
+            scopes: claims.scopes(),
+            sub: claims.sub,
+            client_id: claims.client_id,
+            session_id: claims.sid,
+            org_id: claims.org_id,
+            org_role: claims.org_role,
+            roles: claims.grants.roles,
+            permissions: claims.grants.permissions,
+        }
+    };
+    if let Some(permission) = &body.permission
+        && !response.permissions.contains(permission)
+    {
+        return Err(AuthApiError::Forbidden);
     }
 
-    _ = validate_token::<Claims>(&body.token, &state.config.jwt)
-        .await
-        .map_err(|_| AuthApiError::InvalidToken)?;
-
-    Ok((StatusCode::OK, "Token verification successful").into_response())
+    Ok((StatusCode::OK, Json(response)).into_response())
 }
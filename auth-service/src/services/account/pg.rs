@@ -0,0 +1,69 @@
+use sqlx::{PgPool, postgres::PgPoolOptions};
+
+use crate::{
+    domain::{AccountStore, Email, User, UserRow},
+    error::AuthApiError,
+};
+
+/// Tables keyed by the account's address besides `user`, see `AccountStore::change_email`
+const ACCOUNT_TABLES: [&str; 8] = [
+    "passkey",
+    "totp",
+    "recovery_code",
+    "federated_identity",
+    "api_key",
+    "user_role",
+    "org_member",
+    "org_invitation",
+];
+
+#[derive(Debug, Clone)]
+pub struct PostgresAccountStore {
+    pool: PgPool,
+}
+
+impl PostgresAccountStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
+    pub async fn new_opts(opts: PgPoolOptions, url: &str) -> Result<Self, AuthApiError> {
+        let pool = opts.connect(url).await.map_err(AuthApiError::Db)?;
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait::async_trait]
+impl AccountStore for PostgresAccountStore {
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<User, AuthApiError> {
+        let mut tx = self.pool.begin().await.map_err(AuthApiError::Db)?;
+        // a taken address trips the unique constraint instead of overwriting someone else's row
+        let user = sqlx::query_as::<_, UserRow>(
+            r#"UPDATE "public"."user" SET email = $2, verified = true WHERE email = $1 RETURNING email, password_hash, two_factor, verified, deletion_requested_at, locked_at;"#,
+        )
+        .bind(old.as_ref())
+        .bind(new.as_ref())
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => {
+                AuthApiError::UserAlreadyExists
+            }
+            e => AuthApiError::Db(e),
+        })?
+        .ok_or(AuthApiError::UserNotFound)?;
+        for table in ACCOUNT_TABLES {
+            sqlx::query(&format!(
+                r#"UPDATE "public"."{table}" SET email = $2 WHERE email = $1;"#
+            ))
+            .bind(old.as_ref())
+            .bind(new.as_ref())
+            .execute(&mut *tx)
+            .await
+            .map_err(AuthApiError::Db)?;
+        }
+        // dropping the transaction on an early return rolls every statement back
+        tx.commit().await.map_err(AuthApiError::Db)?;
+        Ok(user.into())
+    }
+}
@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::domain::{ApiKeyRecord, ApiKeyStore, Email};
+use crate::error::AuthApiError;
+
+#[derive(Debug, Default)]
+pub struct InMemoryApiKeyStore {
+    /// Keyed by key hash
+    keys: HashMap<String, ApiKeyRecord>,
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for InMemoryApiKeyStore {
+    async fn add_key(&mut self, record: ApiKeyRecord) -> Result<(), AuthApiError> {
+        if self
+            .keys
+            .values()
+            .any(|k| k.email == record.email && k.name == record.name)
+        {
+            return Err(AuthApiError::InvalidData(
+                "An API key with that name already exists".to_string(),
+            ));
+        }
+        self.keys.insert(record.key_hash.clone(), record);
+        Ok(())
+    }
+
+    async fn get_key(&self, key_hash: &str) -> Result<ApiKeyRecord, AuthApiError> {
+        self.keys
+            .get(key_hash)
+            .filter(|k| !k.is_expired())
+            .cloned()
+            .ok_or(AuthApiError::InvalidToken)
+    }
+
+    async fn list_keys(&self, email: &Email) -> Result<Vec<ApiKeyRecord>, AuthApiError> {
+        let mut keys: Vec<_> = self
+            .keys
+            .values()
+            .filter(|k| k.email == *email)
+            .cloned()
+            .collect();
+        keys.sort_by_key(|k| k.created_at);
+        Ok(keys)
+    }
+
+    async fn revoke_key(&mut self, email: &Email, id: &Uuid) -> Result<(), AuthApiError> {
+        let before = self.keys.len();
+        self.keys.retain(|_, k| !(k.email == *email && k.id == *id));
+        if self.keys.len() == before {
+            return Err(AuthApiError::ApiKeyNotFound);
+        }
+        Ok(())
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        for key in self.keys.values_mut() {
+            if key.email == *old {
+                key.email = new.clone();
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        self.keys.retain(|_, k| k.email != *email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::ApiKey;
+
+    #[tokio::test]
+    async fn test_api_key_lifecycle() {
+        let mut store = InMemoryApiKeyStore::default();
+        let email = Email::parse("keys@test.com").unwrap();
+        let other = Email::parse("other@test.com").unwrap();
+        let key = ApiKey::generate();
+        let record = ApiKeyRecord::new(&key, &email, "ci", &[], None);
+        store.add_key(record.clone()).await.unwrap();
+
+        // names are unique per user
+        let duplicate = ApiKeyRecord::new(&ApiKey::generate(), &email, "ci", &[], None);
+        assert!(store.add_key(duplicate).await.is_err());
+        let theirs = ApiKeyRecord::new(&ApiKey::generate(), &other, "ci", &[], None);
+        store.add_key(theirs.clone()).await.unwrap();
+
+        assert_eq!(store.get_key(&key.hash()).await.unwrap(), record);
+        assert_eq!(store.list_keys(&email).await.unwrap(), vec![record.clone()]);
+
+        // only the owner can revoke
+        assert!(store.revoke_key(&other, &record.id).await.is_err());
+        store.revoke_key(&email, &record.id).await.unwrap();
+        assert!(store.get_key(&key.hash()).await.is_err());
+        assert_eq!(store.list_keys(&other).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_expired_api_key_is_invalid() {
+        let mut store = InMemoryApiKeyStore::default();
+        let email = Email::parse("expired-key@test.com").unwrap();
+        let key = ApiKey::generate();
+        let record = ApiKeyRecord::new(&key, &email, "old", &[], Some(60));
+        store
+            .add_key(ApiKeyRecord {
+                expires_at: Some(record.created_at - 1),
+                ..record
+            })
+            .await
+            .unwrap();
+        assert!(store.get_key(&key.hash()).await.is_err());
+        assert_eq!(store.list_keys(&email).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,2 @@
+pub mod mem;
+pub mod pg;
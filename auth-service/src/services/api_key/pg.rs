@@ -0,0 +1,129 @@
+use sqlx::{PgPool, postgres::PgPoolOptions};
+use uuid::Uuid;
+
+use crate::{
+    domain::{ApiKeyRecord, ApiKeyRow, ApiKeyStore, Email, parse_scopes},
+    error::AuthApiError,
+};
+
+#[derive(Debug, Clone)]
+pub struct PostgresApiKeyStore {
+    pool: PgPool,
+}
+
+impl PostgresApiKeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
+    pub async fn new_opts(opts: PgPoolOptions, url: &str) -> Result<Self, AuthApiError> {
+        let pool = opts.connect(url).await.map_err(AuthApiError::Db)?;
+        Ok(Self { pool })
+    }
+}
+
+impl TryFrom<ApiKeyRow> for ApiKeyRecord {
+    type Error = AuthApiError;
+
+    fn try_from(row: ApiKeyRow) -> Result<Self, Self::Error> {
+        Ok(ApiKeyRecord {
+            id: Uuid::parse_str(&row.id).map_err(|e| AuthApiError::InvalidData(format!("{e}")))?,
+            key_hash: row.key_hash,
+            email: Email::parse(&row.email)?,
+            name: row.name,
+            scopes: parse_scopes(&row.scopes),
+            created_at: row.created_at.timestamp(),
+            expires_at: row.expires_at.map(|t| t.timestamp()),
+        })
+    }
+}
+
+fn timestamp(t: i64) -> Result<chrono::DateTime<chrono::Utc>, AuthApiError> {
+    chrono::DateTime::from_timestamp(t, 0)
+        .ok_or_else(|| AuthApiError::InvalidData("API key timestamp".to_string()))
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for PostgresApiKeyStore {
+    async fn add_key(&mut self, record: ApiKeyRecord) -> Result<(), AuthApiError> {
+        // the name check and the insert are one statement so two requests can't both pass it
+        let result = sqlx::query(
+            r#"
+            INSERT INTO "public"."api_key" (id, key_hash, email, name, scopes, expires_at, created_at)
+            SELECT $1, $2, $3, $4, $5, $6, $7
+            WHERE NOT EXISTS (SELECT 1 FROM "public"."api_key" WHERE email = $3 AND name = $4);
+            "#,
+        )
+        .bind(record.id.to_string())
+        .bind(&record.key_hash)
+        .bind(record.email.as_ref())
+        .bind(&record.name)
+        .bind(record.scopes.join(" "))
+        .bind(record.expires_at.map(timestamp).transpose()?)
+        .bind(timestamp(record.created_at)?)
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        if result.rows_affected() == 0 {
+            return Err(AuthApiError::InvalidData(
+                "An API key with that name already exists".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn get_key(&self, key_hash: &str) -> Result<ApiKeyRecord, AuthApiError> {
+        let row = sqlx::query_as::<_, ApiKeyRow>(
+            r#"SELECT id, key_hash, email, name, scopes, expires_at, created_at FROM "public"."api_key" WHERE key_hash = $1 AND (expires_at IS NULL OR expires_at > now());"#,
+        )
+        .bind(key_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?
+        .ok_or(AuthApiError::InvalidToken)?;
+        row.try_into()
+    }
+
+    async fn list_keys(&self, email: &Email) -> Result<Vec<ApiKeyRecord>, AuthApiError> {
+        let rows = sqlx::query_as::<_, ApiKeyRow>(
+            r#"SELECT id, key_hash, email, name, scopes, expires_at, created_at FROM "public"."api_key" WHERE email = $1 ORDER BY created_at;"#,
+        )
+        .bind(email.as_ref())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        rows.into_iter().map(ApiKeyRecord::try_from).collect()
+    }
+
+    async fn revoke_key(&mut self, email: &Email, id: &Uuid) -> Result<(), AuthApiError> {
+        let result = sqlx::query(r#"DELETE FROM "public"."api_key" WHERE email = $1 AND id = $2;"#)
+            .bind(email.as_ref())
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        if result.rows_affected() == 0 {
+            return Err(AuthApiError::ApiKeyNotFound);
+        }
+        Ok(())
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"UPDATE "public"."api_key" SET email = $2 WHERE email = $1;"#)
+            .bind(old.as_ref())
+            .bind(new.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"DELETE FROM "public"."api_key" WHERE email = $1;"#)
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use crate::domain::BannedTokenStore;
+use crate::domain::{BannedTokenStore, Email};
 use crate::error::AuthApiError;
 
 #[derive(Debug, Clone, Default)]
@@ -9,13 +9,12 @@ pub struct InMemoryBannedTokenStore {
     // reduce collision chances (and/or token checksum)?
     // better to just add token id to claims and store banned token ids?
     tokens: HashSet<String>,
+    versions: HashMap<Email, u64>,
 }
 
 impl InMemoryBannedTokenStore {
     pub fn new() -> Self {
-        Self {
-            tokens: HashSet::new(),
-        }
+        Self::default()
     }
 }
 
@@ -26,13 +25,33 @@ impl BannedTokenStore for InMemoryBannedTokenStore {
         Ok(())
     }
 
+    async fn ban_token_for(&mut self, token: &str, _ttl: u64) -> Result<(), AuthApiError> {
+        // nothing expires in memory, the token fails validation once it is past its exp anyway
+        self.ban_token(token).await
+    }
+
     async fn unban_token(&mut self, token: &str) -> Result<(), AuthApiError> {
         self.tokens.remove(token);
         Ok(())
     }
 
-    async fn is_token_banned(&self, token: &str) -> bool {
-        self.tokens.contains(token)
+    async fn is_token_banned(&self, token: &str) -> Result<bool, AuthApiError> {
+        Ok(self.tokens.contains(token))
+    }
+
+    async fn token_version(&self, email: &Email) -> Result<u64, AuthApiError> {
+        Ok(self.versions.get(email).copied().unwrap_or_default())
+    }
+
+    async fn bump_token_version(&mut self, email: &Email) -> Result<u64, AuthApiError> {
+        let version = self.versions.entry(email.clone()).or_default();
+        *version += 1;
+        Ok(*version)
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        self.versions.remove(email);
+        Ok(())
     }
 }
 
@@ -45,12 +64,21 @@ mod tests {
         let mut store = InMemoryBannedTokenStore::new();
         let token = "test_token";
 
-        assert!(!store.is_token_banned(token).await);
+        assert!(!store.is_token_banned(token).await.unwrap());
 
         store.ban_token(token).await.unwrap();
-        assert!(store.is_token_banned(token).await);
+        assert!(store.is_token_banned(token).await.unwrap());
 
         store.unban_token(token).await.unwrap();
-        assert!(!store.is_token_banned(token).await);
+        assert!(!store.is_token_banned(token).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_bump_token_version() {
+        let mut store = InMemoryBannedTokenStore::new();
+        let email = Email::parse("version@test.com").unwrap();
+        assert_eq!(store.token_version(&email).await.unwrap(), 0);
+        assert_eq!(store.bump_token_version(&email).await.unwrap(), 1);
+        assert_eq!(store.token_version(&email).await.unwrap(), 1);
     }
 }
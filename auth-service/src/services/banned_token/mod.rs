@@ -1,2 +1,3 @@
 pub mod mem;
+pub mod pg;
 pub mod redis;
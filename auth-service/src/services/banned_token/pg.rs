@@ -0,0 +1,114 @@
+use sqlx::{PgPool, postgres::PgPoolOptions};
+
+use crate::domain::{BannedTokenStore, Email};
+use crate::error::AuthApiError;
+
+/// Bans expire with the token, expired ones are swept as new ones come in.
+/// Versions never expire, they have to outlive every token issued under them.
+#[derive(Debug, Clone)]
+pub struct PostgresBannedTokenStore {
+    pool: PgPool,
+    ttl_ban: u64,
+}
+
+impl PostgresBannedTokenStore {
+    pub fn new(pool: PgPool, ttl_ban: u64) -> Self {
+        Self {
+            pool: pool.clone(),
+            ttl_ban,
+        }
+    }
+
+    pub async fn new_opts(
+        opts: PgPoolOptions,
+        url: &str,
+        ttl_ban: u64,
+    ) -> Result<Self, AuthApiError> {
+        let pool = opts.connect(url).await.map_err(AuthApiError::Db)?;
+        Ok(Self { pool, ttl_ban })
+    }
+}
+
+#[async_trait::async_trait]
+impl BannedTokenStore for PostgresBannedTokenStore {
+    async fn ban_token(&mut self, token: &str) -> Result<(), AuthApiError> {
+        let ttl = self.ttl_ban;
+        self.ban_token_for(token, ttl).await
+    }
+
+    async fn ban_token_for(&mut self, token: &str, ttl: u64) -> Result<(), AuthApiError> {
+        let mut tx = self.pool.begin().await.map_err(AuthApiError::Db)?;
+        sqlx::query(r#"DELETE FROM "public"."banned_token" WHERE expires_at <= now();"#)
+            .execute(&mut *tx)
+            .await
+            .map_err(AuthApiError::Db)?;
+        sqlx::query(
+            r#"
+            INSERT INTO "public"."banned_token" (token, expires_at)
+            VALUES ($1, now() + make_interval(secs => $2))
+            ON CONFLICT (token) DO UPDATE SET expires_at = EXCLUDED.expires_at;
+            "#,
+        )
+        .bind(token)
+        .bind(ttl as f64)
+        .execute(&mut *tx)
+        .await
+        .map_err(AuthApiError::Db)?;
+        tx.commit().await.map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn unban_token(&mut self, token: &str) -> Result<(), AuthApiError> {
+        sqlx::query(r#"DELETE FROM "public"."banned_token" WHERE token = $1;"#)
+            .bind(token)
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn is_token_banned(&self, token: &str) -> Result<bool, AuthApiError> {
+        sqlx::query_scalar::<_, bool>(
+            r#"SELECT EXISTS (SELECT 1 FROM "public"."banned_token" WHERE token = $1 AND expires_at > now());"#,
+        )
+        .bind(token)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)
+    }
+
+    async fn token_version(&self, email: &Email) -> Result<u64, AuthApiError> {
+        let version = sqlx::query_scalar::<_, i64>(
+            r#"SELECT version FROM "public"."token_version" WHERE email = $1;"#,
+        )
+        .bind(email.as_ref())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        Ok(version.unwrap_or_default() as u64)
+    }
+
+    async fn bump_token_version(&mut self, email: &Email) -> Result<u64, AuthApiError> {
+        let version = sqlx::query_scalar::<_, i64>(
+            r#"
+            INSERT INTO "public"."token_version" (email, version) VALUES ($1, 1)
+            ON CONFLICT (email) DO UPDATE SET version = "token_version".version + 1
+            RETURNING version;
+            "#,
+        )
+        .bind(email.as_ref())
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        Ok(version as u64)
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"DELETE FROM "public"."token_version" WHERE email = $1;"#)
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+}
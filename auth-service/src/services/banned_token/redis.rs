@@ -1,11 +1,12 @@
 use crate::config::RedisConfig;
-use crate::domain::{BannedTokenStore, RedisConnection, make_redis_key};
+use crate::domain::{BannedTokenStore, Email, RedisConnection, make_redis_key};
 use crate::error::AuthApiError;
 use redis::Commands;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token";
+const TOKEN_VERSION_KEY_PREFIX: &str = "token_version";
 
 #[derive(Debug)]
 pub struct RedisBannedTokenStore {
@@ -39,8 +40,14 @@ impl RedisBannedTokenStore {
 #[async_trait::async_trait]
 impl BannedTokenStore for RedisBannedTokenStore {
     async fn ban_token(&mut self, token: &str) -> Result<(), crate::error::AuthApiError> {
-        let token_key = make_redis_key(BANNED_TOKEN_KEY_PREFIX, token);
         let ttl = self.config.ttl_ban;
+        self.ban_token_for(token, ttl).await
+    }
+
+    async fn ban_token_for(&mut self, token: &str, ttl: u64) -> Result<(), AuthApiError> {
+        let token_key = make_redis_key(BANNED_TOKEN_KEY_PREFIX, token);
+        // SETEX refuses a zero expiry
+        let ttl = ttl.max(1);
         let value = true;
         let mut guard = self.conn.write().await;
         guard
@@ -57,9 +64,35 @@ impl BannedTokenStore for RedisBannedTokenStore {
         Ok(())
     }
 
-    async fn is_token_banned(&self, token: &str) -> bool {
+    async fn is_token_banned(&self, token: &str) -> Result<bool, AuthApiError> {
         let key = make_redis_key(BANNED_TOKEN_KEY_PREFIX, token);
         let mut guard = self.conn.write().await;
-        guard.0.exists::<_, bool>(&key).unwrap_or(false)
+        guard.0.exists::<_, bool>(&key).map_err(AuthApiError::Redis)
+    }
+
+    async fn token_version(&self, email: &Email) -> Result<u64, AuthApiError> {
+        let key = make_redis_key(TOKEN_VERSION_KEY_PREFIX, email.as_ref());
+        let mut guard = self.conn.write().await;
+        guard
+            .0
+            .get::<_, Option<u64>>(&key)
+            .map(Option::unwrap_or_default)
+            .map_err(AuthApiError::Redis)
+    }
+
+    async fn bump_token_version(&mut self, email: &Email) -> Result<u64, AuthApiError> {
+        // no expiry, the version has to outlive every token issued under it
+        let key = make_redis_key(TOKEN_VERSION_KEY_PREFIX, email.as_ref());
+        let mut guard = self.conn.write().await;
+        guard
+            .0
+            .incr::<_, _, u64>(&key, 1)
+            .map_err(AuthApiError::Redis)
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        let key = make_redis_key(TOKEN_VERSION_KEY_PREFIX, email.as_ref());
+        let mut guard = self.conn.write().await;
+        guard.0.del::<_, ()>(&key).map_err(AuthApiError::Redis)
     }
 }
@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    DeviceAuthorization, DeviceAuthorizationStatus, DeviceCodeStore, DevicePoll, Email, UserCode,
+};
+use crate::error::AuthApiError;
+
+#[derive(Debug)]
+struct StoredAuthorization {
+    authorization: DeviceAuthorization,
+    /// Unix timestamp in seconds
+    retain_until: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct InMemoryDeviceCodeStore {
+    /// Keyed by device code hash
+    authorizations: HashMap<String, StoredAuthorization>,
+}
+
+/// Whether `authorization` is still waiting for the user behind `code`
+fn is_pending(authorization: &DeviceAuthorization, code: &UserCode) -> bool {
+    authorization.user_code == *code
+        && authorization.status == DeviceAuthorizationStatus::Pending
+        && !authorization.is_expired(chrono::Utc::now().timestamp())
+}
+
+#[async_trait::async_trait]
+impl DeviceCodeStore for InMemoryDeviceCodeStore {
+    async fn add(
+        &mut self,
+        authorization: DeviceAuthorization,
+        retention: u64,
+    ) -> Result<(), AuthApiError> {
+        let now = chrono::Utc::now().timestamp();
+        self.authorizations.retain(|_, a| a.retain_until > now);
+        if self
+            .authorizations
+            .values()
+            .any(|a| a.authorization.user_code == authorization.user_code)
+        {
+            return Err(AuthApiError::InvalidData(
+                "User code already in use".to_string(),
+            ));
+        }
+        self.authorizations.insert(
+            authorization.device_code_hash.clone(),
+            StoredAuthorization {
+                authorization,
+                retain_until: now + retention as i64,
+            },
+        );
+        Ok(())
+    }
+
+    async fn get_by_user_code(&self, code: &UserCode) -> Result<DeviceAuthorization, AuthApiError> {
+        self.authorizations
+            .values()
+            .map(|stored| &stored.authorization)
+            .find(|a| is_pending(a, code))
+            .cloned()
+            .ok_or(AuthApiError::InvalidToken)
+    }
+
+    async fn set_status(
+        &mut self,
+        code: &UserCode,
+        status: DeviceAuthorizationStatus,
+    ) -> Result<(), AuthApiError> {
+        let authorization = self
+            .authorizations
+            .values_mut()
+            .map(|stored| &mut stored.authorization)
+            .find(|a| is_pending(a, code))
+            .ok_or(AuthApiError::InvalidToken)?;
+        authorization.status = status;
+        Ok(())
+    }
+
+    async fn poll(
+        &mut self,
+        device_code_hash: &str,
+        client_id: &str,
+    ) -> Result<DevicePoll, AuthApiError> {
+        let now = chrono::Utc::now().timestamp();
+        let stored = self
+            .authorizations
+            .get_mut(device_code_hash)
+            .filter(|a| a.retain_until > now && a.authorization.client_id == client_id)
+            .ok_or(AuthApiError::InvalidToken)?;
+        let poll = stored.authorization.poll(now);
+        if poll.is_final() {
+            self.authorizations.remove(device_code_hash);
+        }
+        Ok(poll)
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        self.authorizations.retain(|_, stored| {
+            !matches!(&stored.authorization.status, DeviceAuthorizationStatus::Approved { email: approver } if approver == email)
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::DeviceCode;
+
+    #[tokio::test]
+    async fn test_device_code_lifecycle() {
+        let mut store = InMemoryDeviceCodeStore::default();
+        let device_code = DeviceCode::generate();
+        let user_code = UserCode::generate();
+        let authorization =
+            DeviceAuthorization::new(&device_code, user_code.clone(), "cli", "read", 600, 0);
+        store.add(authorization.clone(), 1200).await.unwrap();
+
+        // user codes must be unambiguous
+        let clash = DeviceAuthorization::new(
+            &DeviceCode::generate(),
+            user_code.clone(),
+            "cli",
+            "read",
+            600,
+            0,
+        );
+        assert!(store.add(clash, 1200).await.is_err());
+
+        assert_eq!(
+            store.get_by_user_code(&user_code).await.unwrap(),
+            authorization
+        );
+        assert!(store.poll(&device_code.hash(), "other").await.is_err());
+        assert_eq!(
+            store.poll(&device_code.hash(), "cli").await.unwrap(),
+            DevicePoll::Pending
+        );
+
+        let email = Email::parse("device-store@test.com").unwrap();
+        let approved = DeviceAuthorizationStatus::Approved {
+            email: email.clone(),
+        };
+        store
+            .set_status(&user_code, approved.clone())
+            .await
+            .unwrap();
+        // settled requests can't be decided again
+        assert!(store.set_status(&user_code, approved).await.is_err());
+        assert!(store.get_by_user_code(&user_code).await.is_err());
+
+        assert_eq!(
+            store.poll(&device_code.hash(), "cli").await.unwrap(),
+            DevicePoll::Approved {
+                email,
+                scope: "read".to_string()
+            }
+        );
+        // the answer is only given once
+        assert!(store.poll(&device_code.hash(), "cli").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_expired_device_code() {
+        let mut store = InMemoryDeviceCodeStore::default();
+        let device_code = DeviceCode::generate();
+        let user_code = UserCode::generate();
+        let mut authorization =
+            DeviceAuthorization::new(&device_code, user_code.clone(), "cli", "read", 600, 5);
+        authorization.expires_at = chrono::Utc::now().timestamp() - 1;
+        store.add(authorization, 600).await.unwrap();
+
+        assert!(store.get_by_user_code(&user_code).await.is_err());
+        assert!(
+            store
+                .set_status(&user_code, DeviceAuthorizationStatus::Denied)
+                .await
+                .is_err()
+        );
+        assert_eq!(
+            store.poll(&device_code.hash(), "cli").await.unwrap(),
+            DevicePoll::Expired
+        );
+        assert!(store.poll(&device_code.hash(), "cli").await.is_err());
+    }
+}
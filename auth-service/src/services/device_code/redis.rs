@@ -0,0 +1,226 @@
+use std::sync::Arc;
+
+use redis::Commands;
+use tokio::sync::RwLock;
+
+use crate::{
+    config::RedisConfig,
+    domain::{
+        DeviceAuthorization, DeviceAuthorizationStatus, DeviceCodeStore, DevicePoll, Email,
+        RedisConnection, UserCode, extend_expiry, make_redis_key,
+    },
+    error::AuthApiError,
+};
+
+const DEVICE_CODE_PREFIX: &str = "device_code";
+const DEVICE_USER_CODE_PREFIX: &str = "device_user_code";
+const DEVICE_APPROVER_PREFIX: &str = "device_code_approver";
+
+/// Requests are stored as JSON keyed by device code hash, with a second key mapping the
+/// user code to that hash and a set per user of the requests they approved. Updates run in a `WATCH` transaction so an approval and a
+/// poll landing together can't overwrite each other, and only one poll gets the answer.
+#[derive(Clone, Debug)]
+pub struct RedisDeviceCodeStore {
+    conn: Arc<RwLock<RedisConnection>>,
+}
+
+impl RedisDeviceCodeStore {
+    pub fn new(config: &RedisConfig) -> Result<Self, AuthApiError> {
+        if config.host.is_none() {
+            return Err(AuthApiError::Config(
+                "Redis Host Not Configured".to_string(),
+            ));
+        }
+
+        let port = if let Some(p) = config.port.as_ref() {
+            format!(":{}", p)
+        } else {
+            "".to_string()
+        };
+
+        let client =
+            redis::Client::open(format!("redis://{}{}", &config.host.clone().unwrap(), port))
+                .map_err(AuthApiError::Redis)?;
+        let conn = RedisConnection(client.get_connection()?);
+        Ok(Self {
+            conn: Arc::new(RwLock::new(conn)),
+        })
+    }
+}
+
+fn record_key(device_code_hash: &str) -> String {
+    make_redis_key(DEVICE_CODE_PREFIX, device_code_hash)
+}
+
+fn user_code_key(code: &UserCode) -> String {
+    make_redis_key(DEVICE_USER_CODE_PREFIX, code.as_ref())
+}
+
+fn approver_key(email: &Email) -> String {
+    make_redis_key(DEVICE_APPROVER_PREFIX, email.as_ref())
+}
+
+fn get_record(
+    conn: &mut redis::Connection,
+    key: &str,
+) -> Result<Option<DeviceAuthorization>, AuthApiError> {
+    conn.get::<_, Option<String>>(key)
+        .map_err(AuthApiError::Redis)?
+        .map(|value| {
+            serde_json::from_str(&value)
+                .map_err(|e| AuthApiError::SerializationError(e.to_string()))
+        })
+        .transpose()
+}
+
+fn to_json(authorization: &DeviceAuthorization) -> Result<String, AuthApiError> {
+    serde_json::to_string(authorization)
+        .map_err(|e| AuthApiError::SerializationError(e.to_string()))
+}
+
+/// The hash of the pending request behind `code`
+fn pending_hash(conn: &mut redis::Connection, code: &UserCode) -> Result<String, AuthApiError> {
+    conn.get::<_, Option<String>>(user_code_key(code))
+        .map_err(AuthApiError::Redis)?
+        .ok_or(AuthApiError::InvalidToken)
+}
+
+fn is_pending(authorization: &DeviceAuthorization) -> bool {
+    authorization.status == DeviceAuthorizationStatus::Pending
+        && !authorization.is_expired(chrono::Utc::now().timestamp())
+}
+
+#[async_trait::async_trait]
+impl DeviceCodeStore for RedisDeviceCodeStore {
+    async fn add(
+        &mut self,
+        authorization: DeviceAuthorization,
+        retention: u64,
+    ) -> Result<(), AuthApiError> {
+        let value = to_json(&authorization)?;
+        let mut guard = self.conn.write().await;
+        let claimed = redis::cmd("SET")
+            .arg(user_code_key(&authorization.user_code))
+            .arg(&authorization.device_code_hash)
+            .arg("NX")
+            .arg("EX")
+            .arg(retention)
+            .query::<Option<String>>(&mut guard.0)
+            .map_err(AuthApiError::Redis)?
+            .is_some();
+        if !claimed {
+            return Err(AuthApiError::InvalidData(
+                "User code already in use".to_string(),
+            ));
+        }
+        guard
+            .0
+            .set_ex::<_, _, ()>(
+                record_key(&authorization.device_code_hash),
+                value,
+                retention,
+            )
+            .map_err(AuthApiError::Redis)?;
+        Ok(())
+    }
+
+    async fn get_by_user_code(&self, code: &UserCode) -> Result<DeviceAuthorization, AuthApiError> {
+        let mut guard = self.conn.write().await;
+        let hash = pending_hash(&mut guard.0, code)?;
+        get_record(&mut guard.0, &record_key(&hash))?
+            .filter(is_pending)
+            .ok_or(AuthApiError::InvalidToken)
+    }
+
+    async fn set_status(
+        &mut self,
+        code: &UserCode,
+        status: DeviceAuthorizationStatus,
+    ) -> Result<(), AuthApiError> {
+        let mut guard = self.conn.write().await;
+        let key = record_key(&pending_hash(&mut guard.0, code)?);
+        // the closure can only fail with redis errors, ours ride along in the result
+        redis::transaction(&mut guard.0, &[&key], |conn, pipe| {
+            let mut authorization = match get_record(conn, &key) {
+                Ok(Some(authorization)) if is_pending(&authorization) => authorization,
+                Ok(_) => return Ok(Some(Err(AuthApiError::InvalidToken))),
+                Err(e) => return Ok(Some(Err(e))),
+            };
+            authorization.status = status.clone();
+            let value = match to_json(&authorization) {
+                Ok(value) => value,
+                Err(e) => return Ok(Some(Err(e))),
+            };
+            pipe.cmd("SET").arg(&key).arg(value).arg("KEEPTTL").ignore();
+            if let DeviceAuthorizationStatus::Approved { email } = &status {
+                let ttl = conn.ttl::<_, i64>(&key)?.max(1) as u64;
+                let approver = approver_key(email);
+                pipe.sadd(&approver, &authorization.device_code_hash)
+                    .ignore();
+                extend_expiry(pipe, &approver, ttl);
+            }
+            pipe.query::<Option<()>>(conn)
+                .map(|committed| committed.map(Ok))
+        })
+        .map_err(AuthApiError::Redis)?
+    }
+
+    async fn poll(
+        &mut self,
+        device_code_hash: &str,
+        client_id: &str,
+    ) -> Result<DevicePoll, AuthApiError> {
+        let key = record_key(device_code_hash);
+        let mut guard = self.conn.write().await;
+        redis::transaction(&mut guard.0, &[&key], |conn, pipe| {
+            let mut authorization = match get_record(conn, &key) {
+                Ok(Some(authorization)) if authorization.client_id == client_id => authorization,
+                Ok(_) => return Ok(Some(Err(AuthApiError::InvalidToken))),
+                Err(e) => return Ok(Some(Err(e))),
+            };
+            let poll = authorization.poll(chrono::Utc::now().timestamp());
+            if poll.is_final() {
+                pipe.del(&key)
+                    .ignore()
+                    .del(user_code_key(&authorization.user_code))
+                    .ignore();
+            } else {
+                let value = match to_json(&authorization) {
+                    Ok(value) => value,
+                    Err(e) => return Ok(Some(Err(e))),
+                };
+                pipe.cmd("SET").arg(&key).arg(value).arg("KEEPTTL").ignore();
+            }
+            pipe.query::<Option<()>>(conn)
+                .map(|committed| committed.map(|_| Ok(poll.clone())))
+        })
+        .map_err(AuthApiError::Redis)?
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        let approver = approver_key(email);
+        let mut guard = self.conn.write().await;
+        let hashes = guard
+            .0
+            .smembers::<_, Vec<String>>(&approver)
+            .map_err(AuthApiError::Redis)?;
+        for hash in hashes {
+            let key = record_key(&hash);
+            // collected requests are gone already
+            if let Some(authorization) = get_record(&mut guard.0, &key)? {
+                redis::pipe()
+                    .del(&key)
+                    .ignore()
+                    .del(user_code_key(&authorization.user_code))
+                    .ignore()
+                    .query::<()>(&mut guard.0)
+                    .map_err(AuthApiError::Redis)?;
+            }
+        }
+        guard
+            .0
+            .del::<_, ()>(&approver)
+            .map_err(AuthApiError::Redis)?;
+        Ok(())
+    }
+}
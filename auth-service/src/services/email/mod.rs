@@ -1,7 +1,6 @@
 use crate::domain::{Email, EmailClient, EmailTemplate, Password};
 use crate::error::AuthApiError;
 
-use askama::Template;
 use lettre::message::{Mailbox, header::ContentType};
 use lettre::transport::smtp::authentication::Credentials;
 use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+
+use crate::config::LockoutConfig;
+use crate::domain::{Email, FailedLoginStore, FailedLogins};
+use crate::error::AuthApiError;
+
+#[derive(Debug, Default)]
+pub struct InMemoryFailedLoginStore {
+    records: HashMap<Email, FailedLogins>,
+}
+
+#[async_trait::async_trait]
+impl FailedLoginStore for InMemoryFailedLoginStore {
+    async fn get(&self, email: &Email) -> Result<FailedLogins, AuthApiError> {
+        Ok(self.records.get(email).cloned().unwrap_or_default())
+    }
+
+    async fn record_failure(
+        &mut self,
+        email: &Email,
+        config: &LockoutConfig,
+    ) -> Result<FailedLogins, AuthApiError> {
+        let record = self.records.entry(email.clone()).or_default();
+        record.record_failure(chrono::Utc::now().timestamp(), config);
+        Ok(record.clone())
+    }
+
+    async fn clear(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        self.records.remove(email);
+        Ok(())
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        match self.records.remove(old) {
+            Some(record) => self.records.insert(new.clone(), record),
+            None => self.records.remove(new),
+        };
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_and_clear() {
+        let mut store = InMemoryFailedLoginStore::default();
+        let email = Email::parse("lockout@me.com").unwrap();
+        let config = LockoutConfig {
+            threshold: 2,
+            ..LockoutConfig::default()
+        };
+        let now = chrono::Utc::now().timestamp();
+
+        store.record_failure(&email, &config).await.unwrap();
+        assert_eq!(store.get(&email).await.unwrap().locked_for(now), None);
+        let record = store.record_failure(&email, &config).await.unwrap();
+        assert!(record.locked_for(now).is_some());
+        assert_eq!(store.get(&email).await.unwrap(), record);
+
+        store.clear(&email).await.unwrap();
+        assert_eq!(store.get(&email).await.unwrap(), FailedLogins::default());
+    }
+}
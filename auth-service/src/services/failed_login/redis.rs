@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use redis::Commands;
+use tokio::sync::RwLock;
+
+use crate::{
+    config::{LockoutConfig, RedisConfig},
+    domain::{Email, FailedLoginStore, FailedLogins, RedisConnection, make_redis_key},
+    error::AuthApiError,
+};
+
+const FAILED_LOGIN_PREFIX: &str = "failed_login";
+
+/// Records are stored as JSON keyed by email, expiring once they no longer affect
+/// the backoff. Failures are counted in a `WATCH` transaction so none get lost.
+#[derive(Clone, Debug)]
+pub struct RedisFailedLoginStore {
+    conn: Arc<RwLock<RedisConnection>>,
+}
+
+impl RedisFailedLoginStore {
+    pub fn new(config: &RedisConfig) -> Result<Self, AuthApiError> {
+        if config.host.is_none() {
+            return Err(AuthApiError::Config(
+                "Redis Host Not Configured".to_string(),
+            ));
+        }
+
+        let port = if let Some(p) = config.port.as_ref() {
+            format!(":{}", p)
+        } else {
+            "".to_string()
+        };
+
+        let client =
+            redis::Client::open(format!("redis://{}{}", &config.host.clone().unwrap(), port))
+                .map_err(AuthApiError::Redis)?;
+        let conn = RedisConnection(client.get_connection()?);
+        Ok(Self {
+            conn: Arc::new(RwLock::new(conn)),
+        })
+    }
+}
+
+fn get_record(conn: &mut redis::Connection, key: &str) -> Result<FailedLogins, AuthApiError> {
+    conn.get::<_, Option<String>>(key)
+        .map_err(AuthApiError::Redis)?
+        .map(|value| {
+            serde_json::from_str(&value)
+                .map_err(|e| AuthApiError::SerializationError(e.to_string()))
+        })
+        .transpose()
+        .map(Option::unwrap_or_default)
+}
+
+#[async_trait::async_trait]
+impl FailedLoginStore for RedisFailedLoginStore {
+    async fn get(&self, email: &Email) -> Result<FailedLogins, AuthApiError> {
+        let mut guard = self.conn.write().await;
+        get_record(
+            &mut guard.0,
+            &make_redis_key(FAILED_LOGIN_PREFIX, email.as_ref()),
+        )
+    }
+
+    async fn record_failure(
+        &mut self,
+        email: &Email,
+        config: &LockoutConfig,
+    ) -> Result<FailedLogins, AuthApiError> {
+        let key = make_redis_key(FAILED_LOGIN_PREFIX, email.as_ref());
+        let mut guard = self.conn.write().await;
+        // the closure can only fail with redis errors, ours ride along in the result
+        redis::transaction(&mut guard.0, &[&key], |conn, pipe| {
+            let mut record = match get_record(conn, &key) {
+                Ok(record) => record,
+                Err(e) => return Ok(Some(Err(e))),
+            };
+            let now = chrono::Utc::now().timestamp();
+            record.record_failure(now, config);
+            let value = match serde_json::to_string(&record) {
+                Ok(value) => value,
+                Err(e) => return Ok(Some(Err(AuthApiError::SerializationError(e.to_string())))),
+            };
+            pipe.set_ex(&key, value, record.retention(now, config))
+                .ignore()
+                .query::<Option<()>>(conn)
+                .map(|committed| committed.map(|_| Ok(record.clone())))
+        })
+        .map_err(AuthApiError::Redis)?
+    }
+
+    async fn clear(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        let mut guard = self.conn.write().await;
+        guard
+            .0
+            .del::<_, ()>(make_redis_key(FAILED_LOGIN_PREFIX, email.as_ref()))
+            .map_err(AuthApiError::Redis)
+    }
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        let old = make_redis_key(FAILED_LOGIN_PREFIX, old.as_ref());
+        let new = make_redis_key(FAILED_LOGIN_PREFIX, new.as_ref());
+        let mut guard = self.conn.write().await;
+        // RENAME keeps the expiry, but refuses a missing key
+        if guard
+            .0
+            .exists::<_, bool>(&old)
+            .map_err(AuthApiError::Redis)?
+        {
+            guard.0.rename::<_, _, ()>(&old, &new)
+        } else {
+            guard.0.del::<_, ()>(&new)
+        }
+        .map_err(AuthApiError::Redis)
+    }
+}
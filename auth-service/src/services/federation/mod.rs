@@ -0,0 +1,236 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, decode_header};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::config::IdentityProviderConfig;
+use crate::domain::{CODE_CHALLENGE_METHOD, OAuthError, UpstreamIdentity};
+use crate::error::AuthApiError;
+
+/// Endpoints of an upstream provider, OIDC Discovery section 3
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UpstreamTokenResponse {
+    id_token: Option<String>,
+}
+
+fn upstream_error(e: impl std::fmt::Display) -> AuthApiError {
+    AuthApiError::IdentityProvider(e.to_string())
+}
+
+/// Relying party side of an upstream OpenID Connect provider
+#[derive(Debug, Clone)]
+pub struct UpstreamProvider {
+    config: IdentityProviderConfig,
+    metadata: ProviderMetadata,
+    http: reqwest::Client,
+    /// Fetched on the first token to check, shared by the clones handed out by the cache
+    jwks: Arc<RwLock<Option<JwkSet>>>,
+}
+
+/// Providers discovered so far, by id. A discovery document is used for `ttl` before
+/// it is fetched again, and with it the provider's keys.
+#[derive(Debug)]
+pub struct ProviderCache {
+    ttl: Duration,
+    providers: RwLock<HashMap<String, (Instant, UpstreamProvider)>>,
+}
+
+impl ProviderCache {
+    pub fn new(ttl: u64) -> Self {
+        Self {
+            ttl: Duration::from_secs(ttl),
+            providers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The provider for `config`, discovered again if it was never seen or has expired
+    pub async fn provider(
+        &self,
+        config: &IdentityProviderConfig,
+    ) -> Result<UpstreamProvider, AuthApiError> {
+        if let Some((fetched_at, provider)) = self.providers.read().await.get(&config.id)
+            && fetched_at.elapsed() < self.ttl
+        {
+            return Ok(provider.clone());
+        }
+        let provider = UpstreamProvider::discover(config).await?;
+        self.providers
+            .write()
+            .await
+            .insert(config.id.clone(), (Instant::now(), provider.clone()));
+        Ok(provider)
+    }
+}
+
+impl UpstreamProvider {
+    /// Read the endpoints from the provider's discovery document
+    pub async fn discover(config: &IdentityProviderConfig) -> Result<Self, AuthApiError> {
+        let http = reqwest::Client::new();
+        let url = format!(
+            "{}/.well-known/openid-configuration",
+            config.issuer.trim_end_matches('/')
+        );
+        let metadata = http
+            .get(url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(upstream_error)?
+            .json::<ProviderMetadata>()
+            .await
+            .map_err(upstream_error)?;
+        // OIDC Discovery section 4.3
+        if metadata.issuer != config.issuer {
+            return Err(upstream_error("Discovered issuer does not match"));
+        }
+        Ok(Self {
+            config: config.clone(),
+            metadata,
+            http,
+            jwks: Arc::default(),
+        })
+    }
+
+    /// Where to send the user agent to sign in, OIDC Core section 3.1.2.1
+    pub fn authorization_url(
+        &self,
+        redirect_uri: &str,
+        state: &str,
+        nonce: &str,
+        code_challenge: &str,
+    ) -> Result<String, AuthApiError> {
+        let mut url = Url::parse(&self.metadata.authorization_endpoint).map_err(upstream_error)?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("scope", &self.config.scopes.join(" "))
+            .append_pair("state", state)
+            .append_pair("nonce", nonce)
+            .append_pair("code_challenge", code_challenge)
+            .append_pair("code_challenge_method", CODE_CHALLENGE_METHOD);
+        Ok(url.to_string())
+    }
+
+    /// Redeem the authorization code and return who signed in
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        redirect_uri: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> Result<UpstreamIdentity, AuthApiError> {
+        let response = self
+            .http
+            .post(&self.metadata.token_endpoint)
+            .basic_auth(&self.config.client_id, Some(&self.config.client_secret))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", redirect_uri),
+                ("code_verifier", code_verifier),
+            ])
+            .send()
+            .await
+            .map_err(upstream_error)?;
+        if !response.status().is_success() {
+            return Err(AuthApiError::OAuth(
+                OAuthError::InvalidGrant,
+                "The identity provider refused the code".to_string(),
+            ));
+        }
+        let id_token = response
+            .json::<UpstreamTokenResponse>()
+            .await
+            .map_err(upstream_error)?
+            .id_token
+            .ok_or_else(|| upstream_error("No id_token in the token response"))?;
+        self.validate_id_token(&id_token, nonce).await
+    }
+
+    /// OIDC Core section 3.1.3.7, against the keys the provider publishes
+    async fn validate_id_token(
+        &self,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<UpstreamIdentity, AuthApiError> {
+        let header = decode_header(id_token).map_err(|_| AuthApiError::InvalidToken)?;
+        // shared secret signatures aren't supported, the key has to come from the JWKS
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(AuthApiError::InvalidToken);
+        }
+        let jwk = self.signing_key(header.kid.as_deref()).await?;
+        let key = DecodingKey::from_jwk(&jwk).map_err(|_| AuthApiError::InvalidToken)?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.config.issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        let identity = decode::<UpstreamIdentity>(id_token, &key, &validation)
+            .map_err(|_| AuthApiError::InvalidToken)?
+            .claims;
+        if identity.nonce.as_deref() != Some(nonce) {
+            return Err(AuthApiError::InvalidToken);
+        }
+        Ok(identity)
+    }
+
+    /// The key a token was signed with, from the cached key set. A `kid` the set doesn't
+    /// have fetches it again, the provider may have rotated its keys since.
+    async fn signing_key(&self, kid: Option<&str>) -> Result<Jwk, AuthApiError> {
+        let cached = self.jwks.read().await.clone();
+        let fresh = cached.is_none();
+        let jwks = match cached {
+            Some(jwks) => jwks,
+            None => self.fetch_jwks().await?,
+        };
+        let jwk = match kid {
+            Some(kid) => jwks.find(kid),
+            None if jwks.keys.len() == 1 => jwks.keys.first(),
+            None => None,
+        };
+        if let Some(jwk) = jwk {
+            return Ok(jwk.clone());
+        }
+        match kid {
+            Some(kid) if !fresh => self
+                .fetch_jwks()
+                .await?
+                .find(kid)
+                .cloned()
+                .ok_or(AuthApiError::InvalidToken),
+            _ => Err(AuthApiError::InvalidToken),
+        }
+    }
+
+    async fn fetch_jwks(&self) -> Result<JwkSet, AuthApiError> {
+        let jwks = self
+            .http
+            .get(&self.metadata.jwks_uri)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(upstream_error)?
+            .json::<JwkSet>()
+            .await
+            .map_err(upstream_error)?;
+        *self.jwks.write().await = Some(jwks.clone());
+        Ok(jwks)
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use crate::domain::{Email, FederatedIdentity, IdentityStore};
+use crate::error::AuthApiError;
+
+#[derive(Debug, Default)]
+pub struct InMemoryIdentityStore {
+    /// Keyed by provider and subject
+    identities: HashMap<(String, String), FederatedIdentity>,
+}
+
+#[async_trait::async_trait]
+impl IdentityStore for InMemoryIdentityStore {
+    async fn add_identity(&mut self, identity: FederatedIdentity) -> Result<(), AuthApiError> {
+        let key = (identity.provider.clone(), identity.subject.clone());
+        if self.identities.contains_key(&key) {
+            return Err(AuthApiError::InvalidData(
+                "Identity already linked".to_string(),
+            ));
+        }
+        self.identities.insert(key, identity);
+        Ok(())
+    }
+
+    async fn get_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<FederatedIdentity, AuthApiError> {
+        self.identities
+            .get(&(provider.to_string(), subject.to_string()))
+            .cloned()
+            .ok_or(AuthApiError::UserNotFound)
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        for identity in self.identities.values_mut() {
+            if identity.email == *old {
+                identity.email = new.clone();
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        self.identities
+            .retain(|_, identity| identity.email != *email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_identity_follows_user() {
+        let mut store = InMemoryIdentityStore::default();
+        let old = Email::parse("old@test.com").unwrap();
+        let new = Email::parse("new@test.com").unwrap();
+        let identity = FederatedIdentity {
+            provider: "corp".to_string(),
+            subject: "248289761001".to_string(),
+            email: old.clone(),
+        };
+        store.add_identity(identity.clone()).await.unwrap();
+        assert!(store.add_identity(identity).await.is_err());
+
+        store.change_email(&old, &new).await.unwrap();
+        let linked = store.get_identity("corp", "248289761001").await.unwrap();
+        assert_eq!(linked.email, new);
+        assert!(store.get_identity("other", "248289761001").await.is_err());
+
+        store.delete_user(&new).await.unwrap();
+        assert!(store.get_identity("corp", "248289761001").await.is_err());
+    }
+}
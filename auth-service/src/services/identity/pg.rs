@@ -0,0 +1,91 @@
+use sqlx::{PgPool, postgres::PgPoolOptions};
+
+use crate::{
+    domain::{Email, FederatedIdentity, FederatedIdentityRow, IdentityStore},
+    error::AuthApiError,
+};
+
+#[derive(Debug, Clone)]
+pub struct PostgresIdentityStore {
+    pool: PgPool,
+}
+
+impl PostgresIdentityStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
+    pub async fn new_opts(opts: PgPoolOptions, url: &str) -> Result<Self, AuthApiError> {
+        let pool = opts.connect(url).await.map_err(AuthApiError::Db)?;
+        Ok(Self { pool })
+    }
+}
+
+impl TryFrom<FederatedIdentityRow> for FederatedIdentity {
+    type Error = AuthApiError;
+
+    fn try_from(row: FederatedIdentityRow) -> Result<Self, Self::Error> {
+        Ok(FederatedIdentity {
+            provider: row.provider,
+            subject: row.subject,
+            email: Email::parse(&row.email)?,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl IdentityStore for PostgresIdentityStore {
+    async fn add_identity(&mut self, identity: FederatedIdentity) -> Result<(), AuthApiError> {
+        sqlx::query(
+            r#"INSERT INTO "public"."federated_identity" (provider, subject, email) VALUES ($1, $2, $3);"#,
+        )
+        .bind(&identity.provider)
+        .bind(&identity.subject)
+        .bind(identity.email.as_ref())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => {
+                AuthApiError::InvalidData("Identity already linked".to_string())
+            }
+            e => AuthApiError::Db(e),
+        })?;
+        Ok(())
+    }
+
+    async fn get_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<FederatedIdentity, AuthApiError> {
+        let row = sqlx::query_as::<_, FederatedIdentityRow>(
+            r#"SELECT provider, subject, email, created_at FROM "public"."federated_identity" WHERE provider = $1 AND subject = $2;"#,
+        )
+        .bind(provider)
+        .bind(subject)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?
+        .ok_or(AuthApiError::UserNotFound)?;
+        row.try_into()
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"UPDATE "public"."federated_identity" SET email = $2 WHERE email = $1;"#)
+            .bind(old.as_ref())
+            .bind(new.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"DELETE FROM "public"."federated_identity" WHERE email = $1;"#)
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::domain::{Email, Invitation, InvitationStore};
+use crate::error::AuthApiError;
+
+#[derive(Debug, Default)]
+pub struct InMemoryInvitationStore {
+    invitations: HashMap<Uuid, Invitation>,
+}
+
+impl InMemoryInvitationStore {
+    fn pending<'a>(&'a self, keep: impl Fn(&Invitation) -> bool + 'a) -> Vec<Invitation> {
+        let mut invitations: Vec<_> = self
+            .invitations
+            .values()
+            .filter(|i| !i.is_expired() && keep(i))
+            .cloned()
+            .collect();
+        invitations.sort_by_key(|i| i.created_at);
+        invitations
+    }
+}
+
+#[async_trait::async_trait]
+impl InvitationStore for InMemoryInvitationStore {
+    async fn add_invitation(&mut self, invitation: Invitation) -> Result<(), AuthApiError> {
+        self.invitations.retain(|_, i| {
+            !(i.is_expired() || (i.org_id == invitation.org_id && i.email == invitation.email))
+        });
+        self.invitations.insert(invitation.id, invitation);
+        Ok(())
+    }
+
+    async fn get_invitation(&self, id: &Uuid) -> Result<Invitation, AuthApiError> {
+        self.invitations
+            .get(id)
+            .filter(|i| !i.is_expired())
+            .cloned()
+            .ok_or(AuthApiError::InvitationNotFound)
+    }
+
+    async fn list_org_invitations(&self, org_id: &Uuid) -> Result<Vec<Invitation>, AuthApiError> {
+        Ok(self.pending(|i| i.org_id == *org_id))
+    }
+
+    async fn list_user_invitations(&self, email: &Email) -> Result<Vec<Invitation>, AuthApiError> {
+        Ok(self.pending(|i| i.email == *email))
+    }
+
+    async fn delete_invitation(&mut self, id: &Uuid) -> Result<(), AuthApiError> {
+        self.invitations
+            .remove(id)
+            .map(|_| ())
+            .ok_or(AuthApiError::InvitationNotFound)
+    }
+
+    async fn delete_org(&mut self, org_id: &Uuid) -> Result<(), AuthApiError> {
+        self.invitations.retain(|_, i| i.org_id != *org_id);
+        Ok(())
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        self.invitations
+            .values_mut()
+            .filter(|i| i.email == *old)
+            .for_each(|i| i.email = new.clone());
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        self.invitations.retain(|_, i| i.email != *email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::OrgRole;
+
+    #[tokio::test]
+    async fn test_invitation_replaces_earlier_one() {
+        let mut store = InMemoryInvitationStore::default();
+        let org_id = Uuid::new_v4();
+        let admin = Email::parse("admin@test.com").unwrap();
+        let invitee = Email::parse("invitee@test.com").unwrap();
+        let first = Invitation::new(org_id, &invitee, OrgRole::Member, &admin, 60);
+        let second = Invitation::new(org_id, &invitee, OrgRole::Admin, &admin, 60);
+        store.add_invitation(first.clone()).await.unwrap();
+        store.add_invitation(second.clone()).await.unwrap();
+
+        assert!(store.get_invitation(&first.id).await.is_err());
+        assert_eq!(
+            store.list_user_invitations(&invitee).await.unwrap(),
+            vec![second.clone()]
+        );
+
+        let expired = Invitation::new(Uuid::new_v4(), &invitee, OrgRole::Member, &admin, 0);
+        store.add_invitation(expired.clone()).await.unwrap();
+        assert!(store.get_invitation(&expired.id).await.is_err());
+
+        store.delete_org(&org_id).await.unwrap();
+        assert!(
+            store
+                .list_org_invitations(&org_id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use sqlx::{PgPool, postgres::PgPoolOptions};
+use uuid::Uuid;
+
+use crate::{
+    domain::{Email, Invitation, InvitationStore, OrgInvitationRow},
+    error::AuthApiError,
+};
+
+#[derive(Debug, Clone)]
+pub struct PostgresInvitationStore {
+    pool: PgPool,
+}
+
+impl PostgresInvitationStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
+    pub async fn new_opts(opts: PgPoolOptions, url: &str) -> Result<Self, AuthApiError> {
+        let pool = opts.connect(url).await.map_err(AuthApiError::Db)?;
+        Ok(Self { pool })
+    }
+}
+
+fn parse_id(id: &str) -> Result<Uuid, AuthApiError> {
+    Uuid::parse_str(id).map_err(|e| AuthApiError::InvalidData(format!("{e}")))
+}
+
+impl TryFrom<OrgInvitationRow> for Invitation {
+    type Error = AuthApiError;
+
+    fn try_from(row: OrgInvitationRow) -> Result<Self, Self::Error> {
+        Ok(Invitation {
+            id: parse_id(&row.id)?,
+            org_id: parse_id(&row.org_id)?,
+            email: Email::parse(&row.email)?,
+            role: row.role.parse()?,
+            invited_by: Email::parse(&row.invited_by)?,
+            created_at: row.created_at.timestamp(),
+            expires_at: row.expires_at.timestamp(),
+        })
+    }
+}
+
+fn timestamp(t: i64) -> Result<chrono::DateTime<chrono::Utc>, AuthApiError> {
+    chrono::DateTime::from_timestamp(t, 0)
+        .ok_or_else(|| AuthApiError::InvalidData("Invitation timestamp".to_string()))
+}
+
+#[async_trait::async_trait]
+impl InvitationStore for PostgresInvitationStore {
+    async fn add_invitation(&mut self, invitation: Invitation) -> Result<(), AuthApiError> {
+        let mut tx = self.pool.begin().await.map_err(AuthApiError::Db)?;
+        sqlx::query(
+            r#"DELETE FROM "public"."org_invitation" WHERE (org_id = $1 AND email = $2) OR expires_at <= now();"#,
+        )
+        .bind(invitation.org_id.to_string())
+        .bind(invitation.email.as_ref())
+        .execute(&mut *tx)
+        .await
+        .map_err(AuthApiError::Db)?;
+        sqlx::query(
+            r#"
+            INSERT INTO "public"."org_invitation" (id, org_id, email, role, invited_by, expires_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7);
+            "#,
+        )
+        .bind(invitation.id.to_string())
+        .bind(invitation.org_id.to_string())
+        .bind(invitation.email.as_ref())
+        .bind(invitation.role.as_ref())
+        .bind(invitation.invited_by.as_ref())
+        .bind(timestamp(invitation.expires_at)?)
+        .bind(timestamp(invitation.created_at)?)
+        .execute(&mut *tx)
+        .await
+        .map_err(AuthApiError::Db)?;
+        tx.commit().await.map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn get_invitation(&self, id: &Uuid) -> Result<Invitation, AuthApiError> {
+        let row = sqlx::query_as::<_, OrgInvitationRow>(
+            r#"SELECT id, org_id, email, role, invited_by, expires_at, created_at FROM "public"."org_invitation" WHERE id = $1 AND expires_at > now();"#,
+        )
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?
+        .ok_or(AuthApiError::InvitationNotFound)?;
+        row.try_into()
+    }
+
+    async fn list_org_invitations(&self, org_id: &Uuid) -> Result<Vec<Invitation>, AuthApiError> {
+        let rows = sqlx::query_as::<_, OrgInvitationRow>(
+            r#"SELECT id, org_id, email, role, invited_by, expires_at, created_at FROM "public"."org_invitation" WHERE org_id = $1 AND expires_at > now() ORDER BY created_at;"#,
+        )
+        .bind(org_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        rows.into_iter().map(Invitation::try_from).collect()
+    }
+
+    async fn list_user_invitations(&self, email: &Email) -> Result<Vec<Invitation>, AuthApiError> {
+        let rows = sqlx::query_as::<_, OrgInvitationRow>(
+            r#"SELECT id, org_id, email, role, invited_by, expires_at, created_at FROM "public"."org_invitation" WHERE email = $1 AND expires_at > now() ORDER BY created_at;"#,
+        )
+        .bind(email.as_ref())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        rows.into_iter().map(Invitation::try_from).collect()
+    }
+
+    async fn delete_invitation(&mut self, id: &Uuid) -> Result<(), AuthApiError> {
+        let result = sqlx::query(r#"DELETE FROM "public"."org_invitation" WHERE id = $1;"#)
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        if result.rows_affected() == 0 {
+            return Err(AuthApiError::InvitationNotFound);
+        }
+        Ok(())
+    }
+
+    async fn delete_org(&mut self, org_id: &Uuid) -> Result<(), AuthApiError> {
+        sqlx::query(r#"DELETE FROM "public"."org_invitation" WHERE org_id = $1;"#)
+            .bind(org_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"UPDATE "public"."org_invitation" SET email = $2 WHERE email = $1;"#)
+            .bind(old.as_ref())
+            .bind(new.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"DELETE FROM "public"."org_invitation" WHERE email = $1;"#)
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+}
@@ -1,4 +1,21 @@
+pub mod account;
+pub mod api_key;
 pub mod banned_token;
+pub mod device_code;
+pub mod email;
+pub mod failed_login;
+pub mod federation;
+pub mod identity;
+pub mod invitation;
+pub mod oauth_client;
+pub mod one_time_token;
+pub mod organization;
+pub mod passkey;
+pub mod rate_limit;
+pub mod recovery_code;
+pub mod refresh_token;
+pub mod role;
+pub mod session;
+pub mod totp;
 pub mod two_factor_code;
 pub mod user_store;
-pub mod email;
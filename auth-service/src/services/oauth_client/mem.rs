@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+
+use crate::domain::{ClientStore, OAuthClient, OAuthError};
+use crate::error::AuthApiError;
+
+#[derive(Debug, Default)]
+pub struct InMemoryClientStore {
+    clients: HashMap<String, OAuthClient>,
+}
+
+#[async_trait::async_trait]
+impl ClientStore for InMemoryClientStore {
+    async fn add_client(&mut self, client: OAuthClient) -> Result<(), AuthApiError> {
+        if self.clients.contains_key(&client.client_id) {
+            return Err(AuthApiError::InvalidData(
+                "Client already registered".to_string(),
+            ));
+        }
+        self.clients.insert(client.client_id.clone(), client);
+        Ok(())
+    }
+
+    async fn get_client(&self, client_id: &str) -> Result<OAuthClient, AuthApiError> {
+        self.clients.get(client_id).cloned().ok_or_else(|| {
+            AuthApiError::OAuth(OAuthError::InvalidClient, "Unknown client".to_string())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_get_client() {
+        let mut store = InMemoryClientStore::default();
+        let client = OAuthClient {
+            client_id: "app".to_string(),
+            name: "App".to_string(),
+            redirect_uris: vec!["https://app.test/callback".to_string()],
+            allowed_scopes: vec!["openid".to_string()],
+            secret_hash: None,
+        };
+        store.add_client(client.clone()).await.expect("add client");
+        assert_eq!(store.get_client("app").await.unwrap(), client);
+        assert!(store.add_client(client).await.is_err());
+        assert!(matches!(
+            store.get_client("other").await,
+            Err(AuthApiError::OAuth(OAuthError::InvalidClient, _))
+        ));
+    }
+}
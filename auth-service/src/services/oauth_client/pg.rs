@@ -0,0 +1,81 @@
+use sqlx::{PgPool, postgres::PgPoolOptions};
+
+use crate::{
+    domain::{
+        ClientStore, HashedClientSecret, OAuthClient, OAuthError, OauthClientRow, parse_scopes,
+    },
+    error::AuthApiError,
+};
+
+#[derive(Debug, Clone)]
+pub struct PostgresClientStore {
+    pool: PgPool,
+}
+
+impl PostgresClientStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
+    pub async fn new_opts(opts: PgPoolOptions, url: &str) -> Result<Self, AuthApiError> {
+        let pool = opts.connect(url).await.map_err(AuthApiError::Db)?;
+        Ok(Self { pool })
+    }
+}
+
+impl TryFrom<OauthClientRow> for OAuthClient {
+    type Error = AuthApiError;
+
+    fn try_from(row: OauthClientRow) -> Result<Self, Self::Error> {
+        Ok(OAuthClient {
+            client_id: row.client_id,
+            name: row.name,
+            redirect_uris: serde_json::from_str(&row.redirect_uris)
+                .map_err(|e| AuthApiError::SerializationError(format!("{e}")))?,
+            allowed_scopes: parse_scopes(&row.allowed_scopes),
+            secret_hash: row.secret_hash.map(HashedClientSecret::from_hash),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl ClientStore for PostgresClientStore {
+    async fn add_client(&mut self, client: OAuthClient) -> Result<(), AuthApiError> {
+        let redirect_uris = serde_json::to_string(&client.redirect_uris)
+            .map_err(|e| AuthApiError::SerializationError(format!("{e}")))?;
+        sqlx::query(
+            r#"
+        INSERT INTO "public"."oauth_client" (client_id, name, redirect_uris, allowed_scopes, secret_hash)
+        VALUES ($1, $2, $3, $4, $5);
+        "#,
+        )
+        .bind(&client.client_id)
+        .bind(&client.name)
+        .bind(redirect_uris)
+        .bind(client.allowed_scopes.join(" "))
+        .bind(client.secret_hash.as_ref().map(|h| h.as_ref()))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => {
+                AuthApiError::InvalidData("Client already registered".to_string())
+            }
+            e => AuthApiError::Db(e),
+        })?;
+        Ok(())
+    }
+
+    async fn get_client(&self, client_id: &str) -> Result<OAuthClient, AuthApiError> {
+        let row = sqlx::query_as::<_, OauthClientRow>(
+            r#"SELECT client_id, name, redirect_uris, allowed_scopes, secret_hash, created_at FROM "public"."oauth_client" WHERE client_id = $1;"#,
+        )
+        .bind(client_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?
+        .ok_or_else(|| {
+            AuthApiError::OAuth(OAuthError::InvalidClient, "Unknown client".to_string())
+        })?;
+        row.try_into()
+    }
+}
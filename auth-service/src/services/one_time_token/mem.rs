@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use crate::domain::{Email, OneTimeTokenId, OneTimeTokenStore, TokenPurpose};
+use crate::error::AuthApiError;
+
+#[derive(Debug, Clone)]
+struct OneTimeTokenEntry {
+    email: Email,
+    purpose: TokenPurpose,
+    expires_at: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct InMemoryOneTimeTokenStore {
+    tokens: HashMap<OneTimeTokenId, OneTimeTokenEntry>,
+}
+
+#[async_trait::async_trait]
+impl OneTimeTokenStore for InMemoryOneTimeTokenStore {
+    async fn add_token(
+        &mut self,
+        id: &OneTimeTokenId,
+        purpose: &TokenPurpose,
+        email: &Email,
+        ttl: u64,
+    ) -> Result<(), AuthApiError> {
+        let expires_at = chrono::Utc::now().timestamp() + ttl as i64;
+        self.tokens.insert(
+            id.clone(),
+            OneTimeTokenEntry {
+                email: email.clone(),
+                purpose: purpose.clone(),
+                expires_at,
+            },
+        );
+        Ok(())
+    }
+
+    async fn consume_token(
+        &mut self,
+        id: &OneTimeTokenId,
+        purpose: &TokenPurpose,
+    ) -> Result<Email, AuthApiError> {
+        match self.tokens.remove(id) {
+            Some(entry)
+                if entry.purpose == *purpose
+                    && entry.expires_at > chrono::Utc::now().timestamp() =>
+            {
+                Ok(entry.email)
+            }
+            _ => Err(AuthApiError::InvalidToken),
+        }
+    }
+
+    async fn revoke_tokens(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        self.tokens.retain(|_, entry| entry.email != *email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_one_time_token_consumed_once() {
+        let mut store = InMemoryOneTimeTokenStore::default();
+        let email = Email::parse("user@test.com").expect("valid email");
+        let id = OneTimeTokenId::new();
+        store
+            .add_token(&id, &TokenPurpose::MagicLink, &email, 60)
+            .await
+            .expect("add token");
+
+        let consumed = store
+            .consume_token(&id, &TokenPurpose::MagicLink)
+            .await
+            .expect("consume token");
+        assert_eq!(consumed, email);
+
+        let result = store.consume_token(&id, &TokenPurpose::MagicLink).await;
+        assert!(matches!(result, Err(AuthApiError::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn test_one_time_token_expired() {
+        let mut store = InMemoryOneTimeTokenStore::default();
+        let email = Email::parse("user@test.com").expect("valid email");
+        let id = OneTimeTokenId::new();
+        store
+            .add_token(&id, &TokenPurpose::MagicLink, &email, 0)
+            .await
+            .expect("add token");
+
+        let result = store.consume_token(&id, &TokenPurpose::MagicLink).await;
+        assert!(matches!(result, Err(AuthApiError::InvalidToken)));
+    }
+
+    #[tokio::test]
+    async fn test_one_time_token_revoked() {
+        let mut store = InMemoryOneTimeTokenStore::default();
+        let email = Email::parse("user@test.com").expect("valid email");
+        let other = Email::parse("other@test.com").expect("valid email");
+        let id = OneTimeTokenId::new();
+        let other_id = OneTimeTokenId::new();
+        store
+            .add_token(&id, &TokenPurpose::PasswordReset, &email, 60)
+            .await
+            .expect("add token");
+        store
+            .add_token(&other_id, &TokenPurpose::PasswordReset, &other, 60)
+            .await
+            .expect("add token");
+
+        store.revoke_tokens(&email).await.expect("revoke tokens");
+        let result = store.consume_token(&id, &TokenPurpose::PasswordReset).await;
+        assert!(matches!(result, Err(AuthApiError::InvalidToken)));
+        assert!(
+            store
+                .consume_token(&other_id, &TokenPurpose::PasswordReset)
+                .await
+                .is_ok()
+        );
+    }
+}
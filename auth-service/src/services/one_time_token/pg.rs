@@ -0,0 +1,86 @@
+use sqlx::{PgPool, postgres::PgPoolOptions};
+
+use crate::{
+    domain::{Email, OneTimeTokenId, OneTimeTokenStore, TokenPurpose},
+    error::AuthApiError,
+};
+
+/// Consuming is a single `DELETE ... RETURNING`, so a token only works once even when
+/// the link is opened twice at the same time. Expired tokens are swept on add.
+#[derive(Debug, Clone)]
+pub struct PostgresOneTimeTokenStore {
+    pool: PgPool,
+}
+
+impl PostgresOneTimeTokenStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
+    pub async fn new_opts(opts: PgPoolOptions, url: &str) -> Result<Self, AuthApiError> {
+        let pool = opts.connect(url).await.map_err(AuthApiError::Db)?;
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait::async_trait]
+impl OneTimeTokenStore for PostgresOneTimeTokenStore {
+    async fn add_token(
+        &mut self,
+        id: &OneTimeTokenId,
+        purpose: &TokenPurpose,
+        email: &Email,
+        ttl: u64,
+    ) -> Result<(), AuthApiError> {
+        let mut tx = self.pool.begin().await.map_err(AuthApiError::Db)?;
+        sqlx::query(r#"DELETE FROM "public"."one_time_token" WHERE expires_at <= now();"#)
+            .execute(&mut *tx)
+            .await
+            .map_err(AuthApiError::Db)?;
+        sqlx::query(
+            r#"
+            INSERT INTO "public"."one_time_token" (id, purpose, email, expires_at)
+            VALUES ($1, $2, $3, now() + make_interval(secs => $4));
+            "#,
+        )
+        .bind(id.as_ref().to_string())
+        .bind(purpose.to_string())
+        .bind(email.as_ref())
+        .bind(ttl as f64)
+        .execute(&mut *tx)
+        .await
+        .map_err(AuthApiError::Db)?;
+        tx.commit().await.map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn consume_token(
+        &mut self,
+        id: &OneTimeTokenId,
+        purpose: &TokenPurpose,
+    ) -> Result<Email, AuthApiError> {
+        let email = sqlx::query_scalar::<_, String>(
+            r#"
+            DELETE FROM "public"."one_time_token"
+            WHERE id = $1 AND purpose = $2 AND expires_at > now()
+            RETURNING email;
+            "#,
+        )
+        .bind(id.as_ref().to_string())
+        .bind(purpose.to_string())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?
+        .ok_or(AuthApiError::InvalidToken)?;
+        Email::parse(&email)
+    }
+
+    async fn revoke_tokens(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"DELETE FROM "public"."one_time_token" WHERE email = $1;"#)
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+}
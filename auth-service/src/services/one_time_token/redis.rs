@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use crate::{
+    config::RedisConfig,
+    domain::{
+        Email, OneTimeTokenId, OneTimeTokenStore, RedisConnection, TokenPurpose, extend_expiry,
+        make_redis_key,
+    },
+    error::AuthApiError,
+};
+use redis::Commands;
+use tokio::sync::RwLock;
+
+const ONE_TIME_TOKEN_PREFIX: &str = "one_time_token";
+const ONE_TIME_TOKEN_USER_PREFIX: &str = "one_time_token_user";
+
+#[derive(Clone, Debug)]
+pub struct RedisOneTimeTokenStore {
+    conn: Arc<RwLock<RedisConnection>>,
+}
+
+impl RedisOneTimeTokenStore {
+    pub fn new(config: &RedisConfig) -> Result<Self, AuthApiError> {
+        if config.host.is_none() {
+            return Err(AuthApiError::Config(
+                "Redis Host Not Configured".to_string(),
+            ));
+        }
+
+        let port = if let Some(p) = config.port.as_ref() {
+            format!(":{}", p)
+        } else {
+            "".to_string()
+        };
+
+        let client =
+            redis::Client::open(format!("redis://{}{}", &config.host.clone().unwrap(), port))
+                .map_err(AuthApiError::Redis)?;
+        let conn = RedisConnection(client.get_connection()?);
+        Ok(Self {
+            conn: Arc::new(RwLock::new(conn)),
+        })
+    }
+}
+
+fn token_key(id: &OneTimeTokenId, purpose: &TokenPurpose) -> String {
+    make_redis_key(
+        ONE_TIME_TOKEN_PREFIX,
+        &format!("{}_{}", purpose, id.as_ref()),
+    )
+}
+
+#[async_trait::async_trait]
+impl OneTimeTokenStore for RedisOneTimeTokenStore {
+    async fn add_token(
+        &mut self,
+        id: &OneTimeTokenId,
+        purpose: &TokenPurpose,
+        email: &Email,
+        ttl: u64,
+    ) -> Result<(), AuthApiError> {
+        let key = token_key(id, purpose);
+        // per-user index so every outstanding token can be revoked at once
+        let user_key = make_redis_key(ONE_TIME_TOKEN_USER_PREFIX, email.as_ref());
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .set_ex(&key, email.as_ref(), ttl)
+            .ignore()
+            .sadd(&user_key, &key)
+            .ignore();
+        extend_expiry(&mut pipe, &user_key, ttl);
+        let mut guard = self.conn.write().await;
+        pipe.query::<()>(&mut guard.0)
+            .map_err(AuthApiError::Redis)?;
+        Ok(())
+    }
+
+    async fn consume_token(
+        &mut self,
+        id: &OneTimeTokenId,
+        purpose: &TokenPurpose,
+    ) -> Result<Email, AuthApiError> {
+        let key = token_key(id, purpose);
+        let mut guard = self.conn.write().await;
+        // GETDEL makes the read and the removal a single atomic step
+        let value = guard
+            .0
+            .get_del::<_, Option<String>>(&key)
+            .map_err(AuthApiError::Redis)?
+            .ok_or(AuthApiError::InvalidToken)?;
+        Email::parse(&value)
+    }
+
+    async fn revoke_tokens(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        let user_key = make_redis_key(ONE_TIME_TOKEN_USER_PREFIX, email.as_ref());
+        let mut guard = self.conn.write().await;
+        let mut keys = guard
+            .0
+            .smembers::<_, Vec<String>>(&user_key)
+            .map_err(AuthApiError::Redis)?;
+        keys.push(user_key);
+        guard.0.del::<_, ()>(keys).map_err(AuthApiError::Redis)?;
+        Ok(())
+    }
+}
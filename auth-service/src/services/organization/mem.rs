@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::domain::{Email, Membership, OrgRole, Organization, OrganizationStore};
+use crate::error::AuthApiError;
+
+#[derive(Debug, Default)]
+pub struct InMemoryOrganizationStore {
+    orgs: HashMap<Uuid, Organization>,
+    members: Vec<Membership>,
+}
+
+impl InMemoryOrganizationStore {
+    fn membership_mut(
+        &mut self,
+        org_id: &Uuid,
+        email: &Email,
+    ) -> Result<&mut Membership, AuthApiError> {
+        self.members
+            .iter_mut()
+            .find(|m| m.org_id == *org_id && m.email == *email)
+            .ok_or(AuthApiError::OrganizationNotFound)
+    }
+}
+
+#[async_trait::async_trait]
+impl OrganizationStore for InMemoryOrganizationStore {
+    async fn add_org(&mut self, org: Organization, owner: &Email) -> Result<(), AuthApiError> {
+        self.members
+            .push(Membership::new(org.id, owner, OrgRole::Owner));
+        self.orgs.insert(org.id, org);
+        Ok(())
+    }
+
+    async fn get_org(&self, id: &Uuid) -> Result<Organization, AuthApiError> {
+        self.orgs
+            .get(id)
+            .cloned()
+            .ok_or(AuthApiError::OrganizationNotFound)
+    }
+
+    async fn rename_org(&mut self, id: &Uuid, name: &str) -> Result<(), AuthApiError> {
+        let org = self
+            .orgs
+            .get_mut(id)
+            .ok_or(AuthApiError::OrganizationNotFound)?;
+        org.name = name.to_string();
+        Ok(())
+    }
+
+    async fn delete_org(&mut self, id: &Uuid) -> Result<(), AuthApiError> {
+        self.orgs
+            .remove(id)
+            .ok_or(AuthApiError::OrganizationNotFound)?;
+        self.members.retain(|m| m.org_id != *id);
+        Ok(())
+    }
+
+    async fn add_member(&mut self, membership: Membership) -> Result<(), AuthApiError> {
+        if !self.orgs.contains_key(&membership.org_id) {
+            return Err(AuthApiError::OrganizationNotFound);
+        }
+        if self
+            .members
+            .iter()
+            .any(|m| m.org_id == membership.org_id && m.email == membership.email)
+        {
+            return Err(AuthApiError::InvalidData(
+                "Already a member of the organization".to_string(),
+            ));
+        }
+        self.members.push(membership);
+        Ok(())
+    }
+
+    async fn get_membership(
+        &self,
+        org_id: &Uuid,
+        email: &Email,
+    ) -> Result<Membership, AuthApiError> {
+        self.members
+            .iter()
+            .find(|m| m.org_id == *org_id && m.email == *email)
+            .cloned()
+            .ok_or(AuthApiError::OrganizationNotFound)
+    }
+
+    async fn list_members(&self, org_id: &Uuid) -> Result<Vec<Membership>, AuthApiError> {
+        // memberships are only ever appended, so they are oldest first already
+        Ok(self
+            .members
+            .iter()
+            .filter(|m| m.org_id == *org_id)
+            .cloned()
+            .collect())
+    }
+
+    async fn list_memberships(&self, email: &Email) -> Result<Vec<Membership>, AuthApiError> {
+        Ok(self
+            .members
+            .iter()
+            .filter(|m| m.email == *email)
+            .cloned()
+            .collect())
+    }
+
+    async fn set_member_role(
+        &mut self,
+        org_id: &Uuid,
+        email: &Email,
+        role: OrgRole,
+    ) -> Result<(), AuthApiError> {
+        self.membership_mut(org_id, email)?.role = role;
+        Ok(())
+    }
+
+    async fn remove_member(&mut self, org_id: &Uuid, email: &Email) -> Result<(), AuthApiError> {
+        let before = self.members.len();
+        self.members
+            .retain(|m| !(m.org_id == *org_id && m.email == *email));
+        if self.members.len() == before {
+            return Err(AuthApiError::OrganizationNotFound);
+        }
+        Ok(())
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        for membership in self.members.iter_mut().filter(|m| m.email == *old) {
+            membership.email = new.clone();
+        }
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        self.members.retain(|m| m.email != *email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_membership_lifecycle() {
+        let mut store = InMemoryOrganizationStore::default();
+        let owner = Email::parse("owner@test.com").unwrap();
+        let member = Email::parse("member@test.com").unwrap();
+        let org = Organization::new("Acme").unwrap();
+        let id = org.id;
+        store.add_org(org, &owner).await.unwrap();
+        assert_eq!(
+            store.get_membership(&id, &owner).await.unwrap().role,
+            OrgRole::Owner
+        );
+
+        store
+            .add_member(Membership::new(id, &member, OrgRole::Member))
+            .await
+            .unwrap();
+        assert!(
+            store
+                .add_member(Membership::new(id, &member, OrgRole::Admin))
+                .await
+                .is_err()
+        );
+        store
+            .set_member_role(&id, &member, OrgRole::Admin)
+            .await
+            .unwrap();
+        assert_eq!(store.list_members(&id).await.unwrap().len(), 2);
+        assert_eq!(
+            store.list_memberships(&member).await.unwrap()[0].role,
+            OrgRole::Admin
+        );
+
+        store.delete_org(&id).await.unwrap();
+        assert!(store.list_memberships(&member).await.unwrap().is_empty());
+        assert!(matches!(
+            store.get_membership(&id, &owner).await,
+            Err(AuthApiError::OrganizationNotFound)
+        ));
+    }
+}
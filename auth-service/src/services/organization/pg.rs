@@ -0,0 +1,250 @@
+use sqlx::{PgPool, postgres::PgPoolOptions};
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        Email, Membership, OrgMemberRow, OrgRole, Organization, OrganizationRow, OrganizationStore,
+    },
+    error::AuthApiError,
+};
+
+#[derive(Debug, Clone)]
+pub struct PostgresOrganizationStore {
+    pool: PgPool,
+}
+
+impl PostgresOrganizationStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
+    pub async fn new_opts(opts: PgPoolOptions, url: &str) -> Result<Self, AuthApiError> {
+        let pool = opts.connect(url).await.map_err(AuthApiError::Db)?;
+        Ok(Self { pool })
+    }
+}
+
+fn parse_id(id: &str) -> Result<Uuid, AuthApiError> {
+    Uuid::parse_str(id).map_err(|e| AuthApiError::InvalidData(format!("{e}")))
+}
+
+impl TryFrom<OrganizationRow> for Organization {
+    type Error = AuthApiError;
+
+    fn try_from(row: OrganizationRow) -> Result<Self, Self::Error> {
+        Ok(Organization {
+            id: parse_id(&row.id)?,
+            name: row.name,
+            created_at: row.created_at.timestamp(),
+        })
+    }
+}
+
+impl TryFrom<OrgMemberRow> for Membership {
+    type Error = AuthApiError;
+
+    fn try_from(row: OrgMemberRow) -> Result<Self, Self::Error> {
+        Ok(Membership {
+            org_id: parse_id(&row.org_id)?,
+            email: Email::parse(&row.email)?,
+            role: row.role.parse()?,
+            joined_at: row.created_at.timestamp(),
+        })
+    }
+}
+
+fn timestamp(t: i64) -> Result<chrono::DateTime<chrono::Utc>, AuthApiError> {
+    chrono::DateTime::from_timestamp(t, 0)
+        .ok_or_else(|| AuthApiError::InvalidData("Organization timestamp".to_string()))
+}
+
+#[async_trait::async_trait]
+impl OrganizationStore for PostgresOrganizationStore {
+    async fn add_org(&mut self, org: Organization, owner: &Email) -> Result<(), AuthApiError> {
+        let mut tx = self.pool.begin().await.map_err(AuthApiError::Db)?;
+        sqlx::query(
+            r#"INSERT INTO "public"."organization" (id, name, created_at) VALUES ($1, $2, $3);"#,
+        )
+        .bind(org.id.to_string())
+        .bind(&org.name)
+        .bind(timestamp(org.created_at)?)
+        .execute(&mut *tx)
+        .await
+        .map_err(AuthApiError::Db)?;
+        sqlx::query(
+            r#"INSERT INTO "public"."org_member" (org_id, email, role, created_at) VALUES ($1, $2, $3, $4);"#,
+        )
+        .bind(org.id.to_string())
+        .bind(owner.as_ref())
+        .bind(OrgRole::Owner.as_ref())
+        .bind(timestamp(org.created_at)?)
+        .execute(&mut *tx)
+        .await
+        .map_err(AuthApiError::Db)?;
+        tx.commit().await.map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn get_org(&self, id: &Uuid) -> Result<Organization, AuthApiError> {
+        let row = sqlx::query_as::<_, OrganizationRow>(
+            r#"SELECT id, name, created_at FROM "public"."organization" WHERE id = $1;"#,
+        )
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?
+        .ok_or(AuthApiError::OrganizationNotFound)?;
+        row.try_into()
+    }
+
+    async fn rename_org(&mut self, id: &Uuid, name: &str) -> Result<(), AuthApiError> {
+        let result = sqlx::query(r#"UPDATE "public"."organization" SET name = $2 WHERE id = $1;"#)
+            .bind(id.to_string())
+            .bind(name)
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        if result.rows_affected() == 0 {
+            return Err(AuthApiError::OrganizationNotFound);
+        }
+        Ok(())
+    }
+
+    async fn delete_org(&mut self, id: &Uuid) -> Result<(), AuthApiError> {
+        let mut tx = self.pool.begin().await.map_err(AuthApiError::Db)?;
+        sqlx::query(r#"DELETE FROM "public"."org_member" WHERE org_id = $1;"#)
+            .bind(id.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(AuthApiError::Db)?;
+        let result = sqlx::query(r#"DELETE FROM "public"."organization" WHERE id = $1;"#)
+            .bind(id.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(AuthApiError::Db)?;
+        if result.rows_affected() == 0 {
+            return Err(AuthApiError::OrganizationNotFound);
+        }
+        tx.commit().await.map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn add_member(&mut self, membership: Membership) -> Result<(), AuthApiError> {
+        // the organization check and the insert are one statement so it can't be deleted in between
+        let result = sqlx::query(
+            r#"
+            INSERT INTO "public"."org_member" (org_id, email, role, created_at)
+            SELECT id, $2, $3, $4 FROM "public"."organization" WHERE id = $1;
+            "#,
+        )
+        .bind(membership.org_id.to_string())
+        .bind(membership.email.as_ref())
+        .bind(membership.role.as_ref())
+        .bind(timestamp(membership.joined_at)?)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => {
+                AuthApiError::InvalidData("Already a member of the organization".to_string())
+            }
+            e => AuthApiError::Db(e),
+        })?;
+        if result.rows_affected() == 0 {
+            return Err(AuthApiError::OrganizationNotFound);
+        }
+        Ok(())
+    }
+
+    async fn get_membership(
+        &self,
+        org_id: &Uuid,
+        email: &Email,
+    ) -> Result<Membership, AuthApiError> {
+        let row = sqlx::query_as::<_, OrgMemberRow>(
+            r#"SELECT org_id, email, role, created_at FROM "public"."org_member" WHERE org_id = $1 AND email = $2;"#,
+        )
+        .bind(org_id.to_string())
+        .bind(email.as_ref())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?
+        .ok_or(AuthApiError::OrganizationNotFound)?;
+        row.try_into()
+    }
+
+    async fn list_members(&self, org_id: &Uuid) -> Result<Vec<Membership>, AuthApiError> {
+        let rows = sqlx::query_as::<_, OrgMemberRow>(
+            r#"SELECT org_id, email, role, created_at FROM "public"."org_member" WHERE org_id = $1 ORDER BY created_at;"#,
+        )
+        .bind(org_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        rows.into_iter().map(Membership::try_from).collect()
+    }
+
+    async fn list_memberships(&self, email: &Email) -> Result<Vec<Membership>, AuthApiError> {
+        let rows = sqlx::query_as::<_, OrgMemberRow>(
+            r#"SELECT org_id, email, role, created_at FROM "public"."org_member" WHERE email = $1 ORDER BY created_at;"#,
+        )
+        .bind(email.as_ref())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        rows.into_iter().map(Membership::try_from).collect()
+    }
+
+    async fn set_member_role(
+        &mut self,
+        org_id: &Uuid,
+        email: &Email,
+        role: OrgRole,
+    ) -> Result<(), AuthApiError> {
+        let result = sqlx::query(
+            r#"UPDATE "public"."org_member" SET role = $3 WHERE org_id = $1 AND email = $2;"#,
+        )
+        .bind(org_id.to_string())
+        .bind(email.as_ref())
+        .bind(role.as_ref())
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        if result.rows_affected() == 0 {
+            return Err(AuthApiError::OrganizationNotFound);
+        }
+        Ok(())
+    }
+
+    async fn remove_member(&mut self, org_id: &Uuid, email: &Email) -> Result<(), AuthApiError> {
+        let result =
+            sqlx::query(r#"DELETE FROM "public"."org_member" WHERE org_id = $1 AND email = $2;"#)
+                .bind(org_id.to_string())
+                .bind(email.as_ref())
+                .execute(&self.pool)
+                .await
+                .map_err(AuthApiError::Db)?;
+        if result.rows_affected() == 0 {
+            return Err(AuthApiError::OrganizationNotFound);
+        }
+        Ok(())
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"UPDATE "public"."org_member" SET email = $2 WHERE email = $1;"#)
+            .bind(old.as_ref())
+            .bind(new.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"DELETE FROM "public"."org_member" WHERE email = $1;"#)
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+}
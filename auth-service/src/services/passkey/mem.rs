@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use crate::domain::{Email, PasskeyCeremony, PasskeyCeremonyId, PasskeyCredential, PasskeyStore};
+use crate::error::AuthApiError;
+
+#[derive(Debug, Default)]
+pub struct InMemoryPasskeyStore {
+    /// keyed by encoded credential id
+    passkeys: HashMap<String, PasskeyCredential>,
+    ceremonies: HashMap<PasskeyCeremonyId, (PasskeyCeremony, i64)>,
+}
+
+#[async_trait::async_trait]
+impl PasskeyStore for InMemoryPasskeyStore {
+    async fn add_passkey(&mut self, credential: PasskeyCredential) -> Result<(), AuthApiError> {
+        let id = credential.credential_id();
+        if self.passkeys.contains_key(&id) {
+            return Err(AuthApiError::InvalidData(
+                "Passkey already registered".to_string(),
+            ));
+        }
+        self.passkeys.insert(id, credential);
+        Ok(())
+    }
+
+    async fn get_passkeys(&self, email: &Email) -> Result<Vec<PasskeyCredential>, AuthApiError> {
+        Ok(self
+            .passkeys
+            .values()
+            .filter(|c| c.email == *email)
+            .cloned()
+            .collect())
+    }
+
+    async fn update_passkey(&mut self, credential: &PasskeyCredential) -> Result<(), AuthApiError> {
+        let stored = self
+            .passkeys
+            .get_mut(&credential.credential_id())
+            .ok_or(AuthApiError::Unauthorized)?;
+        // same guard as the pg store: a concurrent, older assertion must not roll the counter back
+        if credential.sign_count != 0 && credential.sign_count <= stored.sign_count {
+            return Err(AuthApiError::PasskeyCounterRegression);
+        }
+        *stored = credential.clone();
+        Ok(())
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        self.passkeys
+            .values_mut()
+            .filter(|c| c.email == *old)
+            .for_each(|c| c.email = new.clone());
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        self.passkeys.retain(|_, c| c.email != *email);
+        Ok(())
+    }
+
+    async fn add_ceremony(
+        &mut self,
+        id: &PasskeyCeremonyId,
+        ceremony: PasskeyCeremony,
+        ttl: u64,
+    ) -> Result<(), AuthApiError> {
+        let expires_at = chrono::Utc::now().timestamp() + ttl as i64;
+        self.ceremonies.insert(id.clone(), (ceremony, expires_at));
+        Ok(())
+    }
+
+    async fn take_ceremony(
+        &mut self,
+        id: &PasskeyCeremonyId,
+    ) -> Result<PasskeyCeremony, AuthApiError> {
+        match self.ceremonies.remove(id) {
+            Some((ceremony, expires_at)) if expires_at > chrono::Utc::now().timestamp() => {
+                Ok(ceremony)
+            }
+            _ => Err(AuthApiError::Unauthorized),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use webauthn_rs::WebauthnBuilder;
+    use webauthn_rs::prelude::{Url, Uuid};
+
+    fn registration_ceremony(email: &Email) -> PasskeyCeremony {
+        let origin = Url::parse("http://localhost:5173").expect("valid url");
+        let webauthn = WebauthnBuilder::new("localhost", &origin)
+            .and_then(|b| b.build())
+            .expect("valid webauthn");
+        let (_, state) = webauthn
+            .start_passkey_registration(Uuid::new_v4(), email.as_ref(), email.as_ref(), None)
+            .expect("registration challenge");
+        PasskeyCeremony::Registration {
+            email: email.clone(),
+            state,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ceremony_taken_once() {
+        let mut store = InMemoryPasskeyStore::default();
+        let email = Email::parse("user@test.com").expect("valid email");
+        let id = PasskeyCeremonyId::new();
+        store
+            .add_ceremony(&id, registration_ceremony(&email), 60)
+            .await
+            .expect("add ceremony");
+
+        let ceremony = store.take_ceremony(&id).await.expect("take ceremony");
+        assert!(matches!(ceremony, PasskeyCeremony::Registration { .. }));
+
+        let result = store.take_ceremony(&id).await;
+        assert!(matches!(result, Err(AuthApiError::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn test_ceremony_expired() {
+        let mut store = InMemoryPasskeyStore::default();
+        let email = Email::parse("user@test.com").expect("valid email");
+        let id = PasskeyCeremonyId::new();
+        store
+            .add_ceremony(&id, registration_ceremony(&email), 0)
+            .await
+            .expect("add ceremony");
+
+        let result = store.take_ceremony(&id).await;
+        assert!(matches!(result, Err(AuthApiError::Unauthorized)));
+    }
+}
@@ -0,0 +1,158 @@
+use sqlx::{PgPool, postgres::PgPoolOptions};
+
+use crate::{
+    domain::{
+        Email, PasskeyCeremony, PasskeyCeremonyId, PasskeyCeremonyRow, PasskeyCredential,
+        PasskeyRow, PasskeyStore,
+    },
+    error::AuthApiError,
+};
+
+#[derive(Debug, Clone)]
+pub struct PostgresPasskeyStore {
+    pool: PgPool,
+}
+
+impl PostgresPasskeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
+    pub async fn new_opts(opts: PgPoolOptions, url: &str) -> Result<Self, AuthApiError> {
+        let pool = opts.connect(url).await.map_err(AuthApiError::Db)?;
+        Ok(Self { pool })
+    }
+}
+
+impl TryFrom<PasskeyRow> for PasskeyCredential {
+    type Error = AuthApiError;
+
+    fn try_from(row: PasskeyRow) -> Result<Self, Self::Error> {
+        Ok(PasskeyCredential {
+            email: Email::parse(&row.email)?,
+            passkey: serde_json::from_str(&row.passkey)
+                .map_err(|e| AuthApiError::SerializationError(format!("{e}")))?,
+            sign_count: row.sign_count as u32,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl PasskeyStore for PostgresPasskeyStore {
+    async fn add_passkey(&mut self, credential: PasskeyCredential) -> Result<(), AuthApiError> {
+        let passkey = serde_json::to_string(&credential.passkey)
+            .map_err(|e| AuthApiError::SerializationError(format!("{e}")))?;
+        sqlx::query(
+            r#"
+        INSERT INTO "public"."passkey" (credential_id, email, passkey, sign_count)
+        VALUES ($1, $2, $3, $4);
+        "#,
+        )
+        .bind(credential.credential_id())
+        .bind(credential.email.as_ref())
+        .bind(passkey)
+        .bind(credential.sign_count as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn get_passkeys(&self, email: &Email) -> Result<Vec<PasskeyCredential>, AuthApiError> {
+        let rows = sqlx::query_as::<_, PasskeyRow>(
+            r#"SELECT credential_id, email, passkey, sign_count, created_at FROM "public"."passkey" WHERE email = $1;"#,
+        )
+        .bind(email.as_ref())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        rows.into_iter().map(PasskeyCredential::try_from).collect()
+    }
+
+    async fn update_passkey(&mut self, credential: &PasskeyCredential) -> Result<(), AuthApiError> {
+        let passkey = serde_json::to_string(&credential.passkey)
+            .map_err(|e| AuthApiError::SerializationError(format!("{e}")))?;
+        // the counter guard makes a concurrent, older assertion lose instead of rolling it back
+        let result = sqlx::query(
+            r#"
+        UPDATE "public"."passkey" SET passkey = $2, sign_count = $3
+        WHERE credential_id = $1 AND (sign_count < $3 OR $3 = 0);
+        "#,
+        )
+        .bind(credential.credential_id())
+        .bind(passkey)
+        .bind(credential.sign_count as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+
+        if result.rows_affected() != 1 {
+            return Err(AuthApiError::PasskeyCounterRegression);
+        }
+        Ok(())
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"UPDATE "public"."passkey" SET email = $2 WHERE email = $1;"#)
+            .bind(old.as_ref())
+            .bind(new.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"DELETE FROM "public"."passkey" WHERE email = $1;"#)
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn add_ceremony(
+        &mut self,
+        id: &PasskeyCeremonyId,
+        ceremony: PasskeyCeremony,
+        ttl: u64,
+    ) -> Result<(), AuthApiError> {
+        let ceremony = serde_json::to_string(&ceremony)
+            .map_err(|e| AuthApiError::SerializationError(format!("{e}")))?;
+        let expires_at = chrono::Utc::now() + chrono::Duration::seconds(ttl as i64);
+        sqlx::query(
+            r#"
+        INSERT INTO "public"."passkey_ceremony" (id, ceremony, expires_at)
+        VALUES ($1, $2, $3);
+        "#,
+        )
+        .bind(id.as_ref().to_string())
+        .bind(ceremony)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn take_ceremony(
+        &mut self,
+        id: &PasskeyCeremonyId,
+    ) -> Result<PasskeyCeremony, AuthApiError> {
+        let row = sqlx::query_as::<_, PasskeyCeremonyRow>(
+            r#"
+        DELETE FROM "public"."passkey_ceremony"
+        WHERE id = $1 AND expires_at > now()
+        RETURNING id, ceremony, expires_at;
+        "#,
+        )
+        .bind(id.as_ref().to_string())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?
+        .ok_or(AuthApiError::Unauthorized)?;
+
+        serde_json::from_str(&row.ceremony)
+            .map_err(|e| AuthApiError::SerializationError(format!("{e}")))
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use crate::domain::{BucketState, RateLimitStore, TokenBucket};
+use crate::error::AuthApiError;
+
+/// Pruning walks every entry, so it runs at most this often, in milliseconds
+const PRUNE_INTERVAL: i64 = 60_000;
+
+#[derive(Debug, Clone)]
+struct Window {
+    started_at: i64,
+    hits: u32,
+    /// Unix timestamp in seconds
+    ends_at: i64,
+}
+
+#[derive(Debug, Clone)]
+struct StoredBucket {
+    state: BucketState,
+    /// Unix timestamp in milliseconds, see `TokenBucket::full_at`
+    full_at: i64,
+}
+
+#[derive(Debug, Default)]
+pub struct InMemoryRateLimitStore {
+    windows: HashMap<String, Window>,
+    buckets: HashMap<String, StoredBucket>,
+    /// Unix timestamp in milliseconds
+    pruned_at: i64,
+}
+
+impl InMemoryRateLimitStore {
+    /// Forget the windows that are over and the buckets that have filled up again,
+    /// otherwise every address ever seen would be kept
+    fn prune(&mut self, now: i64) {
+        if now - self.pruned_at < PRUNE_INTERVAL {
+            return;
+        }
+        self.pruned_at = now;
+        self.windows.retain(|_, w| w.ends_at * 1000 > now);
+        self.buckets.retain(|_, b| b.full_at > now);
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for InMemoryRateLimitStore {
+    async fn hit(&mut self, key: &str, limit: u32, window: u64) -> Result<(), AuthApiError> {
+        self.prune(chrono::Utc::now().timestamp_millis());
+        let now = chrono::Utc::now().timestamp();
+        let fresh = Window {
+            started_at: now,
+            hits: 0,
+            ends_at: now + window as i64,
+        };
+        let entry = self.windows.entry(key.to_string()).or_insert(fresh.clone());
+        if now - entry.started_at >= window as i64 {
+            *entry = fresh;
+        }
+        if entry.hits >= limit {
+            let retry_after = entry.started_at + window as i64 - now;
+            return Err(AuthApiError::TooManyRequests(retry_after.max(1) as u64));
+        }
+        entry.hits += 1;
+        Ok(())
+    }
+
+    async fn take_tokens(
+        &mut self,
+        keys: &[String],
+        bucket: &TokenBucket,
+    ) -> Result<(), AuthApiError> {
+        let now = chrono::Utc::now().timestamp_millis();
+        self.prune(now);
+        let mut taken = Vec::with_capacity(keys.len());
+        let mut retry_after = None;
+        for key in keys {
+            match bucket.take(self.buckets.get(key).map(|b| b.state), now) {
+                (state, Ok(())) => taken.push((key, state)),
+                (_, Err(wait)) => retry_after = retry_after.max(Some(wait)),
+            }
+        }
+        // one empty bucket refuses the request, so the others keep their tokens
+        if let Some(wait) = retry_after {
+            return Err(AuthApiError::TooManyRequests(wait));
+        }
+        for (key, state) in taken {
+            let full_at = bucket.full_at(&state);
+            self.buckets
+                .insert(key.to_string(), StoredBucket { state, full_at });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|k| k.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_hit_limit() {
+        let mut store = InMemoryRateLimitStore::default();
+        for _ in 0..3 {
+            store.hit("resend_a", 3, 60).await.unwrap();
+        }
+        let err = store.hit("resend_a", 3, 60).await.unwrap_err();
+        assert!(matches!(err, AuthApiError::TooManyRequests(s) if s > 0 && s <= 60));
+        // other keys have their own window
+        assert!(store.hit("resend_b", 3, 60).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_take_token() {
+        let mut store = InMemoryRateLimitStore::default();
+        let bucket = TokenBucket {
+            capacity: 2,
+            period: 60,
+        };
+        for _ in 0..2 {
+            store
+                .take_tokens(&keys(&["login_a"]), &bucket)
+                .await
+                .unwrap();
+        }
+        let err = store
+            .take_tokens(&keys(&["login_a"]), &bucket)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AuthApiError::TooManyRequests(s) if s > 0 && s <= 30));
+        assert!(
+            store
+                .take_tokens(&keys(&["login_b"]), &bucket)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_take_tokens_takes_from_all_or_none() {
+        let mut store = InMemoryRateLimitStore::default();
+        let bucket = TokenBucket {
+            capacity: 1,
+            period: 60,
+        };
+        store
+            .take_tokens(&keys(&["login:email:a"]), &bucket)
+            .await
+            .unwrap();
+        // the empty email bucket refuses, the address keeps its token
+        let both = keys(&["login:ip:1", "login:email:a"]);
+        let err = store.take_tokens(&both, &bucket).await.unwrap_err();
+        assert!(matches!(err, AuthApiError::TooManyRequests(_)));
+        assert!(
+            store
+                .take_tokens(&keys(&["login:ip:1"]), &bucket)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_prunes_full_buckets_and_ended_windows() {
+        let mut store = InMemoryRateLimitStore::default();
+        let bucket = TokenBucket {
+            capacity: 2,
+            period: 600,
+        };
+        store
+            .take_tokens(&keys(&["login_a"]), &bucket)
+            .await
+            .unwrap();
+        store.hit("resend_a", 3, 600).await.unwrap();
+        store.hit("resend_b", 3, 0).await.unwrap();
+
+        let now = chrono::Utc::now().timestamp_millis() + PRUNE_INTERVAL;
+        store.prune(now);
+        assert!(store.buckets.contains_key("login_a"));
+        assert!(store.windows.contains_key("resend_a"));
+        assert!(!store.windows.contains_key("resend_b"));
+
+        // ten minutes on the bucket has refilled and the window is over
+        store.prune(now + 600_000);
+        assert!(store.buckets.is_empty());
+        assert!(store.windows.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_window_resets() {
+        let mut store = InMemoryRateLimitStore::default();
+        store.hit("resend", 1, 0).await.unwrap();
+        assert!(store.hit("resend", 1, 0).await.is_ok());
+    }
+}
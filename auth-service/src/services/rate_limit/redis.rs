@@ -0,0 +1,121 @@
+use std::sync::Arc;
+
+use tokio::sync::RwLock;
+
+use crate::{
+    config::RedisConfig,
+    domain::{RateLimitStore, RedisConnection, TokenBucket, make_redis_key},
+    error::AuthApiError,
+};
+
+const RATE_LIMIT_PREFIX: &str = "rate_limit";
+const TOKEN_BUCKET_PREFIX: &str = "token_bucket";
+
+/// `TokenBucket::take` on every bucket in `KEYS` in a script, so replicas taking from
+/// the same bucket queue up behind each other. The clock is Redis's, replicas may not
+/// agree on the time. Nothing is taken unless every bucket has a token.
+/// Returns 0 when the tokens were taken, otherwise the seconds to wait.
+const TAKE_TOKEN_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local period = tonumber(ARGV[2]) * 1000
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local tokens = {}
+local retry_after = 0
+for i, key in ipairs(KEYS) do
+    local state = redis.call('HMGET', key, 'tokens', 'updated_at')
+    tokens[i] = capacity
+    if state[1] then
+        local elapsed = math.max(now - tonumber(state[2]), 0)
+        tokens[i] = math.min(tonumber(state[1]) + elapsed * capacity / period, capacity)
+    end
+    if tokens[i] < 1 then
+        local wait = math.max(math.ceil((1 - tokens[i]) * period / capacity / 1000), 1)
+        retry_after = math.max(retry_after, wait)
+    end
+end
+if retry_after > 0 then
+    return retry_after
+end
+for i, key in ipairs(KEYS) do
+    redis.call('HSET', key, 'tokens', tostring(tokens[i] - 1), 'updated_at', now)
+    -- an empty bucket is full again after one period, so the key can go then
+    redis.call('PEXPIRE', key, period)
+end
+return 0
+"#;
+
+#[derive(Clone, Debug)]
+pub struct RedisRateLimitStore {
+    conn: Arc<RwLock<RedisConnection>>,
+}
+
+impl RedisRateLimitStore {
+    pub fn new(config: &RedisConfig) -> Result<Self, AuthApiError> {
+        if config.host.is_none() {
+            return Err(AuthApiError::Config(
+                "Redis Host Not Configured".to_string(),
+            ));
+        }
+
+        let port = if let Some(p) = config.port.as_ref() {
+            format!(":{}", p)
+        } else {
+            "".to_string()
+        };
+
+        let client =
+            redis::Client::open(format!("redis://{}{}", &config.host.clone().unwrap(), port))
+                .map_err(AuthApiError::Redis)?;
+        let conn = RedisConnection(client.get_connection()?);
+        Ok(Self {
+            conn: Arc::new(RwLock::new(conn)),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for RedisRateLimitStore {
+    async fn hit(&mut self, key: &str, limit: u32, window: u64) -> Result<(), AuthApiError> {
+        let key = make_redis_key(RATE_LIMIT_PREFIX, key);
+        let mut guard = self.conn.write().await;
+        // the first hit opens the window, later ones leave its expiry alone
+        let (hits, ttl): (u32, i64) = redis::pipe()
+            .atomic()
+            .incr(&key, 1)
+            .cmd("EXPIRE")
+            .arg(&key)
+            .arg(window)
+            .arg("NX")
+            .ignore()
+            .ttl(&key)
+            .query(&mut guard.0)
+            .map_err(AuthApiError::Redis)?;
+        if hits > limit {
+            return Err(AuthApiError::TooManyRequests(ttl.max(1) as u64));
+        }
+        Ok(())
+    }
+
+    async fn take_tokens(
+        &mut self,
+        keys: &[String],
+        bucket: &TokenBucket,
+    ) -> Result<(), AuthApiError> {
+        let script = redis::Script::new(TAKE_TOKEN_SCRIPT);
+        let mut invocation = script.prepare_invoke();
+        for key in keys {
+            invocation.key(make_redis_key(TOKEN_BUCKET_PREFIX, key));
+        }
+        let mut guard = self.conn.write().await;
+        let retry_after: u64 = invocation
+            .arg(bucket.capacity.max(1))
+            .arg(bucket.period.max(1))
+            .invoke(&mut guard.0)
+            .map_err(AuthApiError::Redis)?;
+        if retry_after > 0 {
+            return Err(AuthApiError::TooManyRequests(retry_after));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::domain::{Email, RecoveryCodeStore, StoredRecoveryCode};
+use crate::error::AuthApiError;
+
+#[derive(Debug, Default)]
+pub struct InMemoryRecoveryCodeStore {
+    codes: HashMap<Email, Vec<StoredRecoveryCode>>,
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for InMemoryRecoveryCodeStore {
+    async fn replace_codes(
+        &mut self,
+        email: &Email,
+        codes: Vec<StoredRecoveryCode>,
+    ) -> Result<(), AuthApiError> {
+        self.codes.insert(email.clone(), codes);
+        Ok(())
+    }
+
+    async fn get_codes(&self, email: &Email) -> Result<Vec<StoredRecoveryCode>, AuthApiError> {
+        Ok(self.codes.get(email).cloned().unwrap_or_default())
+    }
+
+    async fn remove_code(&mut self, email: &Email, id: &Uuid) -> Result<(), AuthApiError> {
+        let codes = self
+            .codes
+            .get_mut(email)
+            .ok_or(AuthApiError::TwoFactorCodeNotFound)?;
+        let before = codes.len();
+        codes.retain(|c| c.id != *id);
+        if codes.len() == before {
+            return Err(AuthApiError::TwoFactorCodeNotFound);
+        }
+        Ok(())
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        if let Some(codes) = self.codes.remove(old) {
+            self.codes.insert(new.clone(), codes);
+        }
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        self.codes.remove(email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{HashedRecoveryCode, RecoveryCode};
+
+    #[tokio::test]
+    async fn test_recovery_code_consumed_once() {
+        let mut store = InMemoryRecoveryCodeStore::default();
+        let email = Email::parse("user@test.com").expect("valid email");
+        let codes = RecoveryCode::generate_set(2);
+        let mut stored = Vec::new();
+        for code in &codes {
+            stored.push(StoredRecoveryCode::new(
+                HashedRecoveryCode::parse(code).await.unwrap(),
+            ));
+        }
+        store.replace_codes(&email, stored).await.unwrap();
+
+        store
+            .consume_code(&email, &codes[1])
+            .await
+            .expect("first use");
+        let reuse = store.consume_code(&email, &codes[1]).await;
+        assert!(matches!(reuse, Err(AuthApiError::TwoFactorCodeMismatch)));
+        assert_eq!(store.get_codes(&email).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_recovery_codes_replaced() {
+        let mut store = InMemoryRecoveryCodeStore::default();
+        let email = Email::parse("user@test.com").expect("valid email");
+        let old = RecoveryCode::new();
+        let hashed = HashedRecoveryCode::parse(&old).await.unwrap();
+        store
+            .replace_codes(&email, vec![StoredRecoveryCode::new(hashed)])
+            .await
+            .unwrap();
+        store.replace_codes(&email, vec![]).await.unwrap();
+
+        let result = store.consume_code(&email, &old).await;
+        assert!(matches!(result, Err(AuthApiError::TwoFactorCodeMismatch)));
+    }
+}
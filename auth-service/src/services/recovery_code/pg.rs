@@ -0,0 +1,109 @@
+use sqlx::{PgPool, postgres::PgPoolOptions};
+use uuid::Uuid;
+
+use crate::{
+    domain::{Email, HashedRecoveryCode, RecoveryCodeRow, RecoveryCodeStore, StoredRecoveryCode},
+    error::AuthApiError,
+};
+
+#[derive(Debug, Clone)]
+pub struct PostgresRecoveryCodeStore {
+    pool: PgPool,
+}
+
+impl PostgresRecoveryCodeStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
+    pub async fn new_opts(opts: PgPoolOptions, url: &str) -> Result<Self, AuthApiError> {
+        let pool = opts.connect(url).await.map_err(AuthApiError::Db)?;
+        Ok(Self { pool })
+    }
+}
+
+impl TryFrom<RecoveryCodeRow> for StoredRecoveryCode {
+    type Error = AuthApiError;
+
+    fn try_from(row: RecoveryCodeRow) -> Result<Self, Self::Error> {
+        Ok(StoredRecoveryCode {
+            id: Uuid::parse_str(&row.id).map_err(|e| AuthApiError::InvalidData(format!("{e}")))?,
+            hash: HashedRecoveryCode::from_hash(row.code_hash),
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl RecoveryCodeStore for PostgresRecoveryCodeStore {
+    async fn replace_codes(
+        &mut self,
+        email: &Email,
+        codes: Vec<StoredRecoveryCode>,
+    ) -> Result<(), AuthApiError> {
+        let mut tx = self.pool.begin().await.map_err(AuthApiError::Db)?;
+        sqlx::query(r#"DELETE FROM "public"."recovery_code" WHERE email = $1;"#)
+            .bind(email.as_ref())
+            .execute(&mut *tx)
+            .await
+            .map_err(AuthApiError::Db)?;
+        for code in codes {
+            sqlx::query(
+                r#"INSERT INTO "public"."recovery_code" (id, email, code_hash) VALUES ($1, $2, $3);"#,
+            )
+            .bind(code.id.to_string())
+            .bind(email.as_ref())
+            .bind(code.hash.as_ref())
+            .execute(&mut *tx)
+            .await
+            .map_err(AuthApiError::Db)?;
+        }
+        tx.commit().await.map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn get_codes(&self, email: &Email) -> Result<Vec<StoredRecoveryCode>, AuthApiError> {
+        let rows = sqlx::query_as::<_, RecoveryCodeRow>(
+            r#"SELECT id, email, code_hash, created_at FROM "public"."recovery_code" WHERE email = $1;"#,
+        )
+        .bind(email.as_ref())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        rows.into_iter().map(StoredRecoveryCode::try_from).collect()
+    }
+
+    async fn remove_code(&mut self, email: &Email, id: &Uuid) -> Result<(), AuthApiError> {
+        // a concurrent redemption of the same code deletes nothing and loses
+        let result =
+            sqlx::query(r#"DELETE FROM "public"."recovery_code" WHERE email = $1 AND id = $2;"#)
+                .bind(email.as_ref())
+                .bind(id.to_string())
+                .execute(&self.pool)
+                .await
+                .map_err(AuthApiError::Db)?;
+
+        if result.rows_affected() != 1 {
+            return Err(AuthApiError::TwoFactorCodeNotFound);
+        }
+        Ok(())
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"UPDATE "public"."recovery_code" SET email = $2 WHERE email = $1;"#)
+            .bind(old.as_ref())
+            .bind(new.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"DELETE FROM "public"."recovery_code" WHERE email = $1;"#)
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+}
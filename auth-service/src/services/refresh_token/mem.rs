@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::domain::{Email, RefreshToken, RefreshTokenRecord, RefreshTokenStore};
+use crate::error::AuthApiError;
+
+#[derive(Debug, Default)]
+pub struct InMemoryRefreshTokenStore {
+    tokens: HashMap<String, RefreshTokenRecord>,
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for InMemoryRefreshTokenStore {
+    async fn add_token(&mut self, record: RefreshTokenRecord) -> Result<(), AuthApiError> {
+        // used tokens are kept until they expire, presenting one again is how reuse shows
+        self.tokens.retain(|_, record| !record.is_expired());
+        self.tokens.insert(record.token_hash.clone(), record);
+        Ok(())
+    }
+
+    async fn get_token(&self, token_hash: &str) -> Result<RefreshTokenRecord, AuthApiError> {
+        match self.tokens.get(token_hash) {
+            Some(record) if !record.is_expired() => Ok(record.clone()),
+            _ => Err(AuthApiError::InvalidToken),
+        }
+    }
+
+    async fn rotate_token(
+        &mut self,
+        token_hash: &str,
+        next: &RefreshToken,
+        ttl: u64,
+    ) -> Result<RefreshTokenRecord, AuthApiError> {
+        let current = self.get_token(token_hash).await?;
+        if current.used {
+            self.revoke_family(&current.family_id).await?;
+            return Err(AuthApiError::RefreshTokenReuse);
+        }
+        if let Some(record) = self.tokens.get_mut(token_hash) {
+            record.used = true;
+        }
+        let record = current.next_in_family(next, ttl);
+        self.add_token(record.clone()).await?;
+        Ok(record)
+    }
+
+    async fn revoke_family(&mut self, family_id: &Uuid) -> Result<(), AuthApiError> {
+        self.tokens
+            .retain(|_, record| record.family_id != *family_id);
+        Ok(())
+    }
+
+    async fn revoke_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        self.tokens.retain(|_, record| record.email != *email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{AuthMethod, Authentication};
+
+    fn email() -> Email {
+        Email::parse("refresh@test.com").unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rotate_token() {
+        let mut store = InMemoryRefreshTokenStore::default();
+        let first = RefreshToken::generate();
+        let record = RefreshTokenRecord {
+            authentication: Authentication::now(&[AuthMethod::Hwk]),
+            ..RefreshTokenRecord::new(&first, &email(), 60)
+        };
+        store.add_token(record.clone()).await.unwrap();
+
+        let second = RefreshToken::generate();
+        let rotated = store
+            .rotate_token(&first.hash(), &second, 60)
+            .await
+            .unwrap();
+        assert_eq!(rotated.family_id, record.family_id);
+        assert_eq!(rotated.email, email());
+        assert_eq!(rotated.authentication, record.authentication);
+        assert!(store.get_token(&first.hash()).await.unwrap().used);
+        assert!(!store.get_token(&second.hash()).await.unwrap().used);
+    }
+
+    #[tokio::test]
+    async fn test_reuse_revokes_family() {
+        let mut store = InMemoryRefreshTokenStore::default();
+        let first = RefreshToken::generate();
+        store
+            .add_token(RefreshTokenRecord::new(&first, &email(), 60))
+            .await
+            .unwrap();
+        let other = RefreshToken::generate();
+        store
+            .add_token(RefreshTokenRecord::new(&other, &email(), 60))
+            .await
+            .unwrap();
+
+        let second = RefreshToken::generate();
+        store
+            .rotate_token(&first.hash(), &second, 60)
+            .await
+            .unwrap();
+
+        let err = store
+            .rotate_token(&first.hash(), &RefreshToken::generate(), 60)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AuthApiError::RefreshTokenReuse));
+        assert!(store.get_token(&second.hash()).await.is_err());
+        // other logins are unaffected
+        assert!(store.get_token(&other.hash()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_expired_token() {
+        let mut store = InMemoryRefreshTokenStore::default();
+        let token = RefreshToken::generate();
+        store
+            .add_token(RefreshTokenRecord::new(&token, &email(), 0))
+            .await
+            .unwrap();
+        let err = store
+            .rotate_token(&token.hash(), &RefreshToken::generate(), 60)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AuthApiError::InvalidToken));
+    }
+
+    #[tokio::test]
+    async fn test_expired_tokens_are_pruned() {
+        let mut store = InMemoryRefreshTokenStore::default();
+        let expired = RefreshToken::generate();
+        store
+            .add_token(RefreshTokenRecord::new(&expired, &email(), 0))
+            .await
+            .unwrap();
+        let first = RefreshToken::generate();
+        store
+            .add_token(RefreshTokenRecord::new(&first, &email(), 60))
+            .await
+            .unwrap();
+        store
+            .rotate_token(&first.hash(), &RefreshToken::generate(), 60)
+            .await
+            .unwrap();
+        // the used token stays for reuse detection
+        assert_eq!(store.tokens.len(), 2);
+        assert!(!store.tokens.contains_key(&expired.hash()));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod mem;
+pub mod pg;
+pub mod redis;
@@ -0,0 +1,162 @@
+use sqlx::{PgPool, postgres::PgPoolOptions};
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        Authentication, Email, RefreshToken, RefreshTokenRecord, RefreshTokenRow, RefreshTokenStore,
+    },
+    error::AuthApiError,
+};
+
+#[derive(Debug, Clone)]
+pub struct PostgresRefreshTokenStore {
+    pool: PgPool,
+}
+
+impl PostgresRefreshTokenStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
+    pub async fn new_opts(opts: PgPoolOptions, url: &str) -> Result<Self, AuthApiError> {
+        let pool = opts.connect(url).await.map_err(AuthApiError::Db)?;
+        Ok(Self { pool })
+    }
+}
+
+impl TryFrom<RefreshTokenRow> for RefreshTokenRecord {
+    type Error = AuthApiError;
+
+    fn try_from(row: RefreshTokenRow) -> Result<Self, Self::Error> {
+        Ok(RefreshTokenRecord {
+            token_hash: row.token_hash,
+            family_id: Uuid::parse_str(&row.family_id)
+                .map_err(|e| AuthApiError::InvalidData(format!("{e}")))?,
+            email: Email::parse(&row.email)?,
+            expires_at: row.expires_at.timestamp(),
+            used: row.used,
+            authentication: Authentication {
+                auth_time: row.auth_time.map(|t| t.timestamp()),
+                amr: Authentication::parse_amr(&row.amr),
+            },
+            client_id: row.client_id,
+        })
+    }
+}
+
+fn auth_time(record: &RefreshTokenRecord) -> Option<chrono::DateTime<chrono::Utc>> {
+    record
+        .authentication
+        .auth_time
+        .and_then(|t| chrono::DateTime::from_timestamp(t, 0))
+}
+
+fn expires_at(record: &RefreshTokenRecord) -> Result<chrono::DateTime<chrono::Utc>, AuthApiError> {
+    chrono::DateTime::from_timestamp(record.expires_at, 0)
+        .ok_or_else(|| AuthApiError::InvalidData("refresh token expiry".to_string()))
+}
+
+const INSERT_TOKEN: &str = r#"INSERT INTO "public"."refresh_token" (token_hash, family_id, email, expires_at, used, auth_time, amr, client_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8);"#;
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for PostgresRefreshTokenStore {
+    async fn add_token(&mut self, record: RefreshTokenRecord) -> Result<(), AuthApiError> {
+        // used tokens are kept until they expire, presenting one again is how reuse shows
+        sqlx::query(r#"DELETE FROM "public"."refresh_token" WHERE expires_at <= now();"#)
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        sqlx::query(INSERT_TOKEN)
+            .bind(&record.token_hash)
+            .bind(record.family_id.to_string())
+            .bind(record.email.as_ref())
+            .bind(expires_at(&record)?)
+            .bind(record.used)
+            .bind(auth_time(&record))
+            .bind(record.authentication.amr_string())
+            .bind(&record.client_id)
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn get_token(&self, token_hash: &str) -> Result<RefreshTokenRecord, AuthApiError> {
+        let row = sqlx::query_as::<_, RefreshTokenRow>(
+            r#"SELECT token_hash, family_id, email, expires_at, used, auth_time, amr, created_at, client_id FROM "public"."refresh_token" WHERE token_hash = $1 AND expires_at > now();"#,
+        )
+        .bind(token_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?
+        .ok_or(AuthApiError::InvalidToken)?;
+        row.try_into()
+    }
+
+    async fn rotate_token(
+        &mut self,
+        token_hash: &str,
+        next: &RefreshToken,
+        ttl: u64,
+    ) -> Result<RefreshTokenRecord, AuthApiError> {
+        let mut tx = self.pool.begin().await.map_err(AuthApiError::Db)?;
+        // the row lock serializes concurrent rotations of the same token
+        let current: RefreshTokenRecord = sqlx::query_as::<_, RefreshTokenRow>(
+            r#"SELECT token_hash, family_id, email, expires_at, used, auth_time, amr, created_at, client_id FROM "public"."refresh_token" WHERE token_hash = $1 AND expires_at > now() FOR UPDATE;"#,
+        )
+        .bind(token_hash)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(AuthApiError::Db)?
+        .ok_or(AuthApiError::InvalidToken)?
+        .try_into()?;
+
+        if current.used {
+            sqlx::query(r#"DELETE FROM "public"."refresh_token" WHERE family_id = $1;"#)
+                .bind(current.family_id.to_string())
+                .execute(&mut *tx)
+                .await
+                .map_err(AuthApiError::Db)?;
+            tx.commit().await.map_err(AuthApiError::Db)?;
+            return Err(AuthApiError::RefreshTokenReuse);
+        }
+
+        sqlx::query(r#"UPDATE "public"."refresh_token" SET used = true WHERE token_hash = $1;"#)
+            .bind(token_hash)
+            .execute(&mut *tx)
+            .await
+            .map_err(AuthApiError::Db)?;
+        let record = current.next_in_family(next, ttl);
+        sqlx::query(INSERT_TOKEN)
+            .bind(&record.token_hash)
+            .bind(record.family_id.to_string())
+            .bind(record.email.as_ref())
+            .bind(expires_at(&record)?)
+            .bind(record.used)
+            .bind(auth_time(&record))
+            .bind(record.authentication.amr_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(AuthApiError::Db)?;
+        tx.commit().await.map_err(AuthApiError::Db)?;
+        Ok(record)
+    }
+
+    async fn revoke_family(&mut self, family_id: &Uuid) -> Result<(), AuthApiError> {
+        sqlx::query(r#"DELETE FROM "public"."refresh_token" WHERE family_id = $1;"#)
+            .bind(family_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn revoke_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"DELETE FROM "public"."refresh_token" WHERE email = $1;"#)
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+}
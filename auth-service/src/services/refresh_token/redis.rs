@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use redis::Commands;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::{
+    config::RedisConfig,
+    domain::{
+        Email, RedisConnection, RefreshToken, RefreshTokenRecord, RefreshTokenStore, extend_expiry,
+        make_redis_key,
+    },
+    error::AuthApiError,
+};
+
+const REFRESH_TOKEN_PREFIX: &str = "refresh_token";
+const REFRESH_TOKEN_USED_PREFIX: &str = "refresh_token_used";
+const REFRESH_TOKEN_FAMILY_PREFIX: &str = "refresh_token_family";
+const REFRESH_TOKEN_USER_PREFIX: &str = "refresh_token_user";
+
+/// Records are stored as JSON with a matching TTL. The `used` flag lives in its own key
+/// so marking a token used can be a single `SET NX`, which makes concurrent rotations of
+/// the same token race safely: only one of them wins.
+#[derive(Clone, Debug)]
+pub struct RedisRefreshTokenStore {
+    conn: Arc<RwLock<RedisConnection>>,
+}
+
+impl RedisRefreshTokenStore {
+    pub fn new(config: &RedisConfig) -> Result<Self, AuthApiError> {
+        if config.host.is_none() {
+            return Err(AuthApiError::Config(
+                "Redis Host Not Configured".to_string(),
+            ));
+        }
+
+        let port = if let Some(p) = config.port.as_ref() {
+            format!(":{}", p)
+        } else {
+            "".to_string()
+        };
+
+        let client =
+            redis::Client::open(format!("redis://{}{}", &config.host.clone().unwrap(), port))
+                .map_err(AuthApiError::Redis)?;
+        let conn = RedisConnection(client.get_connection()?);
+        Ok(Self {
+            conn: Arc::new(RwLock::new(conn)),
+        })
+    }
+}
+
+fn remaining_ttl(record: &RefreshTokenRecord) -> u64 {
+    (record.expires_at - chrono::Utc::now().timestamp()).max(1) as u64
+}
+
+fn get_record(
+    conn: &mut redis::Connection,
+    token_hash: &str,
+) -> Result<RefreshTokenRecord, AuthApiError> {
+    let value = conn
+        .get::<_, Option<String>>(make_redis_key(REFRESH_TOKEN_PREFIX, token_hash))
+        .map_err(AuthApiError::Redis)?
+        .ok_or(AuthApiError::InvalidToken)?;
+    let mut record: RefreshTokenRecord = serde_json::from_str(&value)
+        .map_err(|e| AuthApiError::SerializationError(e.to_string()))?;
+    record.used = conn
+        .exists::<_, bool>(make_redis_key(REFRESH_TOKEN_USED_PREFIX, token_hash))
+        .map_err(AuthApiError::Redis)?;
+    if record.is_expired() {
+        return Err(AuthApiError::InvalidToken);
+    }
+    Ok(record)
+}
+
+fn revoke(conn: &mut redis::Connection, family_id: &Uuid) -> Result<(), AuthApiError> {
+    let family_key = make_redis_key(REFRESH_TOKEN_FAMILY_PREFIX, &family_id.to_string());
+    let hashes = conn
+        .smembers::<_, Vec<String>>(&family_key)
+        .map_err(AuthApiError::Redis)?;
+    let mut keys: Vec<String> = hashes
+        .iter()
+        .flat_map(|hash| {
+            [
+                make_redis_key(REFRESH_TOKEN_PREFIX, hash),
+                make_redis_key(REFRESH_TOKEN_USED_PREFIX, hash),
+            ]
+        })
+        .collect();
+    keys.push(family_key);
+    conn.del::<_, ()>(keys).map_err(AuthApiError::Redis)?;
+    Ok(())
+}
+
+#[async_trait::async_trait]
+impl RefreshTokenStore for RedisRefreshTokenStore {
+    async fn add_token(&mut self, record: RefreshTokenRecord) -> Result<(), AuthApiError> {
+        let ttl = remaining_ttl(&record);
+        let value = serde_json::to_string(&record)
+            .map_err(|e| AuthApiError::SerializationError(e.to_string()))?;
+        let family_key = make_redis_key(REFRESH_TOKEN_FAMILY_PREFIX, &record.family_id.to_string());
+        let user_key = make_redis_key(REFRESH_TOKEN_USER_PREFIX, record.email.as_ref());
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .set_ex(
+                make_redis_key(REFRESH_TOKEN_PREFIX, &record.token_hash),
+                value,
+                ttl,
+            )
+            .ignore()
+            .sadd(&family_key, &record.token_hash)
+            .ignore()
+            .sadd(&user_key, record.family_id.to_string())
+            .ignore();
+        extend_expiry(&mut pipe, &family_key, ttl);
+        extend_expiry(&mut pipe, &user_key, ttl);
+        let mut guard = self.conn.write().await;
+        pipe.query::<()>(&mut guard.0)
+            .map_err(AuthApiError::Redis)?;
+        Ok(())
+    }
+
+    async fn get_token(&self, token_hash: &str) -> Result<RefreshTokenRecord, AuthApiError> {
+        let mut guard = self.conn.write().await;
+        get_record(&mut guard.0, token_hash)
+    }
+
+    async fn rotate_token(
+        &mut self,
+        token_hash: &str,
+        next: &RefreshToken,
+        ttl: u64,
+    ) -> Result<RefreshTokenRecord, AuthApiError> {
+        let current = {
+            let mut guard = self.conn.write().await;
+            let current = get_record(&mut guard.0, token_hash)?;
+            let marked = redis::cmd("SET")
+                .arg(make_redis_key(REFRESH_TOKEN_USED_PREFIX, token_hash))
+                .arg(true)
+                .arg("NX")
+                .arg("EX")
+                .arg(remaining_ttl(&current))
+                .query::<Option<String>>(&mut guard.0)
+                .map_err(AuthApiError::Redis)?;
+            if marked.is_none() {
+                revoke(&mut guard.0, &current.family_id)?;
+                return Err(AuthApiError::RefreshTokenReuse);
+            }
+            current
+        };
+        let record = current.next_in_family(next, ttl);
+        self.add_token(record.clone()).await?;
+        Ok(record)
+    }
+
+    async fn revoke_family(&mut self, family_id: &Uuid) -> Result<(), AuthApiError> {
+        let mut guard = self.conn.write().await;
+        revoke(&mut guard.0, family_id)
+    }
+
+    async fn revoke_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        let user_key = make_redis_key(REFRESH_TOKEN_USER_PREFIX, email.as_ref());
+        let mut guard = self.conn.write().await;
+        let families = guard
+            .0
+            .smembers::<_, Vec<String>>(&user_key)
+            .map_err(AuthApiError::Redis)?;
+        for family_id in families {
+            if let Ok(family_id) = Uuid::parse_str(&family_id) {
+                revoke(&mut guard.0, &family_id)?;
+            }
+        }
+        guard
+            .0
+            .del::<_, ()>(&user_key)
+            .map_err(AuthApiError::Redis)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::domain::{Email, Role, RoleStore};
+use crate::error::AuthApiError;
+
+#[derive(Debug, Default)]
+pub struct InMemoryRoleStore {
+    /// Keyed by name, so listing comes out sorted
+    roles: BTreeMap<String, Role>,
+    assignments: HashMap<Email, BTreeSet<String>>,
+}
+
+#[async_trait::async_trait]
+impl RoleStore for InMemoryRoleStore {
+    async fn add_role(&mut self, role: Role) -> Result<(), AuthApiError> {
+        if self.roles.contains_key(&role.name) {
+            return Err(AuthApiError::InvalidData(
+                "A role with that name already exists".to_string(),
+            ));
+        }
+        self.roles.insert(role.name.clone(), role);
+        Ok(())
+    }
+
+    async fn get_role(&self, name: &str) -> Result<Role, AuthApiError> {
+        self.roles
+            .get(name)
+            .cloned()
+            .ok_or(AuthApiError::RoleNotFound)
+    }
+
+    async fn list_roles(&self) -> Result<Vec<Role>, AuthApiError> {
+        Ok(self.roles.values().cloned().collect())
+    }
+
+    async fn update_role(&mut self, role: &Role) -> Result<(), AuthApiError> {
+        let stored = self
+            .roles
+            .get_mut(&role.name)
+            .ok_or(AuthApiError::RoleNotFound)?;
+        *stored = role.clone();
+        Ok(())
+    }
+
+    async fn delete_role(&mut self, name: &str) -> Result<(), AuthApiError> {
+        self.roles.remove(name).ok_or(AuthApiError::RoleNotFound)?;
+        for roles in self.assignments.values_mut() {
+            roles.remove(name);
+        }
+        Ok(())
+    }
+
+    async fn assign_role(&mut self, email: &Email, name: &str) -> Result<(), AuthApiError> {
+        if !self.roles.contains_key(name) {
+            return Err(AuthApiError::RoleNotFound);
+        }
+        self.assignments
+            .entry(email.clone())
+            .or_default()
+            .insert(name.to_string());
+        Ok(())
+    }
+
+    async fn unassign_role(&mut self, email: &Email, name: &str) -> Result<(), AuthApiError> {
+        let removed = self
+            .assignments
+            .get_mut(email)
+            .is_some_and(|roles| roles.remove(name));
+        if !removed {
+            return Err(AuthApiError::RoleNotFound);
+        }
+        Ok(())
+    }
+
+    async fn user_roles(&self, email: &Email) -> Result<Vec<Role>, AuthApiError> {
+        Ok(self
+            .assignments
+            .get(email)
+            .into_iter()
+            .flatten()
+            .filter_map(|name| self.roles.get(name).cloned())
+            .collect())
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        if let Some(roles) = self.assignments.remove(old) {
+            self.assignments.insert(new.clone(), roles);
+        }
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        self.assignments.remove(email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_role_assignment() {
+        let mut store = InMemoryRoleStore::default();
+        let email = Email::parse("roles@test.com").unwrap();
+        let support = Role::parse("support", "", &["orders:read".to_string()]).unwrap();
+        store.add_role(support.clone()).await.unwrap();
+        assert!(store.add_role(support.clone()).await.is_err());
+        assert!(matches!(
+            store.assign_role(&email, "billing").await,
+            Err(AuthApiError::RoleNotFound)
+        ));
+
+        store.assign_role(&email, "support").await.unwrap();
+        store.assign_role(&email, "support").await.unwrap();
+        assert_eq!(store.user_roles(&email).await.unwrap(), vec![support]);
+
+        // deleting the role takes it away from its users
+        store.delete_role("support").await.unwrap();
+        assert!(store.user_roles(&email).await.unwrap().is_empty());
+        assert!(matches!(
+            store.unassign_role(&email, "support").await,
+            Err(AuthApiError::RoleNotFound)
+        ));
+    }
+}
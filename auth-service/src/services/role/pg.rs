@@ -0,0 +1,176 @@
+use sqlx::{PgPool, postgres::PgPoolOptions};
+
+use crate::{
+    domain::{Email, Role, RoleRow, RoleStore, parse_scopes},
+    error::AuthApiError,
+};
+
+#[derive(Debug, Clone)]
+pub struct PostgresRoleStore {
+    pool: PgPool,
+}
+
+impl PostgresRoleStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
+    pub async fn new_opts(opts: PgPoolOptions, url: &str) -> Result<Self, AuthApiError> {
+        let pool = opts.connect(url).await.map_err(AuthApiError::Db)?;
+        Ok(Self { pool })
+    }
+}
+
+impl From<RoleRow> for Role {
+    fn from(row: RoleRow) -> Self {
+        Role {
+            name: row.name,
+            description: row.description,
+            permissions: parse_scopes(&row.permissions),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RoleStore for PostgresRoleStore {
+    async fn add_role(&mut self, role: Role) -> Result<(), AuthApiError> {
+        sqlx::query(
+            r#"INSERT INTO "public"."role" (name, description, permissions) VALUES ($1, $2, $3);"#,
+        )
+        .bind(&role.name)
+        .bind(&role.description)
+        .bind(role.permissions.join(" "))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => {
+                AuthApiError::InvalidData("A role with that name already exists".to_string())
+            }
+            e => AuthApiError::Db(e),
+        })?;
+        Ok(())
+    }
+
+    async fn get_role(&self, name: &str) -> Result<Role, AuthApiError> {
+        let row = sqlx::query_as::<_, RoleRow>(
+            r#"SELECT name, description, permissions, created_at FROM "public"."role" WHERE name = $1;"#,
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?
+        .ok_or(AuthApiError::RoleNotFound)?;
+        Ok(row.into())
+    }
+
+    async fn list_roles(&self) -> Result<Vec<Role>, AuthApiError> {
+        let rows = sqlx::query_as::<_, RoleRow>(
+            r#"SELECT name, description, permissions, created_at FROM "public"."role" ORDER BY name;"#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        Ok(rows.into_iter().map(Role::from).collect())
+    }
+
+    async fn update_role(&mut self, role: &Role) -> Result<(), AuthApiError> {
+        let result = sqlx::query(
+            r#"UPDATE "public"."role" SET description = $2, permissions = $3 WHERE name = $1;"#,
+        )
+        .bind(&role.name)
+        .bind(&role.description)
+        .bind(role.permissions.join(" "))
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        if result.rows_affected() == 0 {
+            return Err(AuthApiError::RoleNotFound);
+        }
+        Ok(())
+    }
+
+    async fn delete_role(&mut self, name: &str) -> Result<(), AuthApiError> {
+        let mut tx = self.pool.begin().await.map_err(AuthApiError::Db)?;
+        sqlx::query(r#"DELETE FROM "public"."user_role" WHERE role_name = $1;"#)
+            .bind(name)
+            .execute(&mut *tx)
+            .await
+            .map_err(AuthApiError::Db)?;
+        let result = sqlx::query(r#"DELETE FROM "public"."role" WHERE name = $1;"#)
+            .bind(name)
+            .execute(&mut *tx)
+            .await
+            .map_err(AuthApiError::Db)?;
+        if result.rows_affected() == 0 {
+            return Err(AuthApiError::RoleNotFound);
+        }
+        tx.commit().await.map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn assign_role(&mut self, email: &Email, name: &str) -> Result<(), AuthApiError> {
+        // the role check and the insert are one statement so a role can't be deleted in between
+        sqlx::query(
+            r#"
+            INSERT INTO "public"."user_role" (email, role_name)
+            SELECT $1, name FROM "public"."role" WHERE name = $2
+            ON CONFLICT (email, role_name) DO NOTHING;
+            "#,
+        )
+        .bind(email.as_ref())
+        .bind(name)
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        // nothing inserted either because it was assigned already or the role is missing
+        self.get_role(name).await.map(|_| ())
+    }
+
+    async fn unassign_role(&mut self, email: &Email, name: &str) -> Result<(), AuthApiError> {
+        let result =
+            sqlx::query(r#"DELETE FROM "public"."user_role" WHERE email = $1 AND role_name = $2;"#)
+                .bind(email.as_ref())
+                .bind(name)
+                .execute(&self.pool)
+                .await
+                .map_err(AuthApiError::Db)?;
+        if result.rows_affected() == 0 {
+            return Err(AuthApiError::RoleNotFound);
+        }
+        Ok(())
+    }
+
+    async fn user_roles(&self, email: &Email) -> Result<Vec<Role>, AuthApiError> {
+        let rows = sqlx::query_as::<_, RoleRow>(
+            r#"
+            SELECT r.name, r.description, r.permissions, r.created_at
+            FROM "public"."role" r JOIN "public"."user_role" ur ON ur.role_name = r.name
+            WHERE ur.email = $1 ORDER BY r.name;
+            "#,
+        )
+        .bind(email.as_ref())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        Ok(rows.into_iter().map(Role::from).collect())
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"UPDATE "public"."user_role" SET email = $2 WHERE email = $1;"#)
+            .bind(old.as_ref())
+            .bind(new.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"DELETE FROM "public"."user_role" WHERE email = $1;"#)
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use uuid::Uuid;
+
+use crate::domain::{Email, Session, SessionStore};
+use crate::error::AuthApiError;
+
+#[derive(Debug, Default)]
+pub struct InMemorySessionStore {
+    sessions: HashMap<Uuid, Session>,
+}
+
+#[async_trait::async_trait]
+impl SessionStore for InMemorySessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), AuthApiError> {
+        self.sessions.retain(|_, s| !s.is_expired());
+        self.sessions.insert(session.id, session);
+        Ok(())
+    }
+
+    async fn get_session(&self, id: &Uuid) -> Result<Session, AuthApiError> {
+        self.sessions
+            .get(id)
+            .filter(|s| !s.is_expired())
+            .cloned()
+            .ok_or(AuthApiError::InvalidToken)
+    }
+
+    async fn list_sessions(&self, email: &Email) -> Result<Vec<Session>, AuthApiError> {
+        let mut sessions: Vec<_> = self
+            .sessions
+            .values()
+            .filter(|s| s.email == *email && !s.is_expired())
+            .cloned()
+            .collect();
+        sessions.sort_by_key(|s| s.created_at);
+        Ok(sessions)
+    }
+
+    async fn touch_session(
+        &mut self,
+        id: &Uuid,
+        last_seen_at: i64,
+        expires_at: Option<i64>,
+    ) -> Result<(), AuthApiError> {
+        let session = self
+            .sessions
+            .get_mut(id)
+            .filter(|s| !s.is_expired())
+            .ok_or(AuthApiError::InvalidToken)?;
+        session.last_seen_at = last_seen_at;
+        if let Some(expires_at) = expires_at {
+            session.expires_at = expires_at;
+        }
+        Ok(())
+    }
+
+    async fn set_active_org(
+        &mut self,
+        id: &Uuid,
+        org_id: Option<Uuid>,
+    ) -> Result<(), AuthApiError> {
+        let session = self
+            .sessions
+            .get_mut(id)
+            .filter(|s| !s.is_expired())
+            .ok_or(AuthApiError::InvalidToken)?;
+        session.org_id = org_id;
+        Ok(())
+    }
+
+    async fn revoke_session(&mut self, email: &Email, id: &Uuid) -> Result<(), AuthApiError> {
+        match self.sessions.get(id) {
+            Some(session) if session.email == *email => {
+                self.sessions.remove(id);
+                Ok(())
+            }
+            _ => Err(AuthApiError::SessionNotFound),
+        }
+    }
+
+    async fn revoke_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        self.sessions.retain(|_, s| s.email != *email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::ClientInfo;
+
+    #[tokio::test]
+    async fn test_session_lifecycle() {
+        let mut store = InMemorySessionStore::default();
+        let email = Email::parse("sessions@test.com").unwrap();
+        let other = Email::parse("other-sessions@test.com").unwrap();
+        let session = Session::new(Uuid::new_v4(), &email, &ClientInfo::default(), 3600);
+        store.add_session(session.clone()).await.unwrap();
+        let theirs = Session::new(Uuid::new_v4(), &other, &ClientInfo::default(), 3600);
+        store.add_session(theirs.clone()).await.unwrap();
+
+        store
+            .touch_session(&session.id, session.last_seen_at + 90, None)
+            .await
+            .unwrap();
+        let touched = store.get_session(&session.id).await.unwrap();
+        assert_eq!(touched.last_seen_at, session.last_seen_at + 90);
+        assert_eq!(touched.expires_at, session.expires_at);
+        assert_eq!(store.list_sessions(&email).await.unwrap(), vec![touched]);
+
+        // only the owner can revoke
+        assert!(store.revoke_session(&other, &session.id).await.is_err());
+        store.revoke_session(&email, &session.id).await.unwrap();
+        assert!(store.get_session(&session.id).await.is_err());
+        assert!(store.touch_session(&session.id, 0, None).await.is_err());
+
+        store.revoke_user(&other).await.unwrap();
+        assert!(store.list_sessions(&other).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expired_session_is_gone() {
+        let mut store = InMemorySessionStore::default();
+        let email = Email::parse("expired-session@test.com").unwrap();
+        let session = Session::new(Uuid::new_v4(), &email, &ClientInfo::default(), 3600);
+        store
+            .add_session(Session {
+                expires_at: session.created_at - 1,
+                ..session.clone()
+            })
+            .await
+            .unwrap();
+        assert!(store.get_session(&session.id).await.is_err());
+        assert!(store.list_sessions(&email).await.unwrap().is_empty());
+    }
+}
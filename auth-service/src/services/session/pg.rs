@@ -0,0 +1,164 @@
+use sqlx::{PgPool, postgres::PgPoolOptions};
+use uuid::Uuid;
+
+use crate::{
+    domain::{Email, Session, SessionRow, SessionStore},
+    error::AuthApiError,
+};
+
+#[derive(Debug, Clone)]
+pub struct PostgresSessionStore {
+    pool: PgPool,
+}
+
+impl PostgresSessionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
+    pub async fn new_opts(opts: PgPoolOptions, url: &str) -> Result<Self, AuthApiError> {
+        let pool = opts.connect(url).await.map_err(AuthApiError::Db)?;
+        Ok(Self { pool })
+    }
+}
+
+impl TryFrom<SessionRow> for Session {
+    type Error = AuthApiError;
+
+    fn try_from(row: SessionRow) -> Result<Self, Self::Error> {
+        Ok(Session {
+            id: Uuid::parse_str(&row.id).map_err(|e| AuthApiError::InvalidData(format!("{e}")))?,
+            email: Email::parse(&row.email)?,
+            created_at: row.created_at.timestamp(),
+            last_seen_at: row.last_seen_at.timestamp(),
+            expires_at: row.expires_at.timestamp(),
+            ip: row.ip,
+            user_agent: row.user_agent,
+            org_id: row
+                .org_id
+                .map(|id| Uuid::parse_str(&id))
+                .transpose()
+                .map_err(|e| AuthApiError::InvalidData(format!("{e}")))?,
+        })
+    }
+}
+
+fn timestamp(t: i64) -> Result<chrono::DateTime<chrono::Utc>, AuthApiError> {
+    chrono::DateTime::from_timestamp(t, 0)
+        .ok_or_else(|| AuthApiError::InvalidData("Session timestamp".to_string()))
+}
+
+#[async_trait::async_trait]
+impl SessionStore for PostgresSessionStore {
+    async fn add_session(&mut self, session: Session) -> Result<(), AuthApiError> {
+        // logins that were never logged out of would otherwise pile up
+        sqlx::query(r#"DELETE FROM "public"."session" WHERE email = $1 AND expires_at <= now();"#)
+            .bind(session.email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        sqlx::query(
+            r#"
+            INSERT INTO "public"."session" (id, email, ip, user_agent, created_at, last_seen_at, expires_at, org_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8);
+            "#,
+        )
+        .bind(session.id.to_string())
+        .bind(session.email.as_ref())
+        .bind(&session.ip)
+        .bind(&session.user_agent)
+        .bind(timestamp(session.created_at)?)
+        .bind(timestamp(session.last_seen_at)?)
+        .bind(timestamp(session.expires_at)?)
+        .bind(session.org_id.map(|id| id.to_string()))
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn get_session(&self, id: &Uuid) -> Result<Session, AuthApiError> {
+        let row = sqlx::query_as::<_, SessionRow>(
+            r#"SELECT id, email, ip, user_agent, created_at, last_seen_at, expires_at, org_id FROM "public"."session" WHERE id = $1 AND expires_at > now();"#,
+        )
+        .bind(id.to_string())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?
+        .ok_or(AuthApiError::InvalidToken)?;
+        row.try_into()
+    }
+
+    async fn list_sessions(&self, email: &Email) -> Result<Vec<Session>, AuthApiError> {
+        let rows = sqlx::query_as::<_, SessionRow>(
+            r#"SELECT id, email, ip, user_agent, created_at, last_seen_at, expires_at, org_id FROM "public"."session" WHERE email = $1 AND expires_at > now() ORDER BY created_at;"#,
+        )
+        .bind(email.as_ref())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        rows.into_iter().map(Session::try_from).collect()
+    }
+
+    async fn touch_session(
+        &mut self,
+        id: &Uuid,
+        last_seen_at: i64,
+        expires_at: Option<i64>,
+    ) -> Result<(), AuthApiError> {
+        let result = sqlx::query(
+            r#"UPDATE "public"."session" SET last_seen_at = $2, expires_at = COALESCE($3, expires_at) WHERE id = $1 AND expires_at > now();"#,
+        )
+        .bind(id.to_string())
+        .bind(timestamp(last_seen_at)?)
+        .bind(expires_at.map(timestamp).transpose()?)
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        if result.rows_affected() == 0 {
+            return Err(AuthApiError::InvalidToken);
+        }
+        Ok(())
+    }
+
+    async fn set_active_org(
+        &mut self,
+        id: &Uuid,
+        org_id: Option<Uuid>,
+    ) -> Result<(), AuthApiError> {
+        let result = sqlx::query(
+            r#"UPDATE "public"."session" SET org_id = $2 WHERE id = $1 AND expires_at > now();"#,
+        )
+        .bind(id.to_string())
+        .bind(org_id.map(|id| id.to_string()))
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        if result.rows_affected() == 0 {
+            return Err(AuthApiError::InvalidToken);
+        }
+        Ok(())
+    }
+
+    async fn revoke_session(&mut self, email: &Email, id: &Uuid) -> Result<(), AuthApiError> {
+        let result = sqlx::query(r#"DELETE FROM "public"."session" WHERE email = $1 AND id = $2;"#)
+            .bind(email.as_ref())
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        if result.rows_affected() == 0 {
+            return Err(AuthApiError::SessionNotFound);
+        }
+        Ok(())
+    }
+
+    async fn revoke_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"DELETE FROM "public"."session" WHERE email = $1;"#)
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use crate::domain::{Email, TotpSecret, TotpStore};
+use crate::error::AuthApiError;
+
+#[derive(Debug, Default, Clone)]
+struct TotpEntry {
+    secret: Option<TotpSecret>,
+    pending_secret: Option<TotpSecret>,
+    last_step: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+pub struct InMemoryTotpStore {
+    entries: HashMap<Email, TotpEntry>,
+}
+
+#[async_trait::async_trait]
+impl TotpStore for InMemoryTotpStore {
+    async fn set_pending_secret(
+        &mut self,
+        email: &Email,
+        secret: &TotpSecret,
+    ) -> Result<(), AuthApiError> {
+        self.entries
+            .entry(email.clone())
+            .or_default()
+            .pending_secret = Some(secret.clone());
+        Ok(())
+    }
+
+    async fn get_pending_secret(&self, email: &Email) -> Result<TotpSecret, AuthApiError> {
+        self.entries
+            .get(email)
+            .and_then(|e| e.pending_secret.clone())
+            .ok_or(AuthApiError::TwoFactorCodeNotFound)
+    }
+
+    async fn activate_secret(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        let entry = self
+            .entries
+            .get_mut(email)
+            .ok_or(AuthApiError::TwoFactorCodeNotFound)?;
+        let pending = entry
+            .pending_secret
+            .take()
+            .ok_or(AuthApiError::TwoFactorCodeNotFound)?;
+        entry.secret = Some(pending);
+        entry.last_step = None;
+        Ok(())
+    }
+
+    async fn get_secret(&self, email: &Email) -> Result<TotpSecret, AuthApiError> {
+        self.entries
+            .get(email)
+            .and_then(|e| e.secret.clone())
+            .ok_or(AuthApiError::TwoFactorCodeNotFound)
+    }
+
+    async fn use_step(&mut self, email: &Email, step: u64) -> Result<(), AuthApiError> {
+        let entry = self
+            .entries
+            .get_mut(email)
+            .ok_or(AuthApiError::TwoFactorCodeNotFound)?;
+        if entry.last_step.is_some_and(|last| step <= last) {
+            return Err(AuthApiError::TwoFactorCodeMismatch);
+        }
+        entry.last_step = Some(step);
+        Ok(())
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        if let Some(entry) = self.entries.remove(old) {
+            self.entries.insert(new.clone(), entry);
+        }
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        self.entries.remove(email);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_totp_pending_until_activated() {
+        let mut store = InMemoryTotpStore::default();
+        let email = Email::parse("user@test.com").expect("valid email");
+        let secret = TotpSecret::generate();
+        store
+            .set_pending_secret(&email, &secret)
+            .await
+            .expect("set pending");
+        assert!(store.get_secret(&email).await.is_err());
+
+        store.activate_secret(&email).await.expect("activate");
+        assert_eq!(store.get_secret(&email).await.unwrap(), secret);
+        assert!(store.get_pending_secret(&email).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_totp_step_not_reused() {
+        let mut store = InMemoryTotpStore::default();
+        let email = Email::parse("user@test.com").expect("valid email");
+        store
+            .set_pending_secret(&email, &TotpSecret::generate())
+            .await
+            .expect("set pending");
+        store.activate_secret(&email).await.expect("activate");
+
+        store.use_step(&email, 10).await.expect("first use");
+        let replay = store.use_step(&email, 10).await;
+        assert!(matches!(replay, Err(AuthApiError::TwoFactorCodeMismatch)));
+        let older = store.use_step(&email, 9).await;
+        assert!(matches!(older, Err(AuthApiError::TwoFactorCodeMismatch)));
+        assert!(store.use_step(&email, 11).await.is_ok());
+    }
+}
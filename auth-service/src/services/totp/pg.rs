@@ -0,0 +1,132 @@
+use sqlx::{PgPool, postgres::PgPoolOptions};
+
+use crate::{
+    domain::{Email, TotpRow, TotpSecret, TotpStore},
+    error::AuthApiError,
+};
+
+#[derive(Debug, Clone)]
+pub struct PostgresTotpStore {
+    pool: PgPool,
+}
+
+impl PostgresTotpStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
+    pub async fn new_opts(opts: PgPoolOptions, url: &str) -> Result<Self, AuthApiError> {
+        let pool = opts.connect(url).await.map_err(AuthApiError::Db)?;
+        Ok(Self { pool })
+    }
+
+    async fn get_row(&self, email: &Email) -> Result<TotpRow, AuthApiError> {
+        sqlx::query_as::<_, TotpRow>(
+            r#"SELECT email, secret, pending_secret, last_step FROM "public"."totp" WHERE email = $1;"#,
+        )
+        .bind(email.as_ref())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?
+        .ok_or(AuthApiError::TwoFactorCodeNotFound)
+    }
+}
+
+#[async_trait::async_trait]
+impl TotpStore for PostgresTotpStore {
+    async fn set_pending_secret(
+        &mut self,
+        email: &Email,
+        secret: &TotpSecret,
+    ) -> Result<(), AuthApiError> {
+        sqlx::query(
+            r#"
+        INSERT INTO "public"."totp" (email, pending_secret)
+        VALUES ($1, $2)
+        ON CONFLICT (email) DO UPDATE SET pending_secret = EXCLUDED.pending_secret;
+        "#,
+        )
+        .bind(email.as_ref())
+        .bind(secret.as_ref())
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn get_pending_secret(&self, email: &Email) -> Result<TotpSecret, AuthApiError> {
+        let secret = self
+            .get_row(email)
+            .await?
+            .pending_secret
+            .ok_or(AuthApiError::TwoFactorCodeNotFound)?;
+        TotpSecret::parse(&secret)
+    }
+
+    async fn activate_secret(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        let result = sqlx::query(
+            r#"
+        UPDATE "public"."totp"
+        SET secret = pending_secret, pending_secret = NULL, last_step = NULL
+        WHERE email = $1 AND pending_secret IS NOT NULL;
+        "#,
+        )
+        .bind(email.as_ref())
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+
+        if result.rows_affected() != 1 {
+            return Err(AuthApiError::TwoFactorCodeNotFound);
+        }
+        Ok(())
+    }
+
+    async fn get_secret(&self, email: &Email) -> Result<TotpSecret, AuthApiError> {
+        let secret = self
+            .get_row(email)
+            .await?
+            .secret
+            .ok_or(AuthApiError::TwoFactorCodeNotFound)?;
+        TotpSecret::parse(&secret)
+    }
+
+    async fn use_step(&mut self, email: &Email, step: u64) -> Result<(), AuthApiError> {
+        // compare-and-set so two requests racing with the same code can't both win
+        let result = sqlx::query(
+            r#"
+        UPDATE "public"."totp" SET last_step = $2
+        WHERE email = $1 AND (last_step IS NULL OR last_step < $2);
+        "#,
+        )
+        .bind(email.as_ref())
+        .bind(step as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+
+        if result.rows_affected() != 1 {
+            return Err(AuthApiError::TwoFactorCodeMismatch);
+        }
+        Ok(())
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"UPDATE "public"."totp" SET email = $2 WHERE email = $1;"#)
+            .bind(old.as_ref())
+            .bind(new.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        sqlx::query(r#"DELETE FROM "public"."totp" WHERE email = $1;"#)
+            .bind(email.as_ref())
+            .execute(&self.pool)
+            .await
+            .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+}
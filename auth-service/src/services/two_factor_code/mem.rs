@@ -3,9 +3,49 @@ use std::collections::HashMap;
 use crate::domain::{Email, LoginAttemptId, TwoFactorCode, TwoFactorCodeStore, TwoFactorMethod};
 use crate::error::AuthApiError;
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone)]
+struct Attempt {
+    id: LoginAttemptId,
+    code: TwoFactorCode,
+    failures: u32,
+    /// Unix timestamp
+    expires_at: i64,
+}
+
+impl Attempt {
+    fn is_expired(&self) -> bool {
+        self.expires_at <= chrono::Utc::now().timestamp()
+    }
+}
+
+/// Attempts expire `ttl` seconds after the login started, like the other backends
+#[derive(Debug)]
 pub struct InMemoryTwoFactorCodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFactorCode)>,
+    codes: HashMap<Email, Attempt>,
+    ttl: u64,
+}
+
+impl InMemoryTwoFactorCodeStore {
+    pub fn new(ttl: u64) -> Self {
+        Self {
+            codes: HashMap::new(),
+            ttl,
+        }
+    }
+
+    /// The attempt `attempt_id` of `email`, `None` if another one is pending
+    fn attempt(
+        &mut self,
+        email: &Email,
+        attempt_id: &LoginAttemptId,
+    ) -> Result<Option<&mut Attempt>, AuthApiError> {
+        let attempt = self
+            .codes
+            .get_mut(email)
+            .filter(|attempt| !attempt.is_expired())
+            .ok_or(AuthApiError::TwoFactorCodeNotFound)?;
+        Ok((attempt.id == *attempt_id).then_some(attempt))
+    }
 }
 
 #[async_trait::async_trait]
@@ -17,7 +57,14 @@ impl TwoFactorCodeStore for InMemoryTwoFactorCodeStore {
     ) -> Result<(LoginAttemptId, TwoFactorCode), AuthApiError> {
         let code = TwoFactorCode::new();
         let id = LoginAttemptId::new();
-        self.codes.insert(email.clone(), (id.clone(), code.clone()));
+        self.codes.retain(|_, attempt| !attempt.is_expired());
+        let attempt = Attempt {
+            id: id.clone(),
+            code: code.clone(),
+            failures: 0,
+            expires_at: chrono::Utc::now().timestamp() + self.ttl as i64,
+        };
+        self.codes.insert(email.clone(), attempt);
         Ok((id, code))
     }
 
@@ -27,7 +74,8 @@ impl TwoFactorCodeStore for InMemoryTwoFactorCodeStore {
     ) -> Result<(LoginAttemptId, TwoFactorCode), AuthApiError> {
         self.codes
             .get(email)
-            .cloned()
+            .filter(|attempt| !attempt.is_expired())
+            .map(|attempt| (attempt.id.clone(), attempt.code.clone()))
             .ok_or(AuthApiError::TwoFactorCodeNotFound)
     }
 
@@ -37,6 +85,51 @@ impl TwoFactorCodeStore for InMemoryTwoFactorCodeStore {
             .map(|_| ())
             .ok_or(AuthApiError::TwoFactorCodeNotFound)
     }
+
+    async fn verify_code(
+        &mut self,
+        email: &Email,
+        attempt_id: &LoginAttemptId,
+        code: &TwoFactorCode,
+        max_attempts: u32,
+    ) -> Result<bool, AuthApiError> {
+        let Some(attempt) = self.attempt(email, attempt_id)? else {
+            return Ok(false);
+        };
+        if attempt.code == *code {
+            self.codes.remove(email);
+            return Ok(true);
+        }
+        self.record_failure(email, attempt_id, max_attempts).await?;
+        Ok(false)
+    }
+
+    async fn record_failure(
+        &mut self,
+        email: &Email,
+        attempt_id: &LoginAttemptId,
+        max_attempts: u32,
+    ) -> Result<(), AuthApiError> {
+        if let Some(attempt) = self.attempt(email, attempt_id)? {
+            attempt.failures += 1;
+            if attempt.failures >= max_attempts {
+                self.codes.remove(email);
+            }
+        }
+        Ok(())
+    }
+
+    async fn consume_attempt(
+        &mut self,
+        email: &Email,
+        attempt_id: &LoginAttemptId,
+    ) -> Result<(), AuthApiError> {
+        if self.attempt(email, attempt_id)?.is_none() {
+            return Err(AuthApiError::TwoFactorCodeNotFound);
+        }
+        self.codes.remove(email);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -46,7 +139,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_2fa_store_create_and_get_code() {
-        let mut store = InMemoryTwoFactorCodeStore::default();
+        let mut store = InMemoryTwoFactorCodeStore::new(600);
         let email = Email::parse("user@test.com").expect("valid email");
         let (login_attempt_id, _code) = store
             .new_login_attempt(&email, &TwoFactorMethod::Email)
@@ -58,7 +151,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_2fa_store_remove_code() {
-        let mut store = InMemoryTwoFactorCodeStore::default();
+        let mut store = InMemoryTwoFactorCodeStore::new(600);
         let email = Email::parse("user@test.com").expect("valid email");
         let (_login_attempt_id, _code) = store
             .new_login_attempt(&email, &TwoFactorMethod::Email)
@@ -71,46 +164,130 @@ mod tests {
 
     #[tokio::test]
     async fn test_2fa_store_verify_code() {
-        let mut store = InMemoryTwoFactorCodeStore::default();
+        let mut store = InMemoryTwoFactorCodeStore::new(600);
         let email = Email::parse("user@test.com").expect("valid email");
         let (login_attempt_id, code) = store
             .new_login_attempt(&email, &TwoFactorMethod::Email)
             .await
             .expect("create login attempt");
         let res = store
-            .verify_code(&email, &login_attempt_id, &code)
+            .verify_code(&email, &login_attempt_id, &code, 3)
             .await
             .expect("verify code");
         assert!(res);
+
+        // the code only works once
+        let res = store.verify_code(&email, &login_attempt_id, &code, 3).await;
+        assert!(matches!(res, Err(AuthApiError::TwoFactorCodeNotFound)));
     }
 
     #[tokio::test]
     async fn test_2fa_store_verify_code_fails() {
-        let mut store = InMemoryTwoFactorCodeStore::default();
+        let mut store = InMemoryTwoFactorCodeStore::new(600);
         let email = Email::parse("user@test.com").expect("valid email");
         let (login_attempt_id, code) = store
             .new_login_attempt(&email, &TwoFactorMethod::Email)
             .await
             .expect("create login attempt");
 
-        let wrong_code = TwoFactorCode::new();
+        let wrong = wrong_code(&code);
         let res = store
-            .verify_code(&email, &login_attempt_id, &wrong_code)
+            .verify_code(&email, &login_attempt_id, &wrong, 3)
             .await
             .expect("verify code");
         assert!(!res);
 
         let wrong_id = LoginAttemptId::new();
         let res = store
-            .verify_code(&email, &wrong_id, &code)
+            .verify_code(&email, &wrong_id, &code, 3)
             .await
             .expect("verify code");
         assert!(!res);
 
         let wrong_email = Email::parse("other@test.com").expect("valid email");
         let res = store
-            .verify_code(&wrong_email, &login_attempt_id, &code)
+            .verify_code(&wrong_email, &login_attempt_id, &code, 3)
             .await;
         assert!(matches!(res, Err(AuthApiError::TwoFactorCodeNotFound)));
     }
+
+    #[tokio::test]
+    async fn test_2fa_store_drops_attempt_after_max_failures() {
+        let mut store = InMemoryTwoFactorCodeStore::new(600);
+        let email = Email::parse("user@test.com").expect("valid email");
+        let (login_attempt_id, code) = store
+            .new_login_attempt(&email, &TwoFactorMethod::Email)
+            .await
+            .expect("create login attempt");
+
+        let wrong = wrong_code(&code);
+        store
+            .verify_code(&email, &login_attempt_id, &wrong, 3)
+            .await
+            .expect("verify code");
+        // guesses for another attempt don't count
+        for _ in 0..3 {
+            store
+                .record_failure(&email, &LoginAttemptId::new(), 3)
+                .await
+                .expect("record failure");
+        }
+        store
+            .record_failure(&email, &login_attempt_id, 3)
+            .await
+            .expect("record failure");
+        assert!(store.get_code(&email).await.is_ok());
+        store
+            .verify_code(&email, &login_attempt_id, &wrong, 3)
+            .await
+            .expect("verify code");
+
+        // even the right code is too late now
+        let res = store.verify_code(&email, &login_attempt_id, &code, 3).await;
+        assert!(matches!(res, Err(AuthApiError::TwoFactorCodeNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_2fa_store_consume_attempt() {
+        let mut store = InMemoryTwoFactorCodeStore::new(600);
+        let email = Email::parse("user@test.com").expect("valid email");
+        let (login_attempt_id, _code) = store
+            .new_login_attempt(&email, &TwoFactorMethod::Totp)
+            .await
+            .expect("create login attempt");
+
+        let res = store.consume_attempt(&email, &LoginAttemptId::new()).await;
+        assert!(matches!(res, Err(AuthApiError::TwoFactorCodeNotFound)));
+        store
+            .consume_attempt(&email, &login_attempt_id)
+            .await
+            .expect("consume attempt");
+        let res = store.consume_attempt(&email, &login_attempt_id).await;
+        assert!(matches!(res, Err(AuthApiError::TwoFactorCodeNotFound)));
+    }
+
+    /// Any code but `code`
+    fn wrong_code(code: &TwoFactorCode) -> TwoFactorCode {
+        let wrong = if code.as_ref() == "000000" {
+            "111111"
+        } else {
+            "000000"
+        };
+        TwoFactorCode::try_from(wrong).expect("valid code")
+    }
+
+    #[tokio::test]
+    async fn test_2fa_store_refuses_expired_code() {
+        let mut store = InMemoryTwoFactorCodeStore::new(0);
+        let email = Email::parse("user@test.com").expect("valid email");
+        let (login_attempt_id, code) = store
+            .new_login_attempt(&email, &TwoFactorMethod::Email)
+            .await
+            .expect("create login attempt");
+
+        let res = store.get_code(&email).await;
+        assert!(matches!(res, Err(AuthApiError::TwoFactorCodeNotFound)));
+        let res = store.verify_code(&email, &login_attempt_id, &code, 3).await;
+        assert!(matches!(res, Err(AuthApiError::TwoFactorCodeNotFound)));
+    }
 }
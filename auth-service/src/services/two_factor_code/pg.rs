@@ -1,23 +1,42 @@
-use sqlx::{PgPool, Row, postgres::PgPoolOptions};
+use sqlx::{PgPool, postgres::PgPoolOptions};
 
 use crate::{
-    domain::{Email, LoginAttemptId, TwoFactorCode, TwoFactorCodeStore, TwoFactorMethod},
+    domain::{
+        Email, LoginAttemptId, TwoFactorCode, TwoFactorCodeStore, TwoFactorMethod, TwoFactorRow,
+    },
     error::AuthApiError,
 };
 
+/// Every check is a single statement, so concurrent guesses are all counted and only
+/// one request can delete the row of a matching code. Rows older than `ttl` seconds
+/// are ignored and swept when the next login starts.
 #[derive(Debug, Clone)]
 pub struct PostgresTwoFactorStore {
     pool: PgPool,
+    ttl: u64,
 }
 
 impl PostgresTwoFactorStore {
-    pub fn new(pool: PgPool) -> Self {
-        Self { pool: pool.clone() }
+    pub fn new(pool: PgPool, ttl: u64) -> Self {
+        Self {
+            pool: pool.clone(),
+            ttl,
+        }
     }
 
-    pub async fn new_opts(opts: PgPoolOptions, url: &str) -> Result<Self, AuthApiError> {
+    pub async fn new_opts(opts: PgPoolOptions, url: &str, ttl: u64) -> Result<Self, AuthApiError> {
         let pool = opts.connect(url).await.map_err(AuthApiError::Db)?;
-        Ok(Self { pool })
+        Ok(Self { pool, ttl })
+    }
+
+    /// Bound as the `secs` of `make_interval`
+    fn ttl_secs(&self) -> f64 {
+        self.ttl as f64
+    }
+
+    /// Fails with `TwoFactorCodeNotFound` if no attempt is pending for `email`
+    async fn ensure_pending(&self, email: &Email) -> Result<(), AuthApiError> {
+        self.get_code(email).await.map(|_| ())
     }
 }
 
@@ -30,13 +49,28 @@ impl TwoFactorCodeStore for PostgresTwoFactorStore {
     ) -> Result<(LoginAttemptId, TwoFactorCode), AuthApiError> {
         let code = TwoFactorCode::new();
         let id = LoginAttemptId::new();
-        let added = sqlx::query(r#"INSERT INTO two_factor (email, id, code) VALUES ($1, $2, $3);"#)
-            .bind(email.as_ref())
-            .bind(id.as_ref().to_string())
-            .bind(code.as_ref())
-            .execute(&self.pool)
-            .await
-            .map_err(AuthApiError::Db)?;
+        sqlx::query(
+            r#"
+        DELETE FROM two_factor WHERE created_at <= now() - make_interval(secs => $1);
+        "#,
+        )
+        .bind(self.ttl_secs())
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        let added = sqlx::query(
+            r#"
+        INSERT INTO two_factor (email, id, code) VALUES ($1, $2, $3)
+        ON CONFLICT (email) DO UPDATE
+        SET id = EXCLUDED.id, code = EXCLUDED.code, failures = 0, created_at = now();
+        "#,
+        )
+        .bind(email.as_ref())
+        .bind(id.as_ref().to_string())
+        .bind(code.as_ref())
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
 
         if added.rows_affected() != 1 {
             return Err(AuthApiError::TwoFactorCodeGenFailedToSave);
@@ -49,32 +83,123 @@ impl TwoFactorCodeStore for PostgresTwoFactorStore {
         &self,
         email: &Email,
     ) -> Result<(LoginAttemptId, TwoFactorCode), AuthApiError> {
-        let result = sqlx::query(
+        let row = sqlx::query_as::<_, TwoFactorRow>(
             r#"
-        SELECT email, id, two_factor_code FROM two_factor WHERE email = $1;
+        SELECT * FROM two_factor
+        WHERE email = $1 AND created_at > now() - make_interval(secs => $2);
         "#,
         )
         .bind(email.as_ref())
-        .fetch_one(&self.pool)
+        .bind(self.ttl_secs())
+        .fetch_optional(&self.pool)
         .await
-        .map_err(AuthApiError::Db)?;
+        .map_err(AuthApiError::Db)?
+        .ok_or(AuthApiError::TwoFactorCodeNotFound)?;
 
-        let code = TwoFactorCode::try_from(result.get::<String, _>("code"))?;
-        let id = LoginAttemptId::try_from(result.get::<String, _>("id"))?;
+        let code = TwoFactorCode::try_from(row.code)?;
+        let id = LoginAttemptId::try_from(row.id)?;
 
         Ok((id, code))
     }
 
     async fn remove_code(&mut self, email: &Email) -> Result<(), AuthApiError> {
-        let _ = sqlx::query(
+        sqlx::query(
             r#"
         DELETE FROM two_factor WHERE email = $1;
         "#,
         )
         .bind(email.as_ref())
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        Ok(())
+    }
+
+    async fn verify_code(
+        &mut self,
+        email: &Email,
+        attempt_id: &LoginAttemptId,
+        code: &TwoFactorCode,
+        max_attempts: u32,
+    ) -> Result<bool, AuthApiError> {
+        let matched = sqlx::query(
+            r#"
+        DELETE FROM two_factor
+        WHERE email = $1 AND id = $2 AND code = $3
+            AND created_at > now() - make_interval(secs => $4);
+        "#,
+        )
+        .bind(email.as_ref())
+        .bind(attempt_id.as_ref().to_string())
+        .bind(code.as_ref())
+        .bind(self.ttl_secs())
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        if matched.rows_affected() == 1 {
+            return Ok(true);
+        }
+        self.record_failure(email, attempt_id, max_attempts).await?;
+        Ok(false)
+    }
+
+    async fn record_failure(
+        &mut self,
+        email: &Email,
+        attempt_id: &LoginAttemptId,
+        max_attempts: u32,
+    ) -> Result<(), AuthApiError> {
+        // the failure that reaches the limit deletes the row instead of counting
+        let found = sqlx::query_scalar::<_, bool>(
+            r#"
+        WITH dropped AS (
+            DELETE FROM two_factor
+            WHERE email = $1 AND id = $2 AND failures + 1 >= $3
+                AND created_at > now() - make_interval(secs => $4)
+            RETURNING email
+        ), counted AS (
+            UPDATE two_factor SET failures = failures + 1
+            WHERE email = $1 AND id = $2 AND NOT EXISTS (SELECT 1 FROM dropped)
+                AND created_at > now() - make_interval(secs => $4)
+            RETURNING email
+        )
+        SELECT EXISTS (SELECT 1 FROM dropped) OR EXISTS (SELECT 1 FROM counted);
+        "#,
+        )
+        .bind(email.as_ref())
+        .bind(attempt_id.as_ref().to_string())
+        .bind(max_attempts as i64)
+        .bind(self.ttl_secs())
         .fetch_one(&self.pool)
         .await
         .map_err(AuthApiError::Db)?;
+        if !found {
+            // guesses for another attempt don't count, but one must be pending
+            self.ensure_pending(email).await?;
+        }
+        Ok(())
+    }
+
+    async fn consume_attempt(
+        &mut self,
+        email: &Email,
+        attempt_id: &LoginAttemptId,
+    ) -> Result<(), AuthApiError> {
+        let removed = sqlx::query(
+            r#"
+        DELETE FROM two_factor
+        WHERE email = $1 AND id = $2 AND created_at > now() - make_interval(secs => $3);
+        "#,
+        )
+        .bind(email.as_ref())
+        .bind(attempt_id.as_ref().to_string())
+        .bind(self.ttl_secs())
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        if removed.rows_affected() == 0 {
+            return Err(AuthApiError::TwoFactorCodeNotFound);
+        }
         Ok(())
     }
 }
@@ -13,6 +13,37 @@ use tokio::sync::RwLock;
 
 const TWO_FA_PREFIX: &str = "2fa";
 
+/// Checks the code of a pending attempt, counting misses and removing the attempt on a
+/// match or once `ARGV[3]` misses are reached. Returns 1 on a match, 0 on a miss or
+/// another attempt's id, -1 if nothing is pending.
+const VERIFY_CODE_SCRIPT: &str = r#"
+local attempt = redis.call('HMGET', KEYS[1], 'id', 'code')
+if not attempt[1] then
+    return -1
+end
+if attempt[1] ~= ARGV[1] then
+    return 0
+end
+if attempt[2] == ARGV[2] then
+    redis.call('DEL', KEYS[1])
+    return 1
+end
+if redis.call('HINCRBY', KEYS[1], 'failures', 1) >= tonumber(ARGV[3]) then
+    redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+/// Removes the attempt with id `ARGV[1]`, returning whether it was pending
+const CONSUME_ATTEMPT_SCRIPT: &str = r#"
+if redis.call('HGET', KEYS[1], 'id') == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+/// Attempts are stored as hashes of id, code and failures keyed by email. Checks run as
+/// scripts so concurrent guesses are all counted and a code can't be used twice.
 #[derive(Clone, Debug)]
 pub struct RedisTwoFactorStore {
     config: RedisConfig,
@@ -42,12 +73,28 @@ impl RedisTwoFactorStore {
             conn: Arc::new(RwLock::new(conn)),
         })
     }
-}
 
-#[derive(serde::Deserialize, serde::Serialize)]
-struct TwoFactorEntry {
-    pub id: LoginAttemptId,
-    pub code: TwoFactorCode,
+    /// Runs `VERIFY_CODE_SCRIPT`
+    async fn check(
+        &self,
+        email: &Email,
+        attempt_id: &LoginAttemptId,
+        code: &str,
+        max_attempts: u32,
+    ) -> Result<bool, AuthApiError> {
+        let mut guard = self.conn.write().await;
+        let result: i64 = redis::Script::new(VERIFY_CODE_SCRIPT)
+            .key(make_redis_key(TWO_FA_PREFIX, email.as_ref()))
+            .arg(attempt_id.as_ref().to_string())
+            .arg(code)
+            .arg(max_attempts)
+            .invoke(&mut guard.0)
+            .map_err(AuthApiError::Redis)?;
+        match result {
+            -1 => Err(AuthApiError::TwoFactorCodeNotFound),
+            result => Ok(result == 1),
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -61,17 +108,22 @@ impl TwoFactorCodeStore for RedisTwoFactorStore {
         let id = LoginAttemptId::new();
         let key = make_redis_key(TWO_FA_PREFIX, email.as_ref());
         let mut guard = self.conn.write().await;
-        let entry = TwoFactorEntry {
-            id: id.clone(),
-            code: code.clone(),
-        };
-        guard
-            .0
-            .set_ex::<_, _, ()>(
+        redis::pipe()
+            .atomic()
+            .del(&key)
+            .ignore()
+            .hset_multiple(
                 &key,
-                &serde_json::to_string(&entry).expect("should be json"),
-                self.config.ttl_2fa,
+                &[
+                    ("id", id.as_ref().to_string()),
+                    ("code", code.as_ref().to_string()),
+                    ("failures", "0".to_string()),
+                ],
             )
+            .ignore()
+            .expire(&key, self.config.ttl_2fa as i64)
+            .ignore()
+            .query::<()>(&mut guard.0)
             .map_err(AuthApiError::Redis)?;
 
         Ok((id, code))
@@ -84,10 +136,18 @@ impl TwoFactorCodeStore for RedisTwoFactorStore {
         // write is needed for &mut self on Connection
         let mut guard = self.conn.write().await;
         let key = make_redis_key(TWO_FA_PREFIX, email.as_ref());
-        let value = guard.0.get::<_, String>(&key)?;
-        let entry: TwoFactorEntry = serde_json::from_str(&value)
-            .map_err(|e| AuthApiError::SerializationError(format!("{e}")))?;
-        Ok((entry.id, entry.code))
+        let (id, code) = redis::cmd("HMGET")
+            .arg(&key)
+            .arg("id")
+            .arg("code")
+            .query::<(Option<String>, Option<String>)>(&mut guard.0)?;
+        match (id, code) {
+            (Some(id), Some(code)) => Ok((
+                LoginAttemptId::try_from(id)?,
+                TwoFactorCode::try_from(code)?,
+            )),
+            _ => Err(AuthApiError::TwoFactorCodeNotFound),
+        }
     }
 
     async fn remove_code(&mut self, email: &Email) -> Result<(), AuthApiError> {
@@ -96,4 +156,44 @@ impl TwoFactorCodeStore for RedisTwoFactorStore {
         let _ = guard.0.del::<_, String>(&key);
         Ok(())
     }
+
+    async fn verify_code(
+        &mut self,
+        email: &Email,
+        attempt_id: &LoginAttemptId,
+        code: &TwoFactorCode,
+        max_attempts: u32,
+    ) -> Result<bool, AuthApiError> {
+        self.check(email, attempt_id, code.as_ref(), max_attempts)
+            .await
+    }
+
+    async fn record_failure(
+        &mut self,
+        email: &Email,
+        attempt_id: &LoginAttemptId,
+        max_attempts: u32,
+    ) -> Result<(), AuthApiError> {
+        // codes are never empty, so this always counts as a miss
+        self.check(email, attempt_id, "", max_attempts)
+            .await
+            .map(|_| ())
+    }
+
+    async fn consume_attempt(
+        &mut self,
+        email: &Email,
+        attempt_id: &LoginAttemptId,
+    ) -> Result<(), AuthApiError> {
+        let mut guard = self.conn.write().await;
+        let removed: i64 = redis::Script::new(CONSUME_ATTEMPT_SCRIPT)
+            .key(make_redis_key(TWO_FA_PREFIX, email.as_ref()))
+            .arg(attempt_id.as_ref().to_string())
+            .invoke(&mut guard.0)
+            .map_err(AuthApiError::Redis)?;
+        if removed == 0 {
+            return Err(AuthApiError::TwoFactorCodeNotFound);
+        }
+        Ok(())
+    }
 }
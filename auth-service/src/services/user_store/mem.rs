@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::domain::{Email, User, UserStore};
+use crate::domain::{Email, TwoFactorMethod, User, UserStore};
 use crate::error::AuthApiError;
 
 #[derive(Debug, Clone)]
@@ -38,6 +38,103 @@ impl UserStore for InMemoryUserStore {
             None => Err(AuthApiError::UserNotFound),
         }
     }
+
+    async fn set_two_factor(
+        &mut self,
+        email: &Email,
+        two_factor: &TwoFactorMethod,
+    ) -> Result<(), AuthApiError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(AuthApiError::UserNotFound)?;
+        user.two_factor = two_factor.clone();
+        Ok(())
+    }
+
+    async fn update_user(&mut self, user: &User) -> Result<(), AuthApiError> {
+        let existing = self
+            .users
+            .get_mut(&user.email)
+            .ok_or(AuthApiError::UserNotFound)?;
+        *existing = user.clone();
+        Ok(())
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<User, AuthApiError> {
+        if self.users.contains_key(new) {
+            return Err(AuthApiError::UserAlreadyExists);
+        }
+        let mut user = self.users.remove(old).ok_or(AuthApiError::UserNotFound)?;
+        user.change_email(new.clone());
+        self.users.insert(new.clone(), user.clone());
+        Ok(user)
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        self.users
+            .remove(email)
+            .map(|_| ())
+            .ok_or(AuthApiError::UserNotFound)
+    }
+
+    async fn users_pending_deletion(
+        &self,
+        requested_before: i64,
+    ) -> Result<Vec<Email>, AuthApiError> {
+        Ok(self
+            .users
+            .values()
+            .filter(|u| {
+                u.deletion_requested_at
+                    .is_some_and(|t| t <= requested_before)
+            })
+            .map(|u| u.email.clone())
+            .collect())
+    }
+
+    async fn search_users(
+        &self,
+        search: &str,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<User>, AuthApiError> {
+        let search = search.to_lowercase();
+        let mut users: Vec<&User> = self
+            .users
+            .values()
+            .filter(|u| u.email.as_ref().to_lowercase().contains(&search))
+            .collect();
+        users.sort_by(|a, b| a.email.as_ref().cmp(b.email.as_ref()));
+        Ok(users
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn count_users(&self, search: &str) -> Result<u64, AuthApiError> {
+        let search = search.to_lowercase();
+        Ok(self
+            .users
+            .keys()
+            .filter(|email| email.as_ref().to_lowercase().contains(&search))
+            .count() as u64)
+    }
+
+    async fn set_locked(
+        &mut self,
+        email: &Email,
+        locked_at: Option<i64>,
+    ) -> Result<(), AuthApiError> {
+        let user = self
+            .users
+            .get_mut(email)
+            .ok_or(AuthApiError::UserNotFound)?;
+        user.locked_at = locked_at;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -55,6 +152,9 @@ mod tests {
                 .await
                 .expect("valid password"),
             two_factor: TwoFactorMethod::None,
+            verified: false,
+            deletion_requested_at: None,
+            locked_at: None,
         };
         let res = store.add_user(user).await;
         assert!(res.is_ok());
@@ -69,6 +169,9 @@ mod tests {
                 .await
                 .expect("valid password"),
             two_factor: TwoFactorMethod::None,
+            verified: false,
+            deletion_requested_at: None,
+            locked_at: None,
         };
         _ = store.add_user(user).await;
         assert_eq!(store.users.len(), 1);
@@ -89,6 +192,9 @@ mod tests {
                 .await
                 .expect("valid password"),
             two_factor: TwoFactorMethod::None,
+            verified: false,
+            deletion_requested_at: None,
+            locked_at: None,
         };
         _ = store.add_user(user).await;
         assert_eq!(store.users.len(), 1);
@@ -102,4 +208,157 @@ mod tests {
                 .is_ok()
         );
     }
+
+    #[tokio::test]
+    async fn test_set_two_factor() {
+        let mut store = InMemoryUserStore::new();
+        let email = Email::parse("me@you.com").unwrap();
+        let user = User {
+            email: email.clone(),
+            password: HashedPassword::parse("password")
+                .await
+                .expect("valid password"),
+            two_factor: TwoFactorMethod::None,
+            verified: false,
+            deletion_requested_at: None,
+            locked_at: None,
+        };
+        _ = store.add_user(user).await;
+        store
+            .set_two_factor(&email, &TwoFactorMethod::Totp)
+            .await
+            .expect("set two factor");
+        let user = store.get_user(&email).await.unwrap();
+        assert_eq!(user.two_factor, TwoFactorMethod::Totp);
+
+        let missing = Email::parse("nobody@you.com").unwrap();
+        let res = store.set_two_factor(&missing, &TwoFactorMethod::Totp).await;
+        assert!(matches!(res, Err(AuthApiError::UserNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_update_user() {
+        let mut store = InMemoryUserStore::new();
+        let email = Email::parse("me@you.com").unwrap();
+        let mut user = User {
+            email: email.clone(),
+            password: HashedPassword::parse("password")
+                .await
+                .expect("valid password"),
+            two_factor: TwoFactorMethod::None,
+            verified: false,
+            deletion_requested_at: None,
+            locked_at: None,
+        };
+        let res = store.update_user(&user).await;
+        assert!(matches!(res, Err(AuthApiError::UserNotFound)));
+
+        _ = store.add_user(user.clone()).await;
+        user.password = HashedPassword::parse("new-password")
+            .await
+            .expect("valid password");
+        store.update_user(&user).await.expect("update user");
+        let password = Password::parse("new-password").unwrap();
+        assert!(store.validate_credentials(&email, &password).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_change_email() {
+        let mut store = InMemoryUserStore::new();
+        let old = Email::parse("me@you.com").unwrap();
+        let new = Email::parse("new@you.com").unwrap();
+        let taken = Email::parse("taken@you.com").unwrap();
+        for email in [&old, &taken] {
+            let user = User::new(
+                email.clone(),
+                HashedPassword::parse("password")
+                    .await
+                    .expect("valid password"),
+                TwoFactorMethod::None,
+            );
+            _ = store.add_user(user).await;
+        }
+
+        let res = store.change_email(&old, &taken).await;
+        assert!(matches!(res, Err(AuthApiError::UserAlreadyExists)));
+        assert!(store.get_user(&old).await.is_ok());
+
+        let user = store.change_email(&old, &new).await.expect("change email");
+        assert_eq!(user.email, new);
+        assert!(user.verified);
+        assert!(matches!(
+            store.get_user(&old).await,
+            Err(AuthApiError::UserNotFound)
+        ));
+        assert!(store.get_user(&new).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_delete_user_and_pending_deletion() {
+        let mut store = InMemoryUserStore::new();
+        let email = Email::parse("me@you.com").unwrap();
+        let mut user = User::new(
+            email.clone(),
+            HashedPassword::parse("password")
+                .await
+                .expect("valid password"),
+            TwoFactorMethod::None,
+        );
+        _ = store.add_user(user.clone()).await;
+        assert!(
+            store
+                .users_pending_deletion(i64::MAX)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        user.deletion_requested_at = Some(100);
+        store.update_user(&user).await.expect("update user");
+        assert!(store.users_pending_deletion(99).await.unwrap().is_empty());
+        assert_eq!(
+            store.users_pending_deletion(100).await.unwrap(),
+            vec![email.clone()]
+        );
+
+        store.delete_user(&email).await.expect("delete user");
+        assert!(matches!(
+            store.delete_user(&email).await,
+            Err(AuthApiError::UserNotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_search_users_and_lock() {
+        let mut store = InMemoryUserStore::new();
+        for email in ["carol@acme.com", "alice@acme.com", "bob@other.com"] {
+            let user = User::new(
+                Email::parse(email).unwrap(),
+                HashedPassword::parse("password")
+                    .await
+                    .expect("valid password"),
+                TwoFactorMethod::None,
+            );
+            _ = store.add_user(user).await;
+        }
+
+        let page = store.search_users("ACME", 0, 10).await.unwrap();
+        let emails: Vec<&str> = page.iter().map(|u| u.email.as_ref()).collect();
+        assert_eq!(emails, vec!["alice@acme.com", "carol@acme.com"]);
+        assert_eq!(store.count_users("acme").await.unwrap(), 2);
+        assert_eq!(store.count_users("").await.unwrap(), 3);
+        let page = store.search_users("", 1, 1).await.unwrap();
+        assert_eq!(page[0].email.as_ref(), "bob@other.com");
+
+        let email = Email::parse("bob@other.com").unwrap();
+        store.set_locked(&email, Some(100)).await.unwrap();
+        assert!(store.get_user(&email).await.unwrap().is_locked());
+        store.set_locked(&email, None).await.unwrap();
+        assert!(!store.get_user(&email).await.unwrap().is_locked());
+        let missing = Email::parse("nobody@you.com").unwrap();
+        assert!(matches!(
+            store.set_locked(&missing, None).await,
+            Err(AuthApiError::UserNotFound)
+        ));
+    }
 }
@@ -1,7 +1,7 @@
 use sqlx::{PgPool, postgres::PgPoolOptions};
 
 use crate::{
-    domain::{Email, User, UserRow, data_stores::UserStore},
+    domain::{Email, TwoFactorMethod, User, UserRow, data_stores::UserStore},
     error::AuthApiError,
 };
 
@@ -28,13 +28,14 @@ impl UserStore for PostgresUserStore {
         let result = sqlx::query!(
             r#"
         INSERT into "public"."user" 
-            (email, password_hash, two_factor)
+            (email, password_hash, two_factor, verified)
         values 
-            ($1, $2, $3);
+            ($1, $2, $3, $4);
         "#,
             row.email,
             row.password_hash,
             row.two_factor,
+            row.verified,
         )
         .execute(&self.pool)
         .await
@@ -52,7 +53,7 @@ impl UserStore for PostgresUserStore {
     async fn get_user(&self, email: &Email) -> Result<User, AuthApiError> {
         let user_row = sqlx::query_as!(
             UserRow,
-            r#"SELECT email, password_hash, two_factor from "public"."user" where email = $1;"#,
+            r#"SELECT email, password_hash, two_factor, verified, deletion_requested_at, locked_at from "public"."user" where email = $1;"#,
             email.as_ref(),
         )
         .fetch_one(&self.pool)
@@ -60,4 +61,156 @@ impl UserStore for PostgresUserStore {
         .map_err(AuthApiError::Db)?;
         Ok(user_row.into())
     }
+
+    async fn set_two_factor(
+        &mut self,
+        email: &Email,
+        two_factor: &TwoFactorMethod,
+    ) -> Result<(), AuthApiError> {
+        let result = sqlx::query!(
+            r#"UPDATE "public"."user" SET two_factor = $2 where email = $1;"#,
+            email.as_ref(),
+            two_factor.to_string(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+
+        if result.rows_affected() < 1 {
+            return Err(AuthApiError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    async fn update_user(&mut self, user: &User) -> Result<(), AuthApiError> {
+        let row: UserRow = user.clone().into();
+        let result = sqlx::query!(
+            r#"UPDATE "public"."user" SET password_hash = $2, two_factor = $3, verified = $4, deletion_requested_at = $5, locked_at = $6 where email = $1;"#,
+            row.email,
+            row.password_hash,
+            row.two_factor,
+            row.verified,
+            row.deletion_requested_at,
+            row.locked_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+
+        if result.rows_affected() < 1 {
+            return Err(AuthApiError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    async fn change_email(&mut self, old: &Email, new: &Email) -> Result<User, AuthApiError> {
+        // a single statement, so the primary key moves atomically and a taken address
+        // trips the unique constraint instead of overwriting someone else's row
+        let user_row = sqlx::query_as!(
+            UserRow,
+            r#"UPDATE "public"."user" SET email = $2, verified = true where email = $1 RETURNING email, password_hash, two_factor, verified, deletion_requested_at, locked_at;"#,
+            old.as_ref(),
+            new.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => {
+                AuthApiError::UserAlreadyExists
+            }
+            e => AuthApiError::Db(e),
+        })?
+        .ok_or(AuthApiError::UserNotFound)?;
+        Ok(user_row.into())
+    }
+
+    async fn delete_user(&mut self, email: &Email) -> Result<(), AuthApiError> {
+        let result = sqlx::query!(
+            r#"DELETE FROM "public"."user" where email = $1;"#,
+            email.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+
+        if result.rows_affected() < 1 {
+            return Err(AuthApiError::UserNotFound);
+        }
+        Ok(())
+    }
+
+    async fn users_pending_deletion(
+        &self,
+        requested_before: i64,
+    ) -> Result<Vec<Email>, AuthApiError> {
+        let requested_before = chrono::DateTime::from_timestamp(requested_before, 0)
+            .ok_or_else(|| AuthApiError::InvalidData("timestamp out of range".to_string()))?;
+        let rows = sqlx::query!(
+            r#"SELECT email from "public"."user" where deletion_requested_at <= $1;"#,
+            requested_before,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        rows.into_iter()
+            .map(|row| Email::parse(&row.email))
+            .collect()
+    }
+
+    async fn search_users(
+        &self,
+        search: &str,
+        offset: u64,
+        limit: u64,
+    ) -> Result<Vec<User>, AuthApiError> {
+        // strpos rather than LIKE, so `%` and `_` in the search are taken literally
+        let rows = sqlx::query_as!(
+            UserRow,
+            r#"SELECT email, password_hash, two_factor, verified, deletion_requested_at, locked_at from "public"."user" where strpos(lower(email), lower($1)) > 0 ORDER BY email LIMIT $2 OFFSET $3;"#,
+            search,
+            limit as i64,
+            offset as i64,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        Ok(rows.into_iter().map(User::from).collect())
+    }
+
+    async fn count_users(&self, search: &str) -> Result<u64, AuthApiError> {
+        let row = sqlx::query!(
+            r#"SELECT count(*) as "count!" from "public"."user" where strpos(lower(email), lower($1)) > 0;"#,
+            search,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+        Ok(row.count as u64)
+    }
+
+    async fn set_locked(
+        &mut self,
+        email: &Email,
+        locked_at: Option<i64>,
+    ) -> Result<(), AuthApiError> {
+        let locked_at = locked_at
+            .map(|t| {
+                chrono::DateTime::from_timestamp(t, 0)
+                    .ok_or_else(|| AuthApiError::InvalidData("timestamp out of range".to_string()))
+            })
+            .transpose()?;
+        let result = sqlx::query!(
+            r#"UPDATE "public"."user" SET locked_at = $2 where email = $1;"#,
+            email.as_ref(),
+            locked_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(AuthApiError::Db)?;
+
+        if result.rows_affected() < 1 {
+            return Err(AuthApiError::UserNotFound);
+        }
+        Ok(())
+    }
 }
@@ -1,12 +1,36 @@
 use crate::config::Config;
-use crate::domain::{BannedTokenStore, EmailClient, TwoFactorCodeStore, UserStore};
+use crate::domain::{
+    AccountStore, ApiKeyStore, BannedTokenStore, ClientStore, DeviceCodeStore, EmailClient,
+    FailedLoginStore, IdentityStore, InvitationStore, OneTimeTokenStore, OrganizationStore,
+    PasskeyStore, RateLimitStore, RecoveryCodeStore, RefreshTokenStore, RoleStore, SessionStore,
+    TotpStore, TwoFactorCodeStore, UserStore,
+};
+use crate::services::federation::ProviderCache;
 use sqlx::PgPool;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use webauthn_rs::prelude::Url;
+use webauthn_rs::{Webauthn, WebauthnBuilder};
 
 pub type UserStoreType = Arc<RwLock<dyn UserStore>>;
 pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore>>;
 pub type TwoFactorCodeStoreType = Arc<RwLock<dyn TwoFactorCodeStore>>;
+pub type OneTimeTokenStoreType = Arc<RwLock<dyn OneTimeTokenStore>>;
+pub type PasskeyStoreType = Arc<RwLock<dyn PasskeyStore>>;
+pub type TotpStoreType = Arc<RwLock<dyn TotpStore>>;
+pub type RecoveryCodeStoreType = Arc<RwLock<dyn RecoveryCodeStore>>;
+pub type RefreshTokenStoreType = Arc<RwLock<dyn RefreshTokenStore>>;
+pub type RateLimitStoreType = Arc<RwLock<dyn RateLimitStore>>;
+pub type FailedLoginStoreType = Arc<RwLock<dyn FailedLoginStore>>;
+pub type ClientStoreType = Arc<RwLock<dyn ClientStore>>;
+pub type IdentityStoreType = Arc<RwLock<dyn IdentityStore>>;
+pub type ApiKeyStoreType = Arc<RwLock<dyn ApiKeyStore>>;
+pub type DeviceCodeStoreType = Arc<RwLock<dyn DeviceCodeStore>>;
+pub type SessionStoreType = Arc<RwLock<dyn SessionStore>>;
+pub type RoleStoreType = Arc<RwLock<dyn RoleStore>>;
+pub type OrganizationStoreType = Arc<RwLock<dyn OrganizationStore>>;
+pub type InvitationStoreType = Arc<RwLock<dyn InvitationStore>>;
+pub type AccountStoreType = Arc<RwLock<dyn AccountStore>>;
 pub type EmailClientType = Arc<RwLock<dyn EmailClient>>;
 pub type PoolType = Arc<RwLock<PgPool>>;
 
@@ -15,24 +39,82 @@ pub struct AppState {
     pub user_store: UserStoreType,
     pub banned_tokens: BannedTokenStoreType,
     pub two_factor: TwoFactorCodeStoreType,
+    pub one_time_tokens: OneTimeTokenStoreType,
+    pub passkeys: PasskeyStoreType,
+    pub webauthn: Arc<Webauthn>,
+    pub totp: TotpStoreType,
+    pub recovery_codes: RecoveryCodeStoreType,
+    pub refresh_tokens: RefreshTokenStoreType,
+    pub rate_limits: RateLimitStoreType,
+    pub failed_logins: FailedLoginStoreType,
+    pub clients: ClientStoreType,
+    pub identities: IdentityStoreType,
+    pub api_keys: ApiKeyStoreType,
+    pub device_codes: DeviceCodeStoreType,
+    pub sessions: SessionStoreType,
+    pub roles: RoleStoreType,
+    pub organizations: OrganizationStoreType,
+    pub invitations: InvitationStoreType,
+    /// Only when the stores keyed by an account's address all live in the database
+    pub accounts: Option<AccountStoreType>,
     pub email_client: EmailClientType,
+    pub federation: Arc<ProviderCache>,
     pub config: Config,
 }
 
 impl AppState {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         config: &Config,
         user_store: UserStoreType,
         banned_tokens: BannedTokenStoreType,
         two_factor: TwoFactorCodeStoreType,
+        one_time_tokens: OneTimeTokenStoreType,
+        passkeys: PasskeyStoreType,
+        totp: TotpStoreType,
+        recovery_codes: RecoveryCodeStoreType,
+        refresh_tokens: RefreshTokenStoreType,
+        rate_limits: RateLimitStoreType,
+        failed_logins: FailedLoginStoreType,
+        clients: ClientStoreType,
+        identities: IdentityStoreType,
+        api_keys: ApiKeyStoreType,
+        device_codes: DeviceCodeStoreType,
+        sessions: SessionStoreType,
+        roles: RoleStoreType,
+        organizations: OrganizationStoreType,
+        invitations: InvitationStoreType,
+        accounts: Option<AccountStoreType>,
         email_client: EmailClientType,
     ) -> Self {
+        let rp_origin = Url::parse(&config.webauthn.rp_origin).expect("valid webauthn rp_origin");
+        let webauthn = WebauthnBuilder::new(&config.webauthn.rp_id, &rp_origin)
+            .and_then(|b| b.rp_name(&config.webauthn.rp_name).build())
+            .expect("valid webauthn config");
         Self {
             config: config.clone(),
             banned_tokens,
             user_store,
             two_factor,
+            one_time_tokens,
+            passkeys,
+            webauthn: Arc::new(webauthn),
+            totp,
+            recovery_codes,
+            refresh_tokens,
+            rate_limits,
+            failed_logins,
+            clients,
+            identities,
+            api_keys,
+            device_codes,
+            sessions,
+            roles,
+            organizations,
+            invitations,
+            accounts,
             email_client,
+            federation: Arc::new(ProviderCache::new(config.federation.discovery_ttl)),
         }
     }
 }
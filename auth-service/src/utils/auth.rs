@@ -5,9 +5,15 @@ use base64::engine::GeneralPurpose;
 use jsonwebtoken::{Algorithm, Header, Validation, encode};
 use serde::Deserialize;
 use tokio::sync::OnceCell;
+use uuid::Uuid;
 
 use crate::config::{JwtConfig, JwtKeySecret};
-use crate::domain::{Email, LoginAttemptId};
+use crate::domain::{
+    ApiKey, ApiKeyRecord, Authentication, AuthorizationGrant, CLIENT_CREDENTIALS_GRANT, Email,
+    Grants, LoginAttemptId, OneTimeTokenId, OrgRole, RefreshToken, TokenPurpose, User,
+    parse_scopes,
+};
+use crate::error::AuthApiError;
 use crate::state::AppState;
 use jsonwebtoken::jwk::{JwkSet, KeyAlgorithm};
 
@@ -26,10 +32,117 @@ pub enum GenerateTokenError {
     UnexpectedError(String),
 }
 
-#[derive(serde::Serialize, Deserialize, Debug)]
+/// Kind of a signed token, carried in its `typ` claim.
+///
+/// Every token is signed with the same key, so without it one kind of token
+/// would decode as another, e.g. a magic link as an access token.
+#[derive(serde::Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenType {
+    Access,
+    #[serde(rename = "2fa")]
+    TwoFactor,
+    OneTime,
+    AuthorizationCode,
+    FederatedLogin,
+}
+
+/// Claims of a token kind that this service accepts back, see `validate_token`
+pub trait TokenClaims: serde::de::DeserializeOwned {
+    const TYPE: TokenType;
+
+    fn token_type(&self) -> TokenType;
+}
+
+#[derive(serde::Serialize, Deserialize, Debug, Clone)]
 pub struct Claims {
-    sub: String,
-    exp: usize,
+    pub sub: String,
+    pub exp: usize,
+    /// Always `TokenType::Access`
+    pub typ: TokenType,
+    /// Missing on tokens issued before it was added
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat: Option<usize>,
+    /// Token version of the user at issue time, see `BannedTokenStore::token_version`
+    #[serde(default)]
+    pub ver: u64,
+    /// Only set when issued through a login, see `EmailVerificationPolicy`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+    /// Space delimited scopes, only set on tokens issued to an OAuth client
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// OAuth client the token was issued to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// Grant type for tokens that aren't backed by a user, see `CLIENT_CREDENTIALS_GRANT`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gty: Option<String>,
+    /// Session the token was issued for, only set on session tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<Uuid>,
+    /// Organization the session is acting for, see `/switch-org`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<Uuid>,
+    /// Role of the user in `org_id` at issue time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub org_role: Option<OrgRole>,
+    /// Roles of the user at issue time and the permissions they grant
+    #[serde(flatten)]
+    pub grants: Grants,
+    /// `auth_time` and `amr` of the login behind the token
+    #[serde(flatten)]
+    pub authentication: Authentication,
+}
+
+impl Claims {
+    pub fn new(email: &Email, version: u64, ttl: u64) -> Self {
+        Self::with_subject(email.as_ref(), version, ttl)
+    }
+
+    /// Claims of a client credentials token, the client itself is the subject
+    pub fn for_client(client_id: &str, scope: &str, ttl: u64) -> Self {
+        Claims {
+            scope: Some(scope.to_string()),
+            client_id: Some(client_id.to_string()),
+            gty: Some(CLIENT_CREDENTIALS_GRANT.to_string()),
+            ..Self::with_subject(client_id, 0, ttl)
+        }
+    }
+
+    fn with_subject(sub: &str, version: u64, ttl: u64) -> Self {
+        let now = chrono::Utc::now();
+        let exp = now
+            .checked_add_signed(chrono::Duration::seconds(ttl as i64))
+            .expect("valid timestamp")
+            .timestamp() as usize;
+        Claims {
+            sub: sub.to_string(),
+            exp,
+            typ: TokenType::Access,
+            iat: Some(now.timestamp() as usize),
+            ver: version,
+            email_verified: None,
+            scope: None,
+            client_id: None,
+            gty: None,
+            sid: None,
+            org_id: None,
+            org_role: None,
+            grants: Grants::default(),
+            authentication: Authentication::default(),
+        }
+    }
+
+    /// Issued to a client for itself, `sub` is a client id rather than an email
+    pub fn is_client_credentials(&self) -> bool {
+        self.gty.as_deref() == Some(CLIENT_CREDENTIALS_GRANT)
+    }
+
+    /// Granted scopes, empty for session tokens
+    pub fn scopes(&self) -> Vec<String> {
+        self.scope.as_deref().map(parse_scopes).unwrap_or_default()
+    }
 }
 
 /// Claims for 2FA tokens, which include the login attempt ID and email
@@ -44,9 +157,118 @@ pub struct Claims {
 pub struct TwoFAClaims {
     pub sub: LoginAttemptId,
     exp: usize,
+    typ: TokenType,
     email: Email,
 }
 
+/// Claims for single-use tokens delivered by email (e.g. magic links)
+///
+/// The `jti` is what the `OneTimeTokenStore` tracks, and `purpose` keeps a token
+/// minted for one flow from being accepted by another.
+#[derive(serde::Serialize, Deserialize, Debug)]
+pub struct OneTimeTokenClaims {
+    pub sub: Email,
+    pub jti: OneTimeTokenId,
+    pub purpose: TokenPurpose,
+    /// Address the account moves to, only set on email change tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_email: Option<Email>,
+    exp: usize,
+    typ: TokenType,
+}
+
+/// Claims for an OAuth authorization code
+///
+/// Everything the token endpoint has to check is carried in the code itself,
+/// the `jti` is registered with the `OneTimeTokenStore` so it can only be exchanged once.
+#[derive(serde::Serialize, Deserialize, Debug)]
+pub struct AuthorizationCodeClaims {
+    pub jti: OneTimeTokenId,
+    #[serde(flatten)]
+    pub grant: AuthorizationGrant,
+    exp: usize,
+    typ: TokenType,
+}
+
+/// Claims of the cookie holding a login in flight at an upstream identity provider
+///
+/// Binds the callback to the browser that started the login. The cookie is dropped once
+/// the callback is handled, the provider's code can't be redeemed twice anyway.
+#[derive(serde::Serialize, Deserialize, Debug)]
+pub struct FederatedLoginClaims {
+    /// Id of the provider the login was started at
+    pub provider: String,
+    /// Echoed back by the provider, must match the callback's `state`
+    pub state: String,
+    pub nonce: String,
+    /// PKCE verifier for the code exchange
+    pub code_verifier: String,
+    /// Where to send the user once logged in
+    pub return_to: String,
+    exp: usize,
+    typ: TokenType,
+}
+
+/// OpenID Connect id_token claims, OIDC Core section 2
+#[derive(serde::Serialize, Deserialize, Debug)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    /// Client the token was issued to
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+    #[serde(flatten)]
+    pub authentication: Authentication,
+    /// Only with the `email` scope
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email_verified: Option<bool>,
+}
+
+impl TokenClaims for Claims {
+    const TYPE: TokenType = TokenType::Access;
+
+    fn token_type(&self) -> TokenType {
+        self.typ
+    }
+}
+
+impl TokenClaims for TwoFAClaims {
+    const TYPE: TokenType = TokenType::TwoFactor;
+
+    fn token_type(&self) -> TokenType {
+        self.typ
+    }
+}
+
+impl TokenClaims for OneTimeTokenClaims {
+    const TYPE: TokenType = TokenType::OneTime;
+
+    fn token_type(&self) -> TokenType {
+        self.typ
+    }
+}
+
+impl TokenClaims for AuthorizationCodeClaims {
+    const TYPE: TokenType = TokenType::AuthorizationCode;
+
+    fn token_type(&self) -> TokenType {
+        self.typ
+    }
+}
+
+impl TokenClaims for FederatedLoginClaims {
+    const TYPE: TokenType = TokenType::FederatedLogin;
+
+    fn token_type(&self) -> TokenType {
+        self.typ
+    }
+}
+
 static KEYS: OnceCell<JwkSet> = OnceCell::const_new();
 
 fn get_decoding_key(secret: &JwtKeySecret) -> jsonwebtoken::DecodingKey {
@@ -114,22 +336,19 @@ pub fn get_jwt_header(secret: &JwtKeySecret) -> jsonwebtoken::Header {
     header
 }
 
+/// Generate a short-lived access token carrying `grants`, `ttl` in seconds
 pub fn generate_auth_token(
     email: &Email,
+    version: u64,
+    grants: &Grants,
+    ttl: u64,
     secret: &JwtKeySecret,
 ) -> Result<String, GenerateTokenError> {
-    let exp = chrono::Utc::now()
-        .checked_add_signed(chrono::Duration::hours(24))
-        .expect("valid timestamp")
-        .timestamp() as usize;
-
     let claims = Claims {
-        sub: email.as_ref().to_string(),
-        exp,
+        grants: grants.clone(),
+        ..Claims::new(email, version, ttl)
     };
-
     let header = get_jwt_header(secret);
-
     generate_auth_token_with_claims::<Claims>(&header, &claims, secret)
 }
 
@@ -145,6 +364,7 @@ pub fn generate_2fa_token(
     let claims = TwoFAClaims {
         sub: id.clone(),
         exp,
+        typ: TokenType::TwoFactor,
         email: email.clone(),
     };
     let header = get_jwt_header(secret);
@@ -158,6 +378,182 @@ pub fn generate_2fa_token(
     Ok(buf)
 }
 
+/// Generate a signed single-use token
+///
+/// Returns the token id so the caller can register it with the `OneTimeTokenStore`
+pub fn generate_one_time_token(
+    email: &Email,
+    purpose: &TokenPurpose,
+    ttl: u64,
+    secret: &JwtKeySecret,
+) -> Result<(OneTimeTokenId, String), GenerateTokenError> {
+    generate_one_time_token_with_claims(email, None, purpose, ttl, secret)
+}
+
+/// Generate a signed single-use token for moving the account of `email` to `new_email`
+pub fn generate_email_change_token(
+    email: &Email,
+    new_email: &Email,
+    purpose: &TokenPurpose,
+    ttl: u64,
+    secret: &JwtKeySecret,
+) -> Result<(OneTimeTokenId, String), GenerateTokenError> {
+    generate_one_time_token_with_claims(email, Some(new_email), purpose, ttl, secret)
+}
+
+fn generate_one_time_token_with_claims(
+    email: &Email,
+    new_email: Option<&Email>,
+    purpose: &TokenPurpose,
+    ttl: u64,
+    secret: &JwtKeySecret,
+) -> Result<(OneTimeTokenId, String), GenerateTokenError> {
+    let exp = chrono::Utc::now()
+        .checked_add_signed(chrono::Duration::seconds(ttl as i64))
+        .expect("valid timestamp")
+        .timestamp() as usize;
+    let claims = OneTimeTokenClaims {
+        sub: email.clone(),
+        jti: OneTimeTokenId::new(),
+        purpose: purpose.clone(),
+        new_email: new_email.cloned(),
+        exp,
+        typ: TokenType::OneTime,
+    };
+    let header = get_jwt_header(secret);
+    let token = generate_auth_token_with_claims::<OneTimeTokenClaims>(&header, &claims, secret)?;
+    Ok((claims.jti, token))
+}
+
+/// Generate a signed authorization code, returns the id to register with the `OneTimeTokenStore`
+pub fn generate_authorization_code(
+    grant: AuthorizationGrant,
+    ttl: u64,
+    secret: &JwtKeySecret,
+) -> Result<(OneTimeTokenId, String), GenerateTokenError> {
+    let exp = chrono::Utc::now()
+        .checked_add_signed(chrono::Duration::seconds(ttl as i64))
+        .expect("valid timestamp")
+        .timestamp() as usize;
+    let claims = AuthorizationCodeClaims {
+        jti: OneTimeTokenId::new(),
+        grant,
+        exp,
+        typ: TokenType::AuthorizationCode,
+    };
+    let header = get_jwt_header(secret);
+    let token =
+        generate_auth_token_with_claims::<AuthorizationCodeClaims>(&header, &claims, secret)?;
+    Ok((claims.jti, token))
+}
+
+/// Signed state of a federated login, kept in a cookie until the provider calls back
+pub fn generate_federated_login_token(
+    provider: &str,
+    state: &str,
+    nonce: &str,
+    code_verifier: &str,
+    return_to: &str,
+    ttl: u64,
+    secret: &JwtKeySecret,
+) -> Result<String, GenerateTokenError> {
+    let exp = chrono::Utc::now()
+        .checked_add_signed(chrono::Duration::seconds(ttl as i64))
+        .expect("valid timestamp")
+        .timestamp() as usize;
+    let claims = FederatedLoginClaims {
+        provider: provider.to_string(),
+        state: state.to_string(),
+        nonce: nonce.to_string(),
+        code_verifier: code_verifier.to_string(),
+        return_to: return_to.to_string(),
+        exp,
+        typ: TokenType::FederatedLogin,
+    };
+    let header = get_jwt_header(secret);
+    generate_auth_token_with_claims::<FederatedLoginClaims>(&header, &claims, secret)
+}
+
+/// Access token for an OAuth client acting on behalf of `email`.
+/// It carries the current token version, so revoking the user's sessions revokes it too.
+pub async fn generate_oauth_access_token(
+    state: &AppState,
+    email: &Email,
+    client_id: &str,
+    scope: &str,
+) -> Result<String, AuthApiError> {
+    let version = state
+        .banned_tokens
+        .read()
+        .await
+        .token_version(email)
+        .await?;
+    let secret = &state.config.jwt.secret;
+    let claims = Claims {
+        scope: Some(scope.to_string()),
+        client_id: Some(client_id.to_string()),
+        ..Claims::new(email, version, state.config.oauth.access_token_ttl)
+    };
+    let header = get_jwt_header(secret);
+    generate_auth_token_with_claims::<Claims>(&header, &claims, secret)
+        .map_err(|e| AuthApiError::UnexpectedError(e.to_string()))
+}
+
+/// Access token for an OAuth client acting on its own behalf, client credentials grant
+pub fn generate_client_access_token(
+    state: &AppState,
+    client_id: &str,
+    scope: &str,
+) -> Result<String, AuthApiError> {
+    let secret = &state.config.jwt.secret;
+    let claims = Claims::for_client(client_id, scope, state.config.oauth.access_token_ttl);
+    let header = get_jwt_header(secret);
+    generate_auth_token_with_claims::<Claims>(&header, &claims, secret)
+        .map_err(|e| AuthApiError::UnexpectedError(e.to_string()))
+}
+
+/// id_token for the user behind `grant`, valid as long as the access token issued with it
+pub fn generate_id_token(
+    state: &AppState,
+    grant: &AuthorizationGrant,
+    user: &User,
+) -> Result<String, AuthApiError> {
+    let now = chrono::Utc::now().timestamp() as usize;
+    let with_email = grant.has_scope("email");
+    let claims = IdTokenClaims {
+        iss: state.config.oauth.issuer.clone(),
+        sub: grant.sub.as_ref().to_string(),
+        aud: grant.client_id.clone(),
+        exp: now + state.config.oauth.access_token_ttl as usize,
+        iat: now,
+        nonce: grant.nonce.clone(),
+        authentication: grant.authentication.clone(),
+        email: with_email.then(|| user.email.as_ref().to_string()),
+        email_verified: with_email.then_some(user.verified),
+    };
+    let secret = &state.config.jwt.secret;
+    let header = get_jwt_header(secret);
+    generate_auth_token_with_claims::<IdTokenClaims>(&header, &claims, secret)
+        .map_err(|e| AuthApiError::UnexpectedError(e.to_string()))
+}
+
+/// Validate the signature and expiry of a single-use token and check it was
+/// issued for `purpose`. Whether it has already been used is up to the store.
+pub async fn validate_one_time_token(
+    token: &str,
+    purpose: &TokenPurpose,
+    config: &JwtConfig,
+) -> Result<OneTimeTokenClaims, GenerateTokenError> {
+    let claims = validate_token::<OneTimeTokenClaims>(token, config).await?;
+    if claims.purpose != *purpose {
+        return Err(GenerateTokenError::UnexpectedError(format!(
+            "token issued for {}, expected {}",
+            claims.purpose, purpose
+        )));
+    }
+    Ok(claims)
+}
+
 fn generate_auth_token_with_claims<C>(
     header: &Header,
     claims: &C,
@@ -179,34 +575,219 @@ where
 //     .map_err(GenerateTokenError::Encoding)
 // }
 
+/// Validate the signature and expiry of a token and check it is of the kind `C` stands for
 pub async fn validate_token<C>(token: &str, config: &JwtConfig) -> Result<C, GenerateTokenError>
 where
-    C: serde::de::DeserializeOwned,
+    C: TokenClaims,
 {
     let key = get_decoding_key(&config.secret);
     let alg = config.secret.alg();
-    jsonwebtoken::decode::<C>(token, &key, &Validation::new(alg))
-        .map_err(GenerateTokenError::Decoding)
-        .map(|data| data.claims)
+    let claims = jsonwebtoken::decode::<C>(token, &key, &Validation::new(alg))
+        .map_err(GenerateTokenError::Decoding)?
+        .claims;
+    if claims.token_type() != C::TYPE {
+        return Err(GenerateTokenError::UnexpectedError(format!(
+            "{:?} token, expected {:?}",
+            claims.token_type(),
+            C::TYPE
+        )));
+    }
+    Ok(claims)
 }
 
 pub fn generate_auth_cookie_raw(
     email: &Email,
     name: &str,
+    ttl: u64,
     secret: &JwtKeySecret,
 ) -> Result<Cookie<'static>, GenerateTokenError> {
-    let token = generate_auth_token(email, secret)?;
+    let token = generate_auth_token(email, 0, &Grants::default(), ttl, secret)?;
     Ok(create_auth_cookie(name, token))
 }
 
 pub fn generate_auth_cookie(
     email: &Email,
+    version: u64,
     config: &JwtConfig,
 ) -> Result<Cookie<'static>, GenerateTokenError> {
-    let token = generate_auth_token(email, &config.secret)?;
+    let token = generate_auth_token(
+        email,
+        version,
+        &Grants::default(),
+        config.access_token_ttl,
+        &config.secret,
+    )?;
     Ok(create_auth_cookie(&config.cookie_name, token))
 }
 
+/// Roles currently assigned to `email` and the permissions they add up to
+pub async fn user_grants(state: &AppState, email: &Email) -> Result<Grants, AuthApiError> {
+    let roles = state.roles.read().await.user_roles(email).await?;
+    Ok(Grants::from_roles(&roles))
+}
+
+/// Active organization of a session and the user's role in it, `None` when the session
+/// has none or the user has since left it, in which case the session drops it too
+async fn active_org(
+    state: &AppState,
+    email: &Email,
+    session_id: &Uuid,
+) -> Result<Option<(Uuid, OrgRole)>, AuthApiError> {
+    let org_id = match state.sessions.read().await.get_session(session_id).await {
+        Ok(session) => session.org_id,
+        Err(AuthApiError::SessionNotFound) => None,
+        Err(e) => return Err(e),
+    };
+    let Some(org_id) = org_id else {
+        return Ok(None);
+    };
+    match state
+        .organizations
+        .read()
+        .await
+        .get_membership(&org_id, email)
+        .await
+    {
+        Ok(membership) => Ok(Some((org_id, membership.role))),
+        Err(AuthApiError::OrganizationNotFound) => {
+            state
+                .sessions
+                .write()
+                .await
+                .set_active_org(session_id, None)
+                .await?;
+            Ok(None)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Auth cookie for `user` carrying their current token version, verification state,
+/// session, active organization, roles and how they logged in
+pub async fn issue_auth_cookie(
+    state: &AppState,
+    user: &User,
+    authentication: &Authentication,
+    session_id: &Uuid,
+) -> Result<Cookie<'static>, AuthApiError> {
+    let version = state
+        .banned_tokens
+        .read()
+        .await
+        .token_version(&user.email)
+        .await?;
+    let grants = user_grants(state, &user.email).await?;
+    let org = active_org(state, &user.email, session_id).await?;
+    let config = &state.config.jwt;
+    let claims = Claims {
+        email_verified: Some(user.verified),
+        sid: Some(*session_id),
+        org_id: org.map(|(id, _)| id),
+        org_role: org.map(|(_, role)| role),
+        grants,
+        authentication: authentication.clone(),
+        ..Claims::new(&user.email, version, config.access_token_ttl)
+    };
+    let header = get_jwt_header(&config.secret);
+    let token = generate_auth_token_with_claims::<Claims>(&header, &claims, &config.secret)
+        .map_err(|e| AuthApiError::UnexpectedError(e.to_string()))?;
+    Ok(create_auth_cookie(&config.cookie_name, token))
+}
+
+/// Validate an auth token and check it hasn't been banned or revoked since it was issued
+pub async fn validate_auth_token(token: &str, state: &AppState) -> Result<Claims, AuthApiError> {
+    let banned = state.banned_tokens.read().await;
+    if banned.is_token_banned(token).await? {
+        return Err(AuthApiError::Unauthorized);
+    }
+    let claims = validate_token::<Claims>(token, &state.config.jwt)
+        .await
+        .map_err(|_| AuthApiError::InvalidToken)?;
+    if claims.is_client_credentials() {
+        // no user behind it, the token is good as long as the client stays registered
+        state
+            .clients
+            .read()
+            .await
+            .get_client(&claims.sub)
+            .await
+            .map_err(|_| AuthApiError::Unauthorized)?;
+        return Ok(claims);
+    }
+    let email = Email::parse(&claims.sub).map_err(|_| AuthApiError::InvalidToken)?;
+    if claims.ver < banned.token_version(&email).await? {
+        return Err(AuthApiError::Unauthorized);
+    }
+    if let Some(sid) = &claims.sid {
+        check_session(state, &email, sid, claims.org_id.as_ref()).await?;
+    }
+    if let Some(org_id) = &claims.org_id {
+        // removed members lose access to the organization right away
+        state
+            .organizations
+            .read()
+            .await
+            .get_membership(org_id, &email)
+            .await
+            .map_err(|_| AuthApiError::Unauthorized)?;
+    }
+    Ok(claims)
+}
+
+/// Refuse tokens of sessions that were logged out of or revoked, or that were issued
+/// for another organization than the one the session acts for now, and note the activity
+async fn check_session(
+    state: &AppState,
+    email: &Email,
+    sid: &Uuid,
+    org_id: Option<&Uuid>,
+) -> Result<(), AuthApiError> {
+    let session = state
+        .sessions
+        .read()
+        .await
+        .get_session(sid)
+        .await
+        .map_err(|_| AuthApiError::Unauthorized)?;
+    if session.email != *email || session.org_id.as_ref() != org_id {
+        return Err(AuthApiError::Unauthorized);
+    }
+    let now = chrono::Utc::now().timestamp();
+    if session.needs_touch(now) {
+        state
+            .sessions
+            .write()
+            .await
+            .touch_session(sid, now, None)
+            .await
+            .map_err(|_| AuthApiError::Unauthorized)?;
+    }
+    Ok(())
+}
+
+/// Look up a personal API key, refusing unknown, expired and revoked keys
+/// and keys of accounts that are locked or waiting to be purged
+pub async fn validate_api_key(token: &str, state: &AppState) -> Result<ApiKeyRecord, AuthApiError> {
+    let key = ApiKey::parse(token)?;
+    let record = state.api_keys.read().await.get_key(&key.hash()).await?;
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&record.email)
+        .await
+        .map_err(|_| AuthApiError::InvalidToken)?;
+    if user.is_pending_deletion() || user.is_locked() {
+        return Err(AuthApiError::InvalidToken);
+    }
+    Ok(record)
+}
+
+/// Cookie carrying an opaque refresh token
+pub fn generate_refresh_cookie(token: &RefreshToken, config: &JwtConfig) -> Cookie<'static> {
+    create_auth_cookie(&config.refresh_cookie_name, token.as_ref().to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use chrono::Utc;
@@ -221,7 +802,7 @@ mod tests {
         let secret = JwtKeySecret::Raw {
             value: JWT_SECRET.to_string(),
         };
-        let cookie = generate_auth_cookie_raw(&email, JWT_COOKIE_NAME, &secret).unwrap();
+        let cookie = generate_auth_cookie_raw(&email, JWT_COOKIE_NAME, 900, &secret).unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME.to_string());
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -246,7 +827,7 @@ mod tests {
         let secret = JwtKeySecret::Raw {
             value: JWT_SECRET.to_string(),
         };
-        let result = generate_auth_token(&email, &secret).unwrap();
+        let result = generate_auth_token(&email, 0, &Grants::default(), 900, &secret).unwrap();
         assert_eq!(result.split('.').count(), 3);
     }
 
@@ -257,9 +838,17 @@ mod tests {
                 value: JWT_SECRET.to_string(),
             },
             cookie_name: JWT_COOKIE_NAME.to_string(),
+            ..JwtConfig::default()
         };
         let email = Email::parse("test@example.com").unwrap();
-        let token = generate_auth_token(&email, &config.secret).expect("valid token");
+        let token = generate_auth_token(
+            &email,
+            0,
+            &Grants::default(),
+            config.access_token_ttl,
+            &config.secret,
+        )
+        .expect("valid token");
         let result: Claims = validate_token(&token, &config).await.expect("valid token");
         assert_eq!(result.sub, "test@example.com");
 
@@ -279,9 +868,17 @@ mod tests {
                 priv_key: "tests/jwt-test-ecdsa.pem".to_string(),
             },
             cookie_name: JWT_COOKIE_NAME.to_string(),
+            ..JwtConfig::default()
         };
         let email = Email::parse("test@example.com").unwrap();
-        let token = generate_auth_token(&email, &config.secret).expect("valid token");
+        let token = generate_auth_token(
+            &email,
+            0,
+            &Grants::default(),
+            config.access_token_ttl,
+            &config.secret,
+        )
+        .expect("valid token");
         let result: Claims = validate_token(&token, &config).await.expect("valid token");
         assert_eq!(result.sub, "test@example.com");
 
@@ -301,9 +898,17 @@ mod tests {
                 priv_key: "tests/jwt-test-rsa.pem".to_string(),
             },
             cookie_name: JWT_COOKIE_NAME.to_string(),
+            ..JwtConfig::default()
         };
         let email = Email::parse("test@example.com").unwrap();
-        let token = generate_auth_token(&email, &config.secret).expect("valid token");
+        let token = generate_auth_token(
+            &email,
+            0,
+            &Grants::default(),
+            config.access_token_ttl,
+            &config.secret,
+        )
+        .expect("valid token");
         let result: Claims = validate_token(&token, &config).await.expect("valid token");
         assert_eq!(result.sub, "test@example.com");
 
@@ -315,6 +920,78 @@ mod tests {
         assert!(result.exp > exp as usize);
     }
 
+    #[tokio::test]
+    async fn test_one_time_token_round_trip() {
+        let config = JwtConfig {
+            secret: JwtKeySecret::Raw {
+                value: JWT_SECRET.to_string(),
+            },
+            cookie_name: JWT_COOKIE_NAME.to_string(),
+            ..JwtConfig::default()
+        };
+        let email = Email::parse("test@example.com").unwrap();
+        let (id, token) =
+            generate_one_time_token(&email, &TokenPurpose::MagicLink, 60, &config.secret)
+                .expect("valid token");
+        let claims = validate_one_time_token(&token, &TokenPurpose::MagicLink, &config)
+            .await
+            .expect("valid token");
+        assert_eq!(claims.jti, id);
+        assert_eq!(claims.sub, email);
+        assert!(claims.new_email.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_email_change_token_carries_new_email() {
+        let config = JwtConfig {
+            secret: JwtKeySecret::Raw {
+                value: JWT_SECRET.to_string(),
+            },
+            cookie_name: JWT_COOKIE_NAME.to_string(),
+            ..JwtConfig::default()
+        };
+        let email = Email::parse("test@example.com").unwrap();
+        let new_email = Email::parse("new@example.com").unwrap();
+        let purpose = TokenPurpose::EmailChange;
+        let (_, token) =
+            generate_email_change_token(&email, &new_email, &purpose, 60, &config.secret)
+                .expect("valid token");
+        let claims = validate_one_time_token(&token, &purpose, &config)
+            .await
+            .expect("valid token");
+        assert_eq!(claims.sub, email);
+        assert_eq!(claims.new_email, Some(new_email));
+        assert!(
+            validate_one_time_token(&token, &TokenPurpose::EmailChangeUndo, &config)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_one_time_token_is_not_an_access_token() {
+        let config = JwtConfig {
+            secret: JwtKeySecret::Raw {
+                value: JWT_SECRET.to_string(),
+            },
+            cookie_name: JWT_COOKIE_NAME.to_string(),
+            ..JwtConfig::default()
+        };
+        let email = Email::parse("test@example.com").unwrap();
+        let (_, token) =
+            generate_one_time_token(&email, &TokenPurpose::PasswordReset, 60, &config.secret)
+                .expect("valid token");
+        assert!(validate_token::<Claims>(&token, &config).await.is_err());
+
+        let token = generate_auth_token(&email, 0, &Grants::default(), 60, &config.secret)
+            .expect("valid token");
+        assert!(
+            validate_token::<OneTimeTokenClaims>(&token, &config)
+                .await
+                .is_err()
+        );
+    }
+
     #[tokio::test]
     async fn test_auth_validate_token_invalid() {
         let token = "invalid_token".to_owned();
@@ -323,6 +1000,7 @@ mod tests {
                 value: JWT_SECRET.to_string(),
             },
             cookie_name: JWT_COOKIE_NAME.to_string(),
+            ..JwtConfig::default()
         };
         let result = validate_token::<Claims>(&token, &config).await;
         assert!(result.is_err());
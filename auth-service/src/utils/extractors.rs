@@ -1,8 +1,21 @@
-use axum::extract::{FromRequest, Request};
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+use axum::extract::{ConnectInfo, FromRef, FromRequest, FromRequestParts, Request};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, header};
 use axum::response::IntoResponse;
 use axum::{Form, Json};
+use axum_extra::extract::CookieJar;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
 use serde::de::DeserializeOwned;
 
+use crate::domain::{ADMIN_ROLE, ClientInfo, Email};
+use crate::error::AuthApiError;
+use crate::state::AppState;
+use crate::utils::auth::{Claims, user_grants, validate_auth_token};
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum FormOrJsonError {
     Invalid,
@@ -84,3 +97,190 @@ where
         Err(FormOrJsonError::Invalid)
     }
 }
+
+/// The user behind a valid, non-banned auth cookie.
+///
+/// Use as a handler argument to require a logged in user; rejects with `AuthApiError`.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub email: Email,
+    /// The raw JWT from the cookie
+    pub token: String,
+    pub claims: Claims,
+}
+
+impl FromRequestParts<AppState> for AuthenticatedUser {
+    type Rejection = AuthApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let jar = CookieJar::from_headers(&parts.headers);
+        let token = jar
+            .get(&state.config.jwt.cookie_name)
+            .ok_or(AuthApiError::MissingToken)?
+            .value()
+            .to_string();
+
+        let claims = validate_auth_token(&token, state).await?;
+        // tokens issued to OAuth clients are scoped, they don't act as a session here
+        if claims.client_id.is_some() {
+            return Err(AuthApiError::Unauthorized);
+        }
+        let email = Email::parse(&claims.sub).map_err(|_| AuthApiError::InvalidToken)?;
+        Ok(AuthenticatedUser {
+            email,
+            token,
+            claims,
+        })
+    }
+}
+
+/// An `AuthenticatedUser` whose token carries the `admin` role and who still has it.
+///
+/// Rejects with `Forbidden` otherwise.
+#[derive(Debug, Clone)]
+pub struct AdminUser(pub AuthenticatedUser);
+
+impl FromRequestParts<AppState> for AdminUser {
+    type Rejection = AuthApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let user = AuthenticatedUser::from_request_parts(parts, state).await?;
+        if !user.claims.grants.has_role(ADMIN_ROLE) {
+            return Err(AuthApiError::Forbidden);
+        }
+        // the claim is as old as the token, the role may have been taken away since
+        if !user_grants(state, &user.email).await?.has_role(ADMIN_ROLE) {
+            return Err(AuthApiError::Forbidden);
+        }
+        Ok(AdminUser(user))
+    }
+}
+
+/// The address a request came from: the peer, unless it is a trusted proxy, in which
+/// case `X-Forwarded-For` is followed back from the right past the trusted hops.
+/// Anything left of the first untrusted hop was written by the client and is ignored.
+fn client_ip(peer: IpAddr, forwarded: Option<&str>, trusted: &[IpAddr]) -> IpAddr {
+    let mut client = peer;
+    if !trusted.contains(&client) {
+        return client;
+    }
+    for hop in forwarded.into_iter().flat_map(|v| v.rsplit(',')) {
+        let Ok(hop) = hop.trim().parse::<IpAddr>() else {
+            break;
+        };
+        client = hop;
+        if !trusted.contains(&hop) {
+            break;
+        }
+    }
+    client
+}
+
+/// Who is behind a request: the address from [`client_ip`], the user agent and the
+/// `X-Client-Id` header naming the OAuth client, if any
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    S: Send + Sync,
+    AppState: FromRef<S>,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let state = AppState::from_ref(state);
+        // the extractor rather than the extension, so tests can mock the peer
+        let peer = ConnectInfo::<SocketAddr>::from_request_parts(parts, &())
+            .await
+            .ok();
+        let forwarded = parts
+            .headers
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok());
+        let ip = peer
+            .map(|ConnectInfo(addr)| {
+                client_ip(addr.ip(), forwarded, &state.config.server.trusted_proxies)
+            })
+            .map(|ip| ip.to_string());
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok());
+        let client_id = parts
+            .headers
+            .get("x-client-id")
+            .and_then(|v| v.to_str().ok())
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty());
+        Ok(ClientInfo {
+            client_id,
+            ..ClientInfo::new(ip, user_agent)
+        })
+    }
+}
+
+/// Token from an `Authorization: Bearer` header, RFC 6750 section 2.1
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("bearer")
+        .then(|| token.trim())
+        .filter(|token| !token.is_empty())
+}
+
+/// Client id and secret from an `Authorization: Basic` header, RFC 6749 section 2.3.1
+pub fn basic_credentials(headers: &HeaderMap) -> Option<(String, String)> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, credentials) = value.split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    let decoded = STANDARD.decode(credentials.trim()).ok()?;
+    let (id, secret) = String::from_utf8(decoded)
+        .ok()?
+        .split_once(':')
+        .map(|(id, secret)| (id.to_string(), secret.to_string()))?;
+    Some((id, secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_client_ip_only_trusts_configured_proxies() {
+        let trusted = [ip("10.0.0.1"), ip("10.0.0.2")];
+        // a client can't pick its own address by sending the header
+        assert_eq!(
+            client_ip(ip("198.51.100.9"), Some("203.0.113.7"), &trusted),
+            ip("198.51.100.9")
+        );
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), Some("203.0.113.7"), &trusted),
+            ip("203.0.113.7")
+        );
+        // hops the client added in front of the proxies are skipped
+        assert_eq!(
+            client_ip(
+                ip("10.0.0.1"),
+                Some("1.2.3.4, 203.0.113.7, 10.0.0.2"),
+                &trusted
+            ),
+            ip("203.0.113.7")
+        );
+        assert_eq!(
+            client_ip(ip("10.0.0.1"), Some("garbage, 10.0.0.2"), &trusted),
+            ip("10.0.0.2")
+        );
+        assert_eq!(client_ip(ip("10.0.0.1"), None, &trusted), ip("10.0.0.1"));
+    }
+}
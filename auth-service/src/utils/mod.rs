@@ -2,3 +2,5 @@ pub mod auth;
 pub mod constants;
 pub mod extractors;
 pub use extractors::*;
+pub mod rate_limit;
+pub use rate_limit::*;
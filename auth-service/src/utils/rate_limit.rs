@@ -0,0 +1,88 @@
+use axum::body::{Body, to_bytes};
+use axum::extract::{FromRef, FromRequest, Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::config::RateLimitKey;
+use crate::domain::{ClientInfo, Email, TokenBucket};
+use crate::error::AuthApiError;
+use crate::state::AppState;
+use crate::utils::FormOrJson;
+
+/// Larger bodies than any of the limited endpoints take
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// State of the `rate_limit` middleware for one endpoint
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    pub state: AppState,
+    /// Prefix of the bucket keys, keeps endpoints from sharing buckets
+    pub name: &'static str,
+    pub bucket: TokenBucket,
+}
+
+impl FromRef<RateLimit> for AppState {
+    fn from_ref(limit: &RateLimit) -> Self {
+        limit.state.clone()
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct EmailField {
+    email: Option<String>,
+}
+
+/// The `email` of a JSON or form body, leaving the body in place for the handler
+async fn target_email(request: Request) -> Result<(Request, Option<Email>), AuthApiError> {
+    let (parts, body) = request.into_parts();
+    let bytes = to_bytes(body, MAX_BODY_SIZE)
+        .await
+        .map_err(|_| AuthApiError::MalformedRequest)?;
+    let copy = Request::from_parts(parts.clone(), Body::from(bytes.clone()));
+    let email = FormOrJson::<EmailField>::from_request(copy, &())
+        .await
+        .ok()
+        .and_then(|FormOrJson(field)| field.email)
+        .and_then(|email| Email::parse(&email).ok());
+    Ok((Request::from_parts(parts, Body::from(bytes)), email))
+}
+
+/// Token bucket limit on an endpoint, keyed by client address and/or target email
+/// as configured. Answers `TooManyRequests` without calling the handler.
+pub async fn rate_limit(
+    State(limit): State<RateLimit>,
+    client: ClientInfo,
+    request: Request,
+    next: Next,
+) -> Result<Response, AuthApiError> {
+    let config = &limit.state.config.rate_limit;
+    if !config.enabled {
+        return Ok(next.run(request).await);
+    }
+
+    let (request, email) = match config.key {
+        RateLimitKey::Ip => (request, None),
+        RateLimitKey::Email | RateLimitKey::IpAndEmail => target_email(request).await?,
+    };
+    let ip = match (config.key, &email) {
+        (RateLimitKey::Email, Some(_)) => None,
+        _ => client.ip,
+    };
+    // case is ignored so it can't be varied to get a fresh bucket
+    let email = email.map(|email| email.as_ref().to_lowercase());
+    let keys = ip
+        .map(|ip| format!("{}:ip:{}", limit.name, ip))
+        .into_iter()
+        .chain(email.map(|email| format!("{}:email:{}", limit.name, email)))
+        .collect::<Vec<_>>();
+
+    // all at once, a request refused on one key mustn't use up the other's token
+    limit
+        .state
+        .rate_limits
+        .write()
+        .await
+        .take_tokens(&keys, &limit.bucket)
+        .await?;
+    Ok(next.run(request).await)
+}
@@ -0,0 +1,233 @@
+use lgr_auth::domain::{
+    DeviceAuthorization, DeviceAuthorizationStatus, DeviceCode, Email, EmailTemplate, Invitation,
+    OrgRole, UserCode,
+};
+use lgr_auth::routes::{DeleteAccountResponse, LoginResponse, purge_deleted_accounts};
+use reqwest::StatusCode;
+
+use crate::common::{TestApp, get_test_app};
+
+/// Sign up and log in, returning the access and refresh tokens
+async fn signup_and_login(app: &TestApp, email: &str) -> (String, String) {
+    let body = serde_json::json!({
+        "method": "email_password",
+        "email": email,
+        "password": "password123",
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    match response.json::<LoginResponse>() {
+        LoginResponse::Success {
+            token,
+            refresh_token,
+            ..
+        } => (token, refresh_token),
+        other => panic!("unexpected login response: {other:?}"),
+    }
+}
+
+async fn login_status(app: &TestApp, email: &str) -> StatusCode {
+    app.post_login(&serde_json::json!({
+        "method": "email_password",
+        "email": email,
+        "password": "password123",
+    }))
+    .await
+    .status_code()
+}
+
+fn auth_jar(app: &TestApp, token: &str) -> cookie::CookieJar {
+    let mut jar = cookie::CookieJar::default();
+    jar.add((app.config.jwt.cookie_name.clone(), token.to_string()));
+    jar
+}
+
+#[tokio::test]
+async fn test_delete_account_blocks_login_and_revokes_tokens() {
+    let app = get_test_app().await;
+    let email = "delete-account@me.com";
+    let (access, refresh) = signup_and_login(app, email).await;
+
+    let response = app
+        .delete_account(&serde_json::json!({ "password": "wrong-password" }))
+        .add_cookies(auth_jar(app, &access))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    assert_eq!(login_status(app, email).await, StatusCode::OK);
+
+    let response = app
+        .delete_account(&serde_json::json!({ "password": "password123" }))
+        .add_cookies(auth_jar(app, &access))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+    let body = response.json::<DeleteAccountResponse>();
+    assert!(body.purge_at > chrono::Utc::now().timestamp());
+    match app.last_email_to(email).expect("email sent").template {
+        EmailTemplate::AccountDeletion(data) => assert!(!data.purge_date.is_empty()),
+        other => panic!("unexpected email template: {other:?}"),
+    }
+
+    assert_eq!(login_status(app, email).await, StatusCode::FORBIDDEN);
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": access }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    let response = app
+        .post_token_refresh()
+        .json(&serde_json::json!({ "refresh_token": refresh }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    // still inside the grace period
+    purge_deleted_accounts(&app.state).await.expect("purge");
+    assert_eq!(login_status(app, email).await, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_restore_account_cancels_pending_deletion() {
+    let app = get_test_app().await;
+    let email = "restore-account@me.com";
+    let (access, _) = signup_and_login(app, email).await;
+    let response = app
+        .delete_account(&serde_json::json!({ "password": "password123" }))
+        .add_cookies(auth_jar(app, &access))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+    assert_eq!(login_status(app, email).await, StatusCode::FORBIDDEN);
+
+    let response = app
+        .post_restore_account(&serde_json::json!({
+            "email": email,
+            "password": "wrong-password",
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    assert_eq!(login_status(app, email).await, StatusCode::FORBIDDEN);
+
+    let response = app
+        .post_restore_account(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(login_status(app, email).await, StatusCode::OK);
+    let parsed = Email::parse(email).expect("valid email");
+    let user = app.state.user_store.read().await.get_user(&parsed).await;
+    assert_eq!(user.expect("user").deletion_requested_at, None);
+}
+
+#[tokio::test]
+async fn test_purge_removes_account_after_grace_period() {
+    let app = get_test_app().await;
+    let email = "purge-account@me.com";
+    let (access, _) = signup_and_login(app, email).await;
+    let response = app
+        .delete_account(&serde_json::json!({ "password": "password123" }))
+        .add_cookies(auth_jar(app, &access))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+
+    // pretend the request was made before the grace period started
+    let parsed = Email::parse(email).expect("valid email");
+    {
+        let mut users = app.state.user_store.write().await;
+        let mut user = users.get_user(&parsed).await.expect("user");
+        let grace = app.config.auth.account_deletion_grace_period as i64;
+        user.deletion_requested_at = Some(chrono::Utc::now().timestamp() - grace - 3600);
+        users.update_user(&user).await.expect("update user");
+    }
+    // state kept about the address outside the account itself
+    let admin = Email::parse("purge-admin@me.com").expect("valid email");
+    let invitation = Invitation::new(uuid::Uuid::new_v4(), &parsed, OrgRole::Member, &admin, 600);
+    app.state
+        .invitations
+        .write()
+        .await
+        .add_invitation(invitation)
+        .await
+        .expect("invited");
+    app.state
+        .failed_logins
+        .write()
+        .await
+        .record_failure(&parsed, &app.config.lockout)
+        .await
+        .expect("failure recorded");
+    let device_code = DeviceCode::generate();
+    let user_code = UserCode::generate();
+    let mut device_codes = app.state.device_codes.write().await;
+    device_codes
+        .add(
+            DeviceAuthorization::new(&device_code, user_code.clone(), "purge-cli", "", 600, 0),
+            600,
+        )
+        .await
+        .expect("device request");
+    device_codes
+        .set_status(
+            &user_code,
+            DeviceAuthorizationStatus::Approved {
+                email: parsed.clone(),
+            },
+        )
+        .await
+        .expect("approved");
+    drop(device_codes);
+
+    let purged = purge_deleted_accounts(&app.state).await.expect("purge");
+    assert!(purged >= 1);
+    let invitations = app.state.invitations.read().await;
+    assert!(
+        invitations
+            .list_user_invitations(&parsed)
+            .await
+            .expect("invitations")
+            .is_empty()
+    );
+    drop(invitations);
+    let failed = app.state.failed_logins.read().await.get(&parsed).await;
+    assert_eq!(failed.expect("failed logins").failures, 0);
+    assert!(
+        app.state
+            .device_codes
+            .write()
+            .await
+            .poll(&device_code.hash(), "purge-cli")
+            .await
+            .is_err()
+    );
+    assert!(
+        app.state
+            .user_store
+            .read()
+            .await
+            .get_user(&parsed)
+            .await
+            .is_err()
+    );
+    assert_eq!(
+        app.state
+            .banned_tokens
+            .read()
+            .await
+            .token_version(&parsed)
+            .await
+            .expect("token version"),
+        0
+    );
+
+    // the address is free again
+    signup_and_login(app, email).await;
+}
+
+#[tokio::test]
+async fn test_delete_account_400_without_auth_cookie() {
+    let app = get_test_app().await;
+    let response = app
+        .delete_account(&serde_json::json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
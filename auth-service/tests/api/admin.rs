@@ -0,0 +1,233 @@
+use lgr_auth::config::Config;
+use lgr_auth::domain::{ClientInfo, Email, Session, TwoFactorMethod};
+use lgr_auth::routes::{LoginResponse, UserDetails, UserPage, build_app_router};
+use reqwest::StatusCode;
+
+use crate::common::TestApp;
+
+const ADMIN: &str = "admin-api@me.com";
+
+/// An app where `ADMIN` is given the admin role at startup
+async fn app_with_admin() -> TestApp {
+    let mut config = Config::default();
+    config.rbac.admins = vec![ADMIN.to_string()];
+    TestApp::new(&config).await
+}
+
+async fn signup(app: &TestApp, email: &str, two_factor: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "method": "email_password",
+            "email": email,
+            "password": "password123",
+            "two_factor": two_factor,
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+}
+
+async fn login(app: &TestApp, email: &str) -> axum_test::TestResponse {
+    app.post_login(&serde_json::json!({
+        "method": "email_password",
+        "email": email,
+        "password": "password123",
+    }))
+    .await
+}
+
+/// Log in, returning the access token
+async fn signup_and_login(app: &TestApp, email: &str) -> String {
+    signup(app, email, "none").await;
+    let response = login(app, email).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    match response.json::<LoginResponse>() {
+        LoginResponse::Success { token, .. } => token,
+        other => panic!("unexpected login response: {other:?}"),
+    }
+}
+
+fn auth_jar(app: &TestApp, token: &str) -> cookie::CookieJar {
+    let mut jar = cookie::CookieJar::default();
+    jar.add((app.config.jwt.cookie_name.clone(), token.to_string()));
+    jar
+}
+
+async fn verify(app: &TestApp, token: &str) -> StatusCode {
+    app.post_verify_token(&serde_json::json!({ "token": token }))
+        .await
+        .status_code()
+}
+
+#[tokio::test]
+async fn test_admin_searches_users_by_page() {
+    let app = app_with_admin().await;
+    let admin = signup_and_login(&app, ADMIN).await;
+    for email in ["carol@search.com", "alice@search.com", "bob@search.com"] {
+        signup(&app, email, "none").await;
+    }
+
+    let response = app
+        .get_admin_users(&serde_json::json!({ "search": "SEARCH.com", "per_page": 2 }))
+        .add_cookies(auth_jar(&app, &admin))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let page = response.json::<UserPage>();
+    assert_eq!(page.total, 3);
+    let emails: Vec<&str> = page.users.iter().map(|u| u.email.as_ref()).collect();
+    assert_eq!(emails, vec!["alice@search.com", "bob@search.com"]);
+
+    let response = app
+        .get_admin_users(&serde_json::json!({ "search": "search.com", "per_page": 2, "page": 2 }))
+        .add_cookies(auth_jar(&app, &admin))
+        .await;
+    let page = response.json::<UserPage>();
+    assert_eq!(page.users.len(), 1);
+    assert_eq!(page.users[0].email.as_ref(), "carol@search.com");
+}
+
+#[tokio::test]
+async fn test_admin_api_requires_the_admin_role() {
+    let app = app_with_admin().await;
+    let user = signup_and_login(&app, "admin-api-user@me.com").await;
+
+    let response = app
+        .get_admin_users(&serde_json::json!({}))
+        .add_cookies(auth_jar(&app, &user))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+    let response = app
+        .post_admin_lock(ADMIN)
+        .add_cookies(auth_jar(&app, &user))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+    let response = app.get_admin_users(&serde_json::json!({})).await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_locked_account_is_signed_out_and_cant_log_in() {
+    let app = app_with_admin().await;
+    let admin = signup_and_login(&app, ADMIN).await;
+    let email = "admin-lock@me.com";
+    let user = signup_and_login(&app, email).await;
+
+    let response = app
+        .post_admin_lock(email)
+        .add_cookies(auth_jar(&app, &admin))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NO_CONTENT);
+    assert_eq!(verify(&app, &user).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        login(&app, email).await.status_code(),
+        StatusCode::FORBIDDEN
+    );
+    let response = app
+        .get_admin_user(email)
+        .add_cookies(auth_jar(&app, &admin))
+        .await;
+    assert!(response.json::<UserDetails>().user.locked_at.is_some());
+
+    // admins can't lock themselves out
+    let response = app
+        .post_admin_lock(ADMIN)
+        .add_cookies(auth_jar(&app, &admin))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = app
+        .post_admin_unlock(email)
+        .add_cookies(auth_jar(&app, &admin))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NO_CONTENT);
+    assert_eq!(login(&app, email).await.status_code(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_admin_resets_two_factor_and_logs_out() {
+    let app = app_with_admin().await;
+    let admin = signup_and_login(&app, ADMIN).await;
+    let email = "admin-2fa@me.com";
+    signup(&app, email, "email").await;
+
+    let response = app
+        .get_admin_user(email)
+        .add_cookies(auth_jar(&app, &admin))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(
+        response.json::<UserDetails>().user.two_factor,
+        TwoFactorMethod::Email
+    );
+    assert_eq!(
+        login(&app, email).await.status_code(),
+        StatusCode::PARTIAL_CONTENT
+    );
+    let parsed = Email::parse(email).expect("valid email");
+    let session = Session::new(
+        uuid::Uuid::new_v4(),
+        &parsed,
+        &ClientInfo::default(),
+        app.config.auth.refresh_token_ttl,
+    );
+    app.state
+        .sessions
+        .write()
+        .await
+        .add_session(session)
+        .await
+        .expect("session");
+
+    let response = app
+        .delete_admin_two_factor(email)
+        .add_cookies(auth_jar(&app, &admin))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NO_CONTENT);
+    // the pending login and the sessions go with the old factor
+    assert!(
+        app.state
+            .two_factor
+            .read()
+            .await
+            .get_code(&parsed)
+            .await
+            .is_err()
+    );
+    let sessions = app.state.sessions.read().await.list_sessions(&parsed).await;
+    assert!(sessions.expect("sessions").is_empty());
+    let response = login(&app, email).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let token = match response.json::<LoginResponse>() {
+        LoginResponse::Success { token, .. } => token,
+        other => panic!("unexpected login response: {other:?}"),
+    };
+
+    let response = app
+        .post_admin_logout(email)
+        .add_cookies(auth_jar(&app, &admin))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NO_CONTENT);
+    assert_eq!(verify(&app, &token).await, StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .post_admin_logout("nobody@me.com")
+        .add_cookies(auth_jar(&app, &admin))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_admin_routes_are_documented() {
+    let app = app_with_admin().await;
+    let (_, api) = build_app_router(app.state.clone()).split_for_parts();
+    for path in [
+        "/admin/users",
+        "/admin/users/{email}",
+        "/admin/users/{email}/lock",
+        "/admin/users/{email}/2fa",
+    ] {
+        assert!(
+            api.paths.paths.contains_key(path),
+            "{path} not in the api doc"
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use lgr_auth::domain::{ApiKeyRecord, EmailTemplate};
+use lgr_auth::routes::{CreateApiKeyResponse, LoginResponse, VerifyTokenResponse};
+use reqwest::StatusCode;
+
+use crate::common::{TestApp, get_test_app, query_param};
+
+/// Sign up and log in, returning the access token
+async fn signup_and_login(app: &TestApp, email: &str) -> String {
+    let body = serde_json::json!({
+        "method": "email_password",
+        "email": email,
+        "password": "password123",
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    match response.json::<LoginResponse>() {
+        LoginResponse::Success { token, .. } => token,
+        other => panic!("unexpected login response: {other:?}"),
+    }
+}
+
+fn auth_jar(app: &TestApp, token: &str) -> cookie::CookieJar {
+    let mut jar = cookie::CookieJar::default();
+    jar.add((app.config.jwt.cookie_name.clone(), token.to_string()));
+    jar
+}
+
+async fn create_key(app: &TestApp, token: &str, body: serde_json::Value) -> CreateApiKeyResponse {
+    let response = app
+        .post_api_key(&body)
+        .add_cookies(auth_jar(app, token))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    response.json::<CreateApiKeyResponse>()
+}
+
+async fn verify_status(app: &TestApp, token: &str) -> StatusCode {
+    app.post_verify_token(&serde_json::json!({ "token": token }))
+        .await
+        .status_code()
+}
+
+#[tokio::test]
+async fn test_api_key_lifecycle() {
+    let app = get_test_app().await;
+    let email = "api-keys@me.com";
+    let token = signup_and_login(app, email).await;
+
+    let created = create_key(
+        app,
+        &token,
+        serde_json::json!({ "name": "ci", "scopes": ["orders:read"], "expires_in": 3600 }),
+    )
+    .await;
+    assert!(created.key.starts_with("lgr_"));
+    assert_eq!(created.record.name, "ci");
+    assert!(created.record.expires_at.is_some());
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": created.key }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body = response.json::<VerifyTokenResponse>();
+    assert_eq!(body.sub, email);
+    assert_eq!(body.scopes, vec!["orders:read"]);
+    assert_eq!(body.client_id, None);
+
+    // listed without the key itself
+    let response = app.get_api_keys().add_cookies(auth_jar(app, &token)).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(!response.text().contains(&created.key));
+    let keys = response.json::<Vec<ApiKeyRecord>>();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].id, created.record.id);
+
+    let id = created.record.id.to_string();
+    let response = app
+        .delete_api_key(&id)
+        .add_cookies(auth_jar(app, &token))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        verify_status(app, &created.key).await,
+        StatusCode::UNAUTHORIZED
+    );
+    let response = app
+        .delete_api_key(&id)
+        .add_cookies(auth_jar(app, &token))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_api_key_names_are_unique_per_user() {
+    let app = get_test_app().await;
+    let token = signup_and_login(app, "api-keys-names@me.com").await;
+    create_key(app, &token, serde_json::json!({ "name": "laptop" })).await;
+    let response = app
+        .post_api_key(&serde_json::json!({ "name": "laptop" }))
+        .add_cookies(auth_jar(app, &token))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let other = signup_and_login(app, "api-keys-names-other@me.com").await;
+    create_key(app, &other, serde_json::json!({ "name": "laptop" })).await;
+
+    for body in [
+        serde_json::json!({ "name": " " }),
+        serde_json::json!({ "name": "scoped", "scopes": ["two words"] }),
+        serde_json::json!({ "name": "instant", "expires_in": 0 }),
+    ] {
+        let response = app
+            .post_api_key(&body)
+            .add_cookies(auth_jar(app, &token))
+            .await;
+        assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
+
+#[tokio::test]
+async fn test_api_keys_are_per_user() {
+    let app = get_test_app().await;
+    let token = signup_and_login(app, "api-keys-owner@me.com").await;
+    let created = create_key(app, &token, serde_json::json!({ "name": "mine" })).await;
+
+    let other = signup_and_login(app, "api-keys-intruder@me.com").await;
+    let response = app.get_api_keys().add_cookies(auth_jar(app, &other)).await;
+    assert!(response.json::<Vec<ApiKeyRecord>>().is_empty());
+    let response = app
+        .delete_api_key(&created.record.id.to_string())
+        .add_cookies(auth_jar(app, &other))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(verify_status(app, &created.key).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_api_key_requires_login_and_valid_key() {
+    let app = get_test_app().await;
+    let response = app
+        .post_api_key(&serde_json::json!({ "name": "anonymous" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        verify_status(app, "lgr_not-a-real-key").await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    // an API key doesn't stand in for the session cookie
+    let token = signup_and_login(app, "api-keys-session@me.com").await;
+    let created = create_key(app, &token, serde_json::json!({ "name": "script" })).await;
+    let response = app
+        .post_api_key(&serde_json::json!({ "name": "from-key" }))
+        .add_cookies(auth_jar(app, &created.key))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_api_key_follows_email_change() {
+    let app = get_test_app().await;
+    let old = "api-keys-moved-old@me.com";
+    let new = "api-keys-moved-new@me.com";
+    let token = signup_and_login(app, old).await;
+    let created = create_key(app, &token, serde_json::json!({ "name": "ci" })).await;
+
+    let response = app
+        .post_change_email(&serde_json::json!({ "new_email": new }))
+        .add_cookies(auth_jar(app, &token))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+    let link_url = match app.last_email_to(new).expect("confirmation sent").template {
+        EmailTemplate::EmailChange(data) => data.link_url,
+        other => panic!("unexpected email template: {other:?}"),
+    };
+    let confirm = query_param(&link_url, "token").expect("token in link");
+    let response = app
+        .post_confirm_email_change(&serde_json::json!({ "token": confirm }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": created.key }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(response.json::<VerifyTokenResponse>().sub, new);
+}
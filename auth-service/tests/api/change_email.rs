@@ -0,0 +1,242 @@
+use lgr_auth::domain::{Email, EmailTemplate, Invitation, OrgRole};
+use lgr_auth::routes::LoginResponse;
+use reqwest::{StatusCode, Url};
+
+use crate::common::{TestApp, get_test_app};
+
+/// Sign up and log in, returning the access and refresh tokens
+async fn signup_and_login(app: &TestApp, email: &str) -> (String, String) {
+    let body = serde_json::json!({
+        "method": "email_password",
+        "email": email,
+        "password": "password123",
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    match response.json::<LoginResponse>() {
+        LoginResponse::Success {
+            token,
+            refresh_token,
+            ..
+        } => (token, refresh_token),
+        other => panic!("unexpected login response: {other:?}"),
+    }
+}
+
+async fn login_status(app: &TestApp, email: &str) -> StatusCode {
+    app.post_login(&serde_json::json!({
+        "method": "email_password",
+        "email": email,
+        "password": "password123",
+    }))
+    .await
+    .status_code()
+}
+
+fn auth_jar(app: &TestApp, token: &str) -> cookie::CookieJar {
+    let mut jar = cookie::CookieJar::default();
+    jar.add((app.config.jwt.cookie_name.clone(), token.to_string()));
+    jar
+}
+
+fn token_from(link_url: &str) -> String {
+    let url = Url::parse(link_url).expect("valid url");
+    url.query_pairs()
+        .find_map(|(key, value)| (key == "token").then(|| value.to_string()))
+        .expect("token in link")
+}
+
+/// Request a change and return the (confirm, undo) tokens from the two emails
+async fn request_change(app: &TestApp, token: &str, old: &str, new: &str) -> (String, String) {
+    let response = app
+        .post_change_email(&serde_json::json!({ "new_email": new }))
+        .add_cookies(auth_jar(app, token))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+
+    let confirm = match app.last_email_to(new).expect("confirmation sent").template {
+        EmailTemplate::EmailChange(data) => token_from(&data.link_url),
+        other => panic!("unexpected email template: {other:?}"),
+    };
+    let undo = match app.last_email_to(old).expect("notice sent").template {
+        EmailTemplate::EmailChangeNotice(data) => {
+            assert_eq!(data.new_email, new);
+            token_from(&data.link_url)
+        }
+        other => panic!("unexpected email template: {other:?}"),
+    };
+    (confirm, undo)
+}
+
+#[tokio::test]
+async fn test_change_email_confirm_moves_account_and_revokes_tokens() {
+    let app = get_test_app().await;
+    let old = "change-email-old@me.com";
+    let new = "change-email-new@me.com";
+    let (access, refresh) = signup_and_login(app, old).await;
+    let (confirm, _) = request_change(app, &access, old, new).await;
+
+    // nothing changes until the new address is confirmed
+    assert_eq!(login_status(app, old).await, StatusCode::OK);
+
+    let body = serde_json::json!({ "token": confirm });
+    let response = app.post_confirm_email_change(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let response = app.post_confirm_email_change(&body).await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    assert_eq!(login_status(app, new).await, StatusCode::OK);
+    assert_eq!(login_status(app, old).await, StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": access }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    let response = app
+        .post_token_refresh()
+        .json(&serde_json::json!({ "refresh_token": refresh }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_change_email_undo_after_confirm_restores_old_address() {
+    let app = get_test_app().await;
+    let old = "undo-email-old@me.com";
+    let new = "undo-email-new@me.com";
+    let (access, _) = signup_and_login(app, old).await;
+    let (confirm, undo) = request_change(app, &access, old, new).await;
+    let response = app
+        .post_confirm_email_change(&serde_json::json!({ "token": confirm }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let (new_access, _) = match app
+        .post_login(&serde_json::json!({
+            "method": "email_password",
+            "email": new,
+            "password": "password123",
+        }))
+        .await
+        .json::<LoginResponse>()
+    {
+        LoginResponse::Success {
+            token,
+            refresh_token,
+            ..
+        } => (token, refresh_token),
+        other => panic!("unexpected login response: {other:?}"),
+    };
+
+    let response = app
+        .post_undo_email_change(&serde_json::json!({ "token": undo }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    assert_eq!(login_status(app, old).await, StatusCode::OK);
+    assert_eq!(login_status(app, new).await, StatusCode::UNAUTHORIZED);
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": new_access }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_change_email_undo_before_confirm_cancels_change() {
+    let app = get_test_app().await;
+    let old = "cancel-email-old@me.com";
+    let new = "cancel-email-new@me.com";
+    let (access, _) = signup_and_login(app, old).await;
+    let (confirm, undo) = request_change(app, &access, old, new).await;
+
+    let response = app
+        .post_undo_email_change(&serde_json::json!({ "token": undo }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let response = app
+        .post_confirm_email_change(&serde_json::json!({ "token": confirm }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    assert_eq!(login_status(app, old).await, StatusCode::OK);
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": access }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_change_email_rejects_taken_or_same_address() {
+    let app = get_test_app().await;
+    let email = "change-email-taken@me.com";
+    let other = "change-email-other@me.com";
+    let (access, _) = signup_and_login(app, email).await;
+    signup_and_login(app, other).await;
+
+    let response = app
+        .post_change_email(&serde_json::json!({ "new_email": other }))
+        .add_cookies(auth_jar(app, &access))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CONFLICT);
+
+    let response = app
+        .post_change_email(&serde_json::json!({ "new_email": email }))
+        .add_cookies(auth_jar(app, &access))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = app
+        .post_change_email(&serde_json::json!({ "new_email": "someone@me.com" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_change_email_moves_invitations_and_failed_logins() {
+    let app = get_test_app().await;
+    let old = "change-email-invited@me.com";
+    let new = "change-email-invited-new@me.com";
+    let (access, _) = signup_and_login(app, old).await;
+    let (confirm, _) = request_change(app, &access, old, new).await;
+
+    let (old, new) = (Email::parse(old).unwrap(), Email::parse(new).unwrap());
+    let admin = Email::parse("change-email-admin@me.com").unwrap();
+    let invitation = Invitation::new(uuid::Uuid::new_v4(), &old, OrgRole::Member, &admin, 600);
+    app.state
+        .invitations
+        .write()
+        .await
+        .add_invitation(invitation.clone())
+        .await
+        .expect("invited");
+    app.state
+        .failed_logins
+        .write()
+        .await
+        .record_failure(&old, &app.config.lockout)
+        .await
+        .expect("failure recorded");
+
+    let response = app
+        .post_confirm_email_change(&serde_json::json!({ "token": confirm }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let invitations = app.state.invitations.read().await;
+    let moved = invitations.list_user_invitations(&new).await.unwrap();
+    assert_eq!(moved.len(), 1);
+    assert_eq!(moved[0].id, invitation.id);
+    assert!(
+        invitations
+            .list_user_invitations(&old)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    drop(invitations);
+    // a lockout can't be shaken off by changing the address
+    let failed_logins = app.state.failed_logins.read().await;
+    assert_eq!(failed_logins.get(&new).await.unwrap().failures, 1);
+    assert_eq!(failed_logins.get(&old).await.unwrap().failures, 0);
+}
@@ -1,20 +1,30 @@
 #![allow(dead_code)]
 
+use base64::Engine;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE};
 use lgr_auth::database::Database;
-use lgr_auth::state::AppState;
+use lgr_auth::utils::auth::TwoFAClaims;
+use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
 use tokio::sync::RwLock;
 
+use axum::extract::connect_info::MockConnectInfo;
 use axum::response::IntoResponse;
 use axum_extra::extract::CookieJar;
 use axum_test::{TestRequest, TestResponse};
 use lgr_auth::Application;
 use lgr_auth::config::Config;
-use lgr_auth::domain::{Email, EmailClient, EmailTemplate};
+use lgr_auth::domain::{
+    ClientSecret, Email, EmailClient, EmailTemplate, HashedClientSecret, OAuthClient,
+};
+use lgr_auth::state::AppState;
 use tokio::sync::OnceCell;
 
 static APP: OnceCell<TestApp> = OnceCell::const_new();
 
+/// Address the requests of `TestApp::with_peer` come from
+pub const TEST_PEER: ([u8; 4], u16) = ([127, 0, 0, 1], 40000);
+
 pub async fn get_test_app() -> &'static TestApp {
     APP.get_or_init(|| async { TestApp::new(&Config::default()).await })
         .await
@@ -154,10 +164,22 @@ pub struct TestApp {
     pub config: Config,
     pub server: axum_test::TestServer,
     pub emails: CapturedEmails,
+    /// Shared with the router, for reaching into the stores directly
+    pub state: AppState,
 }
 
 impl TestApp {
     pub async fn new(config: &Config) -> Self {
+        Self::build(config, None).await
+    }
+
+    /// Requests come from `TEST_PEER`, so they are rate limited by address and
+    /// forwarded headers are believed if it is configured as a trusted proxy
+    pub async fn with_peer(config: &Config) -> Self {
+        Self::build(config, Some(SocketAddr::from(TEST_PEER))).await
+    }
+
+    async fn build(config: &Config, peer: Option<SocketAddr>) -> Self {
         let mut state = Application::build_app_state(config)
             .await
             .expect("valid state");
@@ -168,15 +190,19 @@ impl TestApp {
             outbox: emails.clone(),
         }));
 
-        let app = Application::build_router(config, state)
+        let mut app = Application::build_router(config, state.clone())
             .await
             .expect("Failed to build application.");
+        if let Some(peer) = peer {
+            app = app.layer(MockConnectInfo(peer));
+        }
         let server = axum_test::TestServer::new(app).expect("Failed to start test server.");
         Self {
             jar: CookieJar::new(),
             config: config.clone(),
             server,
             emails,
+            state,
         }
     }
 
@@ -223,6 +249,396 @@ impl TestApp {
     {
         self.server.post("/verify-token").json(body)
     }
+
+    pub fn post_verify_magic_link<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/verify-magic-link").json(body)
+    }
+
+    pub fn post_passkey_register_finish<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/passkey/register/finish").json(body)
+    }
+
+    pub fn post_passkey_login_finish<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/passkey/login/finish").json(body)
+    }
+
+    pub fn post_regenerate_recovery_codes<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/2fa/recovery-codes").json(body)
+    }
+
+    pub fn post_totp_enroll(&self) -> TestRequest {
+        self.server.post("/2fa/totp/enroll")
+    }
+
+    pub fn post_totp_confirm<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/2fa/totp/confirm").json(body)
+    }
+
+    pub fn post_forgot_password<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/password/forgot").json(body)
+    }
+
+    pub fn post_reset_password<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/password/reset").json(body)
+    }
+
+    pub fn post_change_password<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/password/change").json(body)
+    }
+
+    pub fn post_unlock_account<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/unlock-account").json(body)
+    }
+
+    pub fn post_verify_email<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/verify-email").json(body)
+    }
+
+    pub fn post_resend_verification<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/verify-email/resend").json(body)
+    }
+
+    pub fn post_change_email<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/email/change").json(body)
+    }
+
+    pub fn post_confirm_email_change<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/email/change/confirm").json(body)
+    }
+
+    pub fn post_undo_email_change<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/email/change/undo").json(body)
+    }
+
+    pub fn delete_account<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.delete("/account").json(body)
+    }
+
+    pub fn post_restore_account<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/account/restore").json(body)
+    }
+
+    pub fn post_api_key<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/api-keys").json(body)
+    }
+
+    pub fn get_api_keys(&self) -> TestRequest {
+        self.server.get("/api-keys")
+    }
+
+    pub fn delete_api_key(&self, id: &str) -> TestRequest {
+        self.server.delete(&format!("/api-keys/{id}"))
+    }
+
+    pub fn get_sessions(&self) -> TestRequest {
+        self.server.get("/sessions")
+    }
+
+    pub fn delete_session(&self, id: &str) -> TestRequest {
+        self.server.delete(&format!("/sessions/{id}"))
+    }
+
+    pub fn post_role<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/roles").json(body)
+    }
+
+    pub fn get_roles(&self) -> TestRequest {
+        self.server.get("/roles")
+    }
+
+    pub fn put_role<Body>(&self, name: &str, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.put(&format!("/roles/{name}")).json(body)
+    }
+
+    pub fn delete_role(&self, name: &str) -> TestRequest {
+        self.server.delete(&format!("/roles/{name}"))
+    }
+
+    pub fn get_user_roles(&self, email: &str) -> TestRequest {
+        self.server.get(&format!("/users/{email}/roles"))
+    }
+
+    pub fn post_user_role<Body>(&self, email: &str, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server
+            .post(&format!("/users/{email}/roles"))
+            .json(body)
+    }
+
+    pub fn delete_user_role(&self, email: &str, name: &str) -> TestRequest {
+        self.server.delete(&format!("/users/{email}/roles/{name}"))
+    }
+
+    pub fn get_admin_users<Params>(&self, params: &Params) -> TestRequest
+    where
+        Params: serde::Serialize,
+    {
+        self.server.get("/admin/users").add_query_params(params)
+    }
+
+    pub fn get_admin_user(&self, email: &str) -> TestRequest {
+        self.server.get(&format!("/admin/users/{email}"))
+    }
+
+    pub fn post_admin_lock(&self, email: &str) -> TestRequest {
+        self.server.post(&format!("/admin/users/{email}/lock"))
+    }
+
+    pub fn post_admin_unlock(&self, email: &str) -> TestRequest {
+        self.server.post(&format!("/admin/users/{email}/unlock"))
+    }
+
+    pub fn delete_admin_two_factor(&self, email: &str) -> TestRequest {
+        self.server.delete(&format!("/admin/users/{email}/2fa"))
+    }
+
+    pub fn post_admin_logout(&self, email: &str) -> TestRequest {
+        self.server.post(&format!("/admin/users/{email}/logout"))
+    }
+
+    pub fn post_org<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/orgs").json(body)
+    }
+
+    pub fn get_orgs(&self) -> TestRequest {
+        self.server.get("/orgs")
+    }
+
+    pub fn get_org(&self, id: &str) -> TestRequest {
+        self.server.get(&format!("/orgs/{id}"))
+    }
+
+    pub fn delete_org(&self, id: &str) -> TestRequest {
+        self.server.delete(&format!("/orgs/{id}"))
+    }
+
+    pub fn get_org_members(&self, id: &str) -> TestRequest {
+        self.server.get(&format!("/orgs/{id}/members"))
+    }
+
+    pub fn put_org_member<Body>(&self, id: &str, email: &str, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server
+            .put(&format!("/orgs/{id}/members/{email}"))
+            .json(body)
+    }
+
+    pub fn delete_org_member(&self, id: &str, email: &str) -> TestRequest {
+        self.server.delete(&format!("/orgs/{id}/members/{email}"))
+    }
+
+    pub fn post_org_invitation<Body>(&self, id: &str, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server
+            .post(&format!("/orgs/{id}/invitations"))
+            .json(body)
+    }
+
+    pub fn get_invitations(&self) -> TestRequest {
+        self.server.get("/invitations")
+    }
+
+    pub fn post_accept_invitation(&self, id: &str) -> TestRequest {
+        self.server.post(&format!("/invitations/{id}/accept"))
+    }
+
+    pub fn post_switch_org<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/switch-org").json(body)
+    }
+
+    pub fn get_oauth_authorize<Params>(&self, params: &Params) -> TestRequest
+    where
+        Params: serde::Serialize,
+    {
+        self.server.get("/oauth/authorize").add_query_params(params)
+    }
+
+    pub fn post_oauth_authorize<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/oauth/authorize").form(body)
+    }
+
+    pub fn get_userinfo(&self) -> TestRequest {
+        self.server.get("/userinfo")
+    }
+
+    pub async fn get_openid_configuration(&self) -> TestResponse {
+        self.server.get("/.well-known/openid-configuration").await
+    }
+
+    pub fn post_oauth_token<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/oauth/token").form(body)
+    }
+
+    pub fn post_device_code<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/device/code").form(body)
+    }
+
+    pub fn get_device_verify(&self, user_code: &str) -> TestRequest {
+        self.server
+            .get("/device/verify")
+            .add_query_param("user_code", user_code)
+    }
+
+    pub fn post_device_verify<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/device/verify").json(body)
+    }
+
+    pub fn post_introspect<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/introspect").form(body)
+    }
+
+    pub fn post_revoke<Body>(&self, body: &Body) -> TestRequest
+    where
+        Body: serde::Serialize,
+    {
+        self.server.post("/revoke").form(body)
+    }
+
+    /// Body is optional, the refresh cookie is used otherwise
+    pub fn post_token_refresh(&self) -> TestRequest {
+        self.server.post("/token/refresh")
+    }
+
+    /// Most recent email sent to `to`, if any
+    pub fn last_email_to(&self, to: &str) -> Option<SentEmail> {
+        self.emails
+            .0
+            .lock()
+            .expect("email outbox")
+            .iter()
+            .rev()
+            .find(|email| email.to == to)
+            .cloned()
+    }
+}
+
+/// Value of query parameter `name` in a redirect location
+pub fn query_param(location: &str, name: &str) -> Option<String> {
+    let url = reqwest::Url::parse(location).expect("valid url");
+    url.query_pairs()
+        .find_map(|(key, value)| (key == name).then(|| value.to_string()))
+}
+
+/// Login attempt id carried in the `payload` of a 2FA redirect url
+pub fn two_factor_attempt_id(redirect_url: &str) -> String {
+    let url = reqwest::Url::parse(redirect_url).expect("valid url");
+    let payload = url
+        .query_pairs()
+        .find_map(|(key, value)| (key == "payload").then(|| value.to_string()))
+        .expect("jwt payload");
+    let payload = URL_SAFE.decode(payload).expect("decoded");
+    let payload = str::from_utf8(&payload).expect("string");
+    let jwt =
+        jsonwebtoken::dangerous::insecure_decode::<TwoFAClaims>(payload).expect("valid token data");
+    jwt.claims.sub.as_ref().to_string()
+}
+
+/// Register an OAuth client that authenticates with a secret, returning the secret
+pub async fn register_confidential_client(app: &TestApp, client_id: &str) -> ClientSecret {
+    let secret = ClientSecret::generate();
+    app.state
+        .clients
+        .write()
+        .await
+        .add_client(OAuthClient {
+            client_id: client_id.to_string(),
+            name: "Test Service".to_string(),
+            redirect_uris: vec![],
+            allowed_scopes: vec!["orders:read".to_string(), "orders:write".to_string()],
+            secret_hash: Some(HashedClientSecret::parse(&secret).await.expect("hashed")),
+        })
+        .await
+        .expect("client registered");
+    secret
+}
+
+/// `Authorization` header value for `client_secret_basic`
+pub fn basic_auth(client_id: &str, secret: &str) -> String {
+    format!("Basic {}", STANDARD.encode(format!("{client_id}:{secret}")))
 }
 
 /// Runs schema migrations defined by shki output
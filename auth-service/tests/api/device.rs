@@ -0,0 +1,278 @@
+use lgr_auth::config::Config;
+use lgr_auth::domain::OAuthClient;
+use lgr_auth::routes::{
+    DeviceCodeResponse, DeviceVerifyResponse, LoginResponse, OpenIdConfiguration, TokenResponse,
+};
+use lgr_auth::utils::auth::{Claims, validate_token};
+use reqwest::StatusCode;
+
+use crate::common::{TestApp, basic_auth, get_test_app, register_confidential_client};
+
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+async fn register_client(app: &TestApp, client_id: &str) {
+    app.state
+        .clients
+        .write()
+        .await
+        .add_client(OAuthClient {
+            client_id: client_id.to_string(),
+            name: "Test CLI".to_string(),
+            redirect_uris: vec![],
+            allowed_scopes: vec!["repo:read".to_string(), "repo:write".to_string()],
+            secret_hash: None,
+        })
+        .await
+        .expect("client registered");
+}
+
+/// Sign up and log in, returning the access token
+async fn signup_and_login(app: &TestApp, email: &str) -> String {
+    let body = serde_json::json!({
+        "method": "email_password",
+        "email": email,
+        "password": "password123",
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    match response.json::<LoginResponse>() {
+        LoginResponse::Success { token, .. } => token,
+        other => panic!("unexpected login response: {other:?}"),
+    }
+}
+
+fn auth_jar(app: &TestApp, token: &str) -> cookie::CookieJar {
+    let mut jar = cookie::CookieJar::default();
+    jar.add((app.config.jwt.cookie_name.clone(), token.to_string()));
+    jar
+}
+
+/// An app whose devices may poll as fast as they like
+async fn unthrottled_app() -> TestApp {
+    let mut config = Config::default();
+    config.oauth.device_poll_interval = 0;
+    TestApp::new(&config).await
+}
+
+async fn start(app: &TestApp, client_id: &str, scope: &str) -> DeviceCodeResponse {
+    let response = app
+        .post_device_code(&serde_json::json!({ "client_id": client_id, "scope": scope }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    response.json::<DeviceCodeResponse>()
+}
+
+async fn poll(app: &TestApp, client_id: &str, device_code: &str) -> axum_test::TestResponse {
+    app.post_oauth_token(&serde_json::json!({
+        "grant_type": DEVICE_CODE_GRANT,
+        "device_code": device_code,
+        "client_id": client_id,
+    }))
+    .await
+}
+
+fn assert_oauth_error(response: axum_test::TestResponse, error: &str) {
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(response.json::<serde_json::Value>()["error"], error);
+}
+
+async fn decide(app: &TestApp, token: &str, user_code: &str, decision: &str) -> StatusCode {
+    app.post_device_verify(&serde_json::json!({
+        "user_code": user_code,
+        "decision": decision,
+    }))
+    .add_cookies(auth_jar(app, token))
+    .await
+    .status_code()
+}
+
+#[tokio::test]
+async fn test_device_authorization_flow() {
+    let app = unthrottled_app().await;
+    let client_id = "device-flow-cli";
+    register_client(&app, client_id).await;
+
+    let device = start(&app, client_id, "repo:read").await;
+    assert!(device.verification_uri.ends_with("/device.html"));
+    assert!(
+        device
+            .verification_uri_complete
+            .ends_with(&format!("?user_code={}", device.user_code))
+    );
+    assert_eq!(device.expires_in, app.config.oauth.device_code_ttl);
+    assert_oauth_error(
+        poll(&app, client_id, &device.device_code).await,
+        "authorization_pending",
+    );
+
+    // the user has to be logged in to see the request
+    let response = app.get_device_verify(&device.user_code).await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    let token = signup_and_login(&app, "device-flow@me.com").await;
+    let typed = device.user_code.to_lowercase().replace('-', " ");
+    let response = app
+        .get_device_verify(&typed)
+        .add_cookies(auth_jar(&app, &token))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body = response.json::<DeviceVerifyResponse>();
+    assert_eq!(body.user_code, device.user_code);
+    assert_eq!(body.client_name, "Test CLI");
+    assert_eq!(body.scopes, vec!["repo:read".to_string()]);
+
+    assert_eq!(
+        decide(&app, &token, &device.user_code, "approve").await,
+        StatusCode::NO_CONTENT
+    );
+    // decided once
+    assert_eq!(
+        decide(&app, &token, &device.user_code, "deny").await,
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+
+    let response = poll(&app, client_id, &device.device_code).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(response.header("cache-control"), "no-store");
+    let body = response.json::<TokenResponse>();
+    assert_eq!(body.scope, "repo:read");
+    assert!(body.id_token.is_none());
+    let claims = validate_token::<Claims>(&body.access_token, &app.config.jwt)
+        .await
+        .expect("valid token");
+    assert_eq!(claims.sub, "device-flow@me.com");
+    assert_eq!(claims.client_id.as_deref(), Some(client_id));
+
+    // the device code is spent
+    assert_oauth_error(
+        poll(&app, client_id, &device.device_code).await,
+        "invalid_grant",
+    );
+}
+
+#[tokio::test]
+async fn test_device_polling_too_fast_slows_down() {
+    let app = get_test_app().await;
+    let client_id = "device-slow-cli";
+    register_client(app, client_id).await;
+    let device = start(app, client_id, "repo:read").await;
+    assert_eq!(device.interval, app.config.oauth.device_poll_interval);
+
+    assert_oauth_error(
+        poll(app, client_id, &device.device_code).await,
+        "authorization_pending",
+    );
+    assert_oauth_error(poll(app, client_id, &device.device_code).await, "slow_down");
+}
+
+#[tokio::test]
+async fn test_device_authorization_denied() {
+    let app = unthrottled_app().await;
+    let client_id = "device-deny-cli";
+    register_client(&app, client_id).await;
+    let device = start(&app, client_id, "repo:write").await;
+
+    let token = signup_and_login(&app, "device-deny@me.com").await;
+    assert_eq!(
+        decide(&app, &token, &device.user_code, "deny").await,
+        StatusCode::NO_CONTENT
+    );
+    assert_oauth_error(
+        poll(&app, client_id, &device.device_code).await,
+        "access_denied",
+    );
+}
+
+#[tokio::test]
+async fn test_device_code_is_bound_to_its_client() {
+    let app = unthrottled_app().await;
+    let client_id = "device-bound-cli";
+    let other_id = "device-other-cli";
+    register_client(&app, client_id).await;
+    register_client(&app, other_id).await;
+    let device = start(&app, client_id, "repo:read").await;
+
+    assert_oauth_error(
+        poll(&app, other_id, &device.device_code).await,
+        "invalid_grant",
+    );
+    // the request is untouched for the client it was issued to
+    assert_oauth_error(
+        poll(&app, client_id, &device.device_code).await,
+        "authorization_pending",
+    );
+    assert_oauth_error(
+        poll(&app, client_id, "not-a-device-code").await,
+        "invalid_grant",
+    );
+}
+
+#[tokio::test]
+async fn test_device_code_expires() {
+    let mut config = Config::default();
+    config.oauth.device_code_ttl = 2;
+    let app = TestApp::new(&config).await;
+    let client_id = "device-expired-cli";
+    register_client(&app, client_id).await;
+    // logged in first, the request is only kept for twice its lifetime
+    let token = signup_and_login(&app, "device-expired@me.com").await;
+    let device = start(&app, client_id, "repo:read").await;
+
+    tokio::time::sleep(std::time::Duration::from_millis(2100)).await;
+    let response = app
+        .get_device_verify(&device.user_code)
+        .add_cookies(auth_jar(&app, &token))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        decide(&app, &token, &device.user_code, "approve").await,
+        StatusCode::UNPROCESSABLE_ENTITY
+    );
+    assert_oauth_error(
+        poll(&app, client_id, &device.device_code).await,
+        "expired_token",
+    );
+}
+
+#[tokio::test]
+async fn test_device_code_requires_client_authentication() {
+    let app = get_test_app().await;
+    let response = app
+        .post_device_code(&serde_json::json!({ "client_id": "device-unknown-cli" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    let client_id = "device-confidential";
+    let secret = register_confidential_client(app, client_id).await;
+    let response = app
+        .post_device_code(&serde_json::json!({ "client_id": client_id }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    let response = app
+        .post_device_code(&serde_json::json!({ "scope": "orders:read" }))
+        .add_header("authorization", basic_auth(client_id, secret.as_ref()))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    // scopes are limited to what the client is allowed
+    let response = app
+        .post_device_code(&serde_json::json!({ "scope": "admin" }))
+        .add_header("authorization", basic_auth(client_id, secret.as_ref()))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_device_grant_is_advertised() {
+    let app = get_test_app().await;
+    let body = app
+        .get_openid_configuration()
+        .await
+        .json::<OpenIdConfiguration>();
+    assert!(body.device_authorization_endpoint.ends_with("/device/code"));
+    assert!(
+        body.grant_types_supported
+            .contains(&DEVICE_CODE_GRANT.to_string())
+    );
+}
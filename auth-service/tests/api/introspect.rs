@@ -0,0 +1,155 @@
+use lgr_auth::domain::{Email, Grants, TokenPurpose};
+use lgr_auth::routes::{IntrospectionResponse, LoginResponse, TokenResponse};
+use lgr_auth::utils::auth::{generate_auth_token, generate_one_time_token};
+use reqwest::StatusCode;
+
+use crate::common::{basic_auth, get_test_app, register_confidential_client};
+
+#[tokio::test]
+async fn test_introspect_access_token() {
+    let app = get_test_app().await;
+    let client_id = "introspect-gateway";
+    let secret = register_confidential_client(app, client_id).await;
+
+    let token = app
+        .post_oauth_token(&serde_json::json!({
+            "grant_type": "client_credentials",
+            "scope": "orders:read",
+        }))
+        .add_header("authorization", basic_auth(client_id, secret.as_ref()))
+        .await
+        .json::<TokenResponse>()
+        .access_token;
+
+    let response = app
+        .post_introspect(&serde_json::json!({ "token": token }))
+        .add_header("authorization", basic_auth(client_id, secret.as_ref()))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body = response.json::<IntrospectionResponse>();
+    assert!(body.active);
+    assert_eq!(body.sub.as_deref(), Some(client_id));
+    assert_eq!(body.client_id.as_deref(), Some(client_id));
+    assert_eq!(body.scope.as_deref(), Some("orders:read"));
+    assert_eq!(body.token_type.as_deref(), Some("Bearer"));
+    assert!(body.exp > body.iat);
+
+    // banned tokens are reported inactive
+    app.state
+        .banned_tokens
+        .write()
+        .await
+        .ban_token(&token)
+        .await
+        .expect("banned");
+    let response = app
+        .post_introspect(&serde_json::json!({
+            "token": token,
+            "client_id": client_id,
+            "client_secret": secret.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(
+        response.json::<serde_json::Value>(),
+        serde_json::json!({ "active": false })
+    );
+}
+
+#[tokio::test]
+async fn test_introspect_session_and_refresh_tokens() {
+    let app = get_test_app().await;
+    let client_id = "introspect-api";
+    let secret = register_confidential_client(app, client_id).await;
+
+    let email = Email::parse("introspect@test.com").expect("valid email");
+    let token = generate_auth_token(
+        &email,
+        0,
+        &Grants::default(),
+        app.config.jwt.access_token_ttl,
+        &app.config.jwt.secret,
+    )
+    .expect("valid token");
+    let body = app
+        .post_introspect(&serde_json::json!({ "token": token }))
+        .add_header("authorization", basic_auth(client_id, secret.as_ref()))
+        .await
+        .json::<IntrospectionResponse>();
+    assert!(body.active);
+    assert_eq!(body.sub.as_deref(), Some("introspect@test.com"));
+    assert!(body.scope.is_none());
+
+    let credentials = serde_json::json!({
+        "method": "email_password",
+        "email": "introspect-refresh@test.com",
+        "password": "password123",
+    });
+    app.post_signup(&credentials).await;
+    let refresh_token = match app.post_login(&credentials).await.json::<LoginResponse>() {
+        LoginResponse::Success { refresh_token, .. } => refresh_token,
+        other => panic!("unexpected login response: {other:?}"),
+    };
+    let body = app
+        .post_introspect(&serde_json::json!({
+            "token": refresh_token,
+            "token_type_hint": "refresh_token",
+        }))
+        .add_header("authorization", basic_auth(client_id, secret.as_ref()))
+        .await
+        .json::<IntrospectionResponse>();
+    assert!(body.active);
+    assert_eq!(body.sub.as_deref(), Some("introspect-refresh@test.com"));
+    assert_eq!(body.token_type.as_deref(), Some("refresh_token"));
+
+    let body = app
+        .post_introspect(&serde_json::json!({ "token": "garbage" }))
+        .add_header("authorization", basic_auth(client_id, secret.as_ref()))
+        .await
+        .json::<IntrospectionResponse>();
+    assert!(!body.active);
+}
+
+#[tokio::test]
+async fn test_introspect_reports_other_tokens_inactive() {
+    let app = get_test_app().await;
+    let client_id = "introspect-one-time";
+    let secret = register_confidential_client(app, client_id).await;
+
+    let email = Email::parse("introspect-one-time@test.com").expect("valid email");
+    for purpose in [TokenPurpose::MagicLink, TokenPurpose::EmailVerification] {
+        let (_, token) = generate_one_time_token(&email, &purpose, 600, &app.config.jwt.secret)
+            .expect("valid token");
+        let response = app
+            .post_introspect(&serde_json::json!({ "token": token }))
+            .add_header("authorization", basic_auth(client_id, secret.as_ref()))
+            .await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+        assert_eq!(
+            response.json::<serde_json::Value>(),
+            serde_json::json!({ "active": false })
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_introspect_requires_client_authentication() {
+    let app = get_test_app().await;
+    let client_id = "introspect-unauthenticated";
+    register_confidential_client(app, client_id).await;
+
+    let response = app
+        .post_introspect(&serde_json::json!({ "token": "anything" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .post_introspect(&serde_json::json!({ "token": "anything" }))
+        .add_header("authorization", basic_auth(client_id, "wrong"))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        response.json::<serde_json::Value>()["error"],
+        "invalid_client"
+    );
+}
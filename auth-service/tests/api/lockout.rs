@@ -0,0 +1,152 @@
+use lgr_auth::config::Config;
+use lgr_auth::domain::EmailTemplate;
+use reqwest::StatusCode;
+
+use crate::common::{TestApp, query_param};
+
+/// An app locking accounts after 3 failed logins, optionally emailing an unlock link
+async fn app_with_lockout(unlock_by_email: bool) -> TestApp {
+    let mut config = Config::default();
+    config.lockout.threshold = 3;
+    config.lockout.unlock_by_email = unlock_by_email;
+    TestApp::new(&config).await
+}
+
+async fn signup(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "method": "email_password",
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+}
+
+async fn login_status(app: &TestApp, email: &str, password: &str) -> StatusCode {
+    app.post_login(&serde_json::json!({
+        "method": "email_password",
+        "email": email,
+        "password": password,
+    }))
+    .await
+    .status_code()
+}
+
+#[tokio::test]
+async fn test_repeated_failures_lock_the_account() {
+    let app = app_with_lockout(false).await;
+    let email = "lockout@me.com";
+    signup(&app, email).await;
+
+    for _ in 0..2 {
+        assert_eq!(
+            login_status(&app, email, "wrong-password").await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+    assert_eq!(
+        login_status(&app, email, "wrong-password").await,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+    // the right password doesn't get past the lock either
+    assert_eq!(
+        login_status(&app, email, "password123").await,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+
+    // unknown accounts lock the same way, so lockouts don't reveal who signed up
+    for _ in 0..2 {
+        assert_eq!(
+            login_status(&app, "lockout-nobody@me.com", "wrong-password").await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+    assert_eq!(
+        login_status(&app, "lockout-nobody@me.com", "wrong-password").await,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+}
+
+#[tokio::test]
+async fn test_concurrent_guesses_stop_at_the_threshold() {
+    let mut config = Config::default();
+    config.lockout.threshold = 3;
+    config.rate_limit.enabled = false;
+    let app = TestApp::new(&config).await;
+    let email = "lockout-burst@me.com";
+    signup(&app, email).await;
+
+    let guess = || login_status(&app, email, "wrong-password");
+    let statuses = tokio::join!(guess(), guess(), guess(), guess(), guess(), guess());
+    let statuses = [
+        statuses.0, statuses.1, statuses.2, statuses.3, statuses.4, statuses.5,
+    ];
+    // only the guesses counted before the lock get their password checked
+    let checked = statuses
+        .iter()
+        .filter(|status| **status == StatusCode::UNAUTHORIZED)
+        .count();
+    assert_eq!(checked, 2, "{statuses:?}");
+    assert!(
+        statuses.iter().all(
+            |status| [StatusCode::UNAUTHORIZED, StatusCode::TOO_MANY_REQUESTS].contains(status)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_successful_login_resets_the_count() {
+    let app = app_with_lockout(false).await;
+    let email = "lockout-reset@me.com";
+    signup(&app, email).await;
+
+    for _ in 0..2 {
+        login_status(&app, email, "wrong-password").await;
+    }
+    assert_eq!(
+        login_status(&app, email, "password123").await,
+        StatusCode::OK
+    );
+    for _ in 0..2 {
+        assert_eq!(
+            login_status(&app, email, "wrong-password").await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_unlock_link_lifts_the_lock() {
+    let app = app_with_lockout(true).await;
+    let email = "lockout-unlock@me.com";
+    signup(&app, email).await;
+
+    for _ in 0..3 {
+        login_status(&app, email, "wrong-password").await;
+    }
+    assert_eq!(
+        login_status(&app, email, "password123").await,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+
+    let link = match app.last_email_to(email).expect("unlock email").template {
+        EmailTemplate::AccountUnlock(data) => data.link_url,
+        other => panic!("unexpected email: {other:?}"),
+    };
+    let token = query_param(&link, "token").expect("token in link");
+    let response = app
+        .post_unlock_account(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(
+        login_status(&app, email, "password123").await,
+        StatusCode::OK
+    );
+
+    // the link only works once
+    let response = app
+        .post_unlock_account(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
@@ -8,7 +8,7 @@ async fn test_logout_200_if_valid_jwt() {
     let app = get_test_app().await;
     let email = Email::parse("can@logout.com").unwrap();
     let mock_token =
-        generate_auth_cookie(&email, &app.config.jwt).expect("Failed to generate auth cookie");
+        generate_auth_cookie(&email, 0, &app.config.jwt).expect("Failed to generate auth cookie");
     let mut server_jar = CookieJar::default();
     server_jar.add(mock_token);
     let response = app.post_logout().add_cookies(server_jar).await;
@@ -36,7 +36,7 @@ async fn test_logout_400_if_called_twice_in_a_row() {
     let app = get_test_app().await;
     let email = Email::parse("can@logout.com").unwrap();
     let mock_token =
-        generate_auth_cookie(&email, &app.config.jwt).expect("Failed to generate auth cookie");
+        generate_auth_cookie(&email, 0, &app.config.jwt).expect("Failed to generate auth cookie");
     let mut server_jar = CookieJar::default();
     server_jar.add(mock_token);
     let response = app.post_logout().add_cookies(server_jar).await;
@@ -0,0 +1,134 @@
+use lgr_auth::domain::{Email, EmailTemplate, TwoFactorMethod};
+use lgr_auth::routes::LoginResponse;
+use reqwest::{StatusCode, Url};
+
+use crate::common::{TestApp, get_test_app};
+
+async fn signup(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "method": "email_password",
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+}
+
+fn magic_link_token(app: &TestApp, email: &str) -> String {
+    let sent = app.last_email_to(email).expect("magic link email sent");
+    let data = match sent.template {
+        EmailTemplate::MagicLink(data) => data,
+        other => panic!("unexpected email template: {other:?}"),
+    };
+    let url = Url::parse(&data.link_url).expect("valid url");
+    url.query_pairs()
+        .find_map(|(key, value)| (key == "token").then(|| value.to_string()))
+        .expect("token in link")
+}
+
+#[tokio::test]
+async fn test_magic_link_202_and_email_sent() {
+    let app = get_test_app().await;
+    let email = "magic202@me.com";
+    signup(app, email).await;
+
+    let response = app
+        .post_login(&serde_json::json!({ "method": "magic_link", "email": email }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+    let body = response.json::<LoginResponse>();
+    assert!(matches!(body, LoginResponse::MagicLink { .. }));
+    assert!(!magic_link_token(app, email).is_empty());
+}
+
+#[tokio::test]
+async fn test_magic_link_202_for_unknown_email_without_sending() {
+    let app = get_test_app().await;
+    let email = "magic-unknown@me.com";
+
+    let response = app
+        .post_login(&serde_json::json!({ "method": "magic_link", "email": email }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+    assert!(app.last_email_to(email).is_none());
+}
+
+#[tokio::test]
+async fn test_magic_link_400_if_invalid_email() {
+    let app = get_test_app().await;
+    let response = app
+        .post_login(&serde_json::json!({ "method": "magic_link", "email": "not-an-email" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_verify_magic_link_200_sets_cookie() {
+    let app = get_test_app().await;
+    let email = "magic200@me.com";
+    signup(app, email).await;
+    app.post_login(&serde_json::json!({ "method": "magic_link", "email": email }))
+        .await;
+    let token = magic_link_token(app, email);
+
+    let response = app
+        .post_verify_magic_link(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let auth_cookie = response.cookie(&app.config.jwt.cookie_name);
+    assert!(!auth_cookie.value().is_empty());
+}
+
+#[tokio::test]
+async fn test_verify_magic_link_401_if_reused() {
+    let app = get_test_app().await;
+    let email = "magic-reuse@me.com";
+    signup(app, email).await;
+    app.post_login(&serde_json::json!({ "method": "magic_link", "email": email }))
+        .await;
+    let token = magic_link_token(app, email);
+
+    let body = serde_json::json!({ "token": token });
+    let response = app.post_verify_magic_link(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let response = app.post_verify_magic_link(&body).await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_verify_magic_link_401_if_invalid_token() {
+    let app = get_test_app().await;
+    let response = app
+        .post_verify_magic_link(&serde_json::json!({ "token": "not-a-token" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_verify_magic_link_asks_for_the_second_factor() {
+    let app = get_test_app().await;
+    let email = "magic-totp@me.com";
+    signup(app, email).await;
+    app.state
+        .user_store
+        .write()
+        .await
+        .set_two_factor(&Email::parse(email).unwrap(), &TwoFactorMethod::Totp)
+        .await
+        .expect("totp enabled");
+    app.post_login(&serde_json::json!({ "method": "magic_link", "email": email }))
+        .await;
+    let token = magic_link_token(app, email);
+
+    let response = app
+        .post_verify_magic_link(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::PARTIAL_CONTENT);
+    assert!(response.maybe_cookie(&app.config.jwt.cookie_name).is_none());
+    match response.json::<LoginResponse>() {
+        LoginResponse::TwoFactor { method, .. } => assert_eq!(method, TwoFactorMethod::Totp),
+        other => panic!("unexpected response: {other:?}"),
+    }
+}
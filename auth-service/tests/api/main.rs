@@ -1,8 +1,29 @@
-mod health;
+mod account;
+mod admin;
+mod api_keys;
+mod change_email;
 mod common;
+mod device;
+mod federated;
+mod health;
+mod introspect;
+mod lockout;
 mod login;
 mod logout;
+mod magic_link;
+mod oauth;
+mod orgs;
+mod passkey;
+mod password;
+mod rate_limit;
+mod recovery_codes;
+mod refresh_token;
+mod revoke;
+mod roles;
 mod routes;
+mod sessions;
 mod signup;
+mod totp;
 mod verify_2fa;
+mod verify_email;
 mod verify_token;
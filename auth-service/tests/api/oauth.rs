@@ -0,0 +1,462 @@
+use lgr_auth::domain::{AuthMethod, OAuthClient};
+use lgr_auth::routes::{
+    LoginResponse, OpenIdConfiguration, TokenResponse, UserInfoResponse, VerifyTokenResponse,
+};
+use lgr_auth::utils::auth::{Claims, IdTokenClaims, validate_token};
+use reqwest::StatusCode;
+
+use crate::common::{TestApp, basic_auth, get_test_app, query_param, register_confidential_client};
+
+// example from RFC 7636 appendix B
+const VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+const CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+const REDIRECT_URI: &str = "https://client.test/callback";
+
+async fn register_client(app: &TestApp, client_id: &str) {
+    app.state
+        .clients
+        .write()
+        .await
+        .add_client(OAuthClient {
+            client_id: client_id.to_string(),
+            name: "Test Client".to_string(),
+            redirect_uris: vec![REDIRECT_URI.to_string()],
+            allowed_scopes: vec![
+                "openid".to_string(),
+                "email".to_string(),
+                "profile".to_string(),
+            ],
+            secret_hash: None,
+        })
+        .await
+        .expect("client registered");
+}
+
+/// Sign up and log in, returning the access token
+async fn signup_and_login(app: &TestApp, email: &str) -> String {
+    let body = serde_json::json!({
+        "method": "email_password",
+        "email": email,
+        "password": "password123",
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    match response.json::<LoginResponse>() {
+        LoginResponse::Success { token, .. } => token,
+        other => panic!("unexpected login response: {other:?}"),
+    }
+}
+
+fn auth_jar(app: &TestApp, token: &str) -> cookie::CookieJar {
+    let mut jar = cookie::CookieJar::default();
+    jar.add((app.config.jwt.cookie_name.clone(), token.to_string()));
+    jar
+}
+
+fn authorize_params(client_id: &str) -> Vec<(&'static str, String)> {
+    vec![
+        ("response_type", "code".to_string()),
+        ("client_id", client_id.to_string()),
+        ("redirect_uri", REDIRECT_URI.to_string()),
+        ("scope", "openid".to_string()),
+        ("state", "xyz".to_string()),
+        ("code_challenge", CHALLENGE.to_string()),
+        ("code_challenge_method", "S256".to_string()),
+    ]
+}
+
+/// Approve the consent form and return the redirect location
+async fn approve(app: &TestApp, token: &str, client_id: &str) -> String {
+    approve_with(app, token, authorize_params(client_id)).await
+}
+
+async fn approve_with(app: &TestApp, token: &str, mut form: Vec<(&str, String)>) -> String {
+    form.push(("decision", "approve".to_string()));
+    let response = app
+        .post_oauth_authorize(&form)
+        .add_cookies(auth_jar(app, token))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+    response.header("location").to_str().unwrap().to_string()
+}
+
+fn token_request(client_id: &str, code: &str, verifier: &str) -> serde_json::Value {
+    serde_json::json!({
+        "grant_type": "authorization_code",
+        "code": code,
+        "redirect_uri": REDIRECT_URI,
+        "client_id": client_id,
+        "code_verifier": verifier,
+    })
+}
+
+#[tokio::test]
+async fn test_authorization_code_flow() {
+    let app = get_test_app().await;
+    let client_id = "oauth-flow-client";
+    register_client(app, client_id).await;
+
+    // without a session the user is sent to log in first
+    let response = app.get_oauth_authorize(&authorize_params(client_id)).await;
+    assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+    let location = response.header("location").to_str().unwrap().to_string();
+    let return_to = query_param(&location, "return_to").expect("return_to");
+    assert!(return_to.contains("/oauth/authorize?"));
+
+    let token = signup_and_login(app, "oauth-flow@me.com").await;
+    let response = app
+        .get_oauth_authorize(&authorize_params(client_id))
+        .add_cookies(auth_jar(app, &token))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(response.text().contains("Test Client"));
+
+    let location = approve(app, &token, client_id).await;
+    assert!(location.starts_with(REDIRECT_URI));
+    assert_eq!(query_param(&location, "state").as_deref(), Some("xyz"));
+    let code = query_param(&location, "code").expect("code");
+
+    let response = app
+        .post_oauth_token(&token_request(client_id, &code, VERIFIER))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(response.header("cache-control"), "no-store");
+    let body = response.json::<TokenResponse>();
+    assert_eq!(body.token_type, "Bearer");
+    assert_eq!(body.scope, "openid");
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": body.access_token }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    // codes are single use
+    let response = app
+        .post_oauth_token(&token_request(client_id, &code, VERIFIER))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    let body = response.json::<serde_json::Value>();
+    assert_eq!(body["error"], "invalid_grant");
+}
+
+#[tokio::test]
+async fn test_token_rejects_wrong_code_verifier() {
+    let app = get_test_app().await;
+    let client_id = "oauth-pkce-client";
+    register_client(app, client_id).await;
+    let token = signup_and_login(app, "oauth-pkce@me.com").await;
+    let code = query_param(&approve(app, &token, client_id).await, "code").expect("code");
+
+    let wrong = "aBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    let response = app
+        .post_oauth_token(&token_request(client_id, &code, wrong))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json::<serde_json::Value>()["error"],
+        "invalid_grant"
+    );
+
+    // a failed exchange spends the code
+    let response = app
+        .post_oauth_token(&token_request(client_id, &code, VERIFIER))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_token_requires_redirect_uri_named_in_authorization() {
+    let app = get_test_app().await;
+    let client_id = "oauth-token-redirect-client";
+    register_client(app, client_id).await;
+    let token = signup_and_login(app, "oauth-token-redirect@me.com").await;
+
+    let code = query_param(&approve(app, &token, client_id).await, "code").expect("code");
+    let mut body = token_request(client_id, &code, VERIFIER);
+    body.as_object_mut().unwrap().remove("redirect_uri");
+    let response = app.post_oauth_token(&body).await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json::<serde_json::Value>()["error"],
+        "invalid_grant"
+    );
+
+    // left out of both, the single registered uri is used
+    let mut form = authorize_params(client_id);
+    form.retain(|(name, _)| *name != "redirect_uri");
+    let code = query_param(&approve_with(app, &token, form).await, "code").expect("code");
+    let mut body = token_request(client_id, &code, VERIFIER);
+    body.as_object_mut().unwrap().remove("redirect_uri");
+    let response = app.post_oauth_token(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_authorization_code_is_not_an_access_token() {
+    let app = get_test_app().await;
+    let client_id = "oauth-code-bearer-client";
+    register_client(app, client_id).await;
+    let token = signup_and_login(app, "oauth-code-bearer@me.com").await;
+    let code = query_param(&approve(app, &token, client_id).await, "code").expect("code");
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": code }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    assert!(
+        validate_token::<Claims>(&code, &app.config.jwt)
+            .await
+            .is_err()
+    );
+
+    // still good at the token endpoint
+    let response = app
+        .post_oauth_token(&token_request(client_id, &code, VERIFIER))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_authorize_rejects_unregistered_redirect_uri() {
+    let app = get_test_app().await;
+    let client_id = "oauth-redirect-client";
+    register_client(app, client_id).await;
+
+    let mut params = authorize_params(client_id);
+    params[2].1 = "https://evil.test/callback".to_string();
+    let response = app.get_oauth_authorize(&params).await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    assert!(response.maybe_header("location").is_none());
+
+    let response = app.get_oauth_authorize(&authorize_params("unknown")).await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+
+    // once the redirect uri is trusted, errors go back to the client
+    let mut params = authorize_params(client_id);
+    params.retain(|(key, _)| *key != "code_challenge");
+    let response = app.get_oauth_authorize(&params).await;
+    assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+    let location = response.header("location").to_str().unwrap().to_string();
+    assert_eq!(
+        query_param(&location, "error").as_deref(),
+        Some("invalid_request")
+    );
+}
+
+#[tokio::test]
+async fn test_authorize_deny_redirects_access_denied() {
+    let app = get_test_app().await;
+    let client_id = "oauth-deny-client";
+    register_client(app, client_id).await;
+    let token = signup_and_login(app, "oauth-deny@me.com").await;
+
+    let mut form = authorize_params(client_id);
+    form.push(("decision", "deny".to_string()));
+    let response = app
+        .post_oauth_authorize(&form)
+        .add_cookies(auth_jar(app, &token))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::SEE_OTHER);
+    let location = response.header("location").to_str().unwrap().to_string();
+    assert_eq!(
+        query_param(&location, "error").as_deref(),
+        Some("access_denied")
+    );
+    assert_eq!(query_param(&location, "state").as_deref(), Some("xyz"));
+}
+
+#[tokio::test]
+async fn test_openid_configuration() {
+    let app = get_test_app().await;
+    let response = app.get_openid_configuration().await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body = response.json::<OpenIdConfiguration>();
+    let issuer = &app.config.oauth.issuer;
+    assert_eq!(&body.issuer, issuer);
+    assert_eq!(body.token_endpoint, format!("{issuer}/oauth/token"));
+    assert_eq!(body.jwks_uri, format!("{issuer}/.well-known/jwks.json"));
+    assert!(body.scopes_supported.contains(&"openid".to_string()));
+    assert_eq!(body.code_challenge_methods_supported, vec!["S256"]);
+}
+
+#[tokio::test]
+async fn test_openid_id_token_and_userinfo() {
+    let app = get_test_app().await;
+    let client_id = "oidc-client";
+    register_client(app, client_id).await;
+    let email = "oidc-flow@me.com";
+    let token = signup_and_login(app, email).await;
+
+    let mut params = authorize_params(client_id);
+    params[3].1 = "openid email".to_string();
+    params.push(("nonce", "n-0S6_WzA2Mj".to_string()));
+    let location = approve_with(app, &token, params).await;
+    let code = query_param(&location, "code").expect("code");
+    let body = app
+        .post_oauth_token(&token_request(client_id, &code, VERIFIER))
+        .await
+        .json::<TokenResponse>();
+
+    let id_token = body.id_token.expect("id_token with the openid scope");
+    let claims = jsonwebtoken::dangerous::insecure_decode::<IdTokenClaims>(&id_token)
+        .expect("valid id_token")
+        .claims;
+    assert_eq!(claims.iss, app.config.oauth.issuer);
+    assert_eq!(claims.aud, client_id);
+    assert_eq!(claims.sub, email);
+    assert_eq!(claims.nonce.as_deref(), Some("n-0S6_WzA2Mj"));
+    assert_eq!(claims.authentication.amr, vec![AuthMethod::Pwd]);
+    assert!(claims.authentication.auth_time.is_some());
+    assert_eq!(claims.email.as_deref(), Some(email));
+
+    let response = app
+        .get_userinfo()
+        .authorization_bearer(&body.access_token)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let info = response.json::<UserInfoResponse>();
+    assert_eq!(info.sub, email);
+    assert_eq!(info.email.as_deref(), Some(email));
+
+    // a session token is not an OAuth access token
+    let response = app.get_userinfo().authorization_bearer(&token).await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+    let response = app.get_userinfo().await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    assert!(
+        response
+            .header("www-authenticate")
+            .to_str()
+            .unwrap()
+            .starts_with("Bearer")
+    );
+}
+
+#[tokio::test]
+async fn test_token_without_openid_scope_has_no_id_token() {
+    let app = get_test_app().await;
+    let client_id = "oidc-plain-client";
+    register_client(app, client_id).await;
+    let token = signup_and_login(app, "oidc-plain@me.com").await;
+
+    let mut params = authorize_params(client_id);
+    params[3].1 = "profile".to_string();
+    let code = query_param(&approve_with(app, &token, params).await, "code").expect("code");
+    let body = app
+        .post_oauth_token(&token_request(client_id, &code, VERIFIER))
+        .await
+        .json::<TokenResponse>();
+    assert!(body.id_token.is_none());
+
+    let response = app
+        .get_userinfo()
+        .authorization_bearer(&body.access_token)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_client_credentials_grant() {
+    let app = get_test_app().await;
+    let client_id = "cc-service";
+    let secret = register_confidential_client(app, client_id).await;
+
+    let response = app
+        .post_oauth_token(&serde_json::json!({
+            "grant_type": "client_credentials",
+            "scope": "orders:read",
+        }))
+        .add_header("authorization", basic_auth(client_id, secret.as_ref()))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body = response.json::<TokenResponse>();
+    assert_eq!(body.scope, "orders:read");
+    assert!(body.id_token.is_none());
+
+    // signed with the key published in the JWKS
+    let claims = validate_token::<Claims>(&body.access_token, &app.config.jwt)
+        .await
+        .expect("valid token");
+    assert_eq!(claims.sub, client_id);
+    assert!(claims.is_client_credentials());
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": body.access_token }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let verified = response.json::<VerifyTokenResponse>();
+    assert_eq!(verified.sub, client_id);
+    assert_eq!(verified.scopes, vec!["orders:read".to_string()]);
+
+    // client_secret_post, every allowed scope by default
+    let response = app
+        .post_oauth_token(&serde_json::json!({
+            "grant_type": "client_credentials",
+            "client_id": client_id,
+            "client_secret": secret.as_ref(),
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(
+        response.json::<TokenResponse>().scope,
+        "orders:read orders:write"
+    );
+
+    // not a user, so no userinfo
+    let response = app
+        .get_userinfo()
+        .authorization_bearer(&body.access_token)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_client_credentials_rejects_bad_clients() {
+    let app = get_test_app().await;
+    let client_id = "cc-rejected-service";
+    register_confidential_client(app, client_id).await;
+
+    let response = app
+        .post_oauth_token(&serde_json::json!({ "grant_type": "client_credentials" }))
+        .add_header("authorization", basic_auth(client_id, "wrong-secret"))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        response.json::<serde_json::Value>()["error"],
+        "invalid_client"
+    );
+
+    let secret = register_confidential_client(app, "cc-scoped-service").await;
+    let response = app
+        .post_oauth_token(&serde_json::json!({
+            "grant_type": "client_credentials",
+            "scope": "admin",
+        }))
+        .add_header(
+            "authorization",
+            basic_auth("cc-scoped-service", secret.as_ref()),
+        )
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json::<serde_json::Value>()["error"],
+        "invalid_scope"
+    );
+
+    // public clients have nothing to authenticate with
+    let public_id = "cc-public-client";
+    register_client(app, public_id).await;
+    let response = app
+        .post_oauth_token(&serde_json::json!({
+            "grant_type": "client_credentials",
+            "client_id": public_id,
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json::<serde_json::Value>()["error"],
+        "unauthorized_client"
+    );
+}
@@ -0,0 +1,245 @@
+use lgr_auth::domain::{EmailTemplate, Invitation, Membership, OrgRole};
+use lgr_auth::routes::{LoginResponse, OrgResponse, SwitchOrgResponse, VerifyTokenResponse};
+use reqwest::StatusCode;
+
+use crate::common::{TestApp, get_test_app, query_param};
+
+/// Sign up and log in, returning the access and refresh tokens
+async fn signup_and_login_with_refresh(app: &TestApp, email: &str) -> (String, String) {
+    let body = serde_json::json!({
+        "method": "email_password",
+        "email": email,
+        "password": "password123",
+    });
+    assert_eq!(
+        app.post_signup(&body).await.status_code(),
+        StatusCode::CREATED
+    );
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    match response.json::<LoginResponse>() {
+        LoginResponse::Success {
+            token,
+            refresh_token,
+            ..
+        } => (token, refresh_token),
+        other => panic!("unexpected login response: {other:?}"),
+    }
+}
+
+async fn signup_and_login(app: &TestApp, email: &str) -> String {
+    signup_and_login_with_refresh(app, email).await.0
+}
+
+fn auth_jar(app: &TestApp, token: &str) -> cookie::CookieJar {
+    let mut jar = cookie::CookieJar::default();
+    jar.add((app.config.jwt.cookie_name.clone(), token.to_string()));
+    jar
+}
+
+async fn create_org(app: &TestApp, token: &str, name: &str) -> String {
+    let response = app
+        .post_org(&serde_json::json!({ "name": name }))
+        .add_cookies(auth_jar(app, token))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    let org = response.json::<OrgResponse>();
+    assert_eq!(org.role, OrgRole::Owner);
+    org.organization.id.to_string()
+}
+
+/// Invite `email` as a member and accept through the link in the invitation email
+async fn invite_and_accept(app: &TestApp, org: &str, owner: &str, email: &str, token: &str) {
+    let response = app
+        .post_org_invitation(org, &serde_json::json!({ "email": email }))
+        .add_cookies(auth_jar(app, owner))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    let invitation = response.json::<Invitation>();
+    assert_eq!(invitation.role, OrgRole::Member);
+
+    let link = match app.last_email_to(email).expect("invitation email").template {
+        EmailTemplate::OrgInvitation(data) => data.link_url,
+        other => panic!("unexpected email: {other:?}"),
+    };
+    let id = query_param(&link, "id").expect("invitation id");
+    assert_eq!(id, invitation.id.to_string());
+
+    let response = app
+        .post_accept_invitation(&id)
+        .add_cookies(auth_jar(app, token))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(response.json::<Membership>().role, OrgRole::Member);
+}
+
+async fn switch(app: &TestApp, token: &str, org: Option<&str>) -> axum_test::TestResponse {
+    app.post_switch_org(&serde_json::json!({ "org_id": org }))
+        .add_cookies(auth_jar(app, token))
+        .await
+}
+
+async fn verify(app: &TestApp, token: &str) -> axum_test::TestResponse {
+    app.post_verify_token(&serde_json::json!({ "token": token }))
+        .await
+}
+
+#[tokio::test]
+async fn test_invited_member_joins_the_organization() {
+    let app = get_test_app().await;
+    let owner = signup_and_login(app, "orgs-owner@me.com").await;
+    let member = signup_and_login(app, "orgs-member@me.com").await;
+    let org = create_org(app, &owner, "Acme").await;
+
+    invite_and_accept(app, &org, &owner, "orgs-member@me.com", &member).await;
+
+    let response = app.get_orgs().add_cookies(auth_jar(app, &member)).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let orgs = response.json::<Vec<OrgResponse>>();
+    assert_eq!(orgs.len(), 1);
+    assert_eq!(orgs[0].organization.name, "Acme");
+    assert_eq!(orgs[0].role, OrgRole::Member);
+
+    let response = app
+        .get_org_members(&org)
+        .add_cookies(auth_jar(app, &member))
+        .await;
+    assert_eq!(response.json::<Vec<Membership>>().len(), 2);
+
+    // the invitation is used up
+    let response = app
+        .get_invitations()
+        .add_cookies(auth_jar(app, &member))
+        .await;
+    assert!(response.json::<Vec<Invitation>>().is_empty());
+}
+
+#[tokio::test]
+async fn test_members_cant_manage_the_organization() {
+    let app = get_test_app().await;
+    let owner = signup_and_login(app, "orgs-manage-owner@me.com").await;
+    let member = signup_and_login(app, "orgs-manage-member@me.com").await;
+    let outsider = signup_and_login(app, "orgs-manage-outsider@me.com").await;
+    let org = create_org(app, &owner, "Acme").await;
+    invite_and_accept(app, &org, &owner, "orgs-manage-member@me.com", &member).await;
+
+    let response = app
+        .post_org_invitation(&org, &serde_json::json!({ "email": "someone@me.com" }))
+        .add_cookies(auth_jar(app, &member))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+    let response = app
+        .delete_org_member(&org, "orgs-manage-owner@me.com")
+        .add_cookies(auth_jar(app, &member))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+
+    // outsiders can't tell the organization exists
+    let response = app
+        .get_org(&org)
+        .add_cookies(auth_jar(app, &outsider))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+
+    // an admin can't delete, only owners can
+    let response = app
+        .put_org_member(
+            &org,
+            "orgs-manage-member@me.com",
+            &serde_json::json!({ "role": "admin" }),
+        )
+        .add_cookies(auth_jar(app, &owner))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NO_CONTENT);
+    let response = app
+        .delete_org(&org)
+        .add_cookies(auth_jar(app, &member))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+
+    // the last owner has to stay
+    let response = app
+        .delete_org_member(&org, "orgs-manage-owner@me.com")
+        .add_cookies(auth_jar(app, &owner))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = app
+        .delete_org(&org)
+        .add_cookies(auth_jar(app, &owner))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NO_CONTENT);
+    let response = app.get_orgs().add_cookies(auth_jar(app, &member)).await;
+    assert!(response.json::<Vec<OrgResponse>>().is_empty());
+}
+
+#[tokio::test]
+async fn test_switch_org_reissues_the_token() {
+    let app = get_test_app().await;
+    let owner = signup_and_login(app, "orgs-switch-owner@me.com").await;
+    let member = signup_and_login(app, "orgs-switch-member@me.com").await;
+    let org = create_org(app, &owner, "Acme").await;
+
+    // not a member yet
+    let response = switch(app, &member, Some(&org)).await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+
+    invite_and_accept(app, &org, &owner, "orgs-switch-member@me.com", &member).await;
+    let response = switch(app, &member, Some(&org)).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let switched = response.json::<SwitchOrgResponse>();
+    assert_eq!(switched.org_role, Some(OrgRole::Member));
+
+    // the old token is revoked, the new one carries the organization
+    assert_eq!(
+        verify(app, &member).await.status_code(),
+        StatusCode::UNAUTHORIZED
+    );
+    let response = verify(app, &switched.token).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let verified = response.json::<VerifyTokenResponse>();
+    assert_eq!(verified.org_id.map(|id| id.to_string()), Some(org.clone()));
+    assert_eq!(verified.org_role, Some(OrgRole::Member));
+
+    // removed members lose access right away
+    let response = app
+        .delete_org_member(&org, "orgs-switch-member@me.com")
+        .add_cookies(auth_jar(app, &owner))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        verify(app, &switched.token).await.status_code(),
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn test_refresh_keeps_the_active_org() {
+    let app = get_test_app().await;
+    let (owner, refresh_token) = signup_and_login_with_refresh(app, "orgs-refresh@me.com").await;
+    let org = create_org(app, &owner, "Acme").await;
+
+    let response = switch(app, &owner, Some(&org)).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let response = app
+        .post_token_refresh()
+        .json(&serde_json::json!({ "refresh_token": refresh_token }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let token = match response.json::<LoginResponse>() {
+        LoginResponse::Success { token, .. } => token,
+        other => panic!("unexpected refresh response: {other:?}"),
+    };
+    let verified = verify(app, &token).await.json::<VerifyTokenResponse>();
+    assert_eq!(verified.org_role, Some(OrgRole::Owner));
+
+    // and switching back drops it
+    let response = switch(app, &token, None).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let switched = response.json::<SwitchOrgResponse>();
+    let verified = verify(app, &switched.token)
+        .await
+        .json::<VerifyTokenResponse>();
+    assert_eq!(verified.org_id, None);
+}
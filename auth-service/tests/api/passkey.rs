@@ -0,0 +1,129 @@
+use lgr_auth::routes::{LoginResponse, PasskeyRegistrationChallenge};
+use reqwest::StatusCode;
+use webauthn_authenticator_rs::WebauthnAuthenticator;
+use webauthn_authenticator_rs::softpasskey::SoftPasskey;
+use webauthn_rs::prelude::{PublicKeyCredential, Url};
+
+use crate::common::{TestApp, get_test_app};
+
+fn origin() -> Url {
+    Url::parse("http://localhost:5173").expect("valid origin")
+}
+
+async fn register(app: &TestApp, email: &str) -> WebauthnAuthenticator<SoftPasskey> {
+    let response = app
+        .post_signup(&serde_json::json!({ "method": "passkey", "email": email }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let challenge = response.json::<PasskeyRegistrationChallenge>();
+
+    let mut authenticator = WebauthnAuthenticator::new(SoftPasskey::new(true));
+    let credential = authenticator
+        .do_registration(origin(), challenge.options)
+        .expect("registration");
+
+    let response = app
+        .post_passkey_register_finish(&serde_json::json!({
+            "id": challenge.id,
+            "credential": credential,
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    authenticator
+}
+
+/// Starts a login and signs the challenge, returning the ceremony id and assertion
+async fn assert_passkey(
+    app: &TestApp,
+    authenticator: &mut WebauthnAuthenticator<SoftPasskey>,
+    email: &str,
+) -> (serde_json::Value, PublicKeyCredential) {
+    let response = app
+        .post_login(&serde_json::json!({ "method": "passkey", "email": email }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let (id, options) = match response.json::<LoginResponse>() {
+        LoginResponse::Passkey { id, options, .. } => (id, options),
+        other => panic!("unexpected login response: {other:?}"),
+    };
+    let credential = authenticator
+        .do_authentication(origin(), options)
+        .expect("authentication");
+    (serde_json::json!(id), credential)
+}
+
+#[tokio::test]
+async fn test_passkey_register_and_login() {
+    let app = get_test_app().await;
+    let email = "passkey@me.com";
+    let mut authenticator = register(app, email).await;
+
+    let (id, credential) = assert_passkey(app, &mut authenticator, email).await;
+    let response = app
+        .post_passkey_login_finish(&serde_json::json!({ "id": id, "credential": credential }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let auth_cookie = response.cookie(&app.config.jwt.cookie_name);
+    assert!(!auth_cookie.value().is_empty());
+    assert!(matches!(
+        response.json::<LoginResponse>(),
+        LoginResponse::Success { .. }
+    ));
+}
+
+#[tokio::test]
+async fn test_passkey_login_ceremony_single_use() {
+    let app = get_test_app().await;
+    let email = "passkey-reuse@me.com";
+    let mut authenticator = register(app, email).await;
+
+    let (id, credential) = assert_passkey(app, &mut authenticator, email).await;
+    let body = serde_json::json!({ "id": id, "credential": credential });
+    let response = app.post_passkey_login_finish(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let response = app.post_passkey_login_finish(&body).await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_passkey_login_401_on_counter_regression() {
+    let app = get_test_app().await;
+    let email = "passkey-counter@me.com";
+    let mut authenticator = register(app, email).await;
+
+    // two assertions signed in order, then presented out of order
+    let (first_id, first) = assert_passkey(app, &mut authenticator, email).await;
+    let (second_id, second) = assert_passkey(app, &mut authenticator, email).await;
+
+    let response = app
+        .post_passkey_login_finish(&serde_json::json!({ "id": second_id, "credential": second }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let response = app
+        .post_passkey_login_finish(&serde_json::json!({ "id": first_id, "credential": first }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_passkey_login_401_without_passkeys() {
+    let app = get_test_app().await;
+    let response = app
+        .post_login(&serde_json::json!({ "method": "passkey", "email": "nopasskey@me.com" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_passkey_signup_409_if_user_exists() {
+    let app = get_test_app().await;
+    let email = "passkey-exists@me.com";
+    register(app, email).await;
+
+    let response = app
+        .post_signup(&serde_json::json!({ "method": "passkey", "email": email }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CONFLICT);
+}
@@ -0,0 +1,330 @@
+use lgr_auth::config::Config;
+use lgr_auth::domain::EmailTemplate;
+use lgr_auth::routes::{LoginResponse, PasswordResponse};
+use reqwest::{StatusCode, Url};
+
+use crate::common::{TestApp, get_test_app};
+
+/// Sign up and log in, returning the access and refresh tokens
+async fn signup_and_login(app: &TestApp, email: &str) -> (String, String) {
+    let body = serde_json::json!({
+        "method": "email_password",
+        "email": email,
+        "password": "password123",
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    match response.json::<LoginResponse>() {
+        LoginResponse::Success {
+            token,
+            refresh_token,
+            ..
+        } => (token, refresh_token),
+        other => panic!("unexpected login response: {other:?}"),
+    }
+}
+
+async fn login_status(app: &TestApp, email: &str, password: &str) -> StatusCode {
+    app.post_login(&serde_json::json!({
+        "method": "email_password",
+        "email": email,
+        "password": password,
+    }))
+    .await
+    .status_code()
+}
+
+fn reset_token(app: &TestApp, email: &str) -> String {
+    let sent = app.last_email_to(email).expect("reset email sent");
+    let data = match sent.template {
+        EmailTemplate::PasswordReset(data) => data,
+        other => panic!("unexpected email template: {other:?}"),
+    };
+    let url = Url::parse(&data.link_url).expect("valid url");
+    url.query_pairs()
+        .find_map(|(key, value)| (key == "token").then(|| value.to_string()))
+        .expect("token in link")
+}
+
+#[tokio::test]
+async fn test_forgot_password_does_not_reveal_account() {
+    let app = get_test_app().await;
+    let email = "forgot-known@me.com";
+    signup_and_login(app, email).await;
+
+    let known = app
+        .post_forgot_password(&serde_json::json!({ "email": email }))
+        .await;
+    assert_eq!(known.status_code(), StatusCode::ACCEPTED);
+    assert!(!reset_token(app, email).is_empty());
+
+    let unknown_email = "forgot-unknown@me.com";
+    let unknown = app
+        .post_forgot_password(&serde_json::json!({ "email": unknown_email }))
+        .await;
+    assert_eq!(unknown.status_code(), StatusCode::ACCEPTED);
+    assert!(app.last_email_to(unknown_email).is_none());
+
+    assert_eq!(
+        known.json::<PasswordResponse>().message,
+        unknown.json::<PasswordResponse>().message
+    );
+}
+
+#[tokio::test]
+async fn test_reset_password_flow() {
+    let app = get_test_app().await;
+    let email = "reset-flow@me.com";
+    let (access_token, refresh_token) = signup_and_login(app, email).await;
+
+    let response = app
+        .post_forgot_password(&serde_json::json!({ "email": email }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+    let token = reset_token(app, email);
+
+    let body = serde_json::json!({ "token": token, "password": "new-password123" });
+    let response = app.post_reset_password(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    assert_eq!(
+        login_status(app, email, "password123").await,
+        StatusCode::UNAUTHORIZED
+    );
+    assert_eq!(
+        login_status(app, email, "new-password123").await,
+        StatusCode::OK
+    );
+
+    // the link is single-use
+    let response = app.post_reset_password(&body).await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    // tokens issued before the reset are revoked
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": access_token }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    let response = app
+        .post_token_refresh()
+        .json(&serde_json::json!({ "refresh_token": refresh_token }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_reset_password_revokes_other_links() {
+    let app = get_test_app().await;
+    let email = "reset-links@me.com";
+    signup_and_login(app, email).await;
+
+    let body = serde_json::json!({ "email": email });
+    app.post_forgot_password(&body).await;
+    let first = reset_token(app, email);
+    app.post_forgot_password(&body).await;
+    let second = reset_token(app, email);
+
+    let response = app
+        .post_reset_password(&serde_json::json!({ "token": second, "password": "new-password123" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let response = app
+        .post_reset_password(
+            &serde_json::json!({ "token": first, "password": "other-password123" }),
+        )
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_reset_password_400_short_password_keeps_token() {
+    let app = get_test_app().await;
+    let email = "reset-short@me.com";
+    signup_and_login(app, email).await;
+    app.post_forgot_password(&serde_json::json!({ "email": email }))
+        .await;
+    let token = reset_token(app, email);
+
+    let response = app
+        .post_reset_password(&serde_json::json!({ "token": token, "password": "short" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .post_reset_password(&serde_json::json!({ "token": token, "password": "long-enough-now" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_reset_password_401_magic_link_token() {
+    let app = get_test_app().await;
+    let email = "reset-wrong-purpose@me.com";
+    signup_and_login(app, email).await;
+    app.post_login(&serde_json::json!({ "method": "magic_link", "email": email }))
+        .await;
+    let sent = app.last_email_to(email).expect("magic link sent");
+    let link = match sent.template {
+        EmailTemplate::MagicLink(data) => data.link_url,
+        other => panic!("unexpected email template: {other:?}"),
+    };
+    let token = link.split("token=").nth(1).unwrap();
+
+    let response = app
+        .post_reset_password(&serde_json::json!({ "token": token, "password": "new-password123" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+fn auth_jar(app: &TestApp, token: &str) -> cookie::CookieJar {
+    let mut jar = cookie::CookieJar::default();
+    jar.add((app.config.jwt.cookie_name.clone(), token.to_string()));
+    jar
+}
+
+#[tokio::test]
+async fn test_change_password_revokes_other_sessions() {
+    let app = get_test_app().await;
+    let email = "change-password@me.com";
+    let (current, _) = signup_and_login(app, email).await;
+    let (other_access, other_refresh) = match app
+        .post_login(&serde_json::json!({
+            "method": "email_password",
+            "email": email,
+            "password": "password123",
+        }))
+        .await
+        .json::<LoginResponse>()
+    {
+        LoginResponse::Success {
+            token,
+            refresh_token,
+            ..
+        } => (token, refresh_token),
+        other => panic!("unexpected login response: {other:?}"),
+    };
+
+    let body = serde_json::json!({
+        "current_password": "password123",
+        "new_password": "changed-password123",
+    });
+    let response = app
+        .post_change_password(&body)
+        .add_cookies(auth_jar(app, &current))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let fresh = response
+        .cookie(&app.config.jwt.cookie_name)
+        .value()
+        .to_string();
+
+    assert_eq!(
+        login_status(app, email, "changed-password123").await,
+        StatusCode::OK
+    );
+
+    // the session that made the change keeps going with its new token
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": fresh }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    for token in [current, other_access] {
+        let response = app
+            .post_verify_token(&serde_json::json!({ "token": token }))
+            .await;
+        assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    }
+    let response = app
+        .post_token_refresh()
+        .json(&serde_json::json!({ "refresh_token": other_refresh }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_change_password_401_wrong_current_password() {
+    let app = get_test_app().await;
+    let email = "change-password-wrong@me.com";
+    let (current, _) = signup_and_login(app, email).await;
+
+    let body = serde_json::json!({
+        "current_password": "not-my-password",
+        "new_password": "changed-password123",
+    });
+    let response = app
+        .post_change_password(&body)
+        .add_cookies(auth_jar(app, &current))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        login_status(app, email, "password123").await,
+        StatusCode::OK
+    );
+}
+
+#[tokio::test]
+async fn test_change_password_wrong_guesses_lock_the_account() {
+    let mut config = Config::default();
+    config.lockout.threshold = 3;
+    let app = TestApp::new(&config).await;
+    let email = "change-password-guess@me.com";
+    let (current, _) = signup_and_login(&app, email).await;
+
+    let guess = |password: &str| {
+        app.post_change_password(&serde_json::json!({
+            "current_password": password,
+            "new_password": "changed-password123",
+        }))
+        .add_cookies(auth_jar(&app, &current))
+    };
+    for _ in 0..2 {
+        assert_eq!(
+            guess("not-my-password").await.status_code(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+    assert_eq!(
+        guess("not-my-password").await.status_code(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+    // not even the right password gets through while locked
+    assert_eq!(
+        guess("password123").await.status_code(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+    assert_eq!(
+        login_status(&app, email, "password123").await,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+}
+
+#[tokio::test]
+async fn test_change_password_400_short_new_password() {
+    let app = get_test_app().await;
+    let email = "change-password-short@me.com";
+    let (current, _) = signup_and_login(app, email).await;
+
+    let body = serde_json::json!({
+        "current_password": "password123",
+        "new_password": "short",
+    });
+    let response = app
+        .post_change_password(&body)
+        .add_cookies(auth_jar(app, &current))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_change_password_400_without_auth_cookie() {
+    let app = get_test_app().await;
+    let body = serde_json::json!({
+        "current_password": "password123",
+        "new_password": "changed-password123",
+    });
+    let response = app.post_change_password(&body).await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
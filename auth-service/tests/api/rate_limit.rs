@@ -0,0 +1,143 @@
+use lgr_auth::config::{Config, RateLimitKey};
+use lgr_auth::domain::TokenBucket;
+use reqwest::StatusCode;
+
+use crate::common::{TEST_PEER, TestApp};
+
+/// An app allowing bursts of 2 logins and 2 2FA attempts, keyed by `key`
+async fn app_with_limits(key: RateLimitKey) -> TestApp {
+    let mut config = Config::default();
+    config.rate_limit.key = key;
+    // requests come through a proxy, the forwarded address tells clients apart
+    config.server.trusted_proxies = vec![TEST_PEER.0.into()];
+    let bucket = TokenBucket {
+        capacity: 2,
+        period: 60,
+    };
+    config.rate_limit.login = bucket;
+    config.rate_limit.verify_2fa = bucket;
+    TestApp::with_peer(&config).await
+}
+
+async fn login(app: &TestApp, email: &str, ip: &str) -> axum_test::TestResponse {
+    app.post_login(&serde_json::json!({
+        "method": "email_password",
+        "email": email,
+        "password": "password123",
+    }))
+    .add_header("x-forwarded-for", ip)
+    .await
+}
+
+#[tokio::test]
+async fn test_login_is_limited_by_ip() {
+    let app = app_with_limits(RateLimitKey::Ip).await;
+    for email in ["limit-ip-a@me.com", "limit-ip-b@me.com"] {
+        let response = login(&app, email, "10.0.0.1").await;
+        assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    }
+    let response = login(&app, "limit-ip-c@me.com", "10.0.0.1").await;
+    assert_eq!(response.status_code(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = response
+        .header("retry-after")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(retry_after > 0 && retry_after <= 30);
+
+    // other clients have their own bucket
+    let response = login(&app, "limit-ip-c@me.com", "10.0.0.2").await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_forwarded_address_is_ignored_without_a_trusted_proxy() {
+    let mut config = Config::default();
+    config.rate_limit.key = RateLimitKey::Ip;
+    config.rate_limit.login = TokenBucket {
+        capacity: 2,
+        period: 60,
+    };
+    let app = TestApp::with_peer(&config).await;
+    // a new header each time is still the same client
+    for (email, ip) in [
+        ("limit-spoof-a@me.com", "10.0.2.1"),
+        ("limit-spoof-b@me.com", "10.0.2.2"),
+    ] {
+        let response = login(&app, email, ip).await;
+        assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    }
+    let response = login(&app, "limit-spoof-c@me.com", "10.0.2.3").await;
+    assert_eq!(response.status_code(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_login_is_limited_by_email() {
+    let app = app_with_limits(RateLimitKey::Email).await;
+    let email = "limit-email@me.com";
+    for ip in ["10.0.1.1", "10.0.1.2"] {
+        assert_eq!(
+            login(&app, email, ip).await.status_code(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+    assert_eq!(
+        login(&app, email, "10.0.1.3").await.status_code(),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+    // the address counts the same with different case, and in a form body
+    let response = app
+        .server
+        .post("/login")
+        .form(&[
+            ("method", "email_password"),
+            ("email", "LIMIT-EMAIL@me.com"),
+            ("password", "password123"),
+        ])
+        .await;
+    assert_eq!(response.status_code(), StatusCode::TOO_MANY_REQUESTS);
+
+    assert_eq!(
+        login(&app, "limit-email-other@me.com", "10.0.1.1")
+            .await
+            .status_code(),
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn test_2fa_guesses_are_limited() {
+    let app = app_with_limits(RateLimitKey::IpAndEmail).await;
+    let body = serde_json::json!({
+        "method": "email",
+        "email": "limit-2fa@me.com",
+        "id": uuid::Uuid::new_v4().to_string(),
+        "code": "123456",
+    });
+    for _ in 0..2 {
+        let response = app.post_verify_2fa(&body).await;
+        assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    }
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status_code(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_limits_can_be_turned_off() {
+    let mut config = Config::default();
+    config.rate_limit.enabled = false;
+    config.rate_limit.login = TokenBucket {
+        capacity: 1,
+        period: 60,
+    };
+    let app = TestApp::new(&config).await;
+    for _ in 0..3 {
+        assert_eq!(
+            login(&app, "limit-off@me.com", "10.0.2.1")
+                .await
+                .status_code(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
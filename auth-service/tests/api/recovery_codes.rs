@@ -0,0 +1,196 @@
+use cookie::CookieJar;
+use lgr_auth::domain::{Email, EmailTemplate};
+use lgr_auth::routes::{LoginResponse, RecoveryCodesResponse, SignupResponse};
+use lgr_auth::utils::auth::generate_auth_cookie;
+use reqwest::StatusCode;
+
+use crate::common::{TestApp, get_test_app, two_factor_attempt_id};
+
+async fn signup_with_2fa(app: &TestApp, email: &str) -> Vec<String> {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "method": "email_password",
+            "email": email,
+            "password": "password123",
+            "two_factor": "email",
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    response
+        .json::<SignupResponse>()
+        .recovery_codes
+        .expect("recovery codes issued")
+}
+
+/// Log in with the password and return the login attempt id from the emailed link
+async fn login(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_login(&serde_json::json!({
+            "method": "email_password",
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::PARTIAL_CONTENT);
+    assert!(matches!(
+        response.json::<LoginResponse>(),
+        LoginResponse::TwoFactor { .. }
+    ));
+    match app.last_email_to(email).expect("2fa email sent").template {
+        EmailTemplate::TwoFactor(data) => two_factor_attempt_id(&data.redirect_url),
+        other => panic!("unexpected email template: {other:?}"),
+    }
+}
+
+fn auth_cookie(app: &TestApp, email: &str) -> CookieJar {
+    let email = Email::parse(email).unwrap();
+    let mut jar = CookieJar::default();
+    jar.add(generate_auth_cookie(&email, 0, &app.config.jwt).expect("auth cookie"));
+    jar
+}
+
+#[tokio::test]
+async fn test_recovery_code_accepted_once() {
+    let app = get_test_app().await;
+    let email = "recovery-once@me.com";
+    let codes = signup_with_2fa(app, email).await;
+    assert_eq!(codes.len(), 10);
+
+    let id = login(app, email).await;
+    let body = serde_json::json!({
+        "method": "email",
+        "email": email,
+        "id": id,
+        "code": codes[0],
+    });
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(
+        !response
+            .cookie(&app.config.jwt.cookie_name)
+            .value()
+            .is_empty()
+    );
+
+    let id = login(app, email).await;
+    let body = serde_json::json!({
+        "method": "email",
+        "email": email,
+        "id": id,
+        "code": codes[0],
+    });
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_recovery_code_401_with_wrong_attempt_id() {
+    let app = get_test_app().await;
+    let email = "recovery-attempt@me.com";
+    let codes = signup_with_2fa(app, email).await;
+    login(app, email).await;
+
+    let body = serde_json::json!({
+        "method": "email",
+        "email": email,
+        "id": "dc5b25ca-1d7b-4827-8843-c2d1ab9d0f7f",
+        "code": codes[0],
+    });
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_regenerate_recovery_codes_revokes_old_set() {
+    let app = get_test_app().await;
+    let email = "recovery-regen@me.com";
+    let old = signup_with_2fa(app, email).await;
+
+    let response = app
+        .post_regenerate_recovery_codes(&serde_json::json!({ "password": "password123" }))
+        .add_cookies(auth_cookie(app, email))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let new = response.json::<RecoveryCodesResponse>().recovery_codes;
+    assert_eq!(new.len(), 10);
+
+    let id = login(app, email).await;
+    let body = serde_json::json!({
+        "method": "email",
+        "email": email,
+        "id": id,
+        "code": old[0],
+    });
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    let body = serde_json::json!({
+        "method": "email",
+        "email": email,
+        "id": id,
+        "code": new[0],
+    });
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_regenerate_recovery_codes_401_without_proof() {
+    let app = get_test_app().await;
+    let email = "recovery-regen-proof@me.com";
+    let old = signup_with_2fa(app, email).await;
+
+    // a session alone, a wrong password, or a TOTP code on an account without TOTP
+    for body in [
+        serde_json::json!({}),
+        serde_json::json!({ "password": "wrong-password" }),
+        serde_json::json!({ "code": "123456" }),
+    ] {
+        let response = app
+            .post_regenerate_recovery_codes(&body)
+            .add_cookies(auth_cookie(app, email))
+            .await;
+        assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    }
+
+    // the old set still works
+    let id = login(app, email).await;
+    let body = serde_json::json!({
+        "method": "email",
+        "email": email,
+        "id": id,
+        "code": old[0],
+    });
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_regenerate_recovery_codes_422_without_2fa() {
+    let app = get_test_app().await;
+    let email = "recovery-no2fa@me.com";
+    let response = app
+        .post_signup(&serde_json::json!({
+            "method": "email_password",
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    assert!(response.json::<SignupResponse>().recovery_codes.is_none());
+
+    let response = app
+        .post_regenerate_recovery_codes(&serde_json::json!({ "password": "password123" }))
+        .add_cookies(auth_cookie(app, email))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn test_regenerate_recovery_codes_400_without_auth_cookie() {
+    let app = get_test_app().await;
+    let response = app
+        .post_regenerate_recovery_codes(&serde_json::json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,126 @@
+use cookie::CookieJar;
+use lgr_auth::domain::Email;
+use lgr_auth::routes::LoginResponse;
+use reqwest::StatusCode;
+
+use crate::common::{TestApp, get_test_app};
+
+/// Sign up and log in, returning the refresh token
+async fn login(app: &TestApp, email: &str) -> String {
+    let body = serde_json::json!({
+        "method": "email_password",
+        "email": email,
+        "password": "password123",
+    });
+    let response = app.post_signup(&body).await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    let response = app.post_login(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(
+        !response
+            .cookie(&app.config.jwt.refresh_cookie_name)
+            .value()
+            .is_empty()
+    );
+    match response.json::<LoginResponse>() {
+        LoginResponse::Success { refresh_token, .. } => refresh_token,
+        other => panic!("unexpected login response: {other:?}"),
+    }
+}
+
+async fn refresh(app: &TestApp, refresh_token: &str) -> (StatusCode, Option<String>) {
+    let response = app
+        .post_token_refresh()
+        .json(&serde_json::json!({ "refresh_token": refresh_token }))
+        .await;
+    let status = response.status_code();
+    if status != StatusCode::OK {
+        return (status, None);
+    }
+    match response.json::<LoginResponse>() {
+        LoginResponse::Success { refresh_token, .. } => (status, Some(refresh_token)),
+        other => panic!("unexpected refresh response: {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_refresh_rotates_token() {
+    let app = get_test_app().await;
+    let first = login(app, "refresh-rotate@me.com").await;
+
+    let (status, second) = refresh(app, &first).await;
+    assert_eq!(status, StatusCode::OK);
+    let second = second.unwrap();
+    assert_ne!(first, second);
+
+    let (status, third) = refresh(app, &second).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_ne!(third.unwrap(), second);
+}
+
+#[tokio::test]
+async fn test_refresh_from_cookie() {
+    let app = get_test_app().await;
+    let first = login(app, "refresh-cookie@me.com").await;
+
+    let mut jar = CookieJar::default();
+    jar.add((app.config.jwt.refresh_cookie_name.clone(), first));
+    let response = app.post_token_refresh().add_cookies(jar).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(
+        !response
+            .cookie(&app.config.jwt.cookie_name)
+            .value()
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn test_refresh_reuse_revokes_family() {
+    let app = get_test_app().await;
+    let first = login(app, "refresh-reuse@me.com").await;
+
+    let (status, second) = refresh(app, &first).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // replaying the rotated token kills the chain, including the newest token
+    let (status, _) = refresh(app, &first).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = refresh(app, &second.unwrap()).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_refresh_403_if_account_locked() {
+    let app = get_test_app().await;
+    let email = "refresh-locked@me.com";
+    let first = login(app, email).await;
+
+    // locked behind the sessions' back, the refresh still has to refuse
+    app.state
+        .user_store
+        .write()
+        .await
+        .set_locked(
+            &Email::parse(email).unwrap(),
+            Some(chrono::Utc::now().timestamp()),
+        )
+        .await
+        .expect("locked");
+    let (status, _) = refresh(app, &first).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_refresh_401_invalid_token() {
+    let app = get_test_app().await;
+    let (status, _) = refresh(app, "not-a-refresh-token").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_refresh_400_without_token() {
+    let app = get_test_app().await;
+    let response = app.post_token_refresh().await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
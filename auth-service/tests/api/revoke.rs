@@ -0,0 +1,203 @@
+use lgr_auth::domain::{Email, OAuthClient};
+use lgr_auth::routes::{LoginResponse, TokenResponse};
+use reqwest::StatusCode;
+
+use crate::common::{TestApp, basic_auth, get_test_app, register_confidential_client};
+
+async fn client_credentials_token(app: &TestApp, client_id: &str, secret: &str) -> String {
+    app.post_oauth_token(&serde_json::json!({ "grant_type": "client_credentials" }))
+        .add_header("authorization", basic_auth(client_id, secret))
+        .await
+        .json::<TokenResponse>()
+        .access_token
+}
+
+#[tokio::test]
+async fn test_revoke_access_token() {
+    let app = get_test_app().await;
+    let client_id = "revoke-service";
+    let secret = register_confidential_client(app, client_id).await;
+    let token = client_credentials_token(app, client_id, secret.as_ref()).await;
+
+    let response = app
+        .post_revoke(&serde_json::json!({
+            "token": token,
+            "token_type_hint": "access_token",
+        }))
+        .add_header("authorization", basic_auth(client_id, secret.as_ref()))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+
+    // unknown tokens are not an error, RFC 7009 section 2.2
+    let response = app
+        .post_revoke(&serde_json::json!({ "token": "not-a-token" }))
+        .add_header("authorization", basic_auth(client_id, secret.as_ref()))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_revoke_refresh_token_as_public_client() {
+    let app = get_test_app().await;
+    let client_id = "revoke-mobile-app";
+    app.state
+        .clients
+        .write()
+        .await
+        .add_client(OAuthClient {
+            client_id: client_id.to_string(),
+            name: "Mobile App".to_string(),
+            redirect_uris: vec![],
+            allowed_scopes: vec![],
+            secret_hash: None,
+        })
+        .await
+        .expect("client registered");
+
+    let credentials = serde_json::json!({
+        "method": "email_password",
+        "email": "revoke-refresh@test.com",
+        "password": "password123",
+    });
+    app.post_signup(&credentials).await;
+    let refresh_token = match app
+        .post_login(&credentials)
+        .add_header("x-client-id", client_id)
+        .await
+        .json::<LoginResponse>()
+    {
+        LoginResponse::Success { refresh_token, .. } => refresh_token,
+        other => panic!("unexpected login response: {other:?}"),
+    };
+    let email = Email::parse("revoke-refresh@test.com").expect("valid email");
+    let sessions = || async { app.state.sessions.read().await.list_sessions(&email).await };
+    assert_eq!(sessions().await.expect("sessions").len(), 1);
+
+    let response = app
+        .post_revoke(&serde_json::json!({
+            "token": refresh_token,
+            "token_type_hint": "refresh_token",
+            "client_id": client_id,
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let response = app
+        .post_token_refresh()
+        .json(&serde_json::json!({ "refresh_token": refresh_token }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+    // the session the token belonged to is over too
+    assert!(sessions().await.expect("sessions").is_empty());
+}
+
+#[tokio::test]
+async fn test_revoke_refuses_other_clients_refresh_tokens() {
+    let app = get_test_app().await;
+    let owner_secret = register_confidential_client(app, "revoke-refresh-owner").await;
+    let other_secret = register_confidential_client(app, "revoke-refresh-other").await;
+
+    let credentials = serde_json::json!({
+        "method": "email_password",
+        "email": "revoke-refresh-other@test.com",
+        "password": "password123",
+    });
+    app.post_signup(&credentials).await;
+    let login = |client_id: Option<&'static str>| {
+        let request = app.post_login(&credentials);
+        let request = match client_id {
+            Some(id) => request.add_header("x-client-id", id),
+            None => request,
+        };
+        async move {
+            match request.await.json::<LoginResponse>() {
+                LoginResponse::Success { refresh_token, .. } => refresh_token,
+                other => panic!("unexpected login response: {other:?}"),
+            }
+        }
+    };
+    let revoke = |token: String, client_id: &'static str, secret: &str| {
+        app.post_revoke(&serde_json::json!({
+            "token": token,
+            "token_type_hint": "refresh_token",
+        }))
+        .add_header("authorization", basic_auth(client_id, secret))
+    };
+
+    // issued to another client, or to no client at all
+    for token in [login(Some("revoke-refresh-owner")).await, login(None).await] {
+        let response = revoke(token.clone(), "revoke-refresh-other", other_secret.as_ref()).await;
+        assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            response.json::<serde_json::Value>()["error"],
+            "unauthorized_client"
+        );
+        let response = app
+            .post_token_refresh()
+            .json(&serde_json::json!({ "refresh_token": token }))
+            .await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+    }
+
+    let token = login(Some("revoke-refresh-owner")).await;
+    let response = revoke(token, "revoke-refresh-owner", owner_secret.as_ref()).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    // a login can't be tied to a client that isn't registered
+    let response = app
+        .post_login(&credentials)
+        .add_header("x-client-id", "revoke-refresh-unknown")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_revoke_refuses_other_clients_tokens() {
+    let app = get_test_app().await;
+    let owner_secret = register_confidential_client(app, "revoke-owner").await;
+    let other_secret = register_confidential_client(app, "revoke-other").await;
+    let token = client_credentials_token(app, "revoke-owner", owner_secret.as_ref()).await;
+
+    let credentials = serde_json::json!({
+        "method": "email_password",
+        "email": "revoke-session@test.com",
+        "password": "password123",
+    });
+    app.post_signup(&credentials).await;
+    let session_token = match app.post_login(&credentials).await.json::<LoginResponse>() {
+        LoginResponse::Success { token, .. } => token,
+        other => panic!("unexpected login response: {other:?}"),
+    };
+
+    // another client's token, or a session's that names no client
+    for token in [&token, &session_token] {
+        let response = app
+            .post_revoke(&serde_json::json!({ "token": token }))
+            .add_header(
+                "authorization",
+                basic_auth("revoke-other", other_secret.as_ref()),
+            )
+            .await;
+        assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            response.json::<serde_json::Value>()["error"],
+            "unauthorized_client"
+        );
+
+        let response = app
+            .post_verify_token(&serde_json::json!({ "token": token }))
+            .await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+    }
+
+    let response = app
+        .post_revoke(&serde_json::json!({ "token": token }))
+        .add_header("authorization", basic_auth("revoke-owner", "wrong"))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,235 @@
+use lgr_auth::config::Config;
+use lgr_auth::domain::Role;
+use lgr_auth::routes::{CreateApiKeyResponse, LoginResponse, VerifyTokenResponse};
+use lgr_auth::utils::auth::{Claims, validate_token};
+use reqwest::StatusCode;
+
+use crate::common::{TestApp, get_test_app};
+
+const ADMIN: &str = "roles-admin@me.com";
+
+/// An app where `ADMIN` is given the admin role at startup
+async fn app_with_admin() -> TestApp {
+    let mut config = Config::default();
+    config.rbac.admins = vec![ADMIN.to_string()];
+    TestApp::new(&config).await
+}
+
+async fn signup(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "method": "email_password",
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+}
+
+/// Log in, returning the access token
+async fn login(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_login(&serde_json::json!({
+            "method": "email_password",
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    match response.json::<LoginResponse>() {
+        LoginResponse::Success { token, .. } => token,
+        other => panic!("unexpected login response: {other:?}"),
+    }
+}
+
+async fn signup_and_login(app: &TestApp, email: &str) -> String {
+    signup(app, email).await;
+    login(app, email).await
+}
+
+fn auth_jar(app: &TestApp, token: &str) -> cookie::CookieJar {
+    let mut jar = cookie::CookieJar::default();
+    jar.add((app.config.jwt.cookie_name.clone(), token.to_string()));
+    jar
+}
+
+async fn create_role(app: &TestApp, admin: &str, name: &str, permissions: &[&str]) {
+    let response = app
+        .post_role(&serde_json::json!({ "name": name, "permissions": permissions }))
+        .add_cookies(auth_jar(app, admin))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+}
+
+async fn assign(app: &TestApp, admin: &str, email: &str, role: &str) -> StatusCode {
+    app.post_user_role(email, &serde_json::json!({ "role": role }))
+        .add_cookies(auth_jar(app, admin))
+        .await
+        .status_code()
+}
+
+async fn verify(app: &TestApp, token: &str, permission: &str) -> axum_test::TestResponse {
+    app.post_verify_token(&serde_json::json!({ "token": token, "permission": permission }))
+        .await
+}
+
+#[tokio::test]
+async fn test_roles_are_carried_in_tokens() {
+    let app = app_with_admin().await;
+    let admin = signup_and_login(&app, ADMIN).await;
+    create_role(&app, &admin, "support", &["orders:read", "users:read"]).await;
+    let email = "roles-support@me.com";
+    signup(&app, email).await;
+    assert_eq!(
+        assign(&app, &admin, email, "support").await,
+        StatusCode::NO_CONTENT
+    );
+
+    let token = login(&app, email).await;
+    let claims = validate_token::<Claims>(&token, &app.config.jwt)
+        .await
+        .expect("valid token");
+    assert_eq!(claims.grants.roles, vec!["support"]);
+    assert_eq!(claims.grants.permissions, vec!["orders:read", "users:read"]);
+
+    let response = verify(&app, &token, "orders:read").await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body = response.json::<VerifyTokenResponse>();
+    assert_eq!(body.roles, vec!["support"]);
+    assert_eq!(
+        verify(&app, &token, "orders:write").await.status_code(),
+        StatusCode::FORBIDDEN
+    );
+
+    let response = app
+        .get_user_roles(email)
+        .add_cookies(auth_jar(&app, &admin))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let roles = response.json::<Vec<Role>>();
+    assert_eq!(roles.len(), 1);
+    assert_eq!(roles[0].name, "support");
+}
+
+#[tokio::test]
+async fn test_role_changes_show_in_new_tokens() {
+    let app = app_with_admin().await;
+    let admin = signup_and_login(&app, ADMIN).await;
+    create_role(&app, &admin, "billing", &["invoices:read"]).await;
+    let email = "roles-billing@me.com";
+    signup(&app, email).await;
+    assign(&app, &admin, email, "billing").await;
+
+    let response = app
+        .put_role(
+            "billing",
+            &serde_json::json!({ "permissions": ["invoices:read", "invoices:write"] }),
+        )
+        .add_cookies(auth_jar(&app, &admin))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let token = login(&app, email).await;
+    assert_eq!(
+        verify(&app, &token, "invoices:write").await.status_code(),
+        StatusCode::OK
+    );
+
+    let response = app
+        .delete_user_role(email, "billing")
+        .add_cookies(auth_jar(&app, &admin))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NO_CONTENT);
+    let token = login(&app, email).await;
+    assert_eq!(
+        verify(&app, &token, "invoices:read").await.status_code(),
+        StatusCode::FORBIDDEN
+    );
+
+    let response = app
+        .delete_role("billing")
+        .add_cookies(auth_jar(&app, &admin))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        assign(&app, &admin, email, "billing").await,
+        StatusCode::NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn test_admin_role_is_protected() {
+    let app = app_with_admin().await;
+    let admin = signup_and_login(&app, ADMIN).await;
+    let response = app.get_roles().add_cookies(auth_jar(&app, &admin)).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(
+        response
+            .json::<Vec<Role>>()
+            .iter()
+            .any(|r| r.name == "admin")
+    );
+
+    let response = app
+        .delete_role("admin")
+        .add_cookies(auth_jar(&app, &admin))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+    let response = app
+        .delete_user_role(ADMIN, "admin")
+        .add_cookies(auth_jar(&app, &admin))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    // a second admin can revoke the first, whose token stops working at once
+    let other = "roles-second-admin@me.com";
+    signup(&app, other).await;
+    assign(&app, &admin, other, "admin").await;
+    let other_token = login(&app, other).await;
+    let response = app
+        .delete_user_role(ADMIN, "admin")
+        .add_cookies(auth_jar(&app, &other_token))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NO_CONTENT);
+    let response = app.get_roles().add_cookies(auth_jar(&app, &admin)).await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_role_api_requires_admin() {
+    let app = get_test_app().await;
+    let token = signup_and_login(app, "roles-nobody@me.com").await;
+    let response = app.get_roles().add_cookies(auth_jar(app, &token)).await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+    let response = app
+        .post_role(&serde_json::json!({ "name": "sneaky", "permissions": ["*"] }))
+        .add_cookies(auth_jar(app, &token))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+    let response = app.get_roles().await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_api_keys_act_with_current_permissions() {
+    let app = app_with_admin().await;
+    let admin = signup_and_login(&app, ADMIN).await;
+    create_role(&app, &admin, "reporting", &["reports:read"]).await;
+    let email = "roles-api-key@me.com";
+    let token = signup_and_login(&app, email).await;
+    let response = app
+        .post_api_key(&serde_json::json!({ "name": "reports" }))
+        .add_cookies(auth_jar(&app, &token))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+    let key = response.json::<CreateApiKeyResponse>().key;
+
+    assert_eq!(
+        verify(&app, &key, "reports:read").await.status_code(),
+        StatusCode::FORBIDDEN
+    );
+    assign(&app, &admin, email, "reporting").await;
+    assert_eq!(
+        verify(&app, &key, "reports:read").await.status_code(),
+        StatusCode::OK
+    );
+}
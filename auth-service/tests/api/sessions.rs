@@ -0,0 +1,165 @@
+use lgr_auth::config::Config;
+use lgr_auth::routes::{LoginResponse, SessionResponse, VerifyTokenResponse};
+use reqwest::StatusCode;
+
+use crate::common::{TEST_PEER, TestApp, get_test_app};
+
+/// Log in with a user agent, returning the access and refresh tokens
+async fn login(app: &TestApp, email: &str, user_agent: &str) -> (String, String) {
+    let body = serde_json::json!({
+        "method": "email_password",
+        "email": email,
+        "password": "password123",
+    });
+    let response = app
+        .post_login(&body)
+        .add_header("user-agent", user_agent)
+        .add_header("x-forwarded-for", "203.0.113.7, 10.0.0.1")
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    match response.json::<LoginResponse>() {
+        LoginResponse::Success {
+            token,
+            refresh_token,
+            ..
+        } => (token, refresh_token),
+        other => panic!("unexpected login response: {other:?}"),
+    }
+}
+
+async fn signup(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "method": "email_password",
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+}
+
+fn auth_jar(app: &TestApp, token: &str) -> cookie::CookieJar {
+    let mut jar = cookie::CookieJar::default();
+    jar.add((app.config.jwt.cookie_name.clone(), token.to_string()));
+    jar
+}
+
+async fn list(app: &TestApp, token: &str) -> Vec<SessionResponse> {
+    let response = app.get_sessions().add_cookies(auth_jar(app, token)).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    response.json::<Vec<SessionResponse>>()
+}
+
+async fn verify_status(app: &TestApp, token: &str) -> StatusCode {
+    app.post_verify_token(&serde_json::json!({ "token": token }))
+        .await
+        .status_code()
+}
+
+async fn refresh_status(app: &TestApp, refresh_token: &str) -> StatusCode {
+    app.post_token_refresh()
+        .json(&serde_json::json!({ "refresh_token": refresh_token }))
+        .await
+        .status_code()
+}
+
+#[tokio::test]
+async fn test_login_creates_session() {
+    // behind two proxies, the client is the hop before them
+    let mut config = Config::default();
+    config.server.trusted_proxies = vec![TEST_PEER.0.into(), [10, 0, 0, 1].into()];
+    let app = &TestApp::with_peer(&config).await;
+    let email = "sessions-list@me.com";
+    signup(app, email).await;
+    let (laptop, _) = login(app, email, "laptop").await;
+    let (phone, _) = login(app, email, "phone").await;
+
+    let sessions = list(app, &laptop).await;
+    assert_eq!(sessions.len(), 2);
+    let current = sessions
+        .iter()
+        .find(|s| s.current)
+        .expect("current session");
+    assert_eq!(current.session.user_agent.as_deref(), Some("laptop"));
+    assert_eq!(current.session.ip.as_deref(), Some("203.0.113.7"));
+    assert!(
+        sessions
+            .iter()
+            .any(|s| !s.current && s.session.user_agent.as_deref() == Some("phone"))
+    );
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": phone }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body = response.json::<VerifyTokenResponse>();
+    let phone_session = sessions.iter().find(|s| !s.current).unwrap();
+    assert_eq!(body.session_id, Some(phone_session.session.id));
+}
+
+#[tokio::test]
+async fn test_revoked_session_is_refused() {
+    let app = get_test_app().await;
+    let email = "sessions-revoke@me.com";
+    signup(app, email).await;
+    let (laptop, _) = login(app, email, "laptop").await;
+    let (phone, phone_refresh) = login(app, email, "phone").await;
+
+    let sessions = list(app, &laptop).await;
+    let phone_session = sessions.iter().find(|s| !s.current).unwrap();
+    let response = app
+        .delete_session(&phone_session.session.id.to_string())
+        .add_cookies(auth_jar(app, &laptop))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NO_CONTENT);
+
+    assert_eq!(verify_status(app, &phone).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        refresh_status(app, &phone_refresh).await,
+        StatusCode::UNAUTHORIZED
+    );
+    // the other session is untouched
+    assert_eq!(verify_status(app, &laptop).await, StatusCode::OK);
+    assert_eq!(list(app, &laptop).await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_cannot_revoke_another_users_session() {
+    let app = get_test_app().await;
+    signup(app, "sessions-owner@me.com").await;
+    signup(app, "sessions-other@me.com").await;
+    let (owner, _) = login(app, "sessions-owner@me.com", "owner").await;
+    let (other, _) = login(app, "sessions-other@me.com", "other").await;
+
+    let id = list(app, &owner).await[0].session.id.to_string();
+    let response = app
+        .delete_session(&id)
+        .add_cookies(auth_jar(app, &other))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+    let response = app
+        .delete_session("not-a-session")
+        .add_cookies(auth_jar(app, &other))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::NOT_FOUND);
+    assert_eq!(verify_status(app, &owner).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_logout_ends_session() {
+    let app = get_test_app().await;
+    let email = "sessions-logout@me.com";
+    signup(app, email).await;
+    let (laptop, _) = login(app, email, "laptop").await;
+    let (phone, phone_refresh) = login(app, email, "phone").await;
+
+    let response = app.post_logout().add_cookies(auth_jar(app, &phone)).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(
+        refresh_status(app, &phone_refresh).await,
+        StatusCode::UNAUTHORIZED
+    );
+    let sessions = list(app, &laptop).await;
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].current);
+}
@@ -1,3 +1,5 @@
+use lgr_auth::routes::PasskeyRegistrationChallenge;
+
 use crate::common::get_test_app;
 
 #[tokio::test]
@@ -95,3 +97,25 @@ pub async fn test_signup_return_409_user_exists() {
     let response = app.post_signup(&body).await;
     assert_eq!(response.status_code(), reqwest::StatusCode::CONFLICT);
 }
+
+#[tokio::test]
+pub async fn test_signup_passkey_returns_registration_challenge() {
+    let app = get_test_app().await;
+    let response = app
+        .post_signup(&serde_json::json!({
+            "method": "passkey",
+            "email": "signup-passkey@me.com",
+        }))
+        .await;
+    assert_eq!(response.status_code(), reqwest::StatusCode::OK);
+    let challenge = response.json::<PasskeyRegistrationChallenge>();
+    assert_eq!(
+        challenge.options.public_key.user.name,
+        "signup-passkey@me.com"
+    );
+
+    let response = app
+        .post_signup(&serde_json::json!({ "method": "passkey", "email": "testuser" }))
+        .await;
+    assert_eq!(response.status_code(), reqwest::StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,162 @@
+use cookie::CookieJar;
+use lgr_auth::domain::{Email, EmailTemplate, TotpSecret, TwoFactorMethod, totp_now};
+use lgr_auth::routes::{LoginResponse, TotpConfirmResponse, TotpEnrollResponse};
+use lgr_auth::utils::auth::generate_auth_cookie;
+use reqwest::StatusCode;
+
+use crate::common::{TestApp, get_test_app, two_factor_attempt_id};
+
+async fn signup(app: &TestApp, email: &str) -> CookieJar {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "method": "email_password",
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+
+    let email = Email::parse(email).unwrap();
+    let mut jar = CookieJar::default();
+    jar.add(generate_auth_cookie(&email, 0, &app.config.jwt).expect("auth cookie"));
+    jar
+}
+
+async fn enroll(app: &TestApp, jar: &CookieJar) -> TotpSecret {
+    let response = app.post_totp_enroll().add_cookies(jar.clone()).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let body = response.json::<TotpEnrollResponse>();
+    assert!(body.otpauth_uri.starts_with("otpauth://totp/"));
+    TotpSecret::parse(&body.secret).expect("valid secret")
+}
+
+/// Log in with the password and return the login attempt id
+async fn login(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_login(&serde_json::json!({
+            "method": "email_password",
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::PARTIAL_CONTENT);
+    let url = match response.json::<LoginResponse>() {
+        LoginResponse::TwoFactor { method, url, .. } => {
+            assert_eq!(method, TwoFactorMethod::Totp);
+            url
+        }
+        other => panic!("unexpected login response: {other:?}"),
+    };
+
+    two_factor_attempt_id(&url)
+}
+
+#[tokio::test]
+async fn test_totp_enroll_400_without_auth_cookie() {
+    let app = get_test_app().await;
+    let response = app.post_totp_enroll().await;
+    assert_eq!(response.status_code(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_totp_confirm_401_on_wrong_code() {
+    let app = get_test_app().await;
+    let email = "totp-wrong@me.com";
+    let jar = signup(app, email).await;
+    let secret = enroll(app, &jar).await;
+
+    let wrong = secret.generate_code(totp_now() - 300).unwrap();
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": wrong }))
+        .add_cookies(jar)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_totp_login_flow() {
+    let app = get_test_app().await;
+    let email = "totp-flow@me.com";
+    let jar = signup(app, email).await;
+    let secret = enroll(app, &jar).await;
+
+    let now = totp_now();
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": secret.generate_code(now).unwrap() }))
+        .add_cookies(jar)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    let confirmed = response.json::<TotpConfirmResponse>();
+    assert_eq!(confirmed.recovery_codes.len(), 10);
+
+    let id = login(app, email).await;
+    // nothing beyond the signup verification email, TOTP codes aren't sent
+    let last = app.last_email_to(email).map(|sent| sent.template);
+    assert!(matches!(last, Some(EmailTemplate::EmailVerification(_))));
+
+    // the confirm step already used the current step, the skew window allows the next one
+    let code = secret.generate_code(now + 30).unwrap();
+    let body = serde_json::json!({
+        "method": "totp",
+        "email": email,
+        "id": id,
+        "code": code,
+    });
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert!(
+        !response
+            .cookie(&app.config.jwt.cookie_name)
+            .value()
+            .is_empty()
+    );
+
+    // a code can't be replayed, even on a fresh login attempt
+    let id = login(app, email).await;
+    let body = serde_json::json!({
+        "method": "totp",
+        "email": email,
+        "id": id,
+        "code": code,
+    });
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_totp_verify_401_with_email_method() {
+    let app = get_test_app().await;
+    let email = "totp-method@me.com";
+    let jar = signup(app, email).await;
+    let secret = enroll(app, &jar).await;
+    let now = totp_now();
+    let response = app
+        .post_totp_confirm(&serde_json::json!({ "code": secret.generate_code(now).unwrap() }))
+        .add_cookies(jar)
+        .await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let id = login(app, email).await;
+    let body = serde_json::json!({
+        "method": "email",
+        "email": email,
+        "id": id,
+        "code": secret.generate_code(now + 30).unwrap(),
+    });
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_totp_signup_422() {
+    let app = get_test_app().await;
+    let response = app
+        .post_signup(&serde_json::json!({
+            "method": "email_password",
+            "email": "totp-signup@me.com",
+            "password": "password123",
+            "two_factor": "totp",
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+}
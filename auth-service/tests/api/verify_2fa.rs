@@ -1,11 +1,12 @@
 use base64::Engine;
 use base64::engine::GeneralPurpose;
+use lgr_auth::config::Config;
 use lgr_auth::domain::{EmailTemplate, TwoFactorEmailData};
 use lgr_auth::routes::LoginResponse;
 use lgr_auth::utils::auth::TwoFAClaims;
-use reqwest::Url;
+use reqwest::{StatusCode, Url};
 
-use crate::common::get_test_app;
+use crate::common::{TestApp, get_test_app};
 
 #[tokio::test]
 async fn test_verify_2fa_206() {
@@ -88,6 +89,7 @@ async fn test_verify_2fa_200() {
 
     let (code, id) = match email.template {
         EmailTemplate::TwoFactor(data) => parse_email_data(&data),
+        other => panic!("unexpected email template: {other:?}"),
     };
     let body = serde_json::json!({
         "method": "email",
@@ -133,6 +135,7 @@ async fn test_verify_2fa_401() {
 
     let (code, id) = match email.template {
         EmailTemplate::TwoFactor(data) => parse_email_data(&data),
+        other => panic!("unexpected email template: {other:?}"),
     };
 
     if let LoginResponse::TwoFactor { .. } = response_body {
@@ -170,3 +173,84 @@ async fn test_verify_2fa_401() {
         }
     }
 }
+
+/// Sign up with email 2FA
+async fn signup(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "method": "email_password",
+            "email": email,
+            "password": "password123",
+            "two_factor": "email",
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+}
+
+/// Log in up to the second factor, returning the emailed code and the attempt id
+async fn login_for_code(app: &TestApp, email: &str) -> (String, String) {
+    let response = app
+        .post_login(&serde_json::json!({
+            "method": "email_password",
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::PARTIAL_CONTENT);
+    match app.last_email_to(email).expect("2fa email").template {
+        EmailTemplate::TwoFactor(data) => parse_email_data(&data),
+        other => panic!("unexpected email template: {other:?}"),
+    }
+}
+
+async fn verify(app: &TestApp, email: &str, id: &str, code: &str) -> StatusCode {
+    app.post_verify_2fa(&serde_json::json!({
+        "method": "email",
+        "email": email,
+        "id": id,
+        "code": code,
+    }))
+    .await
+    .status_code()
+}
+
+#[tokio::test]
+async fn test_verify_2fa_code_works_once() {
+    let app = get_test_app().await;
+    let email = "verify-2fa-once@me.com";
+    signup(app, email).await;
+    let (code, id) = login_for_code(app, email).await;
+
+    assert_eq!(verify(app, email, &id, &code).await, StatusCode::OK);
+    assert_eq!(
+        verify(app, email, &id, &code).await,
+        StatusCode::UNAUTHORIZED
+    );
+}
+
+#[tokio::test]
+async fn test_verify_2fa_attempt_ends_after_max_failures() {
+    let mut config = Config::default();
+    config.auth.two_factor_max_attempts = 3;
+    let app = TestApp::new(&config).await;
+    let email = "verify-2fa-max@me.com";
+    signup(&app, email).await;
+    let (code, id) = login_for_code(&app, email).await;
+
+    let wrong = if code == "000000" { "111111" } else { "000000" };
+    for _ in 0..3 {
+        assert_eq!(
+            verify(&app, email, &id, wrong).await,
+            StatusCode::UNAUTHORIZED
+        );
+    }
+    // too late for the right code
+    assert_eq!(
+        verify(&app, email, &id, &code).await,
+        StatusCode::UNAUTHORIZED
+    );
+
+    // logging in again starts over
+    let (code, id) = login_for_code(&app, email).await;
+    assert_eq!(verify(&app, email, &id, &code).await, StatusCode::OK);
+}
@@ -0,0 +1,132 @@
+use lgr_auth::config::{Config, EmailVerificationPolicy};
+use lgr_auth::domain::EmailTemplate;
+use lgr_auth::routes::LoginResponse;
+use lgr_auth::utils::auth::Claims;
+use reqwest::{StatusCode, Url};
+
+use crate::common::{TestApp, get_test_app};
+
+async fn signup(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "method": "email_password",
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::CREATED);
+}
+
+async fn login(app: &TestApp, email: &str) -> axum_test::TestResponse {
+    app.post_login(&serde_json::json!({
+        "method": "email_password",
+        "email": email,
+        "password": "password123",
+    }))
+    .await
+}
+
+/// `email_verified` claim of the access token from a successful login
+fn email_verified_claim(response: axum_test::TestResponse) -> Option<bool> {
+    let token = match response.json::<LoginResponse>() {
+        LoginResponse::Success { token, .. } => token,
+        other => panic!("unexpected login response: {other:?}"),
+    };
+    jsonwebtoken::dangerous::insecure_decode::<Claims>(&token)
+        .expect("valid token data")
+        .claims
+        .email_verified
+}
+
+fn verification_token(app: &TestApp, email: &str) -> String {
+    let sent = app.last_email_to(email).expect("verification email sent");
+    let data = match sent.template {
+        EmailTemplate::EmailVerification(data) => data,
+        other => panic!("unexpected email template: {other:?}"),
+    };
+    let url = Url::parse(&data.link_url).expect("valid url");
+    url.query_pairs()
+        .find_map(|(key, value)| (key == "token").then(|| value.to_string()))
+        .expect("token in link")
+}
+
+#[tokio::test]
+async fn test_verify_email_flow() {
+    let app = get_test_app().await;
+    let email = "verify-flow@me.com";
+    signup(app, email).await;
+
+    // unverified accounts can log in by default, flagged in the token
+    let response = login(app, email).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(email_verified_claim(response), Some(false));
+
+    let body = serde_json::json!({ "token": verification_token(app, email) });
+    let response = app.post_verify_email(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let response = login(app, email).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+    assert_eq!(email_verified_claim(response), Some(true));
+
+    // single-use
+    let response = app.post_verify_email(&body).await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_verify_email_401_invalid_token() {
+    let app = get_test_app().await;
+    let response = app
+        .post_verify_email(&serde_json::json!({ "token": "not-a-token" }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_resend_verification_rate_limited() {
+    let app = get_test_app().await;
+    let email = "verify-resend@me.com";
+    signup(app, email).await;
+    let first = verification_token(app, email);
+
+    let body = serde_json::json!({ "email": email });
+    for _ in 0..app.config.auth.verification_resend_limit {
+        let response = app.post_resend_verification(&body).await;
+        assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+    }
+    assert_ne!(verification_token(app, email), first);
+
+    let response = app.post_resend_verification(&body).await;
+    assert_eq!(response.status_code(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_resend_verification_unknown_email() {
+    let app = get_test_app().await;
+    let email = "verify-unknown@me.com";
+    let response = app
+        .post_resend_verification(&serde_json::json!({ "email": email }))
+        .await;
+    assert_eq!(response.status_code(), StatusCode::ACCEPTED);
+    assert!(app.last_email_to(email).is_none());
+}
+
+#[tokio::test]
+async fn test_block_policy_refuses_unverified_login() {
+    let mut config = Config::default();
+    config.auth.email_verification = EmailVerificationPolicy::Block;
+    let app = TestApp::new(&config).await;
+    let email = "verify-block@me.com";
+    signup(&app, email).await;
+
+    let response = login(&app, email).await;
+    assert_eq!(response.status_code(), StatusCode::FORBIDDEN);
+
+    let body = serde_json::json!({ "token": verification_token(&app, email) });
+    let response = app.post_verify_email(&body).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+
+    let response = login(&app, email).await;
+    assert_eq!(response.status_code(), StatusCode::OK);
+}
@@ -1,5 +1,6 @@
-use lgr_auth::domain::Email;
-use lgr_auth::utils::auth::generate_auth_token;
+use lgr_auth::domain::{Email, Grants, TokenPurpose};
+use lgr_auth::routes::VerifyTokenResponse;
+use lgr_auth::utils::auth::{generate_auth_token, generate_one_time_token};
 
 use crate::common::get_test_app;
 
@@ -7,10 +8,34 @@ use crate::common::get_test_app;
 async fn test_verify_token_200() {
     let app = get_test_app().await;
     let email = Email::parse("tester@test.com").expect("valid email");
-    let token = generate_auth_token(&email, &app.config.jwt.secret).expect("valid token");
+    let token = generate_auth_token(
+        &email,
+        0,
+        &Grants::default(),
+        app.config.jwt.access_token_ttl,
+        &app.config.jwt.secret,
+    )
+    .expect("valid token");
     let body = serde_json::json!({ "token": token });
     let response = app.post_verify_token(&body).await;
     assert_eq!(response.status_code(), reqwest::StatusCode::OK);
+    let body = response.json::<VerifyTokenResponse>();
+    assert_eq!(body.sub, "tester@test.com");
+    assert!(body.scopes.is_empty());
+}
+
+#[tokio::test]
+async fn test_verify_token_401_if_not_an_access_token() {
+    let app = get_test_app().await;
+    let email = Email::parse("tester@test.com").expect("valid email");
+    for purpose in [TokenPurpose::MagicLink, TokenPurpose::PasswordReset] {
+        let (_, token) = generate_one_time_token(&email, &purpose, 600, &app.config.jwt.secret)
+            .expect("valid token");
+        let response = app
+            .post_verify_token(&serde_json::json!({ "token": token }))
+            .await;
+        assert_eq!(response.status_code(), reqwest::StatusCode::UNAUTHORIZED);
+    }
 }
 
 #[tokio::test]